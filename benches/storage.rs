@@ -0,0 +1,77 @@
+//! 存储、搜索和侧边栏的基准测试
+//!
+//! 笔记数默认 1000 篇，可以用 NOTHINGBOOK_BENCH_NOTES 环境变量调整：
+//! `NOTHINGBOOK_BENCH_NOTES=5000 cargo bench`
+
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use notes_app::{
+    note::Note,
+    settings::NoteSort,
+    storage::Storage,
+    synthetic::{self, SizeDistribution},
+};
+use std::{collections::HashMap, hint::black_box};
+
+fn note_count() -> usize {
+    std::env::var("NOTHINGBOOK_BENCH_NOTES")
+        .ok()
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(1000)
+}
+
+fn bench_storage(c: &mut Criterion) {
+    let notes = synthetic::generate(note_count(), SizeDistribution::default(), 1);
+    let dir = tempfile::tempdir().unwrap();
+    let storage = Storage::with_dir(dir.path().to_path_buf()).unwrap();
+    for note in &notes {
+        storage.save_note(note).unwrap();
+    }
+
+    let mut group = c.benchmark_group(format!("{} 篇笔记", notes.len()));
+    group.sample_size(10);
+
+    group.bench_function("load_all_notes", |b| {
+        b.iter(|| {
+            let mut loaded = HashMap::new();
+            storage.load_all_notes(&mut loaded).unwrap();
+            black_box(loaded)
+        })
+    });
+
+    let mut note = notes[notes.len() / 2].clone();
+    group.bench_function("save_note", |b| {
+        b.iter(|| {
+            note.content.push('x');
+            storage.save_note(black_box(&note)).unwrap();
+        })
+    });
+
+    group.bench_function("search", |b| {
+        b.iter(|| {
+            notes
+                .iter()
+                .filter(|note| note.matches(black_box("Release")))
+                .count()
+        })
+    });
+
+    // 侧边栏每次整体刷新时做的事：排序，再为每一行生成预览和时间
+    group.bench_function("sidebar_rows", |b| {
+        b.iter_batched(
+            || notes.iter().collect::<Vec<&Note>>(),
+            |mut rows| {
+                let sort = NoteSort::default();
+                rows.sort_by(|a, b| sort.compare(a, b).then(a.id.cmp(&b.id)));
+                rows.into_iter()
+                    .map(|note| (note.preview(), note.formatted_time()))
+                    .collect::<Vec<_>>()
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_storage);
+criterion_main!(benches);
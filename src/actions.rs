@@ -0,0 +1,162 @@
+use gpui::{App, KeyBinding, Menu, MenuItem, actions};
+use gpui_component::input::{Copy, Cut, Paste, Redo, SelectAll, Undo};
+
+// 应用级动作：菜单、快捷键和按钮都通过这些动作分发到 AppView
+actions!(
+    notes_app,
+    [
+        NewNote,
+        SaveNote,
+        RenameNote,
+        PreviousNote,
+        NextNote,
+        LockApp,
+        DeleteNote,
+        OpenSettings,
+        ExportSettings,
+        ImportSettings,
+        ToggleSidebar,
+        ShowGraph,
+        ShowCalendar,
+        ShowBoard,
+        ShowStats,
+        ShowAttachments,
+        ToggleClipboardCapture,
+        ShowPlugins,
+        ShowDiagnostics,
+        ShowTrash,
+        VerifyData,
+        ExportDocx,
+        ExportEpub,
+        ExportLatex,
+        ExportHtml,
+        ExportImage,
+        ExportAnki,
+        ShareByEmail,
+        CopyNoteMarkdown,
+        PublishNote,
+        UnpublishNote,
+        SaveWebPage,
+        ToggleHighlight,
+        TogglePreview,
+        ReadAloud,
+        AiSummarize,
+        AiSuggestTags,
+        AiGenerateTitle,
+        Quit
+    ]
+);
+
+// 侧边栏获得焦点时才生效的动作
+actions!(sidebar, [SelectPrevNote, SelectNextNote, FocusEditor]);
+
+pub const SIDEBAR_CONTEXT: &str = "Sidebar";
+
+pub const APP_NAME: &str = "NothingBook";
+
+pub fn init(cx: &mut App) {
+    cx.on_action(|_: &Quit, cx| cx.quit());
+
+    cx.bind_keys([
+        KeyBinding::new("secondary-n", NewNote, None),
+        KeyBinding::new("secondary-s", SaveNote, None),
+        KeyBinding::new("f2", RenameNote, None),
+        // 文本框里这几个键另有编辑用途（跳到开头结尾、删除到行首或删除词），由文本框优先处理；
+        // 正文中先按 Esc 把焦点交给侧边栏再按，菜单中注明了这一点
+        KeyBinding::new("secondary-backspace", DeleteNote, None),
+        KeyBinding::new("secondary-up", PreviousNote, None),
+        KeyBinding::new("secondary-down", NextNote, None),
+        KeyBinding::new("secondary-,", OpenSettings, None),
+        KeyBinding::new("secondary-\\", ToggleSidebar, None),
+        KeyBinding::new("secondary-shift-g", ShowGraph, None),
+        KeyBinding::new("secondary-shift-c", ShowCalendar, None),
+        KeyBinding::new("secondary-shift-b", ShowBoard, None),
+        KeyBinding::new("secondary-shift-h", ToggleHighlight, None),
+        KeyBinding::new("secondary-shift-p", TogglePreview, None),
+        KeyBinding::new("secondary-shift-r", ReadAloud, None),
+        KeyBinding::new("secondary-shift-l", LockApp, None),
+        KeyBinding::new("secondary-q", Quit, None),
+        KeyBinding::new("up", SelectPrevNote, Some(SIDEBAR_CONTEXT)),
+        KeyBinding::new("down", SelectNextNote, Some(SIDEBAR_CONTEXT)),
+        KeyBinding::new("enter", FocusEditor, Some(SIDEBAR_CONTEXT)),
+        KeyBinding::new("delete", DeleteNote, Some(SIDEBAR_CONTEXT)),
+        KeyBinding::new("backspace", DeleteNote, Some(SIDEBAR_CONTEXT)),
+    ]);
+
+    cx.set_menus(vec![
+        Menu {
+            name: APP_NAME.into(),
+            items: vec![
+                MenuItem::action("设置…", OpenSettings),
+                MenuItem::action("导出设置…", ExportSettings),
+                MenuItem::action("导入设置…", ImportSettings),
+                MenuItem::action("锁定", LockApp),
+                MenuItem::separator(),
+                MenuItem::action("退出", Quit),
+            ],
+        },
+        Menu {
+            name: "文件".into(),
+            items: vec![
+                MenuItem::action("新建笔记", NewNote),
+                MenuItem::action("保存", SaveNote),
+                MenuItem::action("重命名", RenameNote),
+                MenuItem::action("删除笔记…（正文中先按 Esc）", DeleteNote),
+                MenuItem::action("回收站", ShowTrash),
+                MenuItem::separator(),
+                MenuItem::action("导出为 Word (DOCX)…", ExportDocx),
+                MenuItem::action("导出为 EPUB…", ExportEpub),
+                MenuItem::action("导出为 LaTeX…", ExportLatex),
+                MenuItem::action("导出为 HTML…", ExportHtml),
+                MenuItem::action("导出为图片…", ExportImage),
+                MenuItem::action("导出 Anki 卡片…", ExportAnki),
+                MenuItem::separator(),
+                MenuItem::action("通过邮件发送…", ShareByEmail),
+                MenuItem::action("复制为 Markdown", CopyNoteMarkdown),
+                MenuItem::action("发布为链接", PublishNote),
+                MenuItem::action("取消发布", UnpublishNote),
+                MenuItem::separator(),
+                MenuItem::action("剪藏模式", ToggleClipboardCapture),
+                MenuItem::action("稍后阅读（剪贴板中的网址）", SaveWebPage),
+                MenuItem::separator(),
+                MenuItem::action("验证数据", VerifyData),
+            ],
+        },
+        Menu {
+            name: "编辑".into(),
+            items: vec![
+                MenuItem::action("撤销", Undo),
+                MenuItem::action("重做", Redo),
+                MenuItem::separator(),
+                MenuItem::action("剪切", Cut),
+                MenuItem::action("复制", Copy),
+                MenuItem::action("粘贴", Paste),
+                MenuItem::action("全选", SelectAll),
+                MenuItem::separator(),
+                MenuItem::action("高亮", ToggleHighlight),
+                MenuItem::separator(),
+                MenuItem::action("AI 总结笔记", AiSummarize),
+                MenuItem::action("AI 建议标签", AiSuggestTags),
+                MenuItem::action("AI 生成标题", AiGenerateTitle),
+            ],
+        },
+        Menu {
+            name: "视图".into(),
+            items: vec![
+                MenuItem::action("显示/隐藏侧边栏", ToggleSidebar),
+                MenuItem::action("上一篇笔记（正文中先按 Esc）", PreviousNote),
+                MenuItem::action("下一篇笔记（正文中先按 Esc）", NextNote),
+                MenuItem::action("预览/编辑", TogglePreview),
+                MenuItem::action("朗读", ReadAloud),
+                MenuItem::action("关系图谱", ShowGraph),
+                MenuItem::action("日历", ShowCalendar),
+                MenuItem::action("看板", ShowBoard),
+                MenuItem::action("统计", ShowStats),
+                MenuItem::action("附件", ShowAttachments),
+                MenuItem::separator(),
+                MenuItem::action("插件与脚本", ShowPlugins),
+                MenuItem::action("诊断日志", ShowDiagnostics),
+            ],
+        },
+    ]);
+}
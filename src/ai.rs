@@ -0,0 +1,85 @@
+use crate::{curl, note::Note, settings::AiSettings};
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+
+/// 可以交给模型完成的笔记操作
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AiTask {
+    Summarize,
+    SuggestTags,
+    GenerateTitle,
+}
+
+impl AiTask {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Summarize => "总结笔记",
+            Self::SuggestTags => "建议标签",
+            Self::GenerateTitle => "生成标题",
+        }
+    }
+
+    fn instruction(&self) -> &'static str {
+        match self {
+            Self::Summarize => "用两三句话总结用户给出的笔记，使用笔记原文的语言，只输出摘要本身。",
+            Self::SuggestTags => {
+                "为用户给出的笔记建议三到五个简短的标签，只输出标签，用英文逗号分隔，不要加 # 号。"
+            }
+            Self::GenerateTitle => {
+                "为用户给出的笔记起一个不超过二十个字的标题，只输出标题本身，不要加引号。"
+            }
+        }
+    }
+}
+
+/// 调用 OpenAI 兼容的 /chat/completions 接口，返回模型的回复
+///
+/// 通过 curl 发送请求，请求体和 API 密钥不会出现在进程列表中
+pub fn complete(task: AiTask, note: &Note, settings: &AiSettings) -> Result<String> {
+    let body = json!({
+        "model": settings.model,
+        "messages": [
+            { "role": "system", "content": task.instruction() },
+            { "role": "user", "content": note.to_markdown() },
+        ],
+    });
+    let url = format!(
+        "{}/chat/completions",
+        settings.endpoint.trim_end_matches('/')
+    );
+
+    let mut config = String::new();
+    config.push_str(&format!("url = {}\n", curl::quote(&url)));
+    config.push_str("header = \"Content-Type: application/json\"\n");
+    if !settings.api_key.is_empty() {
+        config.push_str(&format!(
+            "header = {}\n",
+            curl::quote(&format!("Authorization: Bearer {}", settings.api_key))
+        ));
+    }
+    config.push_str(&format!(
+        "data-binary = {}\n",
+        curl::quote(&body.to_string())
+    ));
+    let output = curl::request(&settings.curl, &config)?;
+
+    let reply: Value = serde_json::from_slice(&output).context("解析模型回复失败")?;
+    let content = reply["choices"][0]["message"]["content"]
+        .as_str()
+        .context("模型回复中没有内容")?;
+    Ok(content.trim().to_string())
+}
+
+/// 把“建议标签”的回复拆成标签列表
+pub fn parse_tags(reply: &str) -> Vec<String> {
+    reply
+        .split([',', '，', '、', '\n'])
+        .map(|tag| {
+            tag.trim()
+                .trim_matches(['"', '“', '”', '#'])
+                .trim()
+                .to_string()
+        })
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
@@ -0,0 +1,122 @@
+use crate::note::Note;
+
+/// 带这个标签的笔记按“标题 + 正文”拆成卡片，每个小标题是正面，其下内容是背面
+pub const HEADING_CARDS_TAG: &str = "anki";
+
+/// 一张问答卡片
+#[derive(Debug, PartialEq)]
+pub struct Card {
+    pub front: String,
+    pub back: String,
+}
+
+/// 从笔记中提取卡片：优先识别 `Q:` / `A:` 块，没有时对带 anki 标签的笔记按小标题拆分
+pub fn cards(note: &Note) -> Vec<Card> {
+    let cards = qa_cards(&note.content);
+    if cards.is_empty() && note.tags.iter().any(|tag| tag == HEADING_CARDS_TAG) {
+        heading_cards(&note.content)
+    } else {
+        cards
+    }
+}
+
+// Q: 之后到 A: 之前是问题，A: 之后到下一个 Q: 或小标题之前是答案
+fn qa_cards(content: &str) -> Vec<Card> {
+    let mut cards = Vec::new();
+    let mut question: Option<Vec<&str>> = None;
+    let mut answer: Option<Vec<&str>> = None;
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        if let Some(rest) = strip_label(trimmed, 'Q') {
+            push_card(&mut cards, question.take(), answer.take());
+            question = Some(vec![rest]);
+        } else if let Some(rest) = strip_label(trimmed, 'A').filter(|_| question.is_some()) {
+            answer = Some(vec![rest]);
+        } else if is_heading(trimmed) {
+            push_card(&mut cards, question.take(), answer.take());
+        } else if let Some(answer) = answer.as_mut() {
+            answer.push(line);
+        } else if let Some(question) = question.as_mut() {
+            question.push(line);
+        }
+    }
+    push_card(&mut cards, question, answer);
+    cards
+}
+
+// `# 标题`，不包括 `#标签`
+fn is_heading(line: &str) -> bool {
+    let rest = line.trim_start_matches('#');
+    rest.len() < line.len() && (rest.is_empty() || rest.starts_with(' '))
+}
+
+fn strip_label(line: &str, label: char) -> Option<&str> {
+    let rest = line
+        .strip_prefix(label)
+        .or_else(|| line.strip_prefix(label.to_ascii_lowercase()))?;
+    rest.strip_prefix(':')
+        .or_else(|| rest.strip_prefix('：'))
+        .map(str::trim_start)
+}
+
+fn push_card(cards: &mut Vec<Card>, question: Option<Vec<&str>>, answer: Option<Vec<&str>>) {
+    let (Some(question), Some(answer)) = (question, answer) else {
+        return;
+    };
+    let front = question.join("\n").trim().to_string();
+    let back = answer.join("\n").trim().to_string();
+    if !front.is_empty() && !back.is_empty() {
+        cards.push(Card { front, back });
+    }
+}
+
+fn heading_cards(content: &str) -> Vec<Card> {
+    let mut cards = Vec::new();
+    let mut heading: Option<&str> = None;
+    let mut body = Vec::new();
+    for line in content.lines() {
+        if is_heading(line) {
+            push_card(&mut cards, heading.map(|h| vec![h]), Some(body.clone()));
+            heading = Some(line.trim_start_matches('#').trim());
+            body.clear();
+        } else if heading.is_some() {
+            body.push(line);
+        }
+    }
+    push_card(&mut cards, heading.map(|h| vec![h]), Some(body));
+    cards
+}
+
+/// 生成 Anki 可直接导入的制表符分隔文本，第三列是牌组（取笔记标题），第四列是标签
+pub fn to_tsv(notes: &[&Note]) -> String {
+    let mut tsv = String::from("#separator:tab\n#html:true\n#deck column:3\n#tags column:4\n");
+    for note in notes {
+        let deck = field(&note.title);
+        let tags = note
+            .tags
+            .iter()
+            .map(|tag| tag.replace(char::is_whitespace, "_"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        for card in cards(note) {
+            tsv.push_str(&format!(
+                "{}\t{}\t{}\t{}\n",
+                field(&card.front),
+                field(&card.back),
+                deck,
+                tags
+            ));
+        }
+    }
+    tsv
+}
+
+// 字段按 HTML 导入，换行转成 <br>，制表符会打乱列，换成空格
+fn field(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\t', " ")
+        .replace('\n', "<br>")
+}
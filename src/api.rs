@@ -0,0 +1,262 @@
+use crate::{note::Note, settings::ApiSettings};
+use anyhow::{Result, anyhow, bail};
+use chrono::{DateTime, NaiveDate, Utc};
+use futures::channel::mpsc::UnboundedSender;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{io::Read, sync::mpsc, thread, time::Duration};
+use subtle::ConstantTimeEq as _;
+use tiny_http::{Header, Method, Request, Response, Server};
+use url::Url;
+
+// 单次请求正文的上限，防止误发的大文件占满内存
+const MAX_BODY_BYTES: u64 = 4 * 1024 * 1024;
+// 界面线程繁忙时最多等待这么久
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 接口返回的笔记。ID 用字符串，避免 JSON 解析器丢失 u128 精度
+#[derive(Serialize)]
+pub struct ApiNote {
+    pub id: String,
+    pub title: String,
+    pub content: String,
+    pub tags: Vec<String>,
+    pub due_date: Option<NaiveDate>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<&Note> for ApiNote {
+    fn from(note: &Note) -> Self {
+        Self {
+            id: note.id.to_string(),
+            title: note.title.clone(),
+            content: note.content.clone(),
+            tags: note.tags.clone(),
+            due_date: note.due_date,
+            created_at: note.created_at,
+            updated_at: note.updated_at,
+        }
+    }
+}
+
+/// PATCH /notes/{id} 的请求体，只修改提供了的字段
+#[derive(Default, Deserialize)]
+pub struct NotePatch {
+    pub title: Option<String>,
+    pub content: Option<String>,
+    pub tags: Option<Vec<String>>,
+    /// 没有这个字段时不修改，为 null 时清除截止日期
+    #[serde(default, deserialize_with = "present")]
+    pub due_date: Option<Option<NaiveDate>>,
+}
+
+// 字段出现时（包括 null）包一层 Some，和缺少字段区分开
+fn present<'de, D>(deserializer: D) -> Result<Option<Option<NaiveDate>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::deserialize(deserializer).map(Some)
+}
+
+/// POST /notes 的请求体
+#[derive(Deserialize)]
+struct NewNote {
+    title: Option<String>,
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    due_date: Option<NaiveDate>,
+}
+
+/// POST /clip 的请求体
+#[derive(Deserialize)]
+struct ClipPayload {
+    title: Option<String>,
+    #[serde(default)]
+    markdown: String,
+    url: Option<String>,
+}
+
+impl ClipPayload {
+    fn into_note(self) -> Note {
+        let mut note = Note::new();
+        note.title = match (self.title, &self.url) {
+            (Some(title), _) if !title.trim().is_empty() => title.trim().to_string(),
+            (_, Some(url)) => url.clone(),
+            _ => "网页剪藏".to_string(),
+        };
+        note.content = match self.url {
+            Some(url) => format!("{}\n\n来源：{}", self.markdown.trim_end(), url),
+            None => self.markdown,
+        };
+        note
+    }
+}
+
+/// 需要在界面线程上读写笔记库的请求
+pub enum ApiRequest {
+    List,
+    Search(String),
+    Create(Note),
+    Update { id: u128, patch: NotePatch },
+}
+
+pub enum ApiReply {
+    Notes(Vec<ApiNote>),
+    Note(ApiNote),
+    NotFound,
+    Error(String),
+}
+
+/// 一次请求及其回复通道，服务线程会阻塞等待回复
+pub struct ApiCall {
+    pub request: ApiRequest,
+    pub reply: mpsc::Sender<ApiReply>,
+}
+
+/// 在本机端口上提供笔记接口，请求通过 `calls` 交给界面线程处理
+pub fn start(settings: &ApiSettings, calls: UnboundedSender<ApiCall>) -> Result<()> {
+    if settings.token.is_empty() {
+        bail!("未设置访问令牌");
+    }
+    let server = Server::http(("127.0.0.1", settings.port)).map_err(|e| anyhow!(e))?;
+    let token = settings.token.clone();
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            if let Err(e) = handle(request, &token, &calls) {
+                eprintln!("处理接口请求失败: {}", e);
+            }
+        }
+    });
+    Ok(())
+}
+
+fn handle(mut request: Request, token: &str, calls: &UnboundedSender<ApiCall>) -> Result<()> {
+    // 浏览器扩展跨域调用前会先发预检请求
+    if *request.method() == Method::Options {
+        return respond(request, Response::empty(204));
+    }
+    if !authorized(&request, token) {
+        return respond(request, Response::empty(401));
+    }
+
+    let url = Url::parse(&format!("http://localhost{}", request.url()))?;
+    let segments: Vec<&str> = url.path().trim_matches('/').split('/').collect();
+    let api_request = match (request.method(), segments.as_slice()) {
+        (Method::Get, ["notes"]) => ApiRequest::List,
+        (Method::Get, ["search"]) => {
+            let query = url
+                .query_pairs()
+                .find(|(key, _)| key == "q")
+                .map(|(_, value)| value.into_owned())
+                .unwrap_or_default();
+            ApiRequest::Search(query)
+        }
+        (Method::Post, ["notes"]) => match read_json::<NewNote>(&mut request) {
+            Ok(input) => {
+                let mut note = Note::new();
+                if let Some(title) = input.title {
+                    note.title = title;
+                }
+                note.content = input.content;
+                note.tags = input.tags;
+                note.due_date = input.due_date;
+                ApiRequest::Create(note)
+            }
+            Err(e) => return bad_request(request, e),
+        },
+        (Method::Post, ["clip"]) => match read_json::<ClipPayload>(&mut request) {
+            Ok(payload) => ApiRequest::Create(payload.into_note()),
+            Err(e) => return bad_request(request, e),
+        },
+        (Method::Patch, ["notes", id]) => {
+            let Ok(id) = id.parse() else {
+                return respond(request, Response::empty(404));
+            };
+            match read_json::<NotePatch>(&mut request) {
+                Ok(patch) => ApiRequest::Update { id, patch },
+                Err(e) => return bad_request(request, e),
+            }
+        }
+        _ => return respond(request, Response::empty(404)),
+    };
+    // 新笔记的 ID 在这里就已确定，超时后界面线程仍可能建好它
+    let pending = match &api_request {
+        ApiRequest::Create(note) => Some(ApiNote::from(note)),
+        _ => None,
+    };
+    let created = pending.is_some();
+
+    let (reply_tx, reply_rx) = mpsc::channel();
+    let call = ApiCall {
+        request: api_request,
+        reply: reply_tx,
+    };
+    if calls.unbounded_send(call).is_err() {
+        return respond(request, Response::empty(503));
+    }
+    match reply_rx.recv_timeout(REPLY_TIMEOUT) {
+        Ok(ApiReply::Notes(notes)) => respond(request, json(&notes, 200)?),
+        Ok(ApiReply::Note(note)) => respond(request, json(&note, if created { 201 } else { 200 })?),
+        Ok(ApiReply::NotFound) => respond(request, Response::empty(404)),
+        Ok(ApiReply::Error(message)) => respond(
+            request,
+            Response::from_string(message).with_status_code(500),
+        ),
+        // 新建请求已经交出去了，返回 202 和将要使用的 ID，客户端据此查询而不是重试，免得建出两篇
+        Err(_) => match pending {
+            Some(note) => respond(request, json(&note, 202)?),
+            None => respond(request, Response::empty(503)),
+        },
+    }
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(request: &mut Request) -> Result<T> {
+    let mut body = String::new();
+    request
+        .as_reader()
+        .take(MAX_BODY_BYTES)
+        .read_to_string(&mut body)?;
+    Ok(serde_json::from_str(&body)?)
+}
+
+fn json(value: &impl Serialize, status: u16) -> Result<Response<std::io::Cursor<Vec<u8>>>> {
+    Ok(Response::from_data(serde_json::to_vec(value)?)
+        .with_status_code(status)
+        .with_header(header("Content-Type", "application/json")))
+}
+
+fn bad_request(request: Request, error: anyhow::Error) -> Result<()> {
+    let response = Response::from_string(format!("请求格式错误: {}", error)).with_status_code(400);
+    respond(request, response)
+}
+
+fn respond<R: Read>(request: Request, response: Response<R>) -> Result<()> {
+    let response = response
+        .with_header(header("Access-Control-Allow-Origin", "*"))
+        .with_header(header(
+            "Access-Control-Allow-Headers",
+            "Authorization, Content-Type",
+        ))
+        .with_header(header(
+            "Access-Control-Allow-Methods",
+            "GET, POST, PATCH, OPTIONS",
+        ));
+    Ok(request.respond(response)?)
+}
+
+// 逐字节比较所用时间与不匹配的位置无关，免得按响应时间逐位猜出令牌
+fn authorized(request: &Request, token: &str) -> bool {
+    request.headers().iter().any(|h| {
+        h.field.equiv("Authorization")
+            && h.value
+                .as_str()
+                .strip_prefix("Bearer ")
+                .is_some_and(|given| bool::from(given.as_bytes().ct_eq(token.as_bytes())))
+    })
+}
+
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).expect("响应头格式固定，不会出错")
+}
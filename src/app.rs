@@ -1,113 +1,3381 @@
 use crate::{
-    note::Note,
-    note_list::NoteList,
+    actions::{
+        AiGenerateTitle, AiSuggestTags, AiSummarize, CopyNoteMarkdown, DeleteNote, ExportAnki,
+        ExportDocx, ExportEpub, ExportHtml, ExportImage, ExportLatex, ExportSettings,
+        ImportSettings, LockApp, NewNote, NextNote, OpenSettings, PreviousNote, PublishNote, Quit,
+        ReadAloud, RenameNote, SaveNote, SaveWebPage, ShareByEmail, ShowAttachments, ShowBoard,
+        ShowCalendar, ShowDiagnostics, ShowGraph, ShowPlugins, ShowStats, ShowTrash,
+        ToggleClipboardCapture, ToggleHighlight, TogglePreview, ToggleSidebar, UnpublishNote,
+        VerifyData,
+    },
+    ai::{self, AiTask},
+    anki,
+    api::{ApiNote, ApiReply, ApiRequest},
+    attachments::{self, Attachment},
+    card,
+    deep_link::DeepLink,
+    diagnostics,
+    export::{self, ExportFormat},
+    import, markup,
+    note::{DAILY_TITLE_FORMAT, DEFAULT_TITLE, Note, NoteLink},
+    note_index::NoteIndex,
+    note_list::{NoteList, NoteListEvent},
+    ocr,
+    plugins::{Permission, PluginHost, PluginNote},
+    publish::{self, Published},
+    read_later,
+    scripting::{self, Schedule, Script},
+    search_index::SearchIndex,
+    session::Session,
+    settings::AppSettings,
+    share,
+    startup_timing::{StartupPhase, StartupTiming},
+    storage::{NoteConflict, Storage, StorageWriter, TrashedNote, VerifyReport},
+    vault::{Vault, Vaults},
     views::{
-        editor::EditorView,
+        attachments::{AttachmentsEvent, AttachmentsView},
+        board::{BoardEvent, BoardView},
+        calendar::{CalendarEvent, CalendarView},
+        diagnostics::{DiagnosticsEvent, DiagnosticsView},
+        editor::{EditorEvent, EditorView},
+        graph::{GraphEvent, GraphView},
+        integrity::{IntegrityEvent, IntegrityView},
+        lock::{LockEvent, LockView},
+        plugins::{PluginsEvent, PluginsView},
+        settings::{SettingsEvent, SettingsView},
         sidebar::{SidebarEvent, SidebarView},
+        stats::{StatsEvent, StatsView},
+        title_bar::{AppTitleBar, TitleBarEvent},
+        trash::{TrashEvent, TrashView},
     },
+    voice,
+};
+use anyhow::Context as _;
+use chrono::{Local, NaiveDate, Utc};
+use futures::{StreamExt as _, channel::mpsc};
+use gpui::{prelude::FluentBuilder as _, *};
+use gpui_component::{
+    Root, WindowExt as _, button::Button, h_flex, input::Escape, notification::Notification, v_flex,
 };
-use gpui::*;
-use gpui_component::h_flex;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// 剪藏模式把复制的文本追加到这篇笔记
+const CLIPPINGS_TITLE: &str = "剪藏";
+const CLIPBOARD_POLL_INTERVAL: Duration = Duration::from_millis(800);
+/// 检查是否空闲到需要锁定的间隔
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+/// 启动时清理一次回收站，之后每天一次
+const TRASH_PURGE_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+// 右侧主区域当前显示的内容
+enum MainPanel {
+    Editor,
+    Settings(Entity<SettingsView>),
+    Graph(Entity<GraphView>),
+    Calendar(Entity<CalendarView>),
+    Board(Entity<BoardView>),
+    Stats(Entity<StatsView>),
+    Plugins(Entity<PluginsView>),
+    Diagnostics(Entity<DiagnosticsView>),
+    Integrity(Entity<IntegrityView>),
+    Trash(Entity<TrashView>),
+    Attachments(Entity<AttachmentsView>),
+}
+
+type Retry = Rc<dyn Fn(&mut AppView, &mut Window, &mut Context<AppView>)>;
+
+// 操作失败的提示，带重试的提示不会自动消失
+struct ErrorReport {
+    title: SharedString,
+    message: String,
+    retry: Option<Retry>,
+}
 
 pub struct AppView {
+    focus_handle: FocusHandle,
+    title_bar: Entity<AppTitleBar>,
+    sidebar_visible: bool,
     sidebar: Entity<SidebarView>,
     editor: Entity<EditorView>,
     notes: Entity<NoteList>,
+    /// 后台算好的预览和字数
+    index: Entity<NoteIndex>,
+    panel: MainPanel,
+    session: Session,
+    writer: StorageWriter,
+    /// 启动时的后台读取是否已完成
+    loaded: bool,
+    /// 读取完成前收到的链接，读取完成后再打开
+    pending_links: Vec<DeepLink>,
+    /// 剪藏模式开启时轮询剪贴板的任务，丢弃即停止
+    clipboard_capture: Option<Task<()>>,
+    last_clipboard: Option<String>,
+    plugin_host: Option<Arc<PluginHost>>,
+    /// 正在录音的笔记和录音进程
+    recording: Option<(u128, voice::Recording)>,
+    scripts: Vec<Script>,
+    storage: Storage,
+    /// 当前笔记库和全部笔记库，由 main.rs 设置，测试中为 None
+    vaults: Option<(Vault, Vaults)>,
+    /// 失败的操作报告到这里，由窗口显示提示
+    errors: mpsc::UnboundedSender<ErrorReport>,
+    /// 应用锁定时的解锁界面，盖住全部笔记内容
+    lock: Option<Entity<LockView>>,
+    /// 最近一次键盘或鼠标操作，用于空闲锁定
+    last_activity: Instant,
 }
 
 impl AppView {
-    pub fn new(cx: &mut Context<Self>) -> anyhow::Result<Self> {
-        let notes = cx.new(|cx| NoteList::new(cx));
-        let sidebar = cx.new(|_cx| SidebarView::new(notes.clone()));
-        let editor = cx.new(|cx| EditorView::new(cx));
+    /// 笔记、会话、插件和附件都存放在 storage 的数据目录中
+    pub fn new(storage: Storage, window: &mut Window, cx: &mut Context<Self>) -> Self {
+        let title_bar = cx.new(|cx| AppTitleBar::new(window, cx));
+        let notes = cx.new(|cx| NoteList::new(storage.clone(), cx));
+        let index = cx.new(|cx| NoteIndex::new(notes.clone(), cx));
+        let sidebar = cx.new(|cx| SidebarView::new(notes.clone(), index.clone(), cx));
+        let editor =
+            cx.new(|cx| EditorView::new(notes.clone(), storage.attachments_dir(), window, cx));
+        let writer = notes.read(cx).writer().clone();
+
+        window.on_next_frame(|_, cx| StartupTiming::mark(StartupPhase::FirstFrame, cx));
+
+        // 先读上次保存的元数据让侧边栏尽快显示标题，正文、预览和索引随后在后台补上
+        let metadata = {
+            let storage = storage.clone();
+            cx.background_executor()
+                .spawn(async move { storage.load_note_index() })
+        };
+        cx.spawn_in(window, async move |this, cx| {
+            let notes = match metadata.await {
+                Ok(Some(notes)) => notes,
+                Ok(None) => return,
+                Err(e) => {
+                    tracing::warn!("读取笔记索引失败，等待完整加载: {:#}", e);
+                    return;
+                }
+            };
+            this.update_in(cx, |this, window, cx| {
+                this.notes
+                    .update(cx, |list, cx| list.set_metadata(notes, cx));
+                window.on_next_frame(|_, cx| StartupTiming::mark(StartupPhase::Interactive, cx));
+            })
+            .ok();
+        })
+        .detach();
+
+        // 笔记、会话、插件和脚本都在后台读取，读完后再恢复上次的状态
+        let startup = {
+            let storage = storage.clone();
+            cx.background_executor()
+                .spawn(async move { Startup::load(&storage) })
+        };
+        cx.spawn_in(window, async move |this, cx| {
+            let startup = startup.await;
+            this.update_in(cx, |this, window, cx| {
+                this.finish_startup(startup, window, cx)
+            })
+            .ok();
+        })
+        .detach();
 
-        let app = Self {
+        let (errors, mut error_reports) = mpsc::unbounded::<ErrorReport>();
+        cx.spawn_in(window, async move |this, cx| {
+            while let Some(report) = error_reports.next().await {
+                let shown =
+                    this.update_in(cx, |this, window, cx| this.show_error(report, window, cx));
+                if shown.is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+
+        let mut app = Self {
+            focus_handle: cx.focus_handle(),
+            title_bar,
+            sidebar_visible: true,
             sidebar,
             editor,
             notes,
+            index,
+            panel: MainPanel::Editor,
+            session: Session::default(),
+            writer,
+            loaded: false,
+            pending_links: Vec::new(),
+            clipboard_capture: None,
+            last_clipboard: None,
+            plugin_host: None,
+            recording: None,
+            scripts: Vec::new(),
+            storage,
+            vaults: None,
+            errors,
+            lock: None,
+            last_activity: Instant::now(),
         };
 
-        cx.subscribe(
+        cx.subscribe_in(
+            &app.title_bar,
+            window,
+            |this: &mut AppView, _, event: &TitleBarEvent, window, cx| match event {
+                TitleBarEvent::SwitchVault(name) => this.switch_vault(name, cx),
+                TitleBarEvent::AddVault => this.add_vault(window, cx),
+            },
+        )
+        .detach();
+
+        cx.subscribe_in(
+            &app.notes,
+            window,
+            |this: &mut AppView, notes, event: &NoteListEvent, window, cx| match event {
+                NoteListEvent::WritesFailed => {
+                    let Some(failure) = notes.read(cx).write_failure() else {
+                        return;
+                    };
+                    let message = format!(
+                        "{} 项修改尚未写入磁盘，已保留在内存中：{}",
+                        failure.pending, failure.error
+                    );
+                    this.report_error(
+                        "保存失败",
+                        message,
+                        Some(Rc::new(|this, _window, cx| {
+                            if let Err(e) = this.notes.read(cx).retry_writes() {
+                                this.report_error("重试保存失败", format!("{:#}", e), None);
+                            }
+                        })),
+                    );
+                }
+                NoteListEvent::WritesRecovered => {
+                    window.push_notification(Notification::success("未保存的修改已写入磁盘"), cx);
+                }
+                NoteListEvent::Conflict { note_id, copy_id } => {
+                    let notes = notes.read(cx);
+                    let (Some(kept), Some(copy)) = (notes.get(*note_id), notes.get(*copy_id))
+                    else {
+                        return;
+                    };
+                    let (kept, copy) = (kept.title.clone(), copy.title.clone());
+                    this.show_conflict(&kept, &copy, window, cx);
+                    this.reload_editor_note(*note_id, window, cx);
+                }
+                _ => {}
+            },
+        )
+        .detach();
+
+        cx.subscribe_in(
             &app.sidebar,
-            |this: &mut AppView, _, event: &SidebarEvent, cx| {
-                this.handle_sidebar_event(event, cx);
+            window,
+            |this: &mut AppView, _, event: &SidebarEvent, window, cx| {
+                this.handle_sidebar_event(event, window, cx);
+            },
+        )
+        .detach();
+
+        cx.subscribe_in(
+            &app.editor,
+            window,
+            |this: &mut AppView, _, event: &EditorEvent, window, cx| match event {
+                EditorEvent::ContentChanged { note_id, content } => {
+                    this.save_content(*note_id, content.to_string(), cx);
+                }
+                EditorEvent::Rename { note_id, title } => {
+                    this.rename_note(*note_id, title.clone(), cx)
+                }
+                EditorEvent::ToggleRecording => this.toggle_recording(window, cx),
+                EditorEvent::PlayAudio(name) => this.play_audio(name, cx),
+                EditorEvent::OpenLink(link) => this.open_note_link(link.clone(), window, cx),
+                EditorEvent::DirtyChanged(dirty) => {
+                    let dirty = *dirty;
+                    this.title_bar
+                        .update(cx, |title_bar, cx| title_bar.set_dirty(dirty, cx));
+                }
+                EditorEvent::TagsChanged { note_id, tags } => {
+                    this.set_tags(*note_id, tags.clone(), cx)
+                }
+                EditorEvent::DueDateChanged { note_id, due_date } => {
+                    this.set_due_date(*note_id, *due_date, cx)
+                }
             },
         )
         .detach();
 
-        // TODO：订阅edit事件
+        cx.observe_global::<AppSettings>(|this: &mut AppView, cx| {
+            diagnostics::set_enabled(AppSettings::get(cx).diagnostics.enabled);
+            // 从手动保存切回自动保存时，把攒下的更改保存掉
+            if !AppSettings::get(cx).save.manual {
+                this.save_unsaved(cx);
+            }
+            if this.loaded {
+                this.sync_search_index(cx);
+            }
+        })
+        .detach();
+
+        // 切到别的应用时立即写盘，不等合并写入的延迟
+        cx.observe_window_activation(window, |this: &mut AppView, window, cx| {
+            if !window.is_window_active() {
+                if !AppSettings::get(cx).save.manual {
+                    this.save_unsaved(cx);
+                }
+                this.notes.read(cx).save_note_index();
+                // 刷新请求已经发出，不必等待写完
+                drop(this.writer.flush());
+            }
+        })
+        .detach();
+
+        cx.observe(&app.index, |this: &mut AppView, index, cx| {
+            if this.loaded && index.read(cx).is_idle() {
+                StartupTiming::mark(StartupPhase::PreviewsReady, cx);
+            }
+        })
+        .detach();
+
+        cx.spawn_in(window, async move |this, cx| {
+            loop {
+                cx.background_executor().timer(IDLE_CHECK_INTERVAL).await;
+                let result = this.update_in(cx, |this, window, cx| this.lock_if_idle(window, cx));
+                if result.is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+
+        // 手动保存模式下还有未保存的更改时，关闭窗口和退出一样先询问
+        let view = cx.entity().downgrade();
+        window.on_window_should_close(cx, move |window, cx| {
+            let unsaved = view
+                .upgrade()
+                .is_some_and(|view| view.read(cx).editor.read(cx).unsaved_count() > 0);
+            if unsaved {
+                window.dispatch_action(Box::new(Quit), cx);
+            }
+            !unsaved
+        });
+
+        cx.on_app_quit(|this: &mut AppView, cx| {
+            // 等待中的自动保存立即保存；手动保存模式下已在退出前询问过
+            if !AppSettings::get(cx).save.manual {
+                this.save_unsaved(cx);
+            }
+            this.remember_cursor(cx);
+            this.save_session();
+            this.notes.read(cx).save_note_index();
+            // 退出前再试一次之前写盘失败的修改
+            if this.notes.read(cx).write_failure().is_some() {
+                this.notes.read(cx).retry_writes().ok();
+            }
+            // 退出时让 ffmpeg 写完文件，录音本身留在附件目录里
+            let recording = this.recording.take().map(|(_, recording)| recording);
+            // 等后台把排队的写操作做完再退出
+            let flush = this.writer.flush();
+            async move {
+                // 正在退出，来不及显示通知，只记进日志
+                if let Some(Err(e)) = recording.map(|recording| recording.stop()) {
+                    tracing::error!("结束录音失败: {:#}", e);
+                }
+                flush.await;
+            }
+        })
+        .detach();
 
-        Ok(app)
+        if AppSettings::get(cx).lock.is_enabled() {
+            app.lock_app(window, cx);
+        }
+        app
     }
 
-    fn handle_sidebar_event(&mut self, event: &SidebarEvent, cx: &mut Context<Self>) {
-        match event {
-            SidebarEvent::CreateNote => self.create_note(cx),
-            SidebarEvent::DeleteNote(note_id) => self.delete_note(*note_id, cx),
-            SidebarEvent::SelectNote(note_id) => self.select_note(*note_id, cx),
+    pub fn open_deep_link(&mut self, link: DeepLink, window: &mut Window, cx: &mut Context<Self>) {
+        tracing::debug!(loaded = self.loaded, "打开链接 {:?}", link);
+        if !self.loaded {
+            self.pending_links.push(link);
+            return;
+        }
+        match link {
+            DeepLink::Open(note_id) => {
+                if self.notes.read(cx).get(note_id).is_some() {
+                    self.select_note(note_id, window, cx);
+                } else {
+                    tracing::warn!("找不到笔记 {}", note_id);
+                    window.push_notification(
+                        Notification::warning("链接指向的笔记不存在，可能已被删除"),
+                        cx,
+                    );
+                }
+            }
+            DeepLink::New { title, body } => {
+                let mut note = Note::new();
+                if let Some(title) = title {
+                    note.title = title;
+                }
+                note.content = body.unwrap_or_default();
+                self.add_note(note, window, cx);
+            }
+            DeepLink::ReadLater(url) => self.confirm_save_web_page(url, window, cx),
+            DeepLink::Import(path) => self.import_files(&[path], window, cx),
         }
     }
 
-    fn create_note(&mut self, cx: &mut Context<Self>) {
-        let note = Note::new();
-        let id = note.id;
+    pub fn set_vaults(&mut self, current: Vault, vaults: Vaults, cx: &mut Context<Self>) {
+        let names = vaults
+            .all()
+            .into_iter()
+            .map(|vault| vault.name.into())
+            .collect();
+        self.title_bar.update(cx, |title_bar, cx| {
+            title_bar.set_vaults(current.name.clone().into(), names, cx)
+        });
+        self.vaults = Some((current, vaults));
+    }
 
-        if let Err(e) = self.notes.update(cx, |notes, _cx| notes.add(note.clone())) {
-            eprintln!("保存新笔记失败: {}", e);
+    // 笔记列表、设置、插件和本机接口都跟着数据目录走，记下目标后重启最稳妥；
+    // 退出时照常保存会话并写完排队的修改
+    fn switch_vault(&mut self, name: &str, cx: &mut Context<Self>) {
+        let Some((current, vaults)) = &mut self.vaults else {
+            return;
+        };
+        if current.name == name {
             return;
         }
+        let result = vaults
+            .resolve(Some(name))
+            .and_then(|vault| vaults.remember(&vault));
+        match result {
+            Ok(_) => cx.restart(),
+            Err(e) => self.report_error("切换笔记库失败", format!("{:#}", e), None),
+        }
+    }
 
-        self.sidebar.update(cx, |sidebar, _cx| {
-            sidebar.set_selected(Some(id));
+    fn add_vault(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: false,
+            directories: true,
+            multiple: false,
+            prompt: Some("打开为笔记库".into()),
         });
+        cx.spawn_in(window, async move |this, cx| {
+            let Ok(Ok(Some(paths))) = paths.await else {
+                return;
+            };
+            let Some(path) = paths.into_iter().next() else {
+                return;
+            };
+            this.update(cx, |this, cx| {
+                let Some((_, vaults)) = &mut this.vaults else {
+                    return;
+                };
+                match vaults.add(path) {
+                    Ok(vault) => this.switch_vault(&vault.name, cx),
+                    Err(e) => this.report_error("添加笔记库失败", format!("{:#}", e), None),
+                }
+            })
+            .ok();
+        })
+        .detach();
+    }
 
-        self.editor.update(cx, |editor, cx| {
-            editor.load_note(&note, cx);
+    /// 处理本机接口的请求，新建或修改笔记时不打断当前编辑
+    pub fn handle_api_request(
+        &mut self,
+        request: ApiRequest,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> ApiReply {
+        let reply = match request {
+            ApiRequest::List | ApiRequest::Search(_) => {
+                let notes = self.notes.read(cx);
+                let mut found: Vec<&Note> = match &request {
+                    ApiRequest::Search(query) => notes
+                        .get_all()
+                        .into_iter()
+                        .filter(|note| note.matches(query))
+                        .collect(),
+                    _ => notes.get_all(),
+                };
+                found.sort_by_key(|note| std::cmp::Reverse(note.updated_at));
+                return ApiReply::Notes(found.into_iter().map(ApiNote::from).collect());
+            }
+            ApiRequest::Create(note) => {
+                let reply = ApiReply::Note(ApiNote::from(&note));
+                match self.notes.update(cx, |notes, cx| notes.add(note, cx)) {
+                    Ok(()) => reply,
+                    Err(e) => ApiReply::Error(format!("保存笔记失败: {}", e)),
+                }
+            }
+            ApiRequest::Update { id, patch } => {
+                if self.notes.read(cx).get(id).is_none() {
+                    return ApiReply::NotFound;
+                }
+                let result = self.notes.update(cx, |notes, cx| {
+                    notes.update(
+                        id,
+                        |note| {
+                            if let Some(title) = patch.title {
+                                note.title = title;
+                            }
+                            if let Some(content) = patch.content {
+                                note.content = content;
+                            }
+                            if let Some(tags) = patch.tags {
+                                note.tags = tags;
+                            }
+                            if let Some(due_date) = patch.due_date {
+                                note.due_date = due_date;
+                            }
+                            note.updated_at = Utc::now();
+                        },
+                        cx,
+                    )
+                });
+                match result {
+                    Ok(()) => {
+                        self.reload_editor_note(id, window, cx);
+                        match self.notes.read(cx).get(id) {
+                            Some(note) => ApiReply::Note(ApiNote::from(note)),
+                            None => ApiReply::NotFound,
+                        }
+                    }
+                    Err(e) => ApiReply::Error(format!("保存笔记失败: {}", e)),
+                }
+            }
+        };
+        cx.notify();
+        reply
+    }
+
+    /// 重新打开上次退出时正在编辑的笔记
+    fn finish_startup(&mut self, startup: Startup, window: &mut Window, cx: &mut Context<Self>) {
+        self.notes
+            .update(cx, |notes, cx| notes.set_loaded(startup.notes, cx));
+        StartupTiming::mark(StartupPhase::NotesLoaded, cx);
+        // 没有元数据可用时（例如第一次启动），读完正文才算可交互；
+        // 下一帧时摘要任务已经排上，仍然空闲说明没有笔记需要计算
+        let index = self.index.clone();
+        window.on_next_frame(move |_, cx| {
+            StartupTiming::mark(StartupPhase::Interactive, cx);
+            if index.read(cx).is_idle() {
+                StartupTiming::mark(StartupPhase::PreviewsReady, cx);
+            }
         });
+        self.session = startup.session;
+        self.plugin_host = startup.plugin_host;
+        self.scripts = startup.scripts;
+        self.loaded = true;
+
+        for conflict in &startup.conflicts {
+            self.show_conflict(&conflict.kept.title, &conflict.copy.title, window, cx);
+        }
+        self.sync_search_index(cx);
+        self.restore_session(window, cx);
+        self.review_plugin_permissions(window, cx);
+        self.start_script_scheduler(window, cx);
+        self.start_trash_purge(window, cx);
+        for link in std::mem::take(&mut self.pending_links) {
+            self.open_deep_link(link, window, cx);
+        }
+        cx.notify();
+    }
 
+    fn show_conflict(&self, kept: &str, copy: &str, window: &mut Window, cx: &mut Context<Self>) {
+        window.push_notification(
+            Notification::warning(format!(
+                "「{}」在另一处被修改过，已保留较新的版本，另一个版本另存为「{}」",
+                kept, copy
+            ))
+            .title("笔记版本冲突"),
+            cx,
+        );
+    }
+
+    // 载入期间在侧边栏点过的笔记优先于上次会话
+    fn restore_session(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let selected = self.notes.read(cx).selected();
+        if let Some(note_id) = selected.or(self.session.last_note_id) {
+            self.select_note(note_id, window, cx);
+        }
+    }
+
+    fn handle_sidebar_event(
+        &mut self,
+        event: &SidebarEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        // 正文读完前侧边栏只显示元数据，点选只记下选中项，读完后再打开
+        if !self.loaded {
+            match event {
+                SidebarEvent::SelectNote(note_id) => self
+                    .notes
+                    .update(cx, |notes, cx| notes.select(Some(*note_id), cx)),
+                SidebarEvent::OpenSettings => self.open_settings(window, cx),
+                _ => {}
+            }
+            return;
+        }
+        match event {
+            SidebarEvent::CreateNote => self.create_note(None, window, cx),
+            SidebarEvent::CreateChildNote(parent) => self.create_note(Some(*parent), window, cx),
+            SidebarEvent::MoveNote { note_id, parent } => self.move_note(*note_id, *parent, cx),
+            SidebarEvent::DeleteNote(note_id) => self.delete_note(*note_id, window, cx),
+            SidebarEvent::SelectNote(note_id) => self.select_note(*note_id, window, cx),
+            SidebarEvent::OpenSettings => self.open_settings(window, cx),
+            SidebarEvent::FocusEditor => {
+                self.show_editor(cx);
+                self.editor
+                    .update(cx, |editor, cx| editor.focus(window, cx));
+            }
+        }
+    }
+
+    fn on_new_note(&mut self, _: &NewNote, window: &mut Window, cx: &mut Context<Self>) {
+        self.create_note(None, window, cx);
+    }
+
+    fn on_save_note(&mut self, _: &SaveNote, _window: &mut Window, cx: &mut Context<Self>) {
+        self.save_unsaved(cx);
+        // 手动保存时用户期望立即写盘，不等合并写入的延迟
+        drop(self.writer.flush());
+    }
+
+    fn on_toggle_highlight(
+        &mut self,
+        _: &ToggleHighlight,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.editor
+            .update(cx, |editor, cx| editor.toggle_highlight(window, cx));
+    }
+
+    fn on_toggle_preview(
+        &mut self,
+        _: &TogglePreview,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.editor
+            .update(cx, |editor, cx| editor.toggle_preview(window, cx));
+    }
+
+    fn on_read_aloud(&mut self, _: &ReadAloud, window: &mut Window, cx: &mut Context<Self>) {
+        self.show_editor(cx);
+        self.editor
+            .update(cx, |editor, cx| editor.toggle_read_aloud(window, cx));
+    }
+
+    fn on_lock_app(&mut self, _: &LockApp, window: &mut Window, cx: &mut Context<Self>) {
+        if !AppSettings::get(cx).lock.is_enabled() {
+            window.push_notification(
+                Notification::info("请先在设置的“应用锁”中设置密码").title("无法锁定"),
+                cx,
+            );
+            return;
+        }
+        self.lock_app(window, cx);
+    }
+
+    fn lock_app(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.lock.is_some() {
+            return;
+        }
+        // 锁定后不再读出笔记内容
+        self.editor
+            .update(cx, |editor, cx| editor.stop_read_aloud(cx));
+        let lock = cx.new(|cx| LockView::new(window, cx));
+        cx.subscribe_in(
+            &lock,
+            window,
+            |this: &mut AppView, _, event: &LockEvent, window, cx| match event {
+                LockEvent::Unlocked => this.unlock_app(window, cx),
+            },
+        )
+        .detach();
+        lock.update(cx, |lock, cx| lock.focus(window, cx));
+        self.lock = Some(lock);
+        self.title_bar
+            .update(cx, |title_bar, cx| title_bar.set_locked(true, cx));
+        cx.notify();
+    }
+
+    fn unlock_app(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.lock = None;
+        self.last_activity = Instant::now();
+        self.title_bar
+            .update(cx, |title_bar, cx| title_bar.set_locked(false, cx));
+        if matches!(self.panel, MainPanel::Editor) {
+            self.editor
+                .update(cx, |editor, cx| editor.focus(window, cx));
+        }
         cx.notify();
     }
 
-    fn delete_note(&mut self, note_id: u128, cx: &mut Context<Self>) {
-        if let Err(e) = self.notes.update(cx, |notes, _cx| notes.remove(note_id)) {
-            eprintln!("删除笔记失败: {}", e);
+    fn lock_if_idle(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(timeout) = AppSettings::get(cx).lock.idle_timeout() else {
             return;
+        };
+        if self.last_activity.elapsed() >= timeout {
+            self.lock_app(window, cx);
         }
+    }
 
-        self.sidebar.update(cx, |sidebar, _cx| {
-            sidebar.set_selected(None);
-        });
+    fn on_quit(&mut self, _: &Quit, window: &mut Window, cx: &mut Context<Self>) {
+        let unsaved = self.editor.read(cx).unsaved_count();
+        if unsaved == 0 {
+            cx.quit();
+            return;
+        }
+        let answer = window.prompt(
+            PromptLevel::Warning,
+            &format!("有 {} 篇笔记的更改尚未保存", unsaved),
+            Some("不保存的话，这些更改会丢失。"),
+            &["保存并退出", "不保存", "取消"],
+            cx,
+        );
+        cx.spawn_in(window, async move |this, cx| {
+            match answer.await {
+                Ok(0) => {
+                    this.update(cx, |this, cx| this.save_unsaved(cx)).ok();
+                }
+                Ok(1) => {}
+                _ => return,
+            }
+            cx.update(|_, cx| cx.quit()).ok();
+        })
+        .detach();
+    }
+
+    // 快捷键容易误按，先确认再删除
+    fn on_delete_note(&mut self, _: &DeleteNote, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(note) = self
+            .notes
+            .read(cx)
+            .selected()
+            .and_then(|note_id| self.notes.read(cx).get(note_id))
+        else {
+            return;
+        };
+        let note_id = note.id;
+        let answer = window.prompt(
+            PromptLevel::Warning,
+            &format!("删除「{}」？", note.title),
+            Some("笔记会移到回收站，可以从回收站恢复。"),
+            &["删除", "取消"],
+            cx,
+        );
+        cx.spawn_in(window, async move |this, cx| {
+            if answer.await != Ok(0) {
+                return;
+            }
+            this.update_in(cx, |this, window, cx| this.delete_note(note_id, window, cx))
+                .ok();
+        })
+        .detach();
+    }
+
+    fn on_rename_note(&mut self, _: &RenameNote, window: &mut Window, cx: &mut Context<Self>) {
+        if self.current_note_id(cx).is_none() {
+            return;
+        }
+        self.show_editor(cx);
+        self.editor
+            .update(cx, |editor, cx| editor.start_rename(window, cx));
+    }
+
+    fn on_previous_note(&mut self, _: &PreviousNote, window: &mut Window, cx: &mut Context<Self>) {
+        self.select_adjacent(false, window, cx);
+    }
+
+    fn on_next_note(&mut self, _: &NextNote, window: &mut Window, cx: &mut Context<Self>) {
+        self.select_adjacent(true, window, cx);
+    }
+
+    // 按侧边栏的显示顺序切换笔记，焦点留在原处，可以连续切换
+    fn select_adjacent(&mut self, forward: bool, window: &mut Window, cx: &mut Context<Self>) {
+        if !self.loaded {
+            return;
+        }
+        if let Some(note_id) = self.sidebar.read(cx).adjacent(forward) {
+            self.select_note(note_id, window, cx);
+        }
+    }
+
+    // 正文编辑器中按 Esc 回到侧边栏，之后可以用方向键挑选笔记
+    fn on_escape(&mut self, _: &Escape, window: &mut Window, cx: &mut Context<Self>) {
+        if matches!(self.panel, MainPanel::Editor) && self.sidebar_visible {
+            window.focus(&self.sidebar.focus_handle(cx));
+        } else {
+            cx.propagate();
+        }
+    }
+
+    fn on_open_settings(&mut self, _: &OpenSettings, window: &mut Window, cx: &mut Context<Self>) {
+        self.open_settings(window, cx);
+    }
+
+    fn on_show_graph(&mut self, _: &ShowGraph, window: &mut Window, cx: &mut Context<Self>) {
+        self.show_graph(window, cx);
+    }
+
+    fn on_show_calendar(&mut self, _: &ShowCalendar, window: &mut Window, cx: &mut Context<Self>) {
+        self.show_calendar(window, cx);
+    }
+
+    fn on_show_board(&mut self, _: &ShowBoard, window: &mut Window, cx: &mut Context<Self>) {
+        self.show_board(window, cx);
+    }
+
+    fn on_show_stats(&mut self, _: &ShowStats, window: &mut Window, cx: &mut Context<Self>) {
+        self.show_stats(window, cx);
+    }
+
+    fn on_show_plugins(&mut self, _: &ShowPlugins, window: &mut Window, cx: &mut Context<Self>) {
+        self.show_plugins(window, cx);
+    }
+
+    fn on_show_diagnostics(
+        &mut self,
+        _: &ShowDiagnostics,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let diagnostics = cx.new(DiagnosticsView::new);
+        cx.subscribe_in(
+            &diagnostics,
+            window,
+            |this: &mut AppView, _, event: &DiagnosticsEvent, _window, cx| match event {
+                DiagnosticsEvent::Close => this.show_editor(cx),
+            },
+        )
+        .detach();
+        self.panel = MainPanel::Diagnostics(diagnostics);
+        cx.notify();
+    }
+
+    // 在后台扫描全部笔记文件，扫描前先把排队的写操作写完
+    fn on_show_trash(&mut self, _: &ShowTrash, window: &mut Window, cx: &mut Context<Self>) {
+        let storage = self.storage.clone();
+        // 刚删除的笔记可能还在写入队列里
+        let flush = self.writer.flush();
+        cx.spawn_in(window, async move |this, cx| {
+            flush.await;
+            let trashed = cx
+                .background_executor()
+                .spawn(async move { storage.load_trash() })
+                .await;
+            this.update_in(cx, |this, window, cx| match trashed {
+                Ok(trashed) => this.show_trash(trashed, window, cx),
+                Err(e) => this.report_error("打开回收站失败", format!("{:#}", e), None),
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn show_trash(
+        &mut self,
+        trashed: Vec<TrashedNote>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let trash = cx.new(|_| TrashView::new(trashed));
+        cx.subscribe_in(
+            &trash,
+            window,
+            |this: &mut AppView, trash, event: &TrashEvent, window, cx| match event {
+                TrashEvent::Restore(note_id) => {
+                    this.restore_trashed(trash.clone(), *note_id, window, cx)
+                }
+                TrashEvent::Delete(note_id) => {
+                    let note_id = *note_id;
+                    trash.update(cx, |trash, cx| trash.take(note_id, cx));
+                    let storage = this.storage.clone();
+                    if let Err(e) = this.writer.run(move || storage.remove_from_trash(note_id)) {
+                        this.report_error("删除笔记失败", format!("{:#}", e), None);
+                    }
+                }
+                TrashEvent::Empty => this.empty_trash(trash.clone(), window, cx),
+                TrashEvent::Close => this.show_editor(cx),
+            },
+        )
+        .detach();
+        self.panel = MainPanel::Trash(trash);
+        cx.notify();
+    }
+
+    // 恢复的笔记照常通过 NoteList 保存，写入后再从回收站移除
+    fn restore_trashed(
+        &mut self,
+        trash: Entity<TrashView>,
+        note_id: u128,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(trashed) = trash.update(cx, |trash, cx| trash.take(note_id, cx)) else {
+            return;
+        };
+        let title = trashed.note.title.clone();
+        if let Err(e) = self
+            .notes
+            .update(cx, |notes, cx| notes.add(trashed.note, cx))
+        {
+            self.report_error("恢复笔记失败", format!("{:#}", e), None);
+            return;
+        }
+        let storage = self.storage.clone();
+        if let Err(e) = self.writer.run(move || storage.remove_from_trash(note_id)) {
+            self.report_error("恢复笔记失败", format!("{:#}", e), None);
+        }
+        window.push_notification(Notification::success(format!("已恢复「{}」", title)), cx);
+    }
+
+    fn empty_trash(
+        &mut self,
+        trash: Entity<TrashView>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let answer = window.prompt(
+            PromptLevel::Warning,
+            "清空回收站？",
+            Some("回收站中的笔记将被永久删除，无法恢复。"),
+            &["清空", "取消"],
+            cx,
+        );
+        cx.spawn_in(window, async move |this, cx| {
+            if answer.await != Ok(0) {
+                return;
+            }
+            this.update(cx, |this, cx| {
+                let trashed: Vec<u128> = trash.update(cx, |trash, cx| {
+                    let ids = trash.note_ids();
+                    trash.clear(cx);
+                    ids
+                });
+                let storage = this.storage.clone();
+                let result = this.writer.run(move || {
+                    trashed
+                        .iter()
+                        .try_for_each(|note_id| storage.remove_from_trash(*note_id))
+                });
+                if let Err(e) = result {
+                    this.report_error("清空回收站失败", format!("{:#}", e), None);
+                }
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    // 按设置的保留天数永久删除到期的笔记，保留天数为 0 时不清理
+    fn start_trash_purge(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        cx.spawn_in(window, async move |this, cx| {
+            loop {
+                let purged = this.update(cx, |this, cx| {
+                    let Some(retention) = AppSettings::get(cx).trash.retention() else {
+                        return;
+                    };
+                    let storage = this.storage.clone();
+                    let result = this.writer.run(move || {
+                        let purged = storage.purge_trash(retention, Utc::now())?;
+                        if purged > 0 {
+                            tracing::info!("回收站中 {} 篇笔记已到期，永久删除", purged);
+                        }
+                        Ok(())
+                    });
+                    if let Err(e) = result {
+                        tracing::error!("清理回收站失败: {:#}", e);
+                    }
+                });
+                if purged.is_err() {
+                    break;
+                }
+                cx.background_executor().timer(TRASH_PURGE_INTERVAL).await;
+            }
+        })
+        .detach();
+    }
+
+    fn on_show_attachments(
+        &mut self,
+        _: &ShowAttachments,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let mut owners = HashMap::new();
+        attachments::add_owners(&mut owners, self.notes.read(cx).get_all(), false);
+        let storage = self.storage.clone();
+        // 回收站在写入队列之后读取，刚删除的笔记也算在内
+        let flush = self.writer.flush();
+        cx.spawn_in(window, async move |this, cx| {
+            flush.await;
+            let scanned = cx
+                .background_executor()
+                .spawn(async move {
+                    let trashed = storage.load_trash()?;
+                    attachments::add_owners(
+                        &mut owners,
+                        trashed.iter().map(|trashed| &trashed.note),
+                        true,
+                    );
+                    attachments::scan(&storage.attachments_dir(), owners)
+                })
+                .await;
+            this.update_in(cx, |this, window, cx| match scanned {
+                Ok(scanned) => this.show_attachments(scanned, window, cx),
+                Err(e) => this.report_error("读取附件失败", format!("{:#}", e), None),
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn show_attachments(
+        &mut self,
+        scanned: Vec<Attachment>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let view = cx.new(|_| AttachmentsView::new(scanned));
+        cx.subscribe_in(
+            &view,
+            window,
+            |this: &mut AppView, view, event: &AttachmentsEvent, window, cx| match event {
+                AttachmentsEvent::OpenNote(note_id) => this.select_note(*note_id, window, cx),
+                AttachmentsEvent::Delete(name) => {
+                    let referenced = view.read(cx).get(name).map_or(0, |a| a.owners.len());
+                    let detail = if referenced > 0 {
+                        format!(
+                            "仍有 {} 篇笔记引用这个附件，删除后笔记中将无法显示。",
+                            referenced
+                        )
+                    } else {
+                        "删除后无法恢复。".to_string()
+                    };
+                    this.delete_attachments(
+                        view.clone(),
+                        vec![name.clone()],
+                        format!("删除附件「{}」？", name),
+                        detail,
+                        window,
+                        cx,
+                    );
+                }
+                AttachmentsEvent::CleanOrphans => {
+                    let orphans = view.read(cx).orphans();
+                    let message = format!("删除 {} 个未被引用的附件？", orphans.len());
+                    this.delete_attachments(
+                        view.clone(),
+                        orphans,
+                        message,
+                        "这些文件没有被任何笔记或回收站中的笔记引用，删除后无法恢复。".to_string(),
+                        window,
+                        cx,
+                    );
+                }
+                AttachmentsEvent::Close => this.show_editor(cx),
+            },
+        )
+        .detach();
+        self.panel = MainPanel::Attachments(view);
+        cx.notify();
+    }
+
+    fn delete_attachments(
+        &mut self,
+        view: Entity<AttachmentsView>,
+        names: Vec<String>,
+        message: String,
+        detail: String,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let answer = window.prompt(
+            PromptLevel::Warning,
+            &message,
+            Some(&detail),
+            &["删除", "取消"],
+            cx,
+        );
+        cx.spawn_in(window, async move |this, cx| {
+            if answer.await != Ok(0) {
+                return;
+            }
+            this.update(cx, |this, cx| {
+                let mut deleted = Vec::new();
+                for name in names {
+                    match this.storage.delete_attachment(&name) {
+                        Ok(()) => deleted.push(name),
+                        Err(e) => this.report_error("删除附件失败", format!("{:#}", e), None),
+                    }
+                }
+                view.update(cx, |view, cx| view.remove(&deleted, cx));
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn on_verify_data(&mut self, _: &VerifyData, window: &mut Window, cx: &mut Context<Self>) {
+        let storage = self.storage.clone();
+        window.push_notification(Notification::info("正在验证数据…"), cx);
+        let flush = self.writer.flush();
+        cx.spawn_in(window, async move |this, cx| {
+            flush.await;
+            let report = cx
+                .background_executor()
+                .spawn(async move { storage.verify() })
+                .await;
+            this.update_in(cx, |this, window, cx| match report {
+                Ok(report) => this.show_integrity(report, window, cx),
+                Err(e) => this.report_error("验证数据失败", format!("{:#}", e), None),
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn show_integrity(
+        &mut self,
+        report: VerifyReport,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let integrity = cx.new(|_| IntegrityView::new(report));
+        cx.subscribe_in(
+            &integrity,
+            window,
+            |this: &mut AppView, integrity, event: &IntegrityEvent, window, cx| match event {
+                IntegrityEvent::Restore(note_id) => {
+                    this.restore_note(integrity.clone(), *note_id, window, cx)
+                }
+                IntegrityEvent::Close => this.show_editor(cx),
+            },
+        )
+        .detach();
+        self.panel = MainPanel::Integrity(integrity);
+        cx.notify();
+    }
 
-        self.editor.update(cx, |editor, _cx| {
-            editor.clear();
+    // 用备份替换损坏的笔记，照常通过 NoteList 保存，写入新文件和校验和
+    fn restore_note(
+        &mut self,
+        integrity: Entity<IntegrityView>,
+        note_id: u128,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let backup = integrity.read(cx).backup(note_id).cloned();
+        let Some(backup) = backup else {
+            return;
+        };
+        let existing = self.notes.read(cx).get(note_id).is_some();
+        let result = self.notes.update(cx, |notes, cx| {
+            if existing {
+                notes.update(note_id, |note| *note = backup.clone(), cx)
+            } else {
+                notes.add(backup.clone(), cx)
+            }
         });
+        if let Err(e) = result {
+            self.report_error("恢复笔记失败", format!("{:#}", e), None);
+            return;
+        }
+        if self.editor.read(cx).note_id() == Some(note_id) {
+            self.editor
+                .update(cx, |editor, cx| editor.load_note(&backup, window, cx));
+        }
+        integrity.update(cx, |integrity, cx| integrity.mark_restored(note_id, cx));
+    }
 
+    fn on_toggle_sidebar(
+        &mut self,
+        _: &ToggleSidebar,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.sidebar_visible = !self.sidebar_visible;
         cx.notify();
     }
 
-    fn select_note(&mut self, note_id: u128, cx: &mut Context<Self>) {
-        let note_clone = self
+    fn on_toggle_clipboard_capture(
+        &mut self,
+        _: &ToggleClipboardCapture,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.clipboard_capture.take().is_none() {
+            // 开启前已经在剪贴板里的内容不算
+            self.last_clipboard = cx.read_from_clipboard().and_then(|item| item.text());
+            self.clipboard_capture = Some(cx.spawn_in(window, async move |this, cx| {
+                loop {
+                    cx.background_executor()
+                        .timer(CLIPBOARD_POLL_INTERVAL)
+                        .await;
+                    let result =
+                        this.update_in(cx, |this, window, cx| this.capture_clipboard(window, cx));
+                    if result.is_err() {
+                        break;
+                    }
+                }
+            }));
+        }
+        let capturing = self.clipboard_capture.is_some();
+        self.title_bar.update(cx, |title_bar, cx| {
+            title_bar.set_capturing(capturing, cx);
+        });
+    }
+
+    fn capture_clipboard(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(text) = cx.read_from_clipboard().and_then(|item| item.text()) else {
+            return;
+        };
+        if text.trim().is_empty() || self.last_clipboard.as_deref() == Some(text.as_str()) {
+            return;
+        }
+        self.last_clipboard = Some(text.clone());
+
+        let entry = format!(
+            "> {}\n\n{}\n",
+            Local::now().format("%Y-%m-%d %H:%M:%S"),
+            text.trim_end()
+        );
+        let existing = self
             .notes
-            .update(cx, |notes, _cx| notes.get(note_id).cloned());
+            .read(cx)
+            .find_by_title(CLIPPINGS_TITLE)
+            .map(|note| note.id);
+        let result = self.notes.update(cx, |notes, cx| match existing {
+            Some(note_id) => notes.update(
+                note_id,
+                |note| {
+                    if !note.content.is_empty() {
+                        note.content.push('\n');
+                    }
+                    note.content.push_str(&entry);
+                    note.updated_at = Utc::now();
+                },
+                cx,
+            ),
+            None => {
+                let mut note = Note::new();
+                note.title = CLIPPINGS_TITLE.to_string();
+                note.content = entry;
+                notes.add(note, cx)
+            }
+        });
+        if let Err(e) = result {
+            self.report_error("保存剪藏失败", format!("{:#}", e), None);
+            return;
+        }
+        if let Some(note_id) = existing {
+            self.reload_editor_note(note_id, window, cx);
+        }
+        cx.notify();
+    }
 
-        if let Some(note) = note_clone {
-            self.editor.update(cx, |editor, cx| {
-                editor.load_note(&note, cx);
-            });
-            self.sidebar.update(cx, |sidebar, _cx| {
-                sidebar.set_selected(Some(note_id));
+    // 正在编辑的笔记通过编辑器追加，保留撤销历史和光标；其他笔记直接修改
+    fn append_to_note(
+        &mut self,
+        note_id: u128,
+        text: &str,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> anyhow::Result<()> {
+        if self.editor.read(cx).note_id() == Some(note_id) {
+            self.editor
+                .update(cx, |editor, cx| editor.append_line(text, window, cx));
+            return Ok(());
+        }
+        self.notes.update(cx, |notes, cx| {
+            notes.update(
+                note_id,
+                |note| {
+                    if !note.content.is_empty() && !note.content.ends_with('\n') {
+                        note.content.push('\n');
+                    }
+                    note.content.push_str(text);
+                    note.updated_at = Utc::now();
+                },
+                cx,
+            )
+        })
+    }
+
+    // 笔记在编辑器之外被修改后，如果正在编辑它就重新载入，并保持光标位置
+    fn reload_editor_note(&mut self, note_id: u128, window: &mut Window, cx: &mut Context<Self>) {
+        if self.editor.read(cx).note_id() != Some(note_id) {
+            return;
+        }
+        let Some(note) = self.notes.read(cx).get(note_id).cloned() else {
+            return;
+        };
+        self.editor.update(cx, |editor, cx| {
+            let position = editor.cursor_position(cx);
+            editor.load_note(&note, window, cx);
+            editor.set_cursor_position(position, window, cx);
+        });
+    }
+
+    fn set_title(&mut self, title: Option<String>, cx: &mut Context<Self>) {
+        self.title_bar.update(cx, |title_bar, cx| {
+            title_bar.set_note_title(title, cx);
+        });
+    }
+
+    fn show_editor(&mut self, cx: &mut Context<Self>) {
+        self.panel = MainPanel::Editor;
+        cx.notify();
+    }
+
+    /// 报告失败的操作：记录日志并在窗口中提示原因，提供 retry 时显示重试按钮
+    fn report_error(&self, title: &str, message: String, retry: Option<Retry>) {
+        tracing::error!("{}: {}", title, message);
+        let report = ErrorReport {
+            title: title.to_string().into(),
+            message,
+            retry,
+        };
+        self.errors.unbounded_send(report).ok();
+    }
+
+    fn show_error(&mut self, report: ErrorReport, window: &mut Window, cx: &mut Context<Self>) {
+        let view = cx.entity().downgrade();
+        let mut notification = Notification::error(report.message)
+            .title(report.title.clone())
+            .id1::<ErrorReport>(report.title);
+        if let Some(retry) = report.retry {
+            notification = notification.autohide(false).action(move |_, _, cx| {
+                let (view, retry) = (view.clone(), retry.clone());
+                Button::new("retry").label("重试").on_click(cx.listener(
+                    move |notification, _, window, cx| {
+                        notification.dismiss(window, cx);
+                        view.update(cx, |this, cx| retry(this, window, cx)).ok();
+                    },
+                ))
             });
-            cx.notify();
         }
+        window.push_notification(notification, cx);
     }
-}
 
-impl Render for AppView {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
-        h_flex()
-            .size_full()
-            .bg(gpui::rgb(0xffffff))
-            .child(div().w(px(280.0)).h_full().child(self.sidebar.clone()))
-            .child(div().flex_1().h_full().child(self.editor.clone()))
+    fn on_export_settings(
+        &mut self,
+        _: &ExportSettings,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let json = match AppSettings::get(cx).export_json() {
+            Ok(json) => json,
+            Err(e) => {
+                self.report_error("导出设置失败", format!("{:#}", e), None);
+                return;
+            }
+        };
+        let path = cx.prompt_for_new_path(&export_directory(), Some("NothingBook 设置.json"));
+        cx.spawn_in(window, async move |_, cx| {
+            let Ok(Ok(Some(path))) = path.await else {
+                return;
+            };
+            let message = match std::fs::write(&path, json) {
+                Ok(()) => Notification::success("已导出设置，可在另一台机器上导入"),
+                Err(e) => Notification::error(format!("导出设置失败: {}", e)),
+            };
+            cx.update(|window, cx| window.push_notification(message, cx))
+                .ok();
+        })
+        .detach();
+    }
+
+    fn on_import_settings(
+        &mut self,
+        _: &ImportSettings,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: false,
+            prompt: Some("导入设置".into()),
+        });
+        cx.spawn_in(window, async move |this, cx| {
+            let Ok(Ok(Some(paths))) = paths.await else {
+                return;
+            };
+            let Some(path) = paths.into_iter().next() else {
+                return;
+            };
+            this.update_in(cx, |this, window, cx| {
+                this.import_settings(&path, window, cx)
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn import_settings(&mut self, path: &Path, window: &mut Window, cx: &mut Context<Self>) {
+        let imported = std::fs::read_to_string(path)
+            .context("读取设置文件失败")
+            .and_then(|json| AppSettings::get(cx).import_json(&json));
+        match imported {
+            Ok(imported) => {
+                AppSettings::update(cx, |s| *s = imported);
+                // 设置页中的输入框只在创建时读取设置，重新打开才能显示导入的值
+                if matches!(self.panel, MainPanel::Settings(_)) {
+                    self.panel = MainPanel::Editor;
+                    self.open_settings(window, cx);
+                }
+                window.push_notification(Notification::success("已导入设置"), cx);
+            }
+            Err(e) => self.report_error("导入设置失败", format!("{:#}", e), None),
+        }
+    }
+
+    fn open_settings(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if matches!(self.panel, MainPanel::Settings(_)) {
+            return;
+        }
+        let settings = cx.new(|cx| SettingsView::new(window, cx));
+        cx.subscribe(&settings, |this: &mut AppView, _, event: &SettingsEvent, cx| {
+            match event {
+                SettingsEvent::Close => this.show_editor(cx),
+            }
+        })
+        .detach();
+        self.panel = MainPanel::Settings(settings);
+        cx.notify();
+    }
+
+    fn show_graph(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        // 每次打开都重新构建，保证图谱反映最新的笔记和链接
+        let notes = self.notes.clone();
+        let graph = cx.new(|cx| GraphView::new(notes, cx));
+        cx.subscribe_in(
+            &graph,
+            window,
+            |this: &mut AppView, _, event: &GraphEvent, window, cx| match event {
+                GraphEvent::OpenNote(note_id) => this.select_note(*note_id, window, cx),
+                GraphEvent::Close => this.show_editor(cx),
+            },
+        )
+        .detach();
+        self.panel = MainPanel::Graph(graph);
+        cx.notify();
+    }
+
+    fn show_calendar(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let notes = self.notes.clone();
+        let calendar = cx.new(|cx| CalendarView::new(notes, cx));
+        cx.subscribe_in(
+            &calendar,
+            window,
+            |this: &mut AppView, _, event: &CalendarEvent, window, cx| match event {
+                CalendarEvent::OpenDay(date) => this.open_daily_note(*date, window, cx),
+                CalendarEvent::Close => this.show_editor(cx),
+            },
+        )
+        .detach();
+        self.panel = MainPanel::Calendar(calendar);
+        cx.notify();
+    }
+
+    fn show_board(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let notes = self.notes.clone();
+        let board = cx.new(|cx| BoardView::new(notes, cx));
+        cx.subscribe_in(
+            &board,
+            window,
+            |this: &mut AppView, board, event: &BoardEvent, window, cx| match event {
+                BoardEvent::OpenNote(note_id) => this.select_note(*note_id, window, cx),
+                BoardEvent::MoveNote { note_id, status } => {
+                    this.move_note_to_status(*note_id, status, cx);
+                    board.update(cx, |_, cx| cx.notify());
+                }
+                BoardEvent::Close => this.show_editor(cx),
+            },
+        )
+        .detach();
+        self.panel = MainPanel::Board(board);
+        cx.notify();
+    }
+
+    fn show_stats(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let notes = self.notes.clone();
+        let index = self.index.clone();
+        let stats = cx.new(|cx| StatsView::new(notes, index, cx));
+        cx.subscribe_in(
+            &stats,
+            window,
+            |this: &mut AppView, _, event: &StatsEvent, window, cx| match event {
+                StatsEvent::OpenNote(note_id) => this.select_note(*note_id, window, cx),
+                StatsEvent::Close => this.show_editor(cx),
+            },
+        )
+        .detach();
+        self.panel = MainPanel::Stats(stats);
+        cx.notify();
+    }
+
+    fn show_plugins(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let plugins = cx.new(|cx| PluginsView::new(self.plugin_host.as_deref(), &self.scripts, cx));
+        cx.subscribe_in(
+            &plugins,
+            window,
+            |this: &mut AppView, _, event: &PluginsEvent, window, cx| match event {
+                PluginsEvent::RunCommand { plugin, command } => {
+                    this.run_plugin_command(plugin, command, window, cx)
+                }
+                PluginsEvent::Export { plugin, exporter } => {
+                    this.export_with_plugin(plugin, exporter, window, cx)
+                }
+                PluginsEvent::Review(plugin) => {
+                    this.review_plugins(vec![plugin.clone()], window, cx)
+                }
+                PluginsEvent::RunScript(name) => this.run_script(name, window, cx),
+                PluginsEvent::Close => this.show_editor(cx),
+            },
+        )
+        .detach();
+        self.panel = MainPanel::Plugins(plugins);
+        cx.notify();
+    }
+
+    /// 启动时逐个询问是否启用新插件，或权限有变化的插件
+    fn review_plugin_permissions(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(host) = &self.plugin_host else {
+            return;
+        };
+        let settings = &AppSettings::get(cx).plugins;
+        let pending = host
+            .plugins
+            .iter()
+            .filter(|plugin| !settings.is_granted(plugin))
+            .map(|plugin| plugin.manifest.name.clone())
+            .collect();
+        self.review_plugins(pending, window, cx);
+    }
+
+    fn review_plugins(&mut self, names: Vec<String>, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(host) = &self.plugin_host else {
+            return;
+        };
+        let manifests: Vec<_> = host
+            .plugins
+            .iter()
+            .filter(|plugin| names.contains(&plugin.manifest.name))
+            .map(|plugin| (plugin.manifest.clone(), plugin.hash.clone()))
+            .collect();
+        if manifests.is_empty() {
+            return;
+        }
+
+        cx.spawn_in(window, async move |this, cx| {
+            for (manifest, hash) in manifests {
+                let mut detail = String::new();
+                if !manifest.description.is_empty() {
+                    detail.push_str(&manifest.description);
+                    detail.push_str("\n\n");
+                }
+                if manifest.permissions.is_empty() {
+                    detail.push_str("该插件不需要访问笔记。");
+                } else {
+                    detail.push_str("该插件请求以下权限：");
+                    for permission in &manifest.permissions {
+                        detail.push_str("\n· ");
+                        detail.push_str(permission.label());
+                    }
+                }
+                let Ok(answer) = this.update_in(cx, |_, window, cx| {
+                    window.prompt(
+                        PromptLevel::Warning,
+                        &format!("是否启用插件「{}」？", manifest.name),
+                        Some(&detail),
+                        &["启用", "不启用"],
+                        cx,
+                    )
+                }) else {
+                    return;
+                };
+                if answer.await != Ok(0) {
+                    continue;
+                }
+                let result = this.update(cx, |_, cx| {
+                    AppSettings::update(cx, |s| {
+                        s.plugins
+                            .granted
+                            .insert(hash.clone(), manifest.permissions.clone());
+                    });
+                });
+                if result.is_err() {
+                    return;
+                }
+            }
+            // 面板上的启用状态需要重新读取设置
+            this.update_in(cx, |this, window, cx| {
+                if matches!(this.panel, MainPanel::Plugins(_)) {
+                    this.show_plugins(window, cx);
+                }
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn run_plugin_command(
+        &mut self,
+        plugin_name: &str,
+        command: &str,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(host) = self.plugin_host.clone() else {
+            return;
+        };
+        let Some(plugin) = host.plugin(plugin_name) else {
+            return;
+        };
+        let settings = &AppSettings::get(cx).plugins;
+        if !settings.is_granted(plugin) {
+            window.push_notification(
+                Notification::warning(format!("插件「{}」尚未启用", plugin_name)),
+                cx,
+            );
+            return;
+        }
+        let permissions = settings.permissions(plugin).unwrap_or_default().to_vec();
+        let notes = if permissions.contains(&Permission::Read) {
+            let notes = self.notes.read(cx).get_all();
+            notes.into_iter().map(PluginNote::from).collect()
+        } else {
+            Vec::new()
+        };
+
+        let (plugin_name, command) = (plugin_name.to_string(), command.to_string());
+        let task = cx.background_executor().spawn(async move {
+            let plugin = host.plugin(&plugin_name).context("插件已被移除")?;
+            host.run_command(plugin, &command, &permissions, notes)
+        });
+        cx.spawn_in(window, async move |this, cx| {
+            let result = task.await;
+            this.update_in(cx, |this, window, cx| match result {
+                Ok(writes) => this.apply_plugin_writes(writes, window, cx),
+                Err(e) => this.report_error("运行插件命令失败", format!("{:#}", e), None),
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn apply_plugin_writes(
+        &mut self,
+        writes: Vec<PluginNote>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        for write in writes {
+            let existing = write
+                .id
+                .as_deref()
+                .and_then(|id| id.parse::<u128>().ok())
+                .filter(|id| self.notes.read(cx).get(*id).is_some());
+            let result = self.notes.update(cx, |notes, cx| match existing {
+                Some(note_id) => notes.update(
+                    note_id,
+                    |note| {
+                        note.title = write.title;
+                        note.content = write.content;
+                        note.tags = write.tags;
+                        note.updated_at = Utc::now();
+                    },
+                    cx,
+                ),
+                None => {
+                    let mut note = Note::new();
+                    note.title = write.title;
+                    note.content = write.content;
+                    note.tags = write.tags;
+                    notes.add(note, cx)
+                }
+            });
+            if let Err(e) = result {
+                self.report_error("保存插件修改的笔记失败", format!("{:#}", e), None);
+                continue;
+            }
+            if let Some(note_id) = existing {
+                self.reload_editor_note(note_id, window, cx);
+            }
+        }
+        cx.notify();
+    }
+
+    /// 运行启动脚本，并每分钟检查一次到点的每日脚本
+    fn start_script_scheduler(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let startup: Vec<String> = self
+            .scripts
+            .iter()
+            .filter(|script| script.schedule == Some(Schedule::Startup))
+            .map(|script| script.name.clone())
+            .collect();
+        for name in startup {
+            self.run_script(&name, window, cx);
+        }
+        if !self
+            .scripts
+            .iter()
+            .any(|script| matches!(script.schedule, Some(Schedule::Daily(_))))
+        {
+            return;
+        }
+
+        cx.spawn_in(window, async move |this, cx| {
+            loop {
+                let result = this.update_in(cx, |this, window, cx| {
+                    let now = Local::now().naive_local();
+                    let due: Vec<String> = this
+                        .scripts
+                        .iter()
+                        .filter(|script| {
+                            script.is_due(this.session.script_runs.get(&script.name).copied(), now)
+                        })
+                        .map(|script| script.name.clone())
+                        .collect();
+                    for name in due {
+                        this.session.script_runs.insert(name.clone(), now.date());
+                        this.save_session();
+                        this.run_script(&name, window, cx);
+                    }
+                });
+                if result.is_err() {
+                    break;
+                }
+                cx.background_executor()
+                    .timer(Duration::from_secs(60))
+                    .await;
+            }
+        })
+        .detach();
+    }
+
+    fn run_script(&mut self, name: &str, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(script) = self.scripts.iter().find(|script| script.name == name) else {
+            return;
+        };
+        let script = script.clone();
+        let notes: Vec<Note> = self.notes.read(cx).get_all().into_iter().cloned().collect();
+        let versions: HashMap<u128, _> = notes.iter().map(|n| (n.id, n.updated_at)).collect();
+        let task = cx
+            .background_executor()
+            .spawn(async move { scripting::run(&script, notes) });
+        let name = name.to_string();
+        cx.spawn_in(window, async move |this, cx| {
+            let result = task.await;
+            this.update_in(cx, |this, window, cx| match result {
+                Ok(mut changes) => {
+                    // 脚本在后台运行期间被用户改过的笔记以用户的修改为准
+                    changes.retain(|note| {
+                        let current = this.notes.read(cx).get(note.id).map(|n| n.updated_at);
+                        let kept = current.is_none() || current == versions.get(&note.id).copied();
+                        if !kept {
+                            this.report_error(
+                                "脚本的修改没有保存",
+                                format!("「{}」在脚本 {} 运行期间被修改过", note.title, name),
+                                None,
+                            );
+                        }
+                        kept
+                    });
+                    this.apply_note_changes(changes, window, cx)
+                }
+                Err(e) => this.report_error("运行脚本失败", format!("{}: {:#}", name, e), None),
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    // 保存脚本新建或修改的笔记
+    fn apply_note_changes(
+        &mut self,
+        changes: Vec<Note>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        for note in changes {
+            let note_id = note.id;
+            let existing = self.notes.read(cx).get(note_id).is_some();
+            let result = self.notes.update(cx, |notes, cx| {
+                if existing {
+                    notes.update(note_id, |current| *current = note, cx)
+                } else {
+                    notes.add(note, cx)
+                }
+            });
+            if let Err(e) = result {
+                self.report_error("保存脚本修改的笔记失败", format!("{:#}", e), None);
+                continue;
+            }
+            if existing {
+                self.reload_editor_note(note_id, window, cx);
+            }
+        }
+        cx.notify();
+    }
+
+    fn export_with_plugin(
+        &mut self,
+        plugin_name: &str,
+        exporter_id: &str,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(host) = self.plugin_host.clone() else {
+            return;
+        };
+        let Some(plugin) = host.plugin(plugin_name) else {
+            return;
+        };
+        // 导出器同样会拿到笔记内容，未同意或文件变了的插件不能运行
+        if !AppSettings::get(cx).plugins.is_granted(plugin) {
+            window.push_notification(
+                Notification::warning(format!("插件「{}」尚未启用", plugin_name)),
+                cx,
+            );
+            return;
+        }
+        let Some(exporter) = plugin
+            .manifest
+            .exporters
+            .iter()
+            .find(|exporter| exporter.id == exporter_id)
+        else {
+            return;
+        };
+        let Some(note) = self
+            .current_note_id(cx)
+            .and_then(|id| self.notes.read(cx).get(id))
+        else {
+            window.push_notification(Notification::warning("请先选择要导出的笔记"), cx);
+            return;
+        };
+
+        let file_name = format!("{}.{}", export::file_name(&note.title), exporter.extension);
+        let note = PluginNote::from(note);
+        let (plugin_name, exporter_id) = (plugin_name.to_string(), exporter_id.to_string());
+        let bytes = cx.background_executor().spawn(async move {
+            let plugin = host.plugin(&plugin_name).context("插件已被移除")?;
+            host.export(plugin, &exporter_id, &note)
+        });
+        cx.spawn_in(window, async move |this, cx| {
+            let bytes = match bytes.await {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    this.update(cx, |this, _| {
+                        this.report_error("插件导出失败", format!("{:#}", e), None)
+                    })
+                    .ok();
+                    return;
+                }
+            };
+            let Ok(path) = this.update(cx, |_, cx| {
+                cx.prompt_for_new_path(&export_directory(), Some(&file_name))
+            }) else {
+                return;
+            };
+            let Ok(Ok(Some(path))) = path.await else {
+                return;
+            };
+            let message = match std::fs::write(&path, bytes) {
+                Ok(()) => Notification::success(format!("已导出 {}", path.display())),
+                Err(e) => Notification::error(format!("写入导出文件失败: {}", e)),
+            };
+            cx.update(|window, cx| window.push_notification(message, cx))
+                .ok();
+        })
+        .detach();
+    }
+
+    fn on_export_docx(&mut self, _: &ExportDocx, window: &mut Window, cx: &mut Context<Self>) {
+        self.export_with_pandoc(ExportFormat::Docx, window, cx);
+    }
+
+    fn on_export_epub(&mut self, _: &ExportEpub, window: &mut Window, cx: &mut Context<Self>) {
+        self.export_with_pandoc(ExportFormat::Epub, window, cx);
+    }
+
+    fn on_export_latex(&mut self, _: &ExportLatex, window: &mut Window, cx: &mut Context<Self>) {
+        self.export_with_pandoc(ExportFormat::Latex, window, cx);
+    }
+
+    fn on_export_html(&mut self, _: &ExportHtml, window: &mut Window, cx: &mut Context<Self>) {
+        self.export_with_pandoc(ExportFormat::Html, window, cx);
+    }
+
+    fn on_export_image(&mut self, _: &ExportImage, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(note) = self
+            .current_note_id(cx)
+            .and_then(|id| self.notes.read(cx).get(id))
+            .cloned()
+        else {
+            window.push_notification(Notification::warning("请先选择要导出的笔记"), cx);
+            return;
+        };
+        let settings = AppSettings::get(cx);
+        let card = settings.card.clone();
+        let fonts: Vec<String> = std::iter::once(settings.fonts.ui_family.clone())
+            .chain(settings.fonts.fallbacks.iter().cloned())
+            .collect();
+        let file_name = format!("{}.png", note.title);
+        let path = cx.prompt_for_new_path(&export_directory(), Some(&file_name));
+        cx.spawn_in(window, async move |_, cx| {
+            let Ok(Ok(Some(path))) = path.await else {
+                return;
+            };
+            // 读取系统字体和绘制都比较慢，放到后台线程
+            let result = cx
+                .background_executor()
+                .spawn(async move {
+                    let png = card::render_png(&note, &card, &fonts)?;
+                    std::fs::write(&path, png).context("写入图片失败")
+                })
+                .await;
+            let message = match result {
+                Ok(()) => Notification::success("已导出为图片"),
+                Err(e) => Notification::error(format!("导出图片失败: {:#}", e)),
+            };
+            cx.update(|window, cx| window.push_notification(message, cx))
+                .ok();
+        })
+        .detach();
+    }
+
+    // 所有笔记中的问答卡片导出为一份 Anki 可导入的文本文件
+    fn on_export_anki(&mut self, _: &ExportAnki, window: &mut Window, cx: &mut Context<Self>) {
+        let notes = self.notes.read(cx).get_all();
+        let count: usize = notes.iter().map(|note| anki::cards(note).len()).sum();
+        if count == 0 {
+            window.push_notification(
+                Notification::warning(
+                    "没有找到卡片。用 Q: 和 A: 写问答，或给笔记加上 anki 标签按小标题拆分",
+                ),
+                cx,
+            );
+            return;
+        }
+        let tsv = anki::to_tsv(&notes);
+        let path = cx.prompt_for_new_path(&export_directory(), Some("NothingBook 卡片.txt"));
+        cx.spawn_in(window, async move |_, cx| {
+            let Ok(Ok(Some(path))) = path.await else {
+                return;
+            };
+            let message = match std::fs::write(&path, tsv) {
+                Ok(()) => Notification::success(format!("已导出 {} 张卡片", count)),
+                Err(e) => Notification::error(format!("导出卡片失败: {}", e)),
+            };
+            cx.update(|window, cx| window.push_notification(message, cx))
+                .ok();
+        })
+        .detach();
+    }
+
+    fn export_with_pandoc(
+        &mut self,
+        format: ExportFormat,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(note) = self
+            .current_note_id(cx)
+            .and_then(|id| self.notes.read(cx).get(id))
+            .cloned()
+        else {
+            window.push_notification(Notification::warning("请先选择要导出的笔记"), cx);
+            return;
+        };
+        let settings = AppSettings::get(cx).pandoc.clone();
+        let stylesheet = AppSettings::get(cx).preview.stylesheet_path();
+        let file_name = format!("{}.{}", note.title, format.extension());
+        let path = cx.prompt_for_new_path(&export_directory(), Some(&file_name));
+        cx.spawn_in(window, async move |_, cx| {
+            let Ok(Ok(Some(path))) = path.await else {
+                return;
+            };
+            // pandoc 可能要跑好几秒，放到后台线程等待
+            let result = cx
+                .background_executor()
+                .spawn(async move {
+                    export::export_with_pandoc(
+                        &note,
+                        format,
+                        &settings,
+                        stylesheet.as_deref(),
+                        &path,
+                    )
+                })
+                .await;
+            let message = match result {
+                Ok(()) => Notification::success(format!("已导出为 {}", format.label())),
+                Err(e) => Notification::error(format!("导出 {} 失败: {:#}", format.label(), e)),
+            };
+            cx.update(|window, cx| window.push_notification(message, cx))
+                .ok();
+        })
+        .detach();
+    }
+
+    fn on_share_by_email(&mut self, _: &ShareByEmail, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(note) = self
+            .current_note_id(cx)
+            .and_then(|id| self.notes.read(cx).get(id))
+        else {
+            window.push_notification(Notification::warning("请先选择要分享的笔记"), cx);
+            return;
+        };
+        cx.open_url(&share::mailto_url(note));
+    }
+
+    fn on_copy_note_markdown(
+        &mut self,
+        _: &CopyNoteMarkdown,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(markdown) = self
+            .current_note_id(cx)
+            .and_then(|id| self.notes.read(cx).get(id))
+            .map(|note| note.to_markdown())
+        else {
+            return;
+        };
+        // 剪藏模式下不要把自己复制的内容又追加回剪藏笔记
+        self.last_clipboard = Some(markdown.clone());
+        cx.write_to_clipboard(ClipboardItem::new_string(markdown));
+        window.push_notification(Notification::success("已复制到剪贴板"), cx);
+    }
+
+    fn on_publish_note(&mut self, _: &PublishNote, window: &mut Window, cx: &mut Context<Self>) {
+        let settings = AppSettings::get(cx).publish.clone();
+        let Some(note) = self
+            .current_note_id(cx)
+            .and_then(|id| self.notes.read(cx).get(id))
+            .cloned()
+        else {
+            return;
+        };
+        let note_id = note.id;
+        window.push_notification(
+            Notification::info(format!("正在发布「{}」…", note.title)),
+            cx,
+        );
+
+        let request = cx
+            .background_executor()
+            .spawn(async move { publish::publish(&note, &settings) });
+        cx.spawn_in(window, async move |this, cx| {
+            let published = request.await;
+            this.update_in(cx, |this, window, cx| match published {
+                Ok(published) => {
+                    let url = published.url.clone();
+                    this.set_published(note_id, Some(published), cx);
+                    cx.write_to_clipboard(ClipboardItem::new_string(url.clone()));
+                    window.push_notification(
+                        Notification::success(format!("链接已复制到剪贴板：{}", url))
+                            .title("已发布")
+                            .action(move |_, _, _| {
+                                let url = url.clone();
+                                Button::new("open-published").label("打开").on_click(
+                                    move |_, _, cx| {
+                                        cx.stop_propagation();
+                                        cx.open_url(&url);
+                                    },
+                                )
+                            }),
+                        cx,
+                    );
+                }
+                Err(e) => this.report_error("发布失败", format!("{:#}", e), None),
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn on_save_web_page(&mut self, _: &SaveWebPage, window: &mut Window, cx: &mut Context<Self>) {
+        let url = cx
+            .read_from_clipboard()
+            .and_then(|item| item.text())
+            .map(|text| text.trim().to_string())
+            .filter(|text| text.starts_with("http://") || text.starts_with("https://"));
+        match url {
+            Some(url) => self.save_web_page(url, window, cx),
+            None => window.push_notification(
+                Notification::info("先复制要保存的网页链接，再选择 文件 → 稍后阅读"),
+                cx,
+            ),
+        }
+    }
+
+    // 链接可能来自任何网页或程序，先确认再下载
+    fn confirm_save_web_page(&mut self, url: String, window: &mut Window, cx: &mut Context<Self>) {
+        let answer = window.prompt(
+            PromptLevel::Info,
+            "保存这个网页以便稍后阅读？",
+            Some(&format!(
+                "{}\n\n将下载网页正文和其中的图片，存为新笔记。",
+                url
+            )),
+            &["保存", "取消"],
+            cx,
+        );
+        cx.spawn_in(window, async move |this, cx| {
+            if answer.await != Ok(0) {
+                return;
+            }
+            this.update_in(cx, |this, window, cx| this.save_web_page(url, window, cx))
+                .ok();
+        })
+        .detach();
+    }
+
+    /// 下载网页正文存为带 read-later 标签的新笔记，完成后打开它
+    fn save_web_page(&mut self, url: String, window: &mut Window, cx: &mut Context<Self>) {
+        let settings = AppSettings::get(cx).read_later.clone();
+        let storage = self.storage.clone();
+        window.push_notification(Notification::info(format!("正在保存 {} …", url)), cx);
+
+        let request = cx
+            .background_executor()
+            .spawn(async move { read_later::capture(&url, &storage, &settings) });
+        cx.spawn_in(window, async move |this, cx| {
+            let captured = request.await;
+            this.update_in(cx, |this, window, cx| match captured {
+                Ok(mut note) => {
+                    note.title = this.notes.read(cx).unique_title(&note.title, None);
+                    window.push_notification(
+                        Notification::success(format!("已保存为「{}」", note.title))
+                            .title("稍后阅读"),
+                        cx,
+                    );
+                    this.add_note(note, window, cx);
+                }
+                Err(e) => this.report_error("保存网页失败", format!("{:#}", e), None),
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn on_unpublish_note(
+        &mut self,
+        _: &UnpublishNote,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let settings = AppSettings::get(cx).publish.clone();
+        let Some((note_id, published)) = self
+            .current_note_id(cx)
+            .and_then(|id| self.notes.read(cx).get(id))
+            .map(|note| (note.id, note.published.clone()))
+        else {
+            return;
+        };
+        let Some(published) = published else {
+            window.push_notification(Notification::info("这篇笔记还没有发布"), cx);
+            return;
+        };
+
+        let request = cx
+            .background_executor()
+            .spawn(async move { publish::unpublish(&published, &settings) });
+        cx.spawn_in(window, async move |this, cx| {
+            let result = request.await;
+            this.update_in(cx, |this, window, cx| match result {
+                Ok(()) => {
+                    this.set_published(note_id, None, cx);
+                    window.push_notification(Notification::success("已取消发布，原链接已失效"), cx);
+                }
+                Err(e) => this.report_error("取消发布失败", format!("{:#}", e), None),
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    // 只记录发布状态，不算对笔记的修改
+    fn set_published(
+        &mut self,
+        note_id: u128,
+        published: Option<Published>,
+        cx: &mut Context<Self>,
+    ) {
+        let result = self.notes.update(cx, |notes, cx| {
+            notes.update(note_id, |note| note.published = published, cx)
+        });
+        if let Err(e) = result {
+            self.report_error("保存发布状态失败", format!("{:#}", e), None);
+        }
+    }
+
+    fn on_ai_summarize(&mut self, _: &AiSummarize, window: &mut Window, cx: &mut Context<Self>) {
+        self.run_ai_task(AiTask::Summarize, window, cx);
+    }
+
+    fn on_ai_suggest_tags(
+        &mut self,
+        _: &AiSuggestTags,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.run_ai_task(AiTask::SuggestTags, window, cx);
+    }
+
+    fn on_ai_generate_title(
+        &mut self,
+        _: &AiGenerateTitle,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.run_ai_task(AiTask::GenerateTitle, window, cx);
+    }
+
+    fn run_ai_task(&mut self, task: AiTask, window: &mut Window, cx: &mut Context<Self>) {
+        let settings = AppSettings::get(cx).ai.clone();
+        if !settings.enabled {
+            window.push_notification(
+                Notification::info("AI 功能未开启，可在设置 > AI 中启用"),
+                cx,
+            );
+            return;
+        }
+        let Some(note) = self
+            .current_note_id(cx)
+            .and_then(|id| self.notes.read(cx).get(id))
+            .cloned()
+        else {
+            return;
+        };
+        let note_id = note.id;
+        window.push_notification(Notification::info(format!("正在{}…", task.label())), cx);
+
+        let request = cx
+            .background_executor()
+            .spawn(async move { ai::complete(task, &note, &settings) });
+        cx.spawn_in(window, async move |this, cx| {
+            let reply = request.await;
+            this.update_in(cx, |this, window, cx| {
+                let reply = match reply {
+                    Ok(reply) => reply,
+                    Err(e) => {
+                        window.push_notification(
+                            Notification::error(format!("{}失败: {:#}", task.label(), e)),
+                            cx,
+                        );
+                        return;
+                    }
+                };
+                let result = this.notes.update(cx, |notes, cx| {
+                    notes.update(
+                        note_id,
+                        |note| {
+                            match task {
+                                AiTask::Summarize => {
+                                    note.content =
+                                        format!("> **摘要**：{}\n\n{}", reply, note.content);
+                                }
+                                AiTask::SuggestTags => {
+                                    for tag in ai::parse_tags(&reply) {
+                                        if !note.tags.contains(&tag) {
+                                            note.tags.push(tag);
+                                        }
+                                    }
+                                }
+                                AiTask::GenerateTitle => note.title = reply.clone(),
+                            }
+                            note.updated_at = Utc::now();
+                        },
+                        cx,
+                    )
+                });
+                if let Err(e) = result {
+                    this.report_error("保存笔记失败", format!("{:#}", e), None);
+                    return;
+                }
+                this.reload_editor_note(note_id, window, cx);
+                if task == AiTask::GenerateTitle && this.editor.read(cx).note_id() == Some(note_id)
+                {
+                    this.set_title(Some(reply), cx);
+                }
+                window.push_notification(Notification::success(format!("已{}", task.label())), cx);
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    // 设置中开关系统搜索或更换目录后重建镜像
+    fn sync_search_index(&self, cx: &mut Context<Self>) {
+        let dir = AppSettings::get(cx).search_index.dir();
+        if self.notes.read(cx).search_index_dir() == dir.as_deref() {
+            return;
+        }
+        tracing::info!("系统搜索索引目录改为 {:?}", dir);
+        let result = self.notes.update(cx, |notes, cx| {
+            notes.set_search_index(dir.map(SearchIndex::new), cx)
+        });
+        if let Err(e) = result {
+            tracing::error!("更新搜索索引失败: {:#}", e);
+        }
+    }
+
+    // 正在编辑的笔记，其次是侧边栏选中的笔记
+    fn current_note_id(&self, cx: &App) -> Option<u128> {
+        self.editor
+            .read(cx)
+            .note_id()
+            .or(self.notes.read(cx).selected())
+    }
+
+    fn move_note_to_status(&mut self, note_id: u128, status: &str, cx: &mut Context<Self>) {
+        let statuses = AppSettings::get(cx).board.columns.clone();
+        let result = self.notes.update(cx, |notes, cx| {
+            notes.update(note_id, |note| note.set_status(status, &statuses), cx)
+        });
+        if let Err(e) = result {
+            let status = status.to_string();
+            self.report_error(
+                "更新笔记状态失败",
+                format!("{:#}", e),
+                Some(Rc::new(move |this, _window, cx| {
+                    this.move_note_to_status(note_id, &status, cx)
+                })),
+            );
+        }
+    }
+
+    fn set_tags(&mut self, note_id: u128, tags: Vec<String>, cx: &mut Context<Self>) {
+        let result = self.notes.update(cx, |notes, cx| {
+            notes.update(
+                note_id,
+                |note| {
+                    note.tags = tags.clone();
+                    note.updated_at = Utc::now();
+                },
+                cx,
+            )
+        });
+        if let Err(e) = result {
+            self.report_error(
+                "保存标签失败",
+                format!("{:#}", e),
+                Some(Rc::new(move |this, _window, cx| {
+                    this.set_tags(note_id, tags.clone(), cx)
+                })),
+            );
+        }
+    }
+
+    fn set_due_date(&mut self, note_id: u128, due_date: Option<NaiveDate>, cx: &mut Context<Self>) {
+        let result = self.notes.update(cx, |notes, cx| {
+            notes.update(
+                note_id,
+                |note| {
+                    note.due_date = due_date;
+                    note.updated_at = Utc::now();
+                },
+                cx,
+            )
+        });
+        if let Err(e) = result {
+            self.report_error(
+                "保存截止日期失败",
+                format!("{:#}", e),
+                Some(Rc::new(move |this, _window, cx| {
+                    this.set_due_date(note_id, due_date, cx)
+                })),
+            );
+        }
+    }
+
+    fn rename_note(&mut self, note_id: u128, title: String, cx: &mut Context<Self>) {
+        let result = self.notes.update(cx, |notes, cx| {
+            notes.update(
+                note_id,
+                |note| {
+                    note.title = title.clone();
+                    note.updated_at = Utc::now();
+                },
+                cx,
+            )
+        });
+        match result {
+            Ok(()) => self.set_title(Some(title), cx),
+            Err(e) => self.report_error(
+                "重命名笔记失败",
+                format!("{:#}", e),
+                Some(Rc::new(move |this, _window, cx| {
+                    this.rename_note(note_id, title.clone(), cx)
+                })),
+            ),
+        }
+    }
+
+    // 保存失败时正文仍在编辑器里，重试会再次提交同样的内容
+    /// 保存编辑器中所有未保存的更改
+    fn save_unsaved(&mut self, cx: &mut Context<Self>) {
+        let unsaved = self.editor.update(cx, |editor, cx| editor.take_unsaved(cx));
+        for (note_id, content) in unsaved {
+            // 手动保存模式下改过又被删除的笔记不再保存
+            if self.notes.read(cx).get(note_id).is_some() {
+                self.save_content(note_id, content.to_string(), cx);
+            }
+        }
+    }
+
+    fn save_content(&mut self, note_id: u128, content: String, cx: &mut Context<Self>) {
+        let result = self.notes.update(cx, |notes, cx| {
+            notes.update(
+                note_id,
+                |note| {
+                    note.content = content.clone();
+                    note.updated_at = Utc::now();
+                },
+                cx,
+            )
+        });
+        if let Err(e) = result {
+            self.report_error(
+                "保存笔记失败",
+                format!("{:#}", e),
+                Some(Rc::new(move |this, _window, cx| {
+                    this.save_content(note_id, content.clone(), cx)
+                })),
+            );
+        }
+    }
+
+    // 切换笔记前记下当前笔记的光标位置
+    fn remember_cursor(&mut self, cx: &mut Context<Self>) {
+        let editor = self.editor.read(cx);
+        if let Some(note_id) = editor.note_id() {
+            let position = editor.cursor_position(cx);
+            self.session.cursors.insert(note_id, position);
+        }
+    }
+
+    fn save_session(&self) {
+        if let Err(e) = self.writer.save_session(&self.session) {
+            self.report_error("保存会话失败", format!("{:#}", e), None);
+        }
+    }
+
+    /// 打开正文中链接的笔记，双链指向的笔记不存在时以链接文字为标题新建
+    fn open_note_link(&mut self, link: NoteLink, window: &mut Window, cx: &mut Context<Self>) {
+        let link = match link {
+            NoteLink::Id(note_id) => DeepLink::Open(note_id),
+            NoteLink::Title(title) => match self.notes.read(cx).find_by_title(&title) {
+                Some(note) => DeepLink::Open(note.id),
+                None => DeepLink::New {
+                    title: Some(title),
+                    body: None,
+                },
+            },
+        };
+        self.open_deep_link(link, window, cx);
+    }
+
+    /// 打开某天的日记，不存在时以日期为标题新建
+    fn open_daily_note(&mut self, date: NaiveDate, window: &mut Window, cx: &mut Context<Self>) {
+        let title = date.format(DAILY_TITLE_FORMAT).to_string();
+        let existing = self
+            .notes
+            .read(cx)
+            .find_by_title(&title)
+            .map(|note| note.id);
+        match existing {
+            Some(note_id) => self.select_note(note_id, window, cx),
+            None => self.add_note(Note::daily(date), window, cx),
+        }
+    }
+
+    // 默认标题按已有标题编号，创建后直接进入重命名
+    fn create_note(&mut self, parent: Option<u128>, window: &mut Window, cx: &mut Context<Self>) {
+        let mut note = match parent {
+            Some(parent) => Note::child_of(parent),
+            None => Note::new(),
+        };
+        note.title = self.notes.read(cx).unique_title(DEFAULT_TITLE, None);
+        let note_id = note.id;
+        self.add_note(note, window, cx);
+        if self.current_note_id(cx) == Some(note_id) {
+            self.editor
+                .update(cx, |editor, cx| editor.start_rename(window, cx));
+        }
+    }
+
+    fn move_note(&mut self, note_id: u128, parent: Option<u128>, cx: &mut Context<Self>) {
+        if let Err(e) = self
+            .notes
+            .update(cx, |notes, cx| notes.set_parent(note_id, parent, cx))
+        {
+            self.report_error("移动笔记失败", format!("{:#}", e), None);
+        }
+    }
+
+    fn add_note(&mut self, note: Note, window: &mut Window, cx: &mut Context<Self>) {
+        let id = note.id;
+
+        if let Err(e) = self
+            .notes
+            .update(cx, |notes, cx| notes.add(note.clone(), cx))
+        {
+            let note = note.clone();
+            self.report_error(
+                "保存新笔记失败",
+                format!("{:#}", e),
+                Some(Rc::new(move |this, window, cx| {
+                    this.add_note(note.clone(), window, cx)
+                })),
+            );
+            return;
+        }
+
+        self.notes
+            .update(cx, |notes, cx| notes.select(Some(id), cx));
+
+        self.remember_cursor(cx);
+        self.editor.update(cx, |editor, cx| {
+            editor.load_note(&note, window, cx);
+        });
+        self.session.last_note_id = Some(id);
+        self.save_session();
+        self.set_title(Some(note.title.clone()), cx);
+        self.panel = MainPanel::Editor;
+
+        cx.notify();
+    }
+
+    // 拖入的 Markdown 和文本文件逐个导入为新笔记，最后选中最后一篇
+    fn import_files(&mut self, paths: &[PathBuf], window: &mut Window, cx: &mut Context<Self>) {
+        let mut imported = None;
+        let mut count = 0;
+        let mut failed = 0;
+        // 通知里只放第一个失败原因，其余的写进日志
+        let mut first_error = None;
+        let mut skipped = 0;
+        let mut images = Vec::new();
+        for path in paths {
+            if import::is_image(path) {
+                images.push(path.clone());
+                continue;
+            }
+            if !import::is_importable(path) {
+                skipped += 1;
+                continue;
+            }
+            let result = import::note_from_file(path).and_then(|note| {
+                self.notes
+                    .update(cx, |notes, cx| notes.add(note.clone(), cx))
+                    .map(|_| note)
+            });
+            match result {
+                Ok(note) => {
+                    count += 1;
+                    imported = Some(note.id);
+                }
+                Err(e) => {
+                    tracing::warn!("导入失败 {:?}: {:#}", path, e);
+                    if first_error.is_none() {
+                        first_error = Some(format!("{}: {:#}", path.display(), e));
+                    }
+                    failed += 1;
+                }
+            }
+        }
+
+        if let Some(id) = imported {
+            self.select_note(id, window, cx);
+        }
+
+        let mut attached = 0;
+        if !images.is_empty() {
+            match self.current_note_id(cx) {
+                Some(note_id) => {
+                    attached = self.attach_images(note_id, &images, window, cx);
+                    failed += images.len() - attached;
+                }
+                None => skipped += images.len(),
+            }
+        }
+
+        let mut message = if attached > 0 && count == 0 {
+            format!("已添加 {} 张图片", attached)
+        } else if attached > 0 {
+            format!("已导入 {} 篇笔记，添加 {} 张图片", count, attached)
+        } else {
+            format!("已导入 {} 篇笔记", count)
+        };
+        if failed > 0 {
+            message.push_str(&format!("，{} 个文件导入失败", failed));
+        }
+        if skipped > 0 {
+            message.push_str(&format!("，跳过 {} 个不支持的文件", skipped));
+        }
+        if let Some(error) = first_error {
+            message.push_str(&format!("\n{}", error));
+        }
+        let notification = if count + attached == 0 {
+            Notification::warning(message)
+        } else if failed > 0 {
+            Notification::info(message)
+        } else {
+            Notification::success(message)
+        };
+        window.push_notification(notification, cx);
+        cx.notify();
+    }
+
+    // 图片复制到附件目录并在正文末尾插入引用，开启识别时在后台识别文字
+    fn attach_images(
+        &mut self,
+        note_id: u128,
+        images: &[PathBuf],
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> usize {
+        let storage = self.storage.clone();
+        let mut names = Vec::new();
+        for image in images {
+            match storage.save_attachment(image) {
+                Ok(name) => names.push(name),
+                Err(e) => self.report_error(
+                    "添加附件失败",
+                    format!("{}: {:#}", image.display(), e),
+                    None,
+                ),
+            }
+        }
+        if names.is_empty() {
+            return 0;
+        }
+
+        let lines: String = names
+            .iter()
+            .map(|name| markup::attachment_image(name, name) + "\n")
+            .collect();
+        if let Err(e) = self.append_to_note(note_id, &lines, window, cx) {
+            self.report_error("保存笔记失败", format!("{:#}", e), None);
+            return 0;
+        }
+
+        let ocr = AppSettings::get(cx).ocr.clone();
+        if ocr.enabled {
+            let dir = storage.attachments_dir();
+            for name in &names {
+                let (path, ocr) = (dir.join(name), ocr.clone());
+                let task = cx
+                    .background_executor()
+                    .spawn(async move { ocr::recognize(&path, &ocr) });
+                self.store_attachment_text(note_id, name.clone(), task, cx);
+            }
+        }
+        names.len()
+    }
+
+    // 后台识别出附件中的文字后写入笔记，供搜索使用
+    fn store_attachment_text(
+        &mut self,
+        note_id: u128,
+        name: String,
+        task: Task<anyhow::Result<String>>,
+        cx: &mut Context<Self>,
+    ) {
+        cx.spawn(async move |this, cx| {
+            let text = match task.await {
+                Ok(text) if !text.is_empty() => text,
+                Ok(_) => return,
+                Err(e) => {
+                    this.update(cx, |this, _| {
+                        this.report_error("识别附件文字失败", format!("{}: {:#}", name, e), None)
+                    })
+                    .ok();
+                    return;
+                }
+            };
+            this.update(cx, |this, cx| {
+                let result = this.notes.update(cx, |notes, cx| {
+                    notes.update(
+                        note_id,
+                        |note| {
+                            note.attachment_text.insert(name, text);
+                        },
+                        cx,
+                    )
+                });
+                if let Err(e) = result {
+                    this.report_error("保存识别结果失败", format!("{:#}", e), None);
+                }
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    // 第一次点击开始录音，再次点击结束并把录音插入开始录音时的笔记
+    fn toggle_recording(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let settings = AppSettings::get(cx).voice.clone();
+        if let Some((note_id, recording)) = self.recording.take() {
+            self.editor
+                .update(cx, |editor, cx| editor.set_recording(false, cx));
+            let (name, path) = (recording.name.clone(), recording.path.clone());
+            let stop = cx
+                .background_executor()
+                .spawn(async move { recording.stop() });
+            cx.spawn_in(window, async move |this, cx| {
+                let result = stop.await;
+                this.update_in(cx, |this, window, cx| {
+                    if let Err(e) = result {
+                        this.report_error("结束录音失败", format!("{:#}", e), None);
+                        return;
+                    }
+                    let line = format!("<audio controls src=\"attachments/{}\"></audio>\n", name);
+                    if let Err(e) = this.append_to_note(note_id, &line, window, cx) {
+                        this.report_error("保存笔记失败", format!("{:#}", e), None);
+                        return;
+                    }
+                    if settings.transcribe {
+                        let task = cx
+                            .background_executor()
+                            .spawn(async move { voice::transcribe(&path, &settings) });
+                        this.store_attachment_text(note_id, name, task, cx);
+                    }
+                })
+                .ok();
+            })
+            .detach();
+            return;
+        }
+
+        let Some(note_id) = self.editor.read(cx).note_id() else {
+            return;
+        };
+        let result = voice::Recording::start(&settings, &self.storage);
+        match result {
+            Ok(recording) => {
+                self.recording = Some((note_id, recording));
+                self.editor
+                    .update(cx, |editor, cx| editor.set_recording(true, cx));
+            }
+            Err(e) => self.report_error("无法开始录音", format!("{:#}", e), None),
+        }
+    }
+
+    fn play_audio(&mut self, name: &str, cx: &mut Context<Self>) {
+        cx.open_with_system(&self.storage.attachments_dir().join(name));
+    }
+
+    // 删除正在查看的笔记后接着打开列表中相邻的一篇，全部删完才显示空白页
+    fn delete_note(&mut self, note_id: u128, window: &mut Window, cx: &mut Context<Self>) {
+        // 等待中的自动保存先保存，回收站里是最新的内容
+        if !AppSettings::get(cx).save.manual {
+            self.save_unsaved(cx);
+        }
+        let was_current = self.current_note_id(cx) == Some(note_id);
+        let neighbor = self.notes.read(cx).neighbor_of(note_id);
+        if let Err(e) = self.notes.update(cx, |notes, cx| notes.remove(note_id, cx)) {
+            self.report_error(
+                "删除笔记失败",
+                format!("{:#}", e),
+                Some(Rc::new(move |this, window, cx| {
+                    this.delete_note(note_id, window, cx)
+                })),
+            );
+            return;
+        }
+        self.session.forget(note_id);
+
+        if was_current {
+            // 先清空编辑器，免得切换时把已删除笔记的光标位置记进会话
+            self.editor.update(cx, |editor, cx| {
+                editor.clear(window, cx);
+            });
+            match neighbor {
+                Some(neighbor) => self.select_note(neighbor, window, cx),
+                None => {
+                    self.notes.update(cx, |notes, cx| notes.select(None, cx));
+                    self.set_title(None, cx);
+                }
+            }
+        }
+        self.save_session();
+
+        cx.notify();
+    }
+
+    fn select_note(&mut self, note_id: u128, window: &mut Window, cx: &mut Context<Self>) {
+        let note_clone = self.notes.read(cx).get(note_id).cloned();
+
+        if let Some(note) = note_clone {
+            self.remember_cursor(cx);
+            let cursor = self.session.cursor(note_id);
+            self.editor.update(cx, |editor, cx| {
+                editor.load_note(&note, window, cx);
+                if let Some(position) = cursor {
+                    editor.set_cursor_position(position, window, cx);
+                }
+            });
+            self.session.last_note_id = Some(note_id);
+            self.save_session();
+            self.notes
+                .update(cx, |notes, cx| notes.select(Some(note_id), cx));
+            self.set_title(Some(note.title.clone()), cx);
+            self.panel = MainPanel::Editor;
+            cx.notify();
+        }
+    }
+}
+
+// 启动时在后台读取的数据
+struct Startup {
+    notes: HashMap<u128, Note>,
+    /// 读取时发现的重复 ID，已另存冲突副本
+    conflicts: Vec<NoteConflict>,
+    session: Session,
+    plugin_host: Option<Arc<PluginHost>>,
+    scripts: Vec<Script>,
+}
+
+impl Startup {
+    fn load(storage: &Storage) -> Self {
+        let started = Instant::now();
+        let mut notes = HashMap::new();
+        let conflicts = storage.load_all_notes(&mut notes).unwrap_or_else(|e| {
+            tracing::error!("加载笔记失败: {:#}", e);
+            Vec::new()
+        });
+        tracing::info!(
+            notes = notes.len(),
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "读取笔记"
+        );
+        let session = storage.load_session().unwrap_or_else(|e| {
+            tracing::error!("加载会话失败: {}", e);
+            Session::default()
+        });
+        let plugin_host = PluginHost::load(&storage.plugins_dir())
+            .inspect_err(|e| tracing::error!("初始化插件失败: {:#}", e))
+            .ok()
+            .map(Arc::new);
+        let scripts = scripting::load_scripts(&storage.scripts_dir()).unwrap_or_else(|e| {
+            tracing::error!("加载脚本失败: {:#}", e);
+            Vec::new()
+        });
+        Self {
+            notes,
+            conflicts,
+            session,
+            plugin_host,
+            scripts,
+        }
+    }
+}
+
+// 导出文件时保存对话框默认打开的目录
+fn export_directory() -> std::path::PathBuf {
+    dirs::document_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_default()
+}
+
+impl Render for AppView {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        // 锁定时只留下标题栏和解锁界面，快捷键也只响应退出
+        if let Some(lock) = &self.lock {
+            return v_flex()
+                .size_full()
+                .on_action(cx.listener(Self::on_quit))
+                .bg(gpui::rgb(0xffffff))
+                .font(AppSettings::get(cx).fonts.ui_font())
+                .child(self.title_bar.clone())
+                .child(div().flex_1().w_full().child(lock.clone()))
+                .children(Root::render_notification_layer(window, cx))
+                .into_any_element();
+        }
+
+        let main: AnyView = match &self.panel {
+            MainPanel::Editor => self.editor.clone().into(),
+            MainPanel::Settings(settings) => settings.clone().into(),
+            MainPanel::Graph(graph) => graph.clone().into(),
+            MainPanel::Calendar(calendar) => calendar.clone().into(),
+            MainPanel::Board(board) => board.clone().into(),
+            MainPanel::Stats(stats) => stats.clone().into(),
+            MainPanel::Plugins(plugins) => plugins.clone().into(),
+            MainPanel::Diagnostics(diagnostics) => diagnostics.clone().into(),
+            MainPanel::Integrity(integrity) => integrity.clone().into(),
+            MainPanel::Trash(trash) => trash.clone().into(),
+            MainPanel::Attachments(attachments) => attachments.clone().into(),
+        };
+
+        v_flex()
+            .size_full()
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(Self::on_new_note))
+            .on_action(cx.listener(Self::on_save_note))
+            .on_action(cx.listener(Self::on_quit))
+            .on_action(cx.listener(Self::on_lock_app))
+            .on_action(cx.listener(Self::on_delete_note))
+            .on_action(cx.listener(Self::on_open_settings))
+            .on_action(cx.listener(Self::on_export_settings))
+            .on_action(cx.listener(Self::on_import_settings))
+            .on_action(cx.listener(Self::on_toggle_sidebar))
+            .on_action(cx.listener(Self::on_show_graph))
+            .on_action(cx.listener(Self::on_show_calendar))
+            .on_action(cx.listener(Self::on_show_board))
+            .on_action(cx.listener(Self::on_show_stats))
+            .on_action(cx.listener(Self::on_toggle_clipboard_capture))
+            .on_action(cx.listener(Self::on_show_plugins))
+            .on_action(cx.listener(Self::on_show_diagnostics))
+            .on_action(cx.listener(Self::on_verify_data))
+            .on_action(cx.listener(Self::on_show_trash))
+            .on_action(cx.listener(Self::on_show_attachments))
+            .on_action(cx.listener(Self::on_publish_note))
+            .on_action(cx.listener(Self::on_unpublish_note))
+            .on_action(cx.listener(Self::on_save_web_page))
+            .on_action(cx.listener(Self::on_toggle_highlight))
+            .on_action(cx.listener(Self::on_toggle_preview))
+            .on_action(cx.listener(Self::on_rename_note))
+            .on_action(cx.listener(Self::on_previous_note))
+            .on_action(cx.listener(Self::on_next_note))
+            .on_action(cx.listener(Self::on_escape))
+            .on_action(cx.listener(Self::on_read_aloud))
+            .on_action(cx.listener(Self::on_export_docx))
+            .on_action(cx.listener(Self::on_export_epub))
+            .on_action(cx.listener(Self::on_export_latex))
+            .on_action(cx.listener(Self::on_export_html))
+            .on_action(cx.listener(Self::on_export_image))
+            .on_action(cx.listener(Self::on_export_anki))
+            .on_action(cx.listener(Self::on_share_by_email))
+            .on_action(cx.listener(Self::on_copy_note_markdown))
+            .on_action(cx.listener(Self::on_ai_summarize))
+            .on_action(cx.listener(Self::on_ai_suggest_tags))
+            .on_action(cx.listener(Self::on_ai_generate_title))
+            .capture_key_down(cx.listener(|this, _, _, _| this.last_activity = Instant::now()))
+            .on_mouse_move(cx.listener(|this, _, _, _| this.last_activity = Instant::now()))
+            .bg(gpui::rgb(0xffffff))
+            .font(AppSettings::get(cx).fonts.ui_font())
+            .child(self.title_bar.clone())
+            .child(
+                h_flex()
+                    .flex_1()
+                    .w_full()
+                    .overflow_hidden()
+                    .when(self.sidebar_visible, |this| {
+                        this.child(div().w(px(280.0)).h_full().child(self.sidebar.clone()))
+                    })
+                    .child(div().flex_1().h_full().child(main))
+                    .drag_over::<ExternalPaths>(|style, _, _, _| style.bg(gpui::rgb(0xeff6ff)))
+                    .on_drop(cx.listener(|this, paths: &ExternalPaths, window, cx| {
+                        this.import_files(paths.paths(), window, cx);
+                    })),
+            )
+            .children(Root::render_notification_layer(window, cx))
+            .into_any_element()
+    }
+}
+
+impl Focusable for AppView {
+    fn focus_handle(&self, _cx: &App) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // 不能 glob 导入 gpui，否则 #[test] 会解析成 gpui::test
+    use super::AppView;
+    use crate::{
+        actions::{DeleteNote, NextNote, PreviousNote, RenameNote, SaveNote},
+        deep_link::DeepLink,
+        note::{CONFLICT_COPY_SUFFIX, MAX_TITLE_CHARS, Note},
+        settings::{AppSettings, NoteSort},
+        storage::{Storage, WRITE_DELAY},
+        views::{editor::EditorEvent, lock::LockEvent, sidebar::SidebarEvent},
+    };
+    use gpui::{AppContext as _, Entity, TestAppContext, VisualTestContext};
+    use gpui_component::Root;
+    use std::time::{Duration, Instant};
+    use tempfile::TempDir;
+
+    struct TestApp {
+        dir: TempDir,
+        app: Entity<AppView>,
+        cx: &'static mut VisualTestContext,
+    }
+
+    impl TestApp {
+        fn open(cx: &mut TestAppContext) -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            let storage = Storage::with_dir(dir.path().to_path_buf()).unwrap();
+            cx.update(|cx| {
+                gpui_component::init(cx);
+                cx.set_global(AppSettings::default());
+            });
+            let mut app = None;
+            let window = cx.add_window(|window, cx| {
+                let view = cx.new(|cx| AppView::new(storage, window, cx));
+                app = Some(view.clone());
+                Root::new(view, window, cx)
+            });
+            let cx = VisualTestContext::from_window(window.into(), cx).into_mut();
+            cx.run_until_parked();
+            Self {
+                dir,
+                app: app.unwrap(),
+                cx,
+            }
+        }
+
+        fn sidebar_event(&mut self, event: SidebarEvent) {
+            let sidebar = self.app.read_with(self.cx, |app, _| app.sidebar.clone());
+            sidebar.update(self.cx, |_, cx| cx.emit(event));
+            self.cx.run_until_parked();
+        }
+
+        fn editor_event(&mut self, event: EditorEvent) {
+            let editor = self.app.read_with(self.cx, |app, _| app.editor.clone());
+            editor.update(self.cx, |_, cx| cx.emit(event));
+            self.cx.run_until_parked();
+        }
+
+        fn create_note(&mut self) -> u128 {
+            self.sidebar_event(SidebarEvent::CreateNote);
+            self.current_note().expect("新建的笔记应该打开")
+        }
+
+        fn current_note(&mut self) -> Option<u128> {
+            self.app
+                .read_with(self.cx, |app, cx| app.editor.read(cx).note_id())
+        }
+
+        fn selected(&mut self) -> Option<u128> {
+            self.app
+                .read_with(self.cx, |app, cx| app.notes.read(cx).selected())
+        }
+
+        fn note(&mut self, note_id: u128) -> Option<Note> {
+            self.app
+                .read_with(self.cx, |app, cx| app.notes.read(cx).get(note_id).cloned())
+        }
+
+        fn note_count(&mut self) -> usize {
+            self.app
+                .read_with(self.cx, |app, cx| app.notes.read(cx).get_all().len())
+        }
+
+        // 等合并写入的延迟过去，让后台把排队的写操作写到磁盘
+        fn wait_for_writes(&mut self) {
+            self.cx.executor().advance_clock(WRITE_DELAY * 2);
+            self.cx.run_until_parked();
+        }
+
+        fn note_file_exists(&self, note_id: u128) -> bool {
+            self.dir.path().join(format!("{}.json", note_id)).exists()
+        }
+    }
+
+    #[gpui::test]
+    fn create_opens_selects_and_saves_note(cx: &mut TestAppContext) {
+        let mut test = TestApp::open(cx);
+        let note_id = test.create_note();
+
+        assert_eq!(test.note_count(), 1);
+        assert_eq!(test.selected(), Some(note_id));
+        test.wait_for_writes();
+        assert!(test.note_file_exists(note_id));
+    }
+
+    #[gpui::test]
+    fn new_notes_get_numbered_titles_and_start_renaming(cx: &mut TestAppContext) {
+        let mut test = TestApp::open(cx);
+        let titles: Vec<String> = (0..3)
+            .map(|_| {
+                let note_id = test.create_note();
+                test.note(note_id).unwrap().title
+            })
+            .collect();
+        assert_eq!(titles, vec!["新建笔记", "新建笔记 2", "新建笔记 3"]);
+        let renaming = test
+            .app
+            .read_with(test.cx, |app, cx| app.editor.read(cx).is_renaming());
+        assert!(renaming);
+    }
+
+    #[gpui::test]
+    fn keyboard_switches_and_renames_notes(cx: &mut TestAppContext) {
+        let mut test = TestApp::open(cx);
+        // 按标题排序，侧边栏中「新建笔记」在「新建笔记 2」上面
+        test.cx
+            .update(|_, cx| AppSettings::update(cx, |s| s.sidebar.sort = NoteSort::Title));
+        let first = test.create_note();
+        let second = test.create_note();
+        assert_eq!(test.current_note(), Some(second));
+
+        test.cx.dispatch_action(PreviousNote);
+        test.cx.run_until_parked();
+        assert_eq!(test.current_note(), Some(first));
+        assert_eq!(test.selected(), Some(first));
+        // 已经是第一篇，不再移动
+        test.cx.dispatch_action(PreviousNote);
+        test.cx.run_until_parked();
+        assert_eq!(test.current_note(), Some(first));
+
+        let renaming = |test: &mut TestApp| {
+            test.app
+                .read_with(test.cx, |app, cx| app.editor.read(cx).is_renaming())
+        };
+        assert!(!renaming(&mut test));
+        test.cx.dispatch_action(RenameNote);
+        test.cx.run_until_parked();
+        assert!(renaming(&mut test));
+
+        test.cx.dispatch_action(NextNote);
+        test.cx.run_until_parked();
+        assert_eq!(test.current_note(), Some(second));
+        assert_eq!(test.selected(), Some(second));
+    }
+
+    #[gpui::test]
+    fn read_later_links_ask_before_downloading(cx: &mut TestAppContext) {
+        let mut test = TestApp::open(cx);
+        test.app.update_in(test.cx, |app, window, cx| {
+            let link = DeepLink::ReadLater("https://example.com/".to_string());
+            app.open_deep_link(link, window, cx)
+        });
+        test.cx.run_until_parked();
+        assert!(test.cx.has_pending_prompt());
+        test.cx.simulate_prompt_answer("取消");
+        test.cx.run_until_parked();
+        assert_eq!(test.current_note(), None);
+    }
+
+    #[gpui::test]
+    fn delete_note_asks_for_confirmation(cx: &mut TestAppContext) {
+        let mut test = TestApp::open(cx);
+        let note_id = test.create_note();
+
+        test.cx.dispatch_action(DeleteNote);
+        test.cx.run_until_parked();
+        assert!(test.cx.has_pending_prompt());
+        test.cx.simulate_prompt_answer("取消");
+        test.cx.run_until_parked();
+        assert!(test.note(note_id).is_some());
+
+        test.cx.dispatch_action(DeleteNote);
+        test.cx.run_until_parked();
+        test.cx.simulate_prompt_answer("删除");
+        test.cx.run_until_parked();
+        assert!(test.note(note_id).is_none());
+        assert_eq!(test.current_note(), None);
+    }
+
+    #[gpui::test]
+    fn select_loads_note_into_editor(cx: &mut TestAppContext) {
+        let mut test = TestApp::open(cx);
+        let first = test.create_note();
+        let second = test.create_note();
+        assert_eq!(test.current_note(), Some(second));
+
+        test.sidebar_event(SidebarEvent::SelectNote(first));
+        assert_eq!(test.current_note(), Some(first));
+        assert_eq!(test.selected(), Some(first));
+    }
+
+    #[gpui::test]
+    fn rename_updates_note_and_suffixes_duplicates(cx: &mut TestAppContext) {
+        let mut test = TestApp::open(cx);
+        let first = test.create_note();
+        test.editor_event(EditorEvent::Rename {
+            note_id: first,
+            title: "周报".to_string(),
+        });
+        assert_eq!(test.note(first).unwrap().title, "周报");
+
+        let second = test.create_note();
+        let checked = test.app.read_with(test.cx, |app, cx| {
+            app.notes.read(cx).check_title(second, "  周报  ")
+        });
+        assert_eq!(checked, Ok("周报 2".to_string()));
+
+        // 加上序号后仍不超过长度上限
+        let long = "长".repeat(MAX_TITLE_CHARS);
+        test.editor_event(EditorEvent::Rename {
+            note_id: first,
+            title: long.clone(),
+        });
+        let checked = test
+            .app
+            .read_with(test.cx, |app, cx| {
+                app.notes.read(cx).check_title(second, &long)
+            })
+            .unwrap();
+        assert_eq!(checked.chars().count(), MAX_TITLE_CHARS);
+        assert_eq!(checked, format!("{} 2", "长".repeat(MAX_TITLE_CHARS - 2)));
+        let empty = test.app.read_with(test.cx, |app, cx| {
+            app.notes.read(cx).check_title(second, "   ")
+        });
+        assert!(empty.is_err());
+    }
+
+    #[gpui::test]
+    fn editing_tags_saves_note(cx: &mut TestAppContext) {
+        let mut test = TestApp::open(cx);
+        let id = test.create_note();
+        test.editor_event(EditorEvent::TagsChanged {
+            note_id: id,
+            tags: vec!["工作".to_string(), "待办".to_string()],
+        });
+        assert_eq!(test.note(id).unwrap().tags, ["工作", "待办"]);
+    }
+
+    #[gpui::test]
+    fn setting_and_clearing_due_date(cx: &mut TestAppContext) {
+        let mut test = TestApp::open(cx);
+        let id = test.create_note();
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        test.editor_event(EditorEvent::DueDateChanged {
+            note_id: id,
+            due_date: Some(date),
+        });
+        assert_eq!(test.note(id).unwrap().due_date, Some(date));
+
+        test.editor_event(EditorEvent::DueDateChanged {
+            note_id: id,
+            due_date: None,
+        });
+        assert_eq!(test.note(id).unwrap().due_date, None);
+    }
+
+    #[gpui::test]
+    fn delete_opens_neighbor_then_clears_editor(cx: &mut TestAppContext) {
+        let mut test = TestApp::open(cx);
+        let first = test.create_note();
+        let second = test.create_note();
+        test.wait_for_writes();
+
+        let neighbor = test
+            .app
+            .read_with(test.cx, |app, cx| app.notes.read(cx).neighbor_of(second));
+        assert_eq!(neighbor, Some(first));
+        test.sidebar_event(SidebarEvent::DeleteNote(second));
+        assert_eq!(test.note_count(), 1);
+        assert_eq!(test.current_note(), Some(first));
+
+        test.sidebar_event(SidebarEvent::DeleteNote(first));
+        assert_eq!(test.note_count(), 0);
+        assert_eq!(test.current_note(), None);
+        assert_eq!(test.selected(), None);
+        test.wait_for_writes();
+        assert!(!test.note_file_exists(first));
+        assert!(!test.note_file_exists(second));
+    }
+
+    #[gpui::test]
+    fn edit_from_another_machine_is_kept_as_conflict_copy(cx: &mut TestAppContext) {
+        let mut test = TestApp::open(cx);
+        let note_id = test.create_note();
+        test.editor_event(EditorEvent::ContentChanged {
+            note_id,
+            content: "本机".into(),
+        });
+        test.wait_for_writes();
+
+        // 另一台机器的时钟慢了一小时，写入的版本修改时间更早
+        let mut theirs = test.note(note_id).unwrap();
+        theirs.content = "另一台机器".to_string();
+        theirs.updated_at -= chrono::Duration::hours(1);
+        let path = test.dir.path().join(format!("{}.json", note_id));
+        std::fs::write(&path, serde_json::to_string(&theirs).unwrap()).unwrap();
+
+        test.editor_event(EditorEvent::ContentChanged {
+            note_id,
+            content: "本机 2".into(),
+        });
+        test.wait_for_writes();
+        assert_eq!(test.note(note_id).unwrap().content, "本机 2");
+        assert_eq!(test.note_count(), 2);
+        let copy = test.app.read_with(test.cx, |app, cx| {
+            app.notes
+                .read(cx)
+                .get_all()
+                .into_iter()
+                .find(|note| note.id != note_id)
+                .cloned()
+                .unwrap()
+        });
+        assert_eq!(copy.content, "另一台机器");
+        assert_eq!(copy.title, format!("新建笔记{}", CONFLICT_COPY_SUFFIX));
+        assert!(test.note_file_exists(copy.id));
+    }
+
+    #[gpui::test]
+    fn manual_save_keeps_edits_until_saved(cx: &mut TestAppContext) {
+        let mut test = TestApp::open(cx);
+        test.cx
+            .update(|_, cx| AppSettings::update(cx, |s| s.save.manual = true));
+        let first = test.create_note();
+        let editor = test.app.read_with(test.cx, |app, _| app.editor.clone());
+        test.cx.update(|window, cx| {
+            editor.update(cx, |editor, cx| editor.insert(0, "草稿", window, cx))
+        });
+        test.cx.run_until_parked();
+        assert_eq!(test.note(first).unwrap().content, "");
+        assert!(editor.read_with(test.cx, |editor, _| editor.is_dirty()));
+
+        // 切走再切回来，未保存的正文还在
+        let second = test.create_note();
+        assert!(!editor.read_with(test.cx, |editor, _| editor.is_dirty()));
+        test.sidebar_event(SidebarEvent::SelectNote(first));
+        assert!(editor.read_with(test.cx, |editor, _| editor.is_dirty()));
+        assert_eq!(test.note(first).unwrap().content, "");
+
+        test.app.update_in(test.cx, |app, window, cx| {
+            app.on_save_note(&SaveNote, window, cx)
+        });
+        test.cx.run_until_parked();
+        assert_eq!(test.note(first).unwrap().content, "草稿");
+        assert_eq!(test.note(second).unwrap().content, "");
+        assert!(!editor.read_with(test.cx, |editor, _| editor.is_dirty()));
+    }
+
+    #[gpui::test]
+    fn locks_after_idle_timeout(cx: &mut TestAppContext) {
+        let test = TestApp::open(cx);
+        test.cx.update(|_, cx| {
+            AppSettings::update(cx, |s| {
+                s.lock.set_password("secret");
+                s.lock.idle_minutes = 1;
+            })
+        });
+        test.app.update_in(test.cx, |app, window, cx| {
+            app.lock_if_idle(window, cx);
+            assert!(app.lock.is_none());
+            app.last_activity = Instant::now() - Duration::from_secs(61);
+            app.lock_if_idle(window, cx);
+            assert!(app.lock.is_some());
+        });
+
+        let lock = test
+            .app
+            .read_with(test.cx, |app, _| app.lock.clone().unwrap());
+        lock.update(test.cx, |_, cx| cx.emit(LockEvent::Unlocked));
+        test.cx.run_until_parked();
+        assert!(test.app.read_with(test.cx, |app, _| app.lock.is_none()));
     }
 }
@@ -0,0 +1,153 @@
+use crate::note::Note;
+use anyhow::{Context, Result};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AttachmentKind {
+    Image,
+    Audio,
+    Other,
+}
+
+impl AttachmentKind {
+    fn of(name: &str) -> Self {
+        let extension = Path::new(name)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        match extension.as_str() {
+            "png" | "jpg" | "jpeg" | "gif" | "webp" | "bmp" | "svg" => Self::Image,
+            "m4a" | "mp3" | "wav" | "ogg" | "flac" | "aac" => Self::Audio,
+            _ => Self::Other,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Image => "图片",
+            Self::Audio => "录音",
+            Self::Other => "文件",
+        }
+    }
+}
+
+/// 引用附件的笔记
+#[derive(Clone, Debug)]
+pub struct Owner {
+    pub note_id: u128,
+    pub title: String,
+    /// 回收站中的笔记也算引用，恢复后附件还在
+    pub trashed: bool,
+}
+
+/// 附件目录中的一个文件
+#[derive(Clone, Debug)]
+pub struct Attachment {
+    pub name: String,
+    pub path: PathBuf,
+    pub size: u64,
+    pub kind: AttachmentKind,
+    pub owners: Vec<Owner>,
+}
+
+impl Attachment {
+    /// 没有任何笔记（包括回收站中的笔记）引用
+    pub fn is_orphaned(&self) -> bool {
+        self.owners.is_empty()
+    }
+}
+
+/// 按附件名记录引用它的笔记
+pub fn add_owners<'a>(
+    owners: &mut HashMap<String, Vec<Owner>>,
+    notes: impl IntoIterator<Item = &'a Note>,
+    trashed: bool,
+) {
+    for note in notes {
+        for name in note.attachments() {
+            owners.entry(name).or_default().push(Owner {
+                note_id: note.id,
+                title: note.title.clone(),
+                trashed,
+            });
+        }
+    }
+}
+
+/// 列出附件目录中的所有文件，按名称排序
+pub fn scan(dir: &Path, mut owners: HashMap<String, Vec<Owner>>) -> Result<Vec<Attachment>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut attachments = Vec::new();
+    for entry in fs::read_dir(dir).context("无法读取附件目录")? {
+        let entry = entry.context("读取目录条目失败")?;
+        let metadata = entry.metadata().context("读取附件信息失败")?;
+        if !metadata.is_file() {
+            continue;
+        }
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        attachments.push(Attachment {
+            kind: AttachmentKind::of(&name),
+            owners: owners.remove(&name).unwrap_or_default(),
+            path: entry.path(),
+            size: metadata.len(),
+            name,
+        });
+    }
+    attachments.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(attachments)
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const KB: f64 = 1024.0;
+    let bytes = bytes as f64;
+    if bytes < KB {
+        format!("{} B", bytes)
+    } else if bytes < KB * KB {
+        format!("{:.1} KB", bytes / KB)
+    } else {
+        format!("{:.1} MB", bytes / KB / KB)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan_finds_owners_and_orphans() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("图.png"), [0u8; 2048]).unwrap();
+        fs::write(dir.path().join("旧录音.m4a"), b"x").unwrap();
+        let note = Note {
+            content: "![图](attachments/图.png)".to_string(),
+            ..Note::new()
+        };
+        let trashed = Note {
+            content: "![图](attachments/图.png)".to_string(),
+            ..Note::new()
+        };
+        let mut owners = HashMap::new();
+        add_owners(&mut owners, [&note], false);
+        add_owners(&mut owners, [&trashed], true);
+
+        let attachments = scan(dir.path(), owners).unwrap();
+        assert_eq!(attachments.len(), 2);
+        let image = &attachments[0];
+        assert_eq!(image.name, "图.png");
+        assert_eq!(image.kind, AttachmentKind::Image);
+        assert_eq!(format_size(image.size), "2.0 KB");
+        assert_eq!(image.owners.len(), 2);
+        assert!(image.owners[1].trashed);
+        assert!(attachments[1].is_orphaned());
+        assert_eq!(attachments[1].kind, AttachmentKind::Audio);
+    }
+}
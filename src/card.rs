@@ -0,0 +1,280 @@
+use crate::{
+    actions::APP_NAME,
+    markup::{self, Block},
+    note::Note,
+};
+use anyhow::{Context, Result};
+use resvg::{tiny_skia, usvg};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+
+/// 卡片宽度，适合在手机上查看和发到聊天软件
+const WIDTH: f32 = 1080.0;
+const PADDING: f32 = 96.0;
+const TITLE_SIZE: f32 = 60.0;
+const TEXT_SIZE: f32 = 38.0;
+const LINE_HEIGHT: f32 = 1.6;
+/// 正文太长时只画前面这么多行，超出的部分用省略号代替
+const MAX_LINES: usize = 60;
+
+/// 图片卡片的配色
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CardTheme {
+    #[default]
+    Paper,
+    Night,
+    Mint,
+    Sunset,
+}
+
+impl CardTheme {
+    pub const ALL: [Self; 4] = [Self::Paper, Self::Night, Self::Mint, Self::Sunset];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Paper => "纸张",
+            Self::Night => "夜间",
+            Self::Mint => "薄荷",
+            Self::Sunset => "晚霞",
+        }
+    }
+
+    // 背景渐变的起止色、标题色、正文色和次要文字色
+    fn colors(&self) -> [&'static str; 5] {
+        match self {
+            Self::Paper => ["#fffdf7", "#f5efe0", "#1f2937", "#374151", "#9ca3af"],
+            Self::Night => ["#1e293b", "#0f172a", "#f8fafc", "#cbd5e1", "#64748b"],
+            Self::Mint => ["#ecfdf5", "#d1fae5", "#064e3b", "#065f46", "#6ee7b7"],
+            Self::Sunset => ["#fff7ed", "#fde2e4", "#7c2d12", "#9a3412", "#fb923c"],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct CardSettings {
+    pub theme: CardTheme,
+    /// 右下角显示应用名
+    pub watermark: bool,
+}
+
+impl Default for CardSettings {
+    fn default() -> Self {
+        Self {
+            theme: CardTheme::default(),
+            watermark: true,
+        }
+    }
+}
+
+// 卡片中的一行文字
+struct Line {
+    text: String,
+    size: f32,
+    bold: bool,
+    indent: f32,
+}
+
+/// 把笔记画成 PNG 图片，fonts 是按优先顺序排列的字体名
+///
+/// 需要读取系统字体，比较慢，应在后台线程调用。
+pub fn render_png(note: &Note, settings: &CardSettings, fonts: &[String]) -> Result<Vec<u8>> {
+    let svg = card_svg(note, settings, fonts);
+    let mut options = usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+    let tree = usvg::Tree::from_str(&svg, &options).context("生成图片失败")?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height()).context("图片尺寸无效")?;
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    pixmap.encode_png().context("编码 PNG 失败")
+}
+
+/// 卡片的 SVG，正文按估算的字宽折行，高度随内容变化
+pub fn card_svg(note: &Note, settings: &CardSettings, fonts: &[String]) -> String {
+    let [from, to, title_color, text_color, muted] = settings.theme.colors();
+    let lines = layout(note);
+
+    let mut y = PADDING;
+    let mut body = String::new();
+    for line in &lines {
+        y += line.size * LINE_HEIGHT;
+        if line.text.is_empty() {
+            continue;
+        }
+        let color = if line.bold { title_color } else { text_color };
+        let _ = write!(
+            body,
+            r#"<text x="{}" y="{}" font-size="{}" font-weight="{}" fill="{}">{}</text>"#,
+            PADDING + line.indent,
+            y,
+            line.size,
+            if line.bold { "bold" } else { "normal" },
+            color,
+            escape(&line.text)
+        );
+    }
+    y += PADDING;
+    let _ = write!(
+        body,
+        r#"<text x="{}" y="{}" font-size="24" fill="{}">{}</text>"#,
+        PADDING,
+        y,
+        muted,
+        note.updated_at.format("%Y-%m-%d")
+    );
+    if settings.watermark {
+        let _ = write!(
+            body,
+            r#"<text x="{}" y="{}" font-size="24" fill="{}" text-anchor="end">{}</text>"#,
+            WIDTH - PADDING,
+            y,
+            muted,
+            APP_NAME
+        );
+    }
+    let height = (y + PADDING * 0.75).ceil();
+
+    let family = fonts
+        .iter()
+        .map(|font| format!("'{}'", escape(font)))
+        .chain(["sans-serif".to_string()])
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="{family}"><defs><linearGradient id="bg" x1="0" y1="0" x2="0" y2="1"><stop offset="0" stop-color="{from}"/><stop offset="1" stop-color="{to}"/></linearGradient></defs><rect width="{w}" height="{h}" fill="url(#bg)"/>{body}</svg>"#,
+        w = WIDTH,
+        h = height,
+    )
+}
+
+// 标题和正文排成行，Markdown 标记只保留文字
+fn layout(note: &Note) -> Vec<Line> {
+    let width = WIDTH - PADDING * 2.0;
+    let mut lines = Vec::new();
+    let mut push = |text: &str, size: f32, bold: bool, indent: f32| {
+        for text in wrap(text, size, width - indent) {
+            lines.push(Line {
+                text,
+                size,
+                bold,
+                indent,
+            });
+        }
+    };
+    push(&note.title, TITLE_SIZE, true, 0.0);
+    push("", TEXT_SIZE * 0.5, false, 0.0);
+
+    for block in markup::parse_blocks(&note.content) {
+        match block {
+            Block::Heading { level, text } => {
+                let size = if level <= 2 {
+                    TEXT_SIZE * 1.2
+                } else {
+                    TEXT_SIZE
+                };
+                push(&markup::parse_inline(&text).text, size, true, 0.0);
+            }
+            Block::Paragraph(text) | Block::Quote(text) => {
+                for line in markup::parse_inline(&text).text.lines() {
+                    push(line, TEXT_SIZE, false, 0.0);
+                }
+            }
+            Block::ListItem {
+                marker,
+                depth,
+                text,
+            } => {
+                let text = format!("{} {}", marker, markup::parse_inline(&text).text);
+                push(&text, TEXT_SIZE, false, depth as f32 * TEXT_SIZE);
+            }
+            Block::Code(code) => {
+                for line in code.lines() {
+                    push(line, TEXT_SIZE * 0.85, false, TEXT_SIZE * 0.5);
+                }
+            }
+            Block::Image { alt, .. } => push(&format!("[图片] {}", alt), TEXT_SIZE, false, 0.0),
+            Block::Embed { title, .. } => push(&format!("[嵌入] {}", title), TEXT_SIZE, false, 0.0),
+            Block::Rule => push("", TEXT_SIZE, false, 0.0),
+        }
+        push("", TEXT_SIZE * 0.4, false, 0.0);
+    }
+
+    if lines.len() > MAX_LINES {
+        lines.truncate(MAX_LINES);
+        if let Some(last) = lines.last_mut() {
+            last.text.push('…');
+        }
+    }
+    lines
+}
+
+// 中日韩文字和全角符号按一个字号宽，其余字符按半个多字号估算
+fn char_width(ch: char, size: f32) -> f32 {
+    if ch.is_ascii() { size * 0.55 } else { size }
+}
+
+fn wrap(text: &str, size: f32, width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut line_width = 0.0;
+    for ch in text.chars() {
+        let w = char_width(ch, size);
+        if line_width + w > width && !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+            line_width = 0.0;
+        }
+        line.push(ch);
+        line_width += w;
+    }
+    lines.push(line);
+    lines
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_lines_wrap_and_markup_is_stripped() {
+        let note = Note {
+            title: "读书笔记 <一>".to_string(),
+            content: format!("**重点**：{}\n\n- 列表", "长".repeat(40)),
+            ..Note::new()
+        };
+        let lines = layout(&note);
+        let texts: Vec<&str> = lines.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(texts[0], "读书笔记 <一>");
+        assert!(texts[2].starts_with("重点："));
+        // 每行最多 (1080 - 192) / 38 = 23 个汉字
+        assert_eq!(texts[2].chars().count(), 23);
+        assert!(texts.contains(&"• 列表"));
+
+        let svg = card_svg(
+            &note,
+            &CardSettings::default(),
+            &["PingFang SC".to_string()],
+        );
+        assert!(svg.contains("读书笔记 &lt;一&gt;"));
+        assert!(svg.contains(APP_NAME));
+        let plain = card_svg(
+            &note,
+            &CardSettings {
+                watermark: false,
+                ..CardSettings::default()
+            },
+            &[],
+        );
+        assert!(!plain.contains(APP_NAME));
+
+        let png = render_png(&note, &CardSettings::default(), &[]).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+    }
+}
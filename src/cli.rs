@@ -0,0 +1,237 @@
+use crate::{
+    anki,
+    export::{self, ExportFormat},
+    mcp,
+    note::Note,
+    storage::Storage,
+    synthetic::{self, SizeDistribution},
+    vault::Vaults,
+};
+use anyhow::{Context, Result, bail};
+use clap::{Parser, Subcommand};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read},
+    path::PathBuf,
+};
+
+#[derive(Parser)]
+#[command(
+    name = "nothingbook",
+    bin_name = "nothingbook",
+    version,
+    about = "NothingBook 笔记"
+)]
+pub struct Cli {
+    /// 不带子命令时打开图形界面
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// 启动后打开的 notes:// 链接，或要导入为新笔记的文本文件（系统“打开方式”和分享时传入）
+    pub url: Option<String>,
+    /// 启动完成后在终端打印各阶段耗时
+    #[arg(long)]
+    pub startup_timing: bool,
+    /// 使用指定的笔记库，可以是名称或数据目录的路径，默认为上次打开的笔记库
+    #[arg(long, global = true)]
+    pub vault: Option<String>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// 列出所有笔记
+    List,
+    /// 新建笔记
+    Add {
+        #[arg(long)]
+        title: Option<String>,
+        /// 从标准输入读取正文
+        #[arg(long)]
+        stdin: bool,
+    },
+    /// 显示笔记内容
+    Show { id: u128 },
+    /// 按标题和正文搜索笔记
+    Search { query: String },
+    /// 导出笔记，默认为 Markdown，指定 --format 时通过 pandoc 转换
+    Export {
+        #[arg(long)]
+        all: bool,
+        #[arg(long, value_enum)]
+        format: Option<ExportFormat>,
+        dir: PathBuf,
+    },
+    /// 把 Q:/A: 问答块导出为 Anki 可导入的制表符分隔文件
+    Anki { out: PathBuf },
+    /// 生成合成笔记用于压力测试，笔记带“压测”标签
+    Generate {
+        count: usize,
+        /// 正文最短字符数
+        #[arg(long, default_value_t = SizeDistribution::default().min)]
+        min_size: usize,
+        /// 正文最长字符数
+        #[arg(long, default_value_t = SizeDistribution::default().max)]
+        max_size: usize,
+        /// 越大短笔记越多，1 为均匀分布
+        #[arg(long, default_value_t = SizeDistribution::default().skew)]
+        skew: f64,
+        #[arg(long, default_value_t = 1)]
+        seed: u64,
+        /// 写入指定的数据目录而不是应用的数据目录
+        #[arg(long)]
+        data_dir: Option<PathBuf>,
+    },
+    /// 检查笔记文件的校验和，列出损坏或不完整的文件
+    Verify,
+    /// 以 MCP 服务器模式运行，通过标准输入输出向 AI 助手提供笔记工具
+    Mcp,
+    /// 列出所有笔记库
+    Vaults,
+}
+
+/// storage 是 --vault 指定的笔记库，vaults 是全部笔记库
+pub fn run(command: Command, storage: Storage, vaults: &Vaults) -> Result<()> {
+    if let Command::Mcp = command {
+        return mcp::serve(storage);
+    }
+    if let Command::Vaults = command {
+        for vault in vaults.all() {
+            println!("{}\t{}", vault.name, vault.path.display());
+        }
+        return Ok(());
+    }
+    if let Command::Generate {
+        count,
+        min_size,
+        max_size,
+        skew,
+        seed,
+        data_dir,
+    } = command
+    {
+        let storage = match data_dir {
+            Some(dir) => Storage::with_dir(dir)?,
+            None => storage,
+        };
+        let sizes = SizeDistribution {
+            min: min_size,
+            max: max_size,
+            skew,
+        };
+        for note in synthetic::generate(count, sizes, seed) {
+            storage.save_note(&note)?;
+        }
+        println!("已生成 {} 篇合成笔记", count);
+        return Ok(());
+    }
+
+    if let Command::Verify = command {
+        let report = storage.verify()?;
+        for damaged in &report.damaged {
+            let backup = match &damaged.backup {
+                Some(backup) => format!("有 {} 的备份", backup.formatted_time()),
+                None => "没有备份".to_string(),
+            };
+            println!(
+                "{}\t{}\t{}",
+                damaged.path.display(),
+                damaged.problem.label(),
+                backup
+            );
+        }
+        println!(
+            "共检查 {} 篇笔记，{} 篇有问题，{} 篇没有校验和",
+            report.checked,
+            report.damaged.len(),
+            report.unverified
+        );
+        return Ok(());
+    }
+
+    let mut notes = HashMap::new();
+    storage.load_all_notes(&mut notes)?;
+    let mut notes: Vec<Note> = notes.into_values().collect();
+    notes.sort_by_key(|note| std::cmp::Reverse(note.updated_at));
+
+    match command {
+        Command::List => {
+            for note in &notes {
+                print_row(note);
+            }
+        }
+        Command::Add { title, stdin } => {
+            let mut note = Note::new();
+            if let Some(title) = title {
+                note.title = title;
+            }
+            if stdin {
+                io::stdin()
+                    .read_to_string(&mut note.content)
+                    .context("读取标准输入失败")?;
+            }
+            storage.save_note(&note)?;
+            println!("{}", note.id);
+        }
+        Command::Show { id } => {
+            let Some(note) = notes.iter().find(|note| note.id == id) else {
+                bail!("找不到笔记 {}", id);
+            };
+            print!("{}", note.to_markdown());
+        }
+        Command::Search { query } => {
+            for note in notes.iter().filter(|note| note.matches(&query)) {
+                print_row(note);
+            }
+        }
+        Command::Export { all, format, dir } => {
+            if !all {
+                bail!("目前只支持 --all 导出全部笔记");
+            }
+            fs::create_dir_all(&dir).context("无法创建导出目录")?;
+            let settings = storage.load_settings()?;
+            let (pandoc, stylesheet) = (settings.pandoc, settings.preview.stylesheet_path());
+            let extension = format.map_or("md", |format| format.extension());
+            for note in &notes {
+                let mut path =
+                    dir.join(format!("{}.{}", export::file_name(&note.title), extension));
+                // 标题重复时用 ID 区分
+                if path.exists() {
+                    path = dir.join(format!(
+                        "{}-{}.{}",
+                        export::file_name(&note.title),
+                        note.id,
+                        extension
+                    ));
+                }
+                match format {
+                    Some(format) => export::export_with_pandoc(
+                        note,
+                        format,
+                        &pandoc,
+                        stylesheet.as_deref(),
+                        &path,
+                    )
+                    .context(format!("导出失败: {}", note.title))?,
+                    None => fs::write(&path, note.to_markdown())
+                        .context(format!("写入文件失败: {:?}", path))?,
+                }
+            }
+            println!("已导出 {} 篇笔记到 {}", notes.len(), dir.display());
+        }
+        Command::Anki { out } => {
+            let notes: Vec<&Note> = notes.iter().collect();
+            fs::write(&out, anki::to_tsv(&notes)).context(format!("写入文件失败: {:?}", out))?;
+            let count: usize = notes.iter().map(|note| anki::cards(note).len()).sum();
+            println!("已导出 {} 张卡片", count);
+        }
+        // 这几个命令在上面单独处理并提前返回，走到这里说明分派有误，报错而不是崩溃
+        Command::Mcp | Command::Generate { .. } | Command::Verify | Command::Vaults => {
+            bail!("内部错误：命令没有被正确分派")
+        }
+    }
+    Ok(())
+}
+
+fn print_row(note: &Note) {
+    println!("{}\t{}\t{}", note.id, note.formatted_time(), note.title);
+}
@@ -0,0 +1,51 @@
+use anyhow::{Context, Result, bail};
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// 用 curl 发送 HTTP 请求，返回响应正文
+///
+/// 请求以 curl 配置文件的格式经标准输入传入，令牌和请求体不会出现在进程列表中。
+/// 配置中的字符串值用 [`quote`] 转义。
+pub fn request(curl: &str, config: &str) -> Result<Vec<u8>> {
+    let mut child = Command::new(curl)
+        .args([
+            "--silent",
+            "--show-error",
+            "--fail-with-body",
+            "--config",
+            "-",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context(format!("无法启动 curl: {}", curl))?;
+    child
+        .stdin
+        .take()
+        .context("无法写入 curl")?
+        .write_all(config.as_bytes())
+        .context("无法写入 curl")?;
+    let output = child.wait_with_output().context("等待 curl 结束失败")?;
+    if !output.status.success() {
+        bail!(
+            "请求失败: {} {}",
+            String::from_utf8_lossy(&output.stderr).trim(),
+            String::from_utf8_lossy(&output.stdout).trim()
+        );
+    }
+    Ok(output.stdout)
+}
+
+/// curl 配置文件中双引号内的字符串需要转义反斜杠和双引号
+pub fn quote(value: &str) -> String {
+    format!(
+        "\"{}\"",
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
@@ -0,0 +1,59 @@
+use std::path::{Path, PathBuf};
+use url::Url;
+
+pub const URL_SCHEME: &str = "notes";
+
+/// `notes://` 链接对应的操作
+#[derive(Debug, PartialEq)]
+pub enum DeepLink {
+    /// notes://open/<id>
+    Open(u128),
+    /// notes://new?title=...&body=...
+    New {
+        title: Option<String>,
+        body: Option<String>,
+    },
+    /// notes://read-later?url=... 保存网页稍后阅读
+    ReadLater(String),
+    /// 通过“打开方式”或分享传来的文件，file:// 链接或文件路径
+    Import(PathBuf),
+}
+
+impl DeepLink {
+    pub fn parse(link: &str) -> Option<Self> {
+        // Windows 路径 C:\... 也能被解析成 URL，先按路径判断
+        if Path::new(link).is_absolute() {
+            return Some(Self::Import(PathBuf::from(link)));
+        }
+        let url = Url::parse(link).ok()?;
+        if url.scheme() == "file" {
+            return url.to_file_path().ok().map(Self::Import);
+        }
+        if url.scheme() != URL_SCHEME {
+            return None;
+        }
+        match url.host_str()? {
+            "open" => {
+                let id = url.path().trim_matches('/').parse().ok()?;
+                Some(Self::Open(id))
+            }
+            "new" => {
+                let mut title = None;
+                let mut body = None;
+                for (key, value) in url.query_pairs() {
+                    match key.as_ref() {
+                        "title" => title = Some(value.into_owned()),
+                        "body" => body = Some(value.into_owned()),
+                        _ => {}
+                    }
+                }
+                Some(Self::New { title, body })
+            }
+            "read-later" => url
+                .query_pairs()
+                .find(|(key, _)| key == "url")
+                .map(|(_, value)| Self::ReadLater(value.into_owned())),
+            _ => None,
+        }
+    }
+}
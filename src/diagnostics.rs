@@ -0,0 +1,187 @@
+use chrono::{DateTime, Local};
+use std::{
+    collections::VecDeque,
+    fmt::{self, Write as _},
+    fs::{self, File, OpenOptions},
+    io::Write as _,
+    path::{Path, PathBuf},
+    sync::{
+        Mutex, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+use tracing::{Event, Level, Subscriber, field::Field};
+use tracing_subscriber::{
+    Layer, filter::LevelFilter, layer::Context, layer::SubscriberExt as _,
+    util::SubscriberInitExt as _,
+};
+
+/// 诊断面板保留的最近日志条数
+const RECENT_LIMIT: usize = 500;
+/// 日志文件超过这个大小时在启动时换到 .old 文件
+const MAX_LOG_SIZE: u64 = 5 * 1024 * 1024;
+const LOG_FILE_NAME: &str = "nothingbook.log";
+
+#[derive(Clone)]
+pub struct LogEntry {
+    pub time: DateTime<Local>,
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+impl LogEntry {
+    pub fn to_line(&self) -> String {
+        format!(
+            "{} {:<5} {}: {}",
+            self.time.format("%Y-%m-%d %H:%M:%S%.3f"),
+            self.level,
+            self.target,
+            self.message
+        )
+    }
+}
+
+struct Diagnostics {
+    enabled: AtomicBool,
+    recent: Mutex<VecDeque<LogEntry>>,
+    file: Mutex<Option<File>>,
+    path: PathBuf,
+}
+
+static DIAGNOSTICS: OnceLock<Diagnostics> = OnceLock::new();
+
+/// 安装日志：警告和错误始终输出到终端；开启诊断后，本应用的调试日志
+/// 同时写入日志文件和诊断面板
+pub fn init(log_dir: &Path, enabled: bool) {
+    let path = log_dir.join(LOG_FILE_NAME);
+    let diagnostics = Diagnostics {
+        enabled: AtomicBool::new(false),
+        recent: Mutex::new(VecDeque::new()),
+        file: Mutex::new(None),
+        path,
+    };
+    if DIAGNOSTICS.set(diagnostics).is_err() {
+        return;
+    }
+    set_enabled(enabled);
+
+    let stderr = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_filter(LevelFilter::WARN);
+    if let Err(e) = tracing_subscriber::registry()
+        .with(stderr)
+        .with(DiagnosticsLayer)
+        .try_init()
+    {
+        eprintln!("初始化日志失败: {}", e);
+    }
+}
+
+/// 开启或关闭诊断日志，开启时才创建日志文件
+pub fn set_enabled(enabled: bool) {
+    let Some(diagnostics) = DIAGNOSTICS.get() else {
+        return;
+    };
+    if diagnostics.enabled.swap(enabled, Ordering::Relaxed) == enabled {
+        return;
+    }
+    let mut file = diagnostics.file.lock().unwrap();
+    *file = if enabled {
+        open_log_file(&diagnostics.path)
+            .inspect_err(|e| eprintln!("打开日志文件失败 {:?}: {}", diagnostics.path, e))
+            .ok()
+    } else {
+        None
+    };
+}
+
+pub fn is_enabled() -> bool {
+    DIAGNOSTICS
+        .get()
+        .is_some_and(|diagnostics| diagnostics.enabled.load(Ordering::Relaxed))
+}
+
+pub fn log_path() -> Option<PathBuf> {
+    DIAGNOSTICS
+        .get()
+        .map(|diagnostics| diagnostics.path.clone())
+}
+
+/// 最近的日志，最早的在前
+pub fn recent() -> Vec<LogEntry> {
+    DIAGNOSTICS.get().map_or_else(Vec::new, |diagnostics| {
+        diagnostics.recent.lock().unwrap().iter().cloned().collect()
+    })
+}
+
+fn open_log_file(path: &Path) -> std::io::Result<File> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    if fs::metadata(path).is_ok_and(|metadata| metadata.len() > MAX_LOG_SIZE) {
+        fs::rename(path, path.with_extension("log.old"))?;
+    }
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+struct DiagnosticsLayer;
+
+impl<S: Subscriber> Layer<S> for DiagnosticsLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let Some(diagnostics) = DIAGNOSTICS.get() else {
+            return;
+        };
+        if !diagnostics.enabled.load(Ordering::Relaxed) {
+            return;
+        }
+        // 本应用记录到调试级别，依赖库只记录警告和错误
+        let metadata = event.metadata();
+        let own = metadata.target().starts_with(env!("CARGO_CRATE_NAME"));
+        let max_level = if own { Level::DEBUG } else { Level::WARN };
+        if *metadata.level() > max_level {
+            return;
+        }
+
+        let mut message = MessageVisitor(String::new());
+        event.record(&mut message);
+        let entry = LogEntry {
+            time: Local::now(),
+            level: *metadata.level(),
+            target: metadata.target().to_string(),
+            message: message.0,
+        };
+
+        if let Some(file) = diagnostics.file.lock().unwrap().as_mut() {
+            writeln!(file, "{}", entry.to_line()).ok();
+        }
+        let mut recent = diagnostics.recent.lock().unwrap();
+        if recent.len() == RECENT_LIMIT {
+            recent.pop_front();
+        }
+        recent.push_back(entry);
+    }
+}
+
+// message 字段放在最前面，其余字段以 名称=值 附在后面
+struct MessageVisitor(String);
+
+impl tracing::field::Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let fields = std::mem::take(&mut self.0);
+            write!(self.0, "{:?}{}", value, fields).ok();
+        } else {
+            write!(self.0, " {}={:?}", field.name(), value).ok();
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            let fields = std::mem::take(&mut self.0);
+            write!(self.0, "{}{}", value, fields).ok();
+        } else {
+            write!(self.0, " {}={}", field.name(), value).ok();
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use crate::{note::Note, settings::PandocSettings};
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use std::{
+    fs,
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+/// 需要借助 pandoc 生成的导出格式
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ExportFormat {
+    Docx,
+    Epub,
+    Latex,
+    Html,
+}
+
+impl ExportFormat {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Docx => "Word (DOCX)",
+            Self::Epub => "EPUB",
+            Self::Latex => "LaTeX",
+            Self::Html => "HTML",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Docx => "docx",
+            Self::Epub => "epub",
+            Self::Latex => "tex",
+            Self::Html => "html",
+        }
+    }
+
+    // pandoc 的输出格式名
+    fn writer(&self) -> &'static str {
+        match self {
+            Self::Docx => "docx",
+            Self::Epub => "epub",
+            Self::Latex => "latex",
+            Self::Html => "html5",
+        }
+    }
+}
+
+/// 调用 pandoc 把笔记转换为指定格式，写到 `output`
+///
+/// stylesheet 是预览使用的 CSS 文件：HTML 把它写进页面，EPUB 作为电子书的样式表，其他格式忽略。
+pub fn export_with_pandoc(
+    note: &Note,
+    format: ExportFormat,
+    settings: &PandocSettings,
+    stylesheet: Option<&Path>,
+    output: &Path,
+) -> Result<()> {
+    // 内嵌到 HTML 的 <style>，导出的文件单独打开也有样式
+    let header = match (format, stylesheet) {
+        (ExportFormat::Html, Some(stylesheet)) => {
+            let css = fs::read_to_string(stylesheet)
+                .context(format!("无法读取样式表 {:?}", stylesheet))?;
+            let header = std::env::temp_dir().join(format!("nothingbook-style-{}.html", note.id));
+            fs::write(&header, format!("<style>\n{}\n</style>\n", css))
+                .context("无法写入临时文件")?;
+            Some(header)
+        }
+        _ => None,
+    };
+    let result = run_pandoc(
+        note,
+        format,
+        settings,
+        stylesheet,
+        header.as_deref(),
+        output,
+    );
+    if let Some(header) = header {
+        fs::remove_file(header).ok();
+    }
+    result
+}
+
+fn run_pandoc(
+    note: &Note,
+    format: ExportFormat,
+    settings: &PandocSettings,
+    stylesheet: Option<&Path>,
+    header: Option<&Path>,
+    output: &Path,
+) -> Result<()> {
+    let mut command = Command::new(&settings.path);
+    if let Some(header) = header {
+        command.arg("--include-in-header").arg(header);
+    }
+    if let (ExportFormat::Epub, Some(stylesheet)) = (format, stylesheet) {
+        command.arg("--css").arg(stylesheet);
+    }
+    let mut child = command
+        .args([
+            "--from",
+            "markdown",
+            "--to",
+            format.writer(),
+            "--standalone",
+        ])
+        .arg("--metadata")
+        .arg(format!("title={}", note.title))
+        .arg("--output")
+        .arg(output)
+        .args(settings.args(format))
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context(format!(
+            "无法启动 pandoc（{}），请确认已安装",
+            settings.path
+        ))?;
+
+    child
+        .stdin
+        .take()
+        .context("无法写入 pandoc")?
+        .write_all(note.content.as_bytes())
+        .context("无法写入 pandoc")?;
+    let result = child.wait_with_output().context("等待 pandoc 失败")?;
+    if !result.status.success() {
+        bail!(
+            "pandoc 转换失败: {}",
+            String::from_utf8_lossy(&result.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// 去掉文件名中不允许出现的字符
+pub fn file_name(title: &str) -> String {
+    let name: String = title
+        .chars()
+        .map(|ch| match ch {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            ch if ch.is_control() => '_',
+            ch => ch,
+        })
+        .collect();
+    let name = name.trim();
+    if name.is_empty() {
+        "untitled".to_string()
+    } else {
+        name.to_string()
+    }
+}
@@ -0,0 +1,119 @@
+use crate::note::Note;
+use std::collections::HashMap;
+
+const ITERATIONS: usize = 200;
+// 节点与画布边缘保持的留白，避免标题被裁掉
+const MARGIN: f32 = 0.08;
+
+pub struct GraphNode {
+    pub id: u128,
+    pub title: String,
+    /// 归一化坐标，x/y 都在 [0, 1] 内，渲染时再按画布尺寸缩放
+    pub x: f32,
+    pub y: f32,
+}
+
+pub struct NoteGraph {
+    pub nodes: Vec<GraphNode>,
+    /// 以 nodes 下标表示的无向边
+    pub edges: Vec<(usize, usize)>,
+}
+
+impl NoteGraph {
+    /// 以笔记为节点、双链为边构建图，并用力导向算法计算布局
+    pub fn build(notes: &[&Note]) -> Self {
+        let mut notes = notes.to_vec();
+        // 按 ID 排序保证同样的数据每次得到同样的布局
+        notes.sort_by_key(|note| note.id);
+
+        let index_by_title: HashMap<&str, usize> = notes
+            .iter()
+            .enumerate()
+            .map(|(ix, note)| (note.title.as_str(), ix))
+            .collect();
+
+        let mut edges = Vec::new();
+        for (from, note) in notes.iter().enumerate() {
+            for link in note.wiki_links() {
+                if let Some(&to) = index_by_title.get(link.as_str()) {
+                    let edge = (from.min(to), from.max(to));
+                    if from != to && !edges.contains(&edge) {
+                        edges.push(edge);
+                    }
+                }
+            }
+        }
+
+        let count = notes.len().max(1) as f32;
+        let mut nodes: Vec<GraphNode> = notes
+            .iter()
+            .enumerate()
+            .map(|(ix, note)| {
+                let angle = ix as f32 / count * std::f32::consts::TAU;
+                GraphNode {
+                    id: note.id,
+                    title: note.title.clone(),
+                    x: 0.5 + 0.35 * angle.cos(),
+                    y: 0.5 + 0.35 * angle.sin(),
+                }
+            })
+            .collect();
+
+        layout(&mut nodes, &edges);
+        Self { nodes, edges }
+    }
+}
+
+// Fruchterman-Reingold：节点两两相斥，有边的节点相吸，温度逐步降低直到稳定
+fn layout(nodes: &mut [GraphNode], edges: &[(usize, usize)]) {
+    if nodes.len() < 2 {
+        for node in nodes.iter_mut() {
+            node.x = 0.5;
+            node.y = 0.5;
+        }
+        return;
+    }
+
+    let k = (1.0 / nodes.len() as f32).sqrt();
+    let mut temperature = 0.1;
+    let cooling = temperature / ITERATIONS as f32;
+
+    for _ in 0..ITERATIONS {
+        let mut forces = vec![(0.0f32, 0.0f32); nodes.len()];
+
+        for i in 0..nodes.len() {
+            for j in (i + 1)..nodes.len() {
+                let dx = nodes[i].x - nodes[j].x;
+                let dy = nodes[i].y - nodes[j].y;
+                let distance = (dx * dx + dy * dy).sqrt().max(0.001);
+                let force = k * k / distance;
+                let (fx, fy) = (dx / distance * force, dy / distance * force);
+                forces[i].0 += fx;
+                forces[i].1 += fy;
+                forces[j].0 -= fx;
+                forces[j].1 -= fy;
+            }
+        }
+
+        for &(a, b) in edges {
+            let dx = nodes[a].x - nodes[b].x;
+            let dy = nodes[a].y - nodes[b].y;
+            let distance = (dx * dx + dy * dy).sqrt().max(0.001);
+            let force = distance * distance / k;
+            let (fx, fy) = (dx / distance * force, dy / distance * force);
+            forces[a].0 -= fx;
+            forces[a].1 -= fy;
+            forces[b].0 += fx;
+            forces[b].1 += fy;
+        }
+
+        for (node, (fx, fy)) in nodes.iter_mut().zip(forces) {
+            let length = (fx * fx + fy * fy).sqrt().max(0.001);
+            let step = length.min(temperature);
+            node.x = (node.x + fx / length * step).clamp(MARGIN, 1.0 - MARGIN);
+            node.y = (node.y + fy / length * step).clamp(MARGIN, 1.0 - MARGIN);
+        }
+
+        temperature -= cooling;
+    }
+}
@@ -0,0 +1,48 @@
+use crate::note::Note;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::{fs, path::Path};
+
+const IMPORT_EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "bmp", "tif", "tiff"];
+
+/// 能否作为笔记导入，只接受 Markdown 和纯文本文件
+pub fn is_importable(path: &Path) -> bool {
+    path.is_file()
+        && path.extension().is_some_and(|ext| {
+            IMPORT_EXTENSIONS
+                .iter()
+                .any(|allowed| ext.eq_ignore_ascii_case(allowed))
+        })
+}
+
+/// 图片文件作为附件添加到当前笔记
+pub fn is_image(path: &Path) -> bool {
+    path.is_file()
+        && path.extension().is_some_and(|ext| {
+            IMAGE_EXTENSIONS
+                .iter()
+                .any(|allowed| ext.eq_ignore_ascii_case(allowed))
+        })
+}
+
+/// 把文件读成新笔记：标题取文件名，时间取文件的创建和修改时间
+pub fn note_from_file(path: &Path) -> Result<Note> {
+    let content = fs::read_to_string(path).context(format!("读取文件失败: {:?}", path))?;
+    let metadata = fs::metadata(path).context(format!("读取文件信息失败: {:?}", path))?;
+
+    let mut note = Note::new();
+    if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+        note.title = stem.to_string();
+    }
+    note.content = content;
+    if let Ok(modified) = metadata.modified() {
+        note.updated_at = DateTime::<Utc>::from(modified);
+        // 部分文件系统不记录创建时间，用修改时间代替
+        note.created_at = metadata
+            .created()
+            .map(DateTime::<Utc>::from)
+            .unwrap_or(note.updated_at);
+    }
+    Ok(note)
+}
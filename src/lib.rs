@@ -0,0 +1,40 @@
+//! NothingBook 的各个模块，图形界面和命令行都从 main.rs 进入，
+//! 拆成库是为了让 benches 下的基准测试也能使用存储和笔记模块
+
+pub mod actions;
+pub mod ai;
+pub mod anki;
+pub mod api;
+pub mod app;
+pub mod attachments;
+pub mod card;
+pub mod cli;
+pub mod curl;
+pub mod deep_link;
+pub mod diagnostics;
+pub mod export;
+pub mod graph;
+pub mod import;
+pub mod markup;
+pub mod mcp;
+pub mod note;
+pub mod note_index;
+pub mod note_list;
+pub mod ocr;
+pub mod plugins;
+pub mod publish;
+pub mod read_later;
+pub mod scripting;
+pub mod search_index;
+pub mod session;
+pub mod settings;
+pub mod share;
+pub mod speech;
+pub mod startup_timing;
+pub mod stats;
+pub mod storage;
+pub mod stylesheet;
+pub mod synthetic;
+pub mod vault;
+pub mod views;
+pub mod voice;
@@ -1,23 +1,126 @@
 use gpui::*;
 
-use crate::app::AppView;
-mod app;
-mod note;
-mod note_list;
-mod storage;
-mod views;
+use clap::Parser;
+use futures::StreamExt;
+use notes_app::{
+    actions, api,
+    app::AppView,
+    cli,
+    deep_link::{DeepLink, URL_SCHEME},
+    diagnostics, settings,
+    startup_timing::{StartupPhase, StartupTiming},
+    storage::Storage,
+    vault::Vaults,
+};
+use std::time::Instant;
 
 fn main() -> anyhow::Result<()> {
+    let started = Instant::now();
+    let cli = cli::Cli::parse();
+    let mut vaults = Vaults::load(Storage::default_dir()?)?;
+    let vault = vaults.resolve(cli.vault.as_deref())?;
+    let storage = Storage::with_dir(vault.path.clone())?;
+    if let Some(command) = cli.command {
+        return cli::run(command, storage, &vaults);
+    }
+    // 切换笔记库时记下目标再重启，不带 --vault 启动时打开上次的笔记库
+    let vault = vaults.remember(&vault).unwrap_or_else(|e| {
+        eprintln!("保存笔记库列表失败: {:#}", e);
+        vault
+    });
+
+    // 系统转交的 notes:// 链接先放进通道，窗口建好后再逐个处理
+    let (url_tx, mut url_rx) = futures::channel::mpsc::unbounded::<String>();
+    if let Some(url) = cli.url {
+        url_tx.unbounded_send(url).ok();
+    }
+
     let app = Application::new().with_assets(gpui_component_assets::Assets);
-    app.run(|cx| {
+    app.on_open_urls(move |urls| {
+        for url in urls {
+            url_tx.unbounded_send(url).ok();
+        }
+    });
+    let report_timing = cli.startup_timing;
+    // 先按关闭状态安装日志，读到设置后再决定是否开启诊断
+    diagnostics::init(&storage.logs_dir(), false);
+
+    app.run(move |cx| {
+        StartupTiming::init(started, report_timing, cx);
         gpui_component::init(cx);
-        cx.open_window(WindowOptions::default(), |window, cx| {
-            let app_view = cx.new(|cx| AppView::new(cx).expect("初始化应用失败"));
-            let root: Entity<gpui_component::Root> =
-                cx.new(|cx| gpui_component::Root::new(app_view.clone(), window, cx));
-            root
+        settings::AppSettings::init(&storage, cx);
+        diagnostics::set_enabled(settings::AppSettings::get(cx).diagnostics.enabled);
+        actions::init(cx);
+
+        let options = WindowOptions {
+            titlebar: Some(gpui_component::TitleBar::title_bar_options()),
+            window_decorations: Some(WindowDecorations::Client),
+            ..Default::default()
+        };
+        let mut app_view = None;
+        let window = cx
+            .open_window(options, |window, cx| {
+                let view = cx.new(|cx| AppView::new(storage.clone(), window, cx));
+                window.focus(&view.focus_handle(cx));
+                app_view = Some(view.clone());
+                let root: Entity<gpui_component::Root> =
+                    cx.new(|cx| gpui_component::Root::new(view, window, cx));
+                root
+            })
+            .expect("创建窗口失败");
+        let app_view = app_view.expect("创建窗口失败");
+        app_view.update(cx, |view, cx| view.set_vaults(vault, vaults, cx));
+        StartupTiming::mark(StartupPhase::WindowOpened, cx);
+
+        let register = cx.register_url_scheme(URL_SCHEME);
+        cx.spawn(async move |_cx| {
+            if let Err(e) = register.await {
+                eprintln!("注册 {}:// 协议失败: {}", URL_SCHEME, e);
+            }
         })
-        .expect("创建窗口失败");
+        .detach();
+
+        let link_view = app_view.clone();
+        cx.spawn(async move |cx| {
+            while let Some(url) = url_rx.next().await {
+                let Some(link) = DeepLink::parse(&url) else {
+                    eprintln!("无法识别的链接: {}", url);
+                    continue;
+                };
+                let result = window.update(cx, |_, window, cx| {
+                    window.activate_window();
+                    link_view.update(cx, |view, cx| view.open_deep_link(link, window, cx));
+                });
+                if result.is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+
+        let api = settings::AppSettings::get(cx).api.clone();
+        if api.enabled {
+            let (call_tx, mut call_rx) = futures::channel::mpsc::unbounded::<api::ApiCall>();
+            match api::start(&api, call_tx) {
+                Ok(()) => {
+                    cx.spawn(async move |cx| {
+                        while let Some(call) = call_rx.next().await {
+                            let reply = window.update(cx, |_, window, cx| {
+                                app_view.update(cx, |view, cx| {
+                                    view.handle_api_request(call.request, window, cx)
+                                })
+                            });
+                            match reply {
+                                Ok(reply) => call.reply.send(reply).ok(),
+                                Err(_) => break,
+                            };
+                        }
+                    })
+                    .detach();
+                }
+                Err(e) => eprintln!("启动本机接口失败: {}", e),
+            }
+        }
     });
 
     Ok(())
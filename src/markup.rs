@@ -0,0 +1,416 @@
+use std::ops::Range;
+
+/// `==文字==` 标记的高亮
+pub const HIGHLIGHT_MARKER: &str = "==";
+
+/// 预览中的一个块
+#[derive(Clone, Debug, PartialEq)]
+pub enum Block {
+    Heading {
+        level: usize,
+        text: String,
+    },
+    Paragraph(String),
+    /// marker 是 `-` 或 `1.` 这样的列表符号，depth 按缩进计算
+    ListItem {
+        marker: String,
+        depth: usize,
+        text: String,
+    },
+    Quote(String),
+    Code(String),
+    /// 单独一行的图片
+    Image {
+        alt: String,
+        src: String,
+    },
+    /// 单独一行的 `![[标题]]` 或 `![[标题#小节]]`，预览时显示另一篇笔记的内容
+    Embed {
+        title: String,
+        heading: Option<String>,
+    },
+    Rule,
+}
+
+/// 行内格式，范围是去掉标记后的文字中的字节范围
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mark {
+    Bold,
+    Italic,
+    Code,
+    Highlight,
+    Link,
+}
+
+/// 去掉标记后的文字和各段格式
+#[derive(Debug, Default, PartialEq)]
+pub struct Inline {
+    pub text: String,
+    pub marks: Vec<(Range<usize>, Mark)>,
+}
+
+/// 把正文按 Markdown 的常用写法分块，只覆盖笔记里常见的语法
+pub fn parse_blocks(content: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut quote: Vec<&str> = Vec::new();
+    let mut code: Option<Vec<&str>> = None;
+
+    fn flush(blocks: &mut Vec<Block>, paragraph: &mut Vec<&str>, quote: &mut Vec<&str>) {
+        if !paragraph.is_empty() {
+            blocks.push(Block::Paragraph(paragraph.join("\n")));
+            paragraph.clear();
+        }
+        if !quote.is_empty() {
+            blocks.push(Block::Quote(quote.join("\n")));
+            quote.clear();
+        }
+    }
+
+    for line in content.lines() {
+        if let Some(lines) = code.as_mut() {
+            if line.trim_start().starts_with("```") {
+                blocks.push(Block::Code(lines.join("\n")));
+                code = None;
+            } else {
+                lines.push(line);
+            }
+            continue;
+        }
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            flush(&mut blocks, &mut paragraph, &mut quote);
+            code = Some(Vec::new());
+            continue;
+        }
+        if trimmed.is_empty() {
+            flush(&mut blocks, &mut paragraph, &mut quote);
+            continue;
+        }
+        if let Some(text) = trimmed.strip_prefix('>') {
+            if !paragraph.is_empty() {
+                flush(&mut blocks, &mut paragraph, &mut quote);
+            }
+            quote.push(text.trim_start());
+            continue;
+        }
+        if !quote.is_empty() {
+            flush(&mut blocks, &mut paragraph, &mut quote);
+        }
+        if let Some(block) = line_block(line) {
+            flush(&mut blocks, &mut paragraph, &mut quote);
+            blocks.push(block);
+        } else {
+            paragraph.push(trimmed);
+        }
+    }
+    // 没有闭合的代码块也照常显示
+    if let Some(lines) = code {
+        blocks.push(Block::Code(lines.join("\n")));
+    }
+    flush(&mut blocks, &mut paragraph, &mut quote);
+    blocks
+}
+
+// 自成一块的单行：标题、列表项、分隔线和单独的图片
+fn line_block(line: &str) -> Option<Block> {
+    let trimmed = line.trim();
+    let hashes = trimmed.chars().take_while(|&c| c == '#').count();
+    if (1..=6).contains(&hashes)
+        && let Some(text) = trimmed[hashes..].strip_prefix(' ')
+    {
+        return Some(Block::Heading {
+            level: hashes,
+            text: text.trim().to_string(),
+        });
+    }
+    if trimmed.len() >= 3
+        && (trimmed.chars().all(|c| c == '-') || trimmed.chars().all(|c| c == '*'))
+    {
+        return Some(Block::Rule);
+    }
+    let depth = (line.len() - line.trim_start().len()) / 2;
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(text) = trimmed.strip_prefix(bullet) {
+            return Some(Block::ListItem {
+                marker: "•".to_string(),
+                depth,
+                text: text.to_string(),
+            });
+        }
+    }
+    let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0
+        && let Some(text) = trimmed[digits..].strip_prefix(". ")
+    {
+        return Some(Block::ListItem {
+            marker: trimmed[..digits + 1].to_string(),
+            depth,
+            text: text.to_string(),
+        });
+    }
+    if let Some(target) = trimmed
+        .strip_prefix("![[")
+        .and_then(|rest| rest.strip_suffix("]]"))
+        && !target.trim().is_empty()
+    {
+        let (title, heading) = match target.split_once('#') {
+            Some((title, heading)) => (title, Some(heading.trim().to_string())),
+            None => (target, None),
+        };
+        return Some(Block::Embed {
+            title: title.trim().to_string(),
+            heading: heading.filter(|heading| !heading.is_empty()),
+        });
+    }
+    if let Some(rest) = trimmed.strip_prefix("![")
+        && let Some(split) = rest
+            .match_indices("](")
+            .map(|(ix, _)| ix)
+            .find(|&ix| !rest[..ix].ends_with('\\'))
+        && let Some(src) = rest[split + 2..].strip_suffix(')')
+    {
+        // `<...>` 形式的地址可以带空格
+        let src = src
+            .strip_prefix('<')
+            .and_then(|src| src.strip_suffix('>'))
+            .unwrap_or(src);
+        return Some(Block::Image {
+            alt: unescape(&rest[..split]),
+            src: src.to_string(),
+        });
+    }
+    None
+}
+
+/// 引用附件中图片的一行 Markdown
+///
+/// 地址放在尖括号里，文件名带空格也是合法的 CommonMark，pandoc 导出时不会丢图。
+pub fn attachment_image(alt: &str, name: &str) -> String {
+    let mut escaped = String::with_capacity(alt.len());
+    for ch in alt.chars() {
+        if matches!(ch, '\\' | '[' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    format!("![{}](<attachments/{}>)", escaped, name)
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => out.extend(chars.next()),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+/// 标题为 heading 的小节，从标题的下一行到下一个同级或更高级的标题之前
+pub fn section<'a>(content: &'a str, heading: &str) -> Option<&'a str> {
+    let mut start = None;
+    let mut level = 0;
+    let mut in_code = false;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        if in_code {
+            continue;
+        }
+        let Some(Block::Heading {
+            level: line_level,
+            text,
+        }) = line_block(line)
+        else {
+            continue;
+        };
+        match start {
+            None if text == heading.trim() => {
+                start = Some(offset);
+                level = line_level;
+            }
+            Some(start) if line_level <= level => {
+                return Some(content[start..line_start].trim_end());
+            }
+            _ => {}
+        }
+    }
+    start.map(|start| content[start..].trim_end())
+}
+
+/// 解析行内的 `**粗体**`、`*斜体*`、`` `代码` ``、`==高亮==` 和 `[链接](网址)`
+///
+/// 标记可以嵌套，例如 `==**重点**==`；代码中的标记原样保留。没有配对的标记按普通文字处理。
+pub fn parse_inline(source: &str) -> Inline {
+    let mut inline = Inline::default();
+    parse_into(source, &mut inline);
+    inline
+}
+
+fn parse_into(source: &str, inline: &mut Inline) {
+    let mut rest = source;
+    while !rest.is_empty() {
+        let delimited = [
+            ("`", Mark::Code),
+            ("**", Mark::Bold),
+            (HIGHLIGHT_MARKER, Mark::Highlight),
+            ("*", Mark::Italic),
+        ]
+        .into_iter()
+        .find_map(|(marker, mark)| {
+            let body = rest.strip_prefix(marker)?;
+            let end = body.find(marker).filter(|&end| end > 0)?;
+            Some((mark, &body[..end], marker.len() * 2 + end))
+        });
+        if let Some((mark, body, consumed)) = delimited {
+            let start = inline.text.len();
+            if mark == Mark::Code {
+                inline.text.push_str(body);
+            } else {
+                parse_into(body, inline);
+            }
+            inline.marks.push((start..inline.text.len(), mark));
+            rest = &rest[consumed..];
+            continue;
+        }
+        if let Some(body) = rest.strip_prefix('[')
+            && let Some((label, after)) = body.split_once("](")
+            && let Some(end) = after.find(')')
+            && !label.contains('[')
+        {
+            let start = inline.text.len();
+            parse_into(label, inline);
+            inline.marks.push((start..inline.text.len(), Mark::Link));
+            rest = &after[end + 1..];
+            continue;
+        }
+        let ch = rest.chars().next().unwrap_or_default();
+        inline.text.push(ch);
+        rest = &rest[ch.len_utf8()..];
+    }
+}
+
+/// 给选中的文字加上高亮标记，已经是高亮的去掉标记
+pub fn toggle_highlight(selected: &str) -> String {
+    let marker = HIGHLIGHT_MARKER;
+    match selected
+        .strip_prefix(marker)
+        .and_then(|text| text.strip_suffix(marker))
+    {
+        Some(text) if selected.len() >= marker.len() * 2 => text.to_string(),
+        _ => format!("{}{}{}", marker, selected, marker),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inline_marks_nest_and_strip_markers() {
+        let inline = parse_inline("复习 ==**重点**== 和 `a==b` 见[文档](https://example.com)");
+        assert_eq!(inline.text, "复习 重点 和 a==b 见文档");
+        let mark = |text: &str, mark| {
+            let start = inline.text.find(text).unwrap();
+            (start..start + text.len(), mark)
+        };
+        assert_eq!(
+            inline.marks,
+            vec![
+                mark("重点", Mark::Bold),
+                mark("重点", Mark::Highlight),
+                mark("a==b", Mark::Code),
+                mark("文档", Mark::Link),
+            ]
+        );
+        // 没有配对的标记是普通文字
+        assert_eq!(parse_inline("a == b").text, "a == b");
+    }
+
+    #[test]
+    fn blocks_from_common_markdown() {
+        let blocks = parse_blocks(
+            "# 标题\n第一行\n第二行\n\n- 列表\n  1. 子项\n> 引用\n```\nlet x = 1;\n```\n---\n![图](attachments/a.png)",
+        );
+        assert_eq!(
+            blocks,
+            vec![
+                Block::Heading {
+                    level: 1,
+                    text: "标题".to_string()
+                },
+                Block::Paragraph("第一行\n第二行".to_string()),
+                Block::ListItem {
+                    marker: "•".to_string(),
+                    depth: 0,
+                    text: "列表".to_string()
+                },
+                Block::ListItem {
+                    marker: "1.".to_string(),
+                    depth: 1,
+                    text: "子项".to_string()
+                },
+                Block::Quote("引用".to_string()),
+                Block::Code("let x = 1;".to_string()),
+                Block::Rule,
+                Block::Image {
+                    alt: "图".to_string(),
+                    src: "attachments/a.png".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn attachment_images_survive_spaces_and_brackets() {
+        let line = attachment_image("截图 [1].png", "截图 [1].png");
+        assert_eq!(line, r"![截图 \[1\].png](<attachments/截图 [1].png>)");
+        assert_eq!(
+            parse_blocks(&line),
+            vec![Block::Image {
+                alt: "截图 [1].png".to_string(),
+                src: "attachments/截图 [1].png".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn toggling_highlight_adds_and_removes_markers() {
+        assert_eq!(toggle_highlight("重点"), "==重点==");
+        assert_eq!(toggle_highlight("==重点=="), "重点");
+        assert_eq!(toggle_highlight(""), "====");
+        assert_eq!(toggle_highlight("==="), "=======");
+    }
+
+    #[test]
+    fn embeds_and_sections() {
+        assert_eq!(
+            parse_blocks("![[读书笔记#第二章]]\n![[周报]]"),
+            vec![
+                Block::Embed {
+                    title: "读书笔记".to_string(),
+                    heading: Some("第二章".to_string()),
+                },
+                Block::Embed {
+                    title: "周报".to_string(),
+                    heading: None,
+                },
+            ]
+        );
+
+        let content = "# 书名\n## 第一章\n开头\n## 第二章\n正文\n```\n# 不是标题\n```\n### 小节\n细节\n## 第三章\n结尾\n";
+        assert_eq!(
+            section(content, "第二章"),
+            Some("正文\n```\n# 不是标题\n```\n### 小节\n细节")
+        );
+        assert_eq!(section(content, "第三章"), Some("结尾"));
+        assert_eq!(section(content, "第四章"), None);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{note::Note, storage::Storage};
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+};
+
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// 以 Model Context Protocol 服务器的方式运行，通过标准输入输出收发 JSON-RPC 消息
+///
+/// 每次调用工具都重新读取数据目录，图形界面中的修改可以立即被看到。
+pub fn serve(storage: Storage) -> Result<()> {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+
+    for line in stdin.lock().lines() {
+        let line = line.context("读取标准输入失败")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message: Value = match serde_json::from_str(&line) {
+            Ok(message) => message,
+            Err(e) => {
+                let response = error_response(Value::Null, -32700, &format!("解析失败: {}", e));
+                writeln!(stdout, "{}", response)?;
+                stdout.flush()?;
+                continue;
+            }
+        };
+        // 没有 id 的是通知，不需要回复
+        let Some(id) = message.get("id").cloned() else {
+            continue;
+        };
+        let method = message["method"].as_str().unwrap_or_default();
+        let response = match method {
+            "initialize" => result_response(
+                id,
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": { "tools": {} },
+                    "serverInfo": { "name": "nothingbook", "version": env!("CARGO_PKG_VERSION") },
+                }),
+            ),
+            "ping" => result_response(id, json!({})),
+            "tools/list" => result_response(id, json!({ "tools": tools() })),
+            "tools/call" => {
+                let name = message["params"]["name"].as_str().unwrap_or_default();
+                let arguments = &message["params"]["arguments"];
+                let result = match call_tool(&storage, name, arguments) {
+                    Ok(text) => json!({ "content": [{ "type": "text", "text": text }] }),
+                    Err(e) => json!({
+                        "content": [{ "type": "text", "text": format!("{:#}", e) }],
+                        "isError": true,
+                    }),
+                };
+                result_response(id, result)
+            }
+            _ => error_response(id, -32601, &format!("不支持的方法: {}", method)),
+        };
+        writeln!(stdout, "{}", response)?;
+        stdout.flush()?;
+    }
+    Ok(())
+}
+
+fn tools() -> Value {
+    json!([
+        {
+            "name": "list_notes",
+            "description": "列出所有笔记的 ID、标题、标签和修改时间，按修改时间倒序",
+            "inputSchema": { "type": "object", "properties": {} },
+        },
+        {
+            "name": "search_notes",
+            "description": "按关键字搜索笔记标题和正文（不区分大小写）",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "query": { "type": "string" } },
+                "required": ["query"],
+            },
+        },
+        {
+            "name": "read_note",
+            "description": "读取一篇笔记的完整内容",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "id": { "type": "string", "description": "笔记 ID" } },
+                "required": ["id"],
+            },
+        },
+        {
+            "name": "create_note",
+            "description": "新建一篇 Markdown 笔记，返回新笔记的 ID",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "title": { "type": "string" },
+                    "content": { "type": "string" },
+                },
+                "required": ["title", "content"],
+            },
+        },
+    ])
+}
+
+fn call_tool(storage: &Storage, name: &str, arguments: &Value) -> Result<String> {
+    let mut notes = HashMap::new();
+    storage.load_all_notes(&mut notes)?;
+    let mut notes: Vec<Note> = notes.into_values().collect();
+    notes.sort_by_key(|note| std::cmp::Reverse(note.updated_at));
+
+    match name {
+        "list_notes" => Ok(summaries(notes.iter())),
+        "search_notes" => {
+            let query = arguments["query"].as_str().context("缺少参数 query")?;
+            Ok(summaries(notes.iter().filter(|note| note.matches(query))))
+        }
+        "read_note" => {
+            let id: u128 = arguments["id"]
+                .as_str()
+                .context("缺少参数 id")?
+                .parse()
+                .context("笔记 ID 格式错误")?;
+            let note = notes
+                .iter()
+                .find(|note| note.id == id)
+                .context(format!("找不到笔记 {}", id))?;
+            Ok(note.to_markdown())
+        }
+        "create_note" => {
+            let mut note = Note::new();
+            note.title = arguments["title"]
+                .as_str()
+                .context("缺少参数 title")?
+                .to_string();
+            note.content = arguments["content"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string();
+            storage.save_note(&note)?;
+            Ok(format!("已创建笔记 {}", note.id))
+        }
+        _ => anyhow::bail!("未知工具: {}", name),
+    }
+}
+
+fn summaries<'a>(notes: impl Iterator<Item = &'a Note>) -> String {
+    let rows: Vec<Value> = notes
+        .map(|note| {
+            json!({
+                "id": note.id.to_string(),
+                "title": note.title,
+                "tags": note.tags,
+                "updated_at": note.updated_at.to_rfc3339(),
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&rows).unwrap_or_default()
+}
+
+fn result_response(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
@@ -1,34 +1,122 @@
-use chrono::{DateTime, Local};
-use serde::{Deserialize, Serialize};
+use crate::{
+    deep_link::{DeepLink, URL_SCHEME},
+    publish::Published,
+};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone as _, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::Range,
+};
 use uuid::Uuid;
 
+/// 预览默认截取的字符数
+pub const DEFAULT_PREVIEW_CHARS: usize = 50;
+
+/// 新建笔记的默认标题，重名时加上序号
+pub const DEFAULT_TITLE: &str = "新建笔记";
+
+/// 标题最多的字符数
+pub const MAX_TITLE_CHARS: usize = 100;
+
+/// 日记笔记的标题格式，例如 2024-05-01
+pub const DAILY_TITLE_FORMAT: &str = "%Y-%m-%d";
+
+/// 同一笔记的两个版本冲突时，较旧的一个另存为带这个后缀的新笔记
+pub const CONFLICT_COPY_SUFFIX: &str = "（冲突副本）";
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct Note {
     pub id: u128,
     pub title: String,
     pub content: String,
-    pub created_at: DateTime<Local>,
-    pub updated_at: DateTime<Local>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub due_date: Option<NaiveDate>,
+    #[serde(deserialize_with = "deserialize_timestamp")]
+    pub created_at: DateTime<Utc>,
+    #[serde(deserialize_with = "deserialize_timestamp")]
+    pub updated_at: DateTime<Utc>,
+    /// 图片附件中识别出的文字，按附件文件名记录，参与搜索
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attachment_text: BTreeMap<String, String>,
+    /// 发布为分享链接后记录 Gist 信息，取消发布时清除
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published: Option<Published>,
+    /// 父笔记，在侧边栏中显示在父笔记下面
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<u128>,
 }
 
 impl Note {
     pub fn new() -> Self {
-        let now = Local::now();
+        let now = Utc::now();
         Self {
             id: Uuid::new_v4().to_u128_le(),
-            title: "新建笔记".to_string(),
+            title: DEFAULT_TITLE.to_string(),
             content: String::new(),
+            tags: Vec::new(),
+            due_date: None,
             created_at: now,
             updated_at: now,
+            attachment_text: BTreeMap::new(),
+            published: None,
+            parent: None,
+        }
+    }
+
+    /// 挂在 parent 下面的新笔记
+    pub fn child_of(parent: u128) -> Self {
+        Self {
+            parent: Some(parent),
+            ..Self::new()
+        }
+    }
+
+    pub fn daily(date: NaiveDate) -> Self {
+        Self {
+            title: date.format(DAILY_TITLE_FORMAT).to_string(),
+            ..Self::new()
+        }
+    }
+
+    /// 使用新 ID 的副本，标题加上冲突副本的后缀，修改时间不变
+    pub fn conflict_copy(&self) -> Self {
+        Self {
+            id: Uuid::new_v4().to_u128_le(),
+            title: format!("{}{}", self.title, CONFLICT_COPY_SUFFIX),
+            // 发布记录只留给原笔记，免得两篇笔记更新同一个分享链接
+            published: None,
+            ..self.clone()
         }
     }
 
+    /// 标题、正文、标签和截止日期相同时视为同一版本，只是保存时间不同
+    pub fn same_version(&self, other: &Note) -> bool {
+        self.title == other.title
+            && self.content == other.content
+            && self.tags == other.tags
+            && self.due_date == other.due_date
+    }
+
+    /// 标题是日期的笔记视为当天的日记
+    pub fn daily_date(&self) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(self.title.trim(), DAILY_TITLE_FORMAT).ok()
+    }
+
     pub fn preview(&self) -> String {
+        self.preview_with(DEFAULT_PREVIEW_CHARS)
+    }
+
+    /// 正文开头的 max_chars 个字符，超出时加省略号
+    pub fn preview_with(&self, max_chars: usize) -> String {
         if self.content.is_empty() {
             "无内容".to_string()
         } else {
-            let preview: String = self.content.chars().take(50).collect();
-            if self.content.len() > 50 {
+            let preview: String = self.content.chars().take(max_chars).collect();
+            // 按字符而不是字节判断，中文正文不足 max_chars 字时不加省略号
+            if self.content.chars().nth(max_chars).is_some() {
                 format!("{}...", preview)
             } else {
                 preview
@@ -36,8 +124,189 @@ impl Note {
         }
     }
 
+    /// 标题、正文或图片附件的识别文字包含关键字（不区分大小写）
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        self.title.to_lowercase().contains(&query)
+            || self.content.to_lowercase().contains(&query)
+            || self
+                .attachment_text
+                .values()
+                .any(|text| text.to_lowercase().contains(&query))
+    }
+
+    /// 导出为 Markdown，标题作为一级标题
+    pub fn to_markdown(&self) -> String {
+        format!("# {}\n\n{}\n", self.title, self.content)
+    }
+
+    /// 正文中 `[[标题]]` 形式的双链，`[[标题|别名]]` 和 `[[标题#小节]]` 只取标题部分
+    pub fn wiki_links(&self) -> Vec<String> {
+        let mut links = Vec::new();
+        let mut rest = self.content.as_str();
+        while let Some(start) = rest.find("[[") {
+            rest = &rest[start + 2..];
+            let Some(end) = rest.find("]]") else {
+                break;
+            };
+            let target = link_target(&rest[..end]);
+            if !target.is_empty() && !links.iter().any(|l| l == target) {
+                links.push(target.to_string());
+            }
+            rest = &rest[end + 2..];
+        }
+        links
+    }
+
+    /// 正文的第一段，跳过标题行和空行，用于链接预览等只需概览的地方
+    pub fn first_paragraph(&self) -> String {
+        let mut lines = Vec::new();
+        for line in self.content.lines().map(str::trim) {
+            if line.is_empty() {
+                if lines.is_empty() {
+                    continue;
+                }
+                break;
+            }
+            if lines.is_empty() && line.starts_with('#') {
+                continue;
+            }
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
+
+    /// 正文中 `attachments/<文件名>` 形式引用的附件，包括图片和录音
+    pub fn attachments(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut rest = self.content.as_str();
+        while let Some(start) = rest.find("attachments/") {
+            rest = &rest[start + "attachments/".len()..];
+            // 图片引用以右括号结束，录音引用以引号结束；文件名可以带空格
+            let end = rest.find([')', '"', '\'', '>', '\n']).unwrap_or(rest.len());
+            let name = rest[..end].trim();
+            if !name.is_empty() && !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+            rest = &rest[end..];
+        }
+        names
+    }
+
+    /// 把笔记移动到看板的某一列：去掉其他列的标签，加上目标列的标签
+    pub fn set_status(&mut self, status: &str, statuses: &[String]) {
+        self.tags
+            .retain(|tag| tag == status || !statuses.contains(tag));
+        if !self.tags.iter().any(|tag| tag == status) {
+            self.tags.push(status.to_string());
+        }
+        self.updated_at = Utc::now();
+    }
+
+    /// 字数统计：中日韩字符每个算一个字，其余按空白分隔的单词计数
+    pub fn word_count(&self) -> usize {
+        let mut count = 0;
+        let mut in_word = false;
+        for ch in self.content.chars() {
+            if is_cjk(ch) {
+                count += 1;
+                in_word = false;
+            } else if ch.is_alphanumeric() {
+                if !in_word {
+                    count += 1;
+                    in_word = true;
+                }
+            } else {
+                in_word = false;
+            }
+        }
+        count
+    }
+
+    /// 创建时间换算到本地时区，只用于显示和按日期归类
+    pub fn created_local(&self) -> DateTime<Local> {
+        self.created_at.with_timezone(&Local)
+    }
+
+    pub fn updated_local(&self) -> DateTime<Local> {
+        self.updated_at.with_timezone(&Local)
+    }
+
     pub fn formatted_time(&self) -> String {
-        self.updated_at.format("%Y-%m-%d %H:%M").to_string()
+        self.updated_local().format("%Y-%m-%d %H:%M").to_string()
+    }
+}
+
+/// 时间统一以 UTC 保存。旧文件里带本地时区偏移的时间换算成 UTC 读入，
+/// 没有时区的时间按本机时区理解
+fn deserialize_timestamp<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    parse_timestamp(&value)
+        .ok_or_else(|| serde::de::Error::custom(format!("无法识别的时间: {}", value)))
+}
+
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Utc));
+    }
+    let naive = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// 笔记除正文外的字段，启动时先读这些显示侧边栏，正文稍后在后台读取
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NoteMeta {
+    pub id: u128,
+    pub title: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub due_date: Option<NaiveDate>,
+    #[serde(deserialize_with = "deserialize_timestamp")]
+    pub created_at: DateTime<Utc>,
+    #[serde(deserialize_with = "deserialize_timestamp")]
+    pub updated_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<u128>,
+}
+
+impl From<&Note> for NoteMeta {
+    fn from(note: &Note) -> Self {
+        Self {
+            id: note.id,
+            title: note.title.clone(),
+            tags: note.tags.clone(),
+            due_date: note.due_date,
+            created_at: note.created_at,
+            updated_at: note.updated_at,
+            parent: note.parent,
+        }
+    }
+}
+
+impl From<NoteMeta> for Note {
+    /// 正文为空的占位笔记，只用于正文读完之前的显示
+    fn from(meta: NoteMeta) -> Self {
+        Self {
+            id: meta.id,
+            title: meta.title,
+            content: String::new(),
+            tags: meta.tags,
+            due_date: meta.due_date,
+            created_at: meta.created_at,
+            updated_at: meta.updated_at,
+            attachment_text: BTreeMap::new(),
+            published: None,
+            parent: meta.parent,
+        }
     }
 }
 
@@ -46,3 +315,301 @@ impl Default for Note {
         Self::new()
     }
 }
+
+/// 笔记树中显示的一行
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TreeEntry {
+    pub id: u128,
+    /// 顶层笔记为 0
+    pub depth: usize,
+    pub has_children: bool,
+}
+
+/// 按 notes 的顺序把子笔记排在父笔记下面，collapsed 中笔记的子笔记不显示
+///
+/// 父笔记不存在时当作顶层笔记；父子关系成环时从环中第一篇笔记开始显示
+pub fn note_tree(notes: &[&Note], collapsed: &HashSet<u128>) -> Vec<TreeEntry> {
+    let ids: HashSet<u128> = notes.iter().map(|note| note.id).collect();
+    let mut children: HashMap<u128, Vec<u128>> = HashMap::new();
+    let mut roots = Vec::new();
+    for note in notes {
+        match note
+            .parent
+            .filter(|parent| *parent != note.id && ids.contains(parent))
+        {
+            Some(parent) => children.entry(parent).or_default().push(note.id),
+            None => roots.push(note.id),
+        }
+    }
+
+    let mut entries = Vec::new();
+    let mut visited = HashSet::new();
+    // 成环的笔记从任何根都走不到，遍历完根之后按原顺序补上
+    for start in roots.into_iter().chain(notes.iter().map(|note| note.id)) {
+        let mut stack = vec![(start, 0, false)];
+        while let Some((id, depth, hidden)) = stack.pop() {
+            if !visited.insert(id) {
+                continue;
+            }
+            let kids = children.get(&id).map(Vec::as_slice).unwrap_or_default();
+            if !hidden {
+                entries.push(TreeEntry {
+                    id,
+                    depth,
+                    has_children: !kids.is_empty(),
+                });
+            }
+            let hide_kids = hidden || collapsed.contains(&id);
+            stack.extend(kids.iter().rev().map(|&kid| (kid, depth + 1, hide_kids)));
+        }
+    }
+    entries
+}
+
+/// 一行 `<audio src="attachments/...">` 引用的录音附件文件名
+pub fn audio_attachment(line: &str) -> Option<&str> {
+    if !line.trim_start().starts_with("<audio") {
+        return None;
+    }
+    let start = line.find("src=\"attachments/")? + "src=\"attachments/".len();
+    let end = line[start..].find('"')?;
+    Some(&line[start..start + end])
+}
+
+/// 正文中指向另一篇笔记的链接
+#[derive(Clone, Debug, PartialEq)]
+pub enum NoteLink {
+    /// `[[标题]]` 双链
+    Title(String),
+    /// `notes://open/<id>` 链接
+    Id(u128),
+}
+
+/// 位于字节偏移 offset 处的笔记链接，返回链接的字节范围
+pub fn note_link_at(line: &str, offset: usize) -> Option<(Range<usize>, NoteLink)> {
+    if let Some((range, title)) = wiki_link_at(line, offset) {
+        return Some((range, NoteLink::Title(title.to_string())));
+    }
+    let prefix = format!("{}://open/", URL_SCHEME);
+    let mut from = 0;
+    while let Some(start) = line[from..].find(&prefix).map(|ix| from + ix) {
+        let end = line[start..]
+            .find(|c: char| c.is_whitespace() || matches!(c, ')' | ']' | '>' | '"' | '\''))
+            .map_or(line.len(), |ix| start + ix);
+        if offset < start {
+            return None;
+        }
+        if offset < end {
+            return match DeepLink::parse(&line[start..end])? {
+                DeepLink::Open(id) => Some((start..end, NoteLink::Id(id))),
+                _ => None,
+            };
+        }
+        from = end;
+    }
+    None
+}
+
+/// 位于字节偏移 offset 处的 `[[双链]]`，返回整个链接（含括号）的字节范围和目标标题
+///
+/// 只在 offset 所在的一行内查找，双链不跨行。
+pub fn wiki_link_at(line: &str, offset: usize) -> Option<(Range<usize>, &str)> {
+    let mut from = 0;
+    while let Some(start) = line[from..].find("[[").map(|ix| from + ix) {
+        let end = line[start + 2..].find("]]").map(|ix| start + 2 + ix)?;
+        if offset < start {
+            return None;
+        }
+        if offset < end + 2 {
+            let target = link_target(&line[start + 2..end]);
+            return (!target.is_empty()).then_some((start..end + 2, target));
+        }
+        from = end + 2;
+    }
+    None
+}
+
+// `[[标题|别名]]` 和 `[[标题#小节]]` 只取标题部分
+fn link_target(inner: &str) -> &str {
+    inner.split(['|', '#']).next().unwrap_or_default().trim()
+}
+
+fn is_cjk(ch: char) -> bool {
+    matches!(ch,
+        '\u{4E00}'..='\u{9FFF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{3040}'..='\u{30FF}'
+        | '\u{AC00}'..='\u{D7AF}'
+        | '\u{F900}'..='\u{FAFF}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note_with(content: &str) -> Note {
+        Note {
+            content: content.to_string(),
+            ..Note::new()
+        }
+    }
+
+    #[test]
+    fn preview_of_empty_note() {
+        assert_eq!(note_with("").preview(), "无内容");
+    }
+
+    #[test]
+    fn preview_truncates_after_fifty_chars() {
+        let content = "a".repeat(60);
+        assert_eq!(
+            note_with(&content).preview(),
+            format!("{}...", "a".repeat(50))
+        );
+        assert_eq!(note_with(&"a".repeat(50)).preview(), "a".repeat(50));
+    }
+
+    #[test]
+    fn preview_counts_cjk_chars_not_bytes() {
+        // 20 个汉字占 60 字节，不应该被当成超长
+        let short = "中".repeat(20);
+        assert_eq!(note_with(&short).preview(), short);
+
+        let exact = "文".repeat(50);
+        assert_eq!(note_with(&exact).preview(), exact);
+
+        let long = format!("{}{}", "字".repeat(50), "尾");
+        assert_eq!(
+            note_with(&long).preview(),
+            format!("{}...", "字".repeat(50))
+        );
+    }
+
+    #[test]
+    fn preview_with_custom_length() {
+        let note = note_with("一二三四五六");
+        assert_eq!(note.preview_with(3), "一二三...");
+        assert_eq!(note.preview_with(6), "一二三四五六");
+    }
+
+    #[test]
+    fn preview_keeps_emoji_intact() {
+        let content = "🙂".repeat(51);
+        assert_eq!(
+            note_with(&content).preview(),
+            format!("{}...", "🙂".repeat(50))
+        );
+    }
+
+    #[test]
+    fn word_count_mixes_cjk_and_words() {
+        assert_eq!(note_with("你好 world, hello 世界").word_count(), 6);
+        assert_eq!(note_with("").word_count(), 0);
+    }
+
+    #[test]
+    fn wiki_links_strip_alias_and_heading() {
+        let note = note_with("见 [[项目|别名]] 和 [[周报#总结]]，重复的 [[项目]]");
+        assert_eq!(note.wiki_links(), vec!["项目", "周报"]);
+    }
+
+    #[test]
+    fn wiki_link_under_offset() {
+        let line = "见 [[项目|别名]] 和 [[周报]]";
+        let start = line.find("[[").unwrap();
+        let (range, target) = wiki_link_at(line, start + 3).unwrap();
+        assert_eq!(target, "项目");
+        assert_eq!(&line[range], "[[项目|别名]]");
+        assert_eq!(wiki_link_at(line, line.len() - 3).unwrap().1, "周报");
+        assert!(wiki_link_at(line, 0).is_none());
+        assert!(wiki_link_at("[[未闭合", 3).is_none());
+    }
+
+    #[test]
+    fn note_link_by_id_under_offset() {
+        let line = "参见 [会议](notes://open/42) 的结论";
+        let start = line.find("notes://").unwrap();
+        let (range, link) = note_link_at(line, start + 2).unwrap();
+        assert_eq!(link, NoteLink::Id(42));
+        assert_eq!(&line[range], "notes://open/42");
+        assert!(note_link_at(line, line.len() - 1).is_none());
+        assert_eq!(
+            note_link_at("[[项目]]", 2).unwrap().1,
+            NoteLink::Title("项目".to_string())
+        );
+    }
+
+    #[test]
+    fn first_paragraph_skips_headings() {
+        let note = note_with("\n# 标题\n\n第一行\n第二行\n\n第二段");
+        assert_eq!(note.first_paragraph(), "第一行\n第二行");
+        assert_eq!(note_with("").first_paragraph(), "");
+    }
+
+    #[test]
+    fn attachments_from_images_and_recordings() {
+        let note = note_with(
+            "![截图 1.png](attachments/截图 1.png)\n\
+             <audio controls src=\"attachments/录音.m4a\"></audio>\n\
+             再次引用 ![](attachments/截图 1.png)\n\
+             ![带 空格.png](<attachments/带 空格.png>)",
+        );
+        assert_eq!(
+            note.attachments(),
+            vec!["截图 1.png", "录音.m4a", "带 空格.png"]
+        );
+    }
+
+    #[test]
+    fn timestamps_with_local_offset_are_read_as_utc() {
+        let json = r#"{"id":1,"title":"t","content":"","created_at":"2024-05-01T08:00:00+08:00","updated_at":"2024-05-01T09:30:00+08:00"}"#;
+        let note: Note = serde_json::from_str(json).unwrap();
+        assert_eq!(note.created_at.to_rfc3339(), "2024-05-01T00:00:00+00:00");
+        assert_eq!(note.updated_at.to_rfc3339(), "2024-05-01T01:30:00+00:00");
+    }
+
+    #[test]
+    fn timestamps_are_saved_in_utc() {
+        let json = serde_json::to_string(&Note::new()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert!(value["created_at"].as_str().unwrap().ends_with('Z'));
+    }
+
+    #[test]
+    fn children_are_listed_under_their_parent() {
+        let parent = Note::new();
+        let child = Note::child_of(parent.id);
+        let grandchild = Note::child_of(child.id);
+        let orphan = Note::child_of(Uuid::new_v4().to_u128_le());
+        let mut looped = Note::new();
+        let mut other = Note::child_of(looped.id);
+        looped.parent = Some(other.id);
+        other.title = "环".to_string();
+        // 子笔记在列表中排在父笔记前面也要显示在父笔记下面
+        let notes = [&grandchild, &parent, &orphan, &child, &looped, &other];
+
+        let tree = note_tree(&notes, &HashSet::new());
+        let rows: Vec<(u128, usize, bool)> = tree
+            .iter()
+            .map(|entry| (entry.id, entry.depth, entry.has_children))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (parent.id, 0, true),
+                (child.id, 1, true),
+                (grandchild.id, 2, false),
+                (orphan.id, 0, false),
+                (looped.id, 0, true),
+                (other.id, 1, true),
+            ]
+        );
+
+        let collapsed = HashSet::from([parent.id]);
+        let ids: Vec<u128> = note_tree(&notes, &collapsed)
+            .iter()
+            .map(|entry| entry.id)
+            .collect();
+        assert_eq!(ids, vec![parent.id, orphan.id, looped.id, other.id]);
+    }
+}
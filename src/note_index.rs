@@ -0,0 +1,180 @@
+use crate::note::Note;
+use crate::note_list::{NoteList, NoteListEvent};
+use crate::settings::AppSettings;
+use futures::{StreamExt, stream::FuturesUnordered};
+use gpui::{Context, Entity, EventEmitter, SharedString, Subscription};
+use std::collections::HashMap;
+
+/// 每个后台任务处理的笔记数，处理完一批就把结果交给界面
+const CHUNK_SIZE: usize = 200;
+
+/// 从笔记正文派生、计算成本较高的数据
+#[derive(Clone)]
+pub struct NoteSummary {
+    pub preview: SharedString,
+    pub word_count: usize,
+}
+
+impl NoteSummary {
+    fn new(note: &Note, preview_length: usize) -> Self {
+        Self {
+            preview: note.preview_with(preview_length).into(),
+            word_count: note.word_count(),
+        }
+    }
+}
+
+/// 这些笔记的摘要已经算好
+pub struct SummariesReady(pub Vec<u128>);
+
+/// 在后台线程池中为笔记计算预览和字数，算好一批就通知视图
+///
+/// 启动时笔记先显示标题，摘要陆续补上，不会阻塞第一次绘制。
+pub struct NoteIndex {
+    summaries: HashMap<u128, NoteSummary>,
+    /// 每篇笔记最近一次提交计算的版本，旧版本的结果晚到时丢弃
+    revisions: HashMap<u128, u64>,
+    next_revision: u64,
+    /// 还没算完的批次数
+    in_flight: usize,
+    /// 预览截取的字符数，设置改变时重新计算全部预览
+    preview_length: usize,
+    _notes_changed: Subscription,
+    _settings: Subscription,
+}
+
+impl NoteIndex {
+    pub fn new(notes: Entity<NoteList>, cx: &mut Context<Self>) -> Self {
+        let notes_for_settings = notes.clone();
+        let notes_changed = cx.subscribe(&notes, |this, notes, event, cx| match *event {
+            NoteListEvent::Reloaded => this.reindex(&notes, cx),
+            NoteListEvent::NoteAdded(note_id) | NoteListEvent::NoteUpdated(note_id) => {
+                if let Some(note) = notes.read(cx).get(note_id) {
+                    this.index(vec![note.clone()], cx);
+                }
+            }
+            NoteListEvent::NoteRemoved(note_id) => {
+                this.summaries.remove(&note_id);
+                this.revisions.remove(&note_id);
+            }
+            NoteListEvent::SelectionChanged(_)
+            | NoteListEvent::WritesFailed
+            | NoteListEvent::WritesRecovered
+            | NoteListEvent::Conflict { .. } => {}
+        });
+        let settings = cx.observe_global::<AppSettings>(move |this: &mut Self, cx| {
+            let preview_length = AppSettings::get(cx).sidebar.preview_length;
+            if preview_length != this.preview_length {
+                this.preview_length = preview_length;
+                // 清掉版本号后 reindex 会重新提交所有笔记，旧结果晚到时被丢弃
+                this.revisions.clear();
+                this.reindex(&notes_for_settings, cx);
+            }
+        });
+        let mut index = Self {
+            summaries: HashMap::new(),
+            revisions: HashMap::new(),
+            next_revision: 0,
+            in_flight: 0,
+            preview_length: AppSettings::get(cx).sidebar.preview_length,
+            _notes_changed: notes_changed,
+            _settings: settings,
+        };
+        index.reindex(&notes, cx);
+        index
+    }
+
+    /// 没有正在计算的摘要
+    pub fn is_idle(&self) -> bool {
+        self.in_flight == 0
+    }
+
+    pub fn summary(&self, note_id: u128) -> Option<&NoteSummary> {
+        self.summaries.get(&note_id)
+    }
+
+    /// 字数还没算好的笔记按 0 计
+    pub fn word_count(&self, note_id: u128) -> usize {
+        self.summary(note_id)
+            .map_or(0, |summary| summary.word_count)
+    }
+
+    // 整个列表被替换时只计算新出现的笔记，排序变化不需要重算
+    fn reindex(&mut self, notes: &Entity<NoteList>, cx: &mut Context<Self>) {
+        let notes = notes.read(cx);
+        // 正文读完之前列表里只有元数据，等 set_loaded 后再计算
+        if !notes.is_loaded() {
+            return;
+        }
+        self.summaries.retain(|id, _| notes.get(*id).is_some());
+        self.revisions.retain(|id, _| notes.get(*id).is_some());
+        let pending: Vec<Note> = notes
+            .get_all()
+            .into_iter()
+            .filter(|note| !self.revisions.contains_key(&note.id))
+            .cloned()
+            .collect();
+        if !pending.is_empty() {
+            self.index(pending, cx);
+        }
+    }
+
+    fn index(&mut self, notes: Vec<Note>, cx: &mut Context<Self>) {
+        let revision = self.next_revision;
+        self.next_revision += 1;
+        for note in &notes {
+            self.revisions.insert(note.id, revision);
+        }
+
+        let executor = cx.background_executor().clone();
+        let preview_length = self.preview_length;
+        let mut chunks: FuturesUnordered<_> = notes
+            .chunks(CHUNK_SIZE)
+            .map(|chunk| {
+                let chunk = chunk.to_vec();
+                executor.spawn(async move {
+                    chunk
+                        .iter()
+                        .map(|note| (note.id, NoteSummary::new(note, preview_length)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        self.in_flight += chunks.len();
+
+        cx.spawn(async move |this, cx| {
+            while let Some(summaries) = chunks.next().await {
+                let applied = this.update(cx, |this, cx| this.apply(revision, summaries, cx));
+                if applied.is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+    }
+
+    fn apply(
+        &mut self,
+        revision: u64,
+        summaries: Vec<(u128, NoteSummary)>,
+        cx: &mut Context<Self>,
+    ) {
+        self.in_flight -= 1;
+        let mut ready = Vec::new();
+        for (note_id, summary) in summaries {
+            if self.revisions.get(&note_id) == Some(&revision) {
+                self.summaries.insert(note_id, summary);
+                ready.push(note_id);
+            }
+        }
+        if !ready.is_empty() {
+            cx.emit(SummariesReady(ready));
+        }
+        if self.in_flight == 0 {
+            tracing::debug!(notes = self.summaries.len(), "摘要计算完成");
+        }
+        cx.notify();
+    }
+}
+
+impl EventEmitter<SummariesReady> for NoteIndex {}
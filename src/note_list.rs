@@ -1,40 +1,406 @@
-use crate::note::Note;
-use crate::storage::Storage;
-use anyhow::Result;
-
-use gpui::Context;
+use crate::note::{MAX_TITLE_CHARS, Note, NoteMeta};
+use crate::search_index::SearchIndex;
+use crate::settings::{AppSettings, NoteSort};
+use crate::storage::{Storage, StorageWriter, WriteStatus};
+use anyhow::{Result, bail};
+use futures::{StreamExt as _, channel::mpsc};
+use gpui::{Context, EventEmitter, Subscription, Task};
+use indexmap::IndexMap;
 use std::collections::HashMap;
+use std::path::Path;
+use std::time::Instant;
+
+/// 笔记集合的变化，视图据此只更新受影响的部分
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NoteListEvent {
+    /// 整个列表被替换或重新排序，例如启动读取完成、排序方式改变
+    Reloaded,
+    NoteAdded(u128),
+    NoteUpdated(u128),
+    NoteRemoved(u128),
+    SelectionChanged(Option<u128>),
+    /// 后台写盘失败，详情见 write_failure
+    WritesFailed,
+    /// 之前写盘失败的修改都已保存
+    WritesRecovered,
+    /// 笔记在别处被修改过，较新的版本保留在 note_id，另一个版本另存为 copy_id
+    Conflict {
+        note_id: u128,
+        copy_id: u128,
+    },
+}
+
+/// 尚未写入磁盘的修改
+#[derive(Clone, Debug)]
+pub struct WriteFailure {
+    pub pending: usize,
+    pub error: String,
+}
 
+/// 全部笔记，始终按设置中的排序方式排列，各视图按这个顺序显示
+///
+/// 内存中的笔记立即更新，写盘交给后台的 StorageWriter 按顺序完成
 pub struct NoteList {
-    notes: HashMap<u128, Note>,
-    storage: Storage,
+    notes: IndexMap<u128, Note>,
+    sort: NoteSort,
+    writer: StorageWriter,
+    search_index: Option<SearchIndex>,
+    /// 侧边栏中选中的笔记
+    selected: Option<u128>,
+    /// 正文是否已读完；之前列表里只有元数据占位，不能修改
+    loaded: bool,
+    write_failure: Option<WriteFailure>,
+    _settings: Subscription,
+    _write_status: Task<()>,
 }
 
 impl NoteList {
-    pub fn new(_cx: &mut Context<Self>) -> Self {
-        let mut notes: HashMap<u128, Note> = HashMap::new();
-        let storage = Storage::new().unwrap();
-        let _ = storage.load_all_notes(&mut notes);
-        NoteList { notes, storage }
+    /// 创建空列表，笔记由 AppView 在后台读取后通过 set_loaded 填入
+    pub fn new(storage: Storage, cx: &mut Context<Self>) -> Self {
+        let sort = AppSettings::get(cx).sidebar.sort;
+        let settings = cx.observe_global::<AppSettings>(|this: &mut Self, cx| {
+            let sort = AppSettings::get(cx).sidebar.sort;
+            if sort != this.sort {
+                this.sort = sort;
+                this.sort_notes();
+                cx.emit(NoteListEvent::Reloaded);
+                cx.notify();
+            }
+        });
+
+        let (status_tx, mut status_rx) = mpsc::unbounded();
+        let write_status = cx.spawn(async move |this, cx| {
+            while let Some(status) = status_rx.next().await {
+                let updated = this.update(cx, |this, cx| this.on_write_status(status, cx));
+                if updated.is_err() {
+                    break;
+                }
+            }
+        });
+
+        NoteList {
+            notes: IndexMap::new(),
+            sort,
+            writer: StorageWriter::spawn(storage, cx.background_executor(), status_tx),
+            search_index: None,
+            selected: None,
+            loaded: false,
+            write_failure: None,
+            _settings: settings,
+            _write_status: write_status,
+        }
+    }
+
+    fn on_write_status(&mut self, status: WriteStatus, cx: &mut Context<Self>) {
+        match status {
+            WriteStatus::Failed { pending, error } => {
+                self.write_failure = Some(WriteFailure { pending, error });
+                cx.emit(NoteListEvent::WritesFailed);
+            }
+            WriteStatus::Recovered => {
+                self.write_failure = None;
+                cx.emit(NoteListEvent::WritesRecovered);
+            }
+            // 两个版本都已写盘，这里只更新内存
+            WriteStatus::Conflict(conflict) => {
+                let conflict = *conflict;
+                let note_id = conflict.kept.id;
+                let copy_id = conflict.copy.id;
+                self.index_note(&conflict.kept);
+                self.index_note(&conflict.copy);
+                self.notes.insert(note_id, conflict.kept);
+                self.notes.insert(copy_id, conflict.copy);
+                self.sort_notes();
+                cx.emit(NoteListEvent::NoteUpdated(note_id));
+                cx.emit(NoteListEvent::NoteAdded(copy_id));
+                cx.emit(NoteListEvent::Conflict { note_id, copy_id });
+            }
+        }
+        cx.notify();
+    }
+
+    pub fn write_failure(&self) -> Option<&WriteFailure> {
+        self.write_failure.as_ref()
+    }
+
+    /// 重新写入失败的修改，结果通过 WritesFailed/WritesRecovered 事件通知
+    pub fn retry_writes(&self) -> Result<()> {
+        self.writer.retry()
+    }
+
+    /// 正文读完之前先用上次保存的元数据填充列表，侧边栏可以立即显示标题
+    pub fn set_metadata(&mut self, notes: Vec<NoteMeta>, cx: &mut Context<Self>) {
+        if self.loaded {
+            return;
+        }
+        self.notes = notes
+            .into_iter()
+            .map(|meta| (meta.id, Note::from(meta)))
+            .collect();
+        self.sort_notes();
+        cx.emit(NoteListEvent::Reloaded);
+        cx.notify();
     }
 
-    pub fn add(&mut self, note: Note) -> Result<()> {
-        self.storage.save_note(&note)?;
-        self.notes.insert(note.id, note);
+    pub fn set_loaded(&mut self, notes: HashMap<u128, Note>, cx: &mut Context<Self>) {
+        self.notes = notes.into_iter().collect();
+        self.loaded = true;
+        self.sort_notes();
+        self.save_note_index();
+        cx.emit(NoteListEvent::Reloaded);
+        cx.notify();
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+
+    /// 保存元数据供下次启动时快速显示，在退出和窗口失去焦点时调用
+    pub fn save_note_index(&self) {
+        if !self.loaded {
+            return;
+        }
+        let notes = self.notes.values().map(NoteMeta::from).collect();
+        if let Err(e) = self.writer.save_note_index(notes) {
+            tracing::error!("保存笔记索引失败: {:#}", e);
+        }
+    }
+
+    // 正文读完前列表里是占位笔记，写入会用空正文覆盖文件
+    fn ensure_loaded(&self) -> Result<()> {
+        if !self.loaded {
+            bail!("笔记仍在载入，请稍后再试");
+        }
         Ok(())
     }
 
-    pub fn remove(&mut self, id: u128) -> Result<()> {
-        self.storage.delete_note(id)?;
-        self.notes.remove(&id);
+    // 排序键相同时按 ID 排，保证每次结果一致
+    fn sort_notes(&mut self) {
+        let sort = self.sort;
+        self.notes
+            .sort_by(|_, a, _, b| sort.compare(a, b).then(a.id.cmp(&b.id)));
+    }
+
+    pub fn selected(&self) -> Option<u128> {
+        self.selected
+    }
+
+    pub fn select(&mut self, note_id: Option<u128>, cx: &mut Context<Self>) {
+        if self.selected != note_id {
+            self.selected = note_id;
+            cx.emit(NoteListEvent::SelectionChanged(note_id));
+        }
+    }
+
+    /// 笔记在当前排序中的位置
+    pub fn index_of(&self, note_id: u128) -> Option<usize> {
+        self.notes.get_index_of(&note_id)
+    }
+
+    /// 列表中紧挨着的下一篇笔记，没有下一篇时取上一篇
+    pub fn neighbor_of(&self, note_id: u128) -> Option<u128> {
+        let ix = self.index_of(note_id)?;
+        self.notes
+            .get_index(ix + 1)
+            .or_else(|| ix.checked_sub(1).and_then(|ix| self.notes.get_index(ix)))
+            .map(|(id, _)| *id)
+    }
+
+    pub fn writer(&self) -> &StorageWriter {
+        &self.writer
+    }
+
+    pub fn search_index_dir(&self) -> Option<&Path> {
+        self.search_index.as_ref().map(|index| index.dir())
+    }
+
+    /// 开启、关闭或更换系统搜索索引目录，开启时写入全部笔记
+    pub fn set_search_index(
+        &mut self,
+        index: Option<SearchIndex>,
+        cx: &mut Context<Self>,
+    ) -> Result<()> {
+        let old = self.search_index.take();
+        let notes: Vec<Note> = self.notes.values().cloned().collect();
+        let new = index.clone();
+        self.writer.run(move || {
+            if let Some(old) = &old {
+                old.clear()?;
+            }
+            if let Some(index) = &new {
+                let started = Instant::now();
+                index.rebuild(&notes.iter().collect::<Vec<_>>())?;
+                tracing::info!(
+                    notes = notes.len(),
+                    elapsed_ms = started.elapsed().as_millis() as u64,
+                    "重建搜索索引 {:?}",
+                    index.dir()
+                );
+            }
+            Ok(())
+        })?;
+        self.search_index = index;
+        cx.notify();
+        Ok(())
+    }
+
+    pub fn add(&mut self, note: Note, cx: &mut Context<Self>) -> Result<()> {
+        self.ensure_loaded()?;
+        self.writer.save_note(&note)?;
+        self.index_note(&note);
+        let id = note.id;
+        self.notes.insert(id, note);
+        self.sort_notes();
+        cx.emit(NoteListEvent::NoteAdded(id));
+        cx.notify();
         Ok(())
     }
 
+    pub fn update(
+        &mut self,
+        id: u128,
+        f: impl FnOnce(&mut Note),
+        cx: &mut Context<Self>,
+    ) -> Result<()> {
+        self.ensure_loaded()?;
+        let Some(note) = self.notes.get(&id) else {
+            return Ok(());
+        };
+        let mut note = note.clone();
+        f(&mut note);
+        // 先确认能序列化并提交写入，再更新内存
+        self.writer.save_note(&note)?;
+        self.index_note(&note);
+        self.notes.insert(id, note);
+        self.sort_notes();
+        cx.emit(NoteListEvent::NoteUpdated(id));
+        cx.notify();
+        Ok(())
+    }
+
+    /// 把笔记移到 parent 下面，parent 为 None 时移到顶层
+    pub fn set_parent(
+        &mut self,
+        note_id: u128,
+        parent: Option<u128>,
+        cx: &mut Context<Self>,
+    ) -> Result<()> {
+        if let Some(parent) = parent
+            && (parent == note_id || self.is_ancestor(note_id, parent))
+        {
+            bail!("不能移到自己或自己的子笔记下面");
+        }
+        self.update(note_id, |note| note.parent = parent, cx)
+    }
+
+    /// ancestor 是否在 note_id 的父笔记链上
+    pub fn is_ancestor(&self, ancestor: u128, note_id: u128) -> bool {
+        let mut current = self.notes.get(&note_id).and_then(|note| note.parent);
+        // 文件被手动改成环时最多走过每篇笔记一次
+        for _ in 0..self.notes.len() {
+            match current {
+                Some(id) if id == ancestor => return true,
+                Some(id) => current = self.notes.get(&id).and_then(|note| note.parent),
+                None => return false,
+            }
+        }
+        false
+    }
+
+    /// 直接挂在 note_id 下面的子笔记，按列表顺序排列
+    pub fn children_of(&self, note_id: u128) -> Vec<&Note> {
+        self.notes
+            .values()
+            .filter(|note| note.parent == Some(note_id) && note.id != note_id)
+            .collect()
+    }
+
+    /// 删除后子笔记移到被删笔记的父笔记下面，不会跟着删除
+    pub fn remove(&mut self, id: u128, cx: &mut Context<Self>) -> Result<()> {
+        self.ensure_loaded()?;
+        let parent = self.notes.get(&id).and_then(|note| note.parent);
+        let children: Vec<u128> = self.children_of(id).iter().map(|note| note.id).collect();
+        for child in children {
+            self.update(child, |note| note.parent = parent, cx)?;
+        }
+        if let Some(note) = self.notes.get(&id) {
+            self.writer.trash_note(note)?;
+        }
+        if let Some(index) = self.search_index.clone() {
+            self.writer.run_for_note(id, move || index.remove(id))?;
+        }
+        self.notes.shift_remove(&id);
+        if self.selected == Some(id) {
+            self.select(None, cx);
+        }
+        cx.emit(NoteListEvent::NoteRemoved(id));
+        cx.notify();
+        Ok(())
+    }
+
+    // 索引只是笔记的镜像，写入失败不影响保存笔记本身
+    fn index_note(&self, note: &Note) {
+        let Some(index) = self.search_index.clone() else {
+            return;
+        };
+        let note = note.clone();
+        if let Err(e) = self
+            .writer
+            .run_for_note(note.id, move || index.write(&note))
+        {
+            tracing::error!("更新搜索索引失败: {:#}", e);
+        }
+    }
+
     pub fn get(&self, id: u128) -> Option<&Note> {
         self.notes.get(&id)
     }
 
+    pub fn find_by_title(&self, title: &str) -> Option<&Note> {
+        self.notes.values().find(|note| note.title == title)
+    }
+
+    /// 检查重命名输入：去掉首尾空白，不能为空或过长；
+    /// 与其他笔记重名时返回加上序号后的标题
+    pub fn check_title(&self, note_id: u128, input: &str) -> Result<String, String> {
+        let title = input.trim();
+        if title.is_empty() {
+            return Err("标题不能为空".to_string());
+        }
+        if title.chars().count() > MAX_TITLE_CHARS {
+            return Err(format!("标题不能超过 {} 个字", MAX_TITLE_CHARS));
+        }
+        Ok(self.unique_title(title, Some(note_id)))
+    }
+
+    /// 没有其他笔记使用的标题，重名时依次尝试「标题 2」「标题 3」……
+    ///
+    /// 加序号后超过长度上限时截短原标题，结果总不超过 MAX_TITLE_CHARS 个字。
+    pub fn unique_title(&self, title: &str, except: Option<u128>) -> String {
+        let taken = |candidate: &str| {
+            self.notes
+                .values()
+                .any(|note| Some(note.id) != except && note.title == candidate)
+        };
+        if !taken(title) {
+            return title.to_string();
+        }
+        (2..)
+            .map(|n| {
+                let suffix = format!(" {}", n);
+                let base: String = title
+                    .chars()
+                    .take(MAX_TITLE_CHARS.saturating_sub(suffix.len()))
+                    .collect();
+                format!("{}{}", base.trim_end(), suffix)
+            })
+            .find(|candidate| !taken(candidate))
+            .unwrap()
+    }
+
     pub fn get_all(&self) -> Vec<&Note> {
         self.notes.values().collect()
     }
 }
+
+impl EventEmitter<NoteListEvent> for NoteList {}
@@ -0,0 +1,20 @@
+use crate::settings::OcrSettings;
+use anyhow::{Context, Result, bail};
+use std::{path::Path, process::Command};
+
+/// 调用 tesseract 识别图片中的文字
+pub fn recognize(image: &Path, settings: &OcrSettings) -> Result<String> {
+    let output = Command::new(&settings.path)
+        .arg(image)
+        .arg("stdout")
+        .args(["-l", &settings.languages])
+        .output()
+        .context(format!("无法启动 tesseract: {}", settings.path))?;
+    if !output.status.success() {
+        bail!(
+            "tesseract 识别失败: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
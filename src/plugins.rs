@@ -0,0 +1,370 @@
+use crate::note::Note;
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{fs, path::Path};
+use wasmtime::{
+    Caller, Config, Engine, Extern, Linker, Module, Store, StoreLimits, StoreLimitsBuilder,
+};
+
+// 插件每次调用可消耗的燃料，大约相当于一两秒的计算，防止死循环一直占着后台线程
+const FUEL_PER_CALL: u64 = 500_000_000;
+// 插件线性内存的上限，也限制了宿主从插件读取的数据大小
+const MAX_MEMORY_BYTES: usize = 64 << 20;
+const HOST_MODULE: &str = "nothingbook";
+
+/// 插件在清单中声明、需要用户同意的权限
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    /// 读取全部笔记
+    Read,
+    /// 新建或修改笔记
+    Write,
+}
+
+impl Permission {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Read => "读取所有笔记",
+            Self::Write => "新建和修改笔记",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PluginCommand {
+    pub id: String,
+    pub title: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PluginExporter {
+    pub id: String,
+    pub title: String,
+    /// 导出文件的扩展名，不带点
+    pub extension: String,
+}
+
+/// 与 `<name>.wasm` 同名的 `<name>.json` 清单
+#[derive(Clone, Debug, Deserialize)]
+pub struct PluginManifest {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub permissions: Vec<Permission>,
+    #[serde(default)]
+    pub commands: Vec<PluginCommand>,
+    #[serde(default)]
+    pub exporters: Vec<PluginExporter>,
+}
+
+/// 插件看到的笔记。ID 用字符串传递，避免 JSON 解析器丢失 u128 精度
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PluginNote {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub title: String,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl From<&Note> for PluginNote {
+    fn from(note: &Note) -> Self {
+        Self {
+            id: Some(note.id.to_string()),
+            title: note.title.clone(),
+            content: note.content.clone(),
+            tags: note.tags.clone(),
+        }
+    }
+}
+
+pub struct Plugin {
+    pub manifest: PluginManifest,
+    /// .wasm 文件内容的 SHA-256，权限按它记录，替换文件后需要重新同意
+    pub hash: String,
+    module: Module,
+}
+
+// 一次调用期间宿主函数可访问的状态
+struct HostState {
+    name: String,
+    permissions: Vec<Permission>,
+    notes: Vec<PluginNote>,
+    writes: Vec<PluginNote>,
+    limits: StoreLimits,
+}
+
+/// 从插件目录加载 .wasm 插件并负责调用
+///
+/// 插件需导出 `memory` 和 `nb_alloc(len) -> ptr`，命令入口为 `nb_command(id_ptr, id_len)`，
+/// 导出器入口为 `nb_export(id_ptr, id_len, note_ptr, note_len) -> i64`（高 32 位为指针，低 32 位为长度）。
+/// 宿主在 `nothingbook` 模块中提供 `log`、`notes_list`、`note_get` 和 `note_write`。
+pub struct PluginHost {
+    engine: Engine,
+    linker: Linker<HostState>,
+    pub plugins: Vec<Plugin>,
+}
+
+impl PluginHost {
+    pub fn plugin(&self, name: &str) -> Option<&Plugin> {
+        self.plugins
+            .iter()
+            .find(|plugin| plugin.manifest.name == name)
+    }
+
+    pub fn load(dir: &Path) -> Result<Self> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config)?;
+        let linker = Self::linker(&engine)?;
+
+        let mut plugins = Vec::new();
+        if dir.exists() {
+            for entry in fs::read_dir(dir).context("无法读取插件目录")? {
+                let path = entry.context("读取目录条目失败")?.path();
+                if path.extension().is_some_and(|ext| ext == "wasm") {
+                    match Self::load_plugin(&engine, &path) {
+                        Ok(plugin) => plugins.push(plugin),
+                        Err(e) => eprintln!("加载插件失败 {:?}: {:#}", path, e),
+                    }
+                }
+            }
+        }
+        plugins.sort_by(|a, b| a.manifest.name.cmp(&b.manifest.name));
+
+        Ok(Self {
+            engine,
+            linker,
+            plugins,
+        })
+    }
+
+    fn load_plugin(engine: &Engine, path: &Path) -> Result<Plugin> {
+        let manifest_path = path.with_extension("json");
+        let manifest = fs::read_to_string(&manifest_path).context("缺少插件清单")?;
+        let manifest: PluginManifest =
+            serde_json::from_str(&manifest).context("解析插件清单失败")?;
+        let wasm = fs::read(path).context("无法读取插件文件")?;
+        let hash = Sha256::digest(&wasm)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        let module = Module::new(engine, &wasm).context("编译插件失败")?;
+        Ok(Plugin {
+            manifest,
+            hash,
+            module,
+        })
+    }
+
+    /// 运行插件命令，返回插件要求写入的笔记
+    ///
+    /// 插件运行可能要一两秒，界面上应在后台线程调用。
+    pub fn run_command(
+        &self,
+        plugin: &Plugin,
+        command: &str,
+        permissions: &[Permission],
+        notes: Vec<PluginNote>,
+    ) -> Result<Vec<PluginNote>> {
+        let mut call = self.instantiate(plugin, permissions, notes)?;
+        let (ptr, len) = call.write_bytes(command.as_bytes())?;
+        let entry = call
+            .instance
+            .get_typed_func::<(i32, i32), ()>(&mut call.store, "nb_command")
+            .context("插件没有导出 nb_command")?;
+        entry.call(&mut call.store, (ptr, len))?;
+        Ok(call.store.into_data().writes)
+    }
+
+    /// 调用插件导出器，把笔记转换为导出文件的内容
+    pub fn export(&self, plugin: &Plugin, exporter: &str, note: &PluginNote) -> Result<Vec<u8>> {
+        let mut call = self.instantiate(plugin, &[], Vec::new())?;
+        let note = serde_json::to_vec(note)?;
+        let (id_ptr, id_len) = call.write_bytes(exporter.as_bytes())?;
+        let (note_ptr, note_len) = call.write_bytes(&note)?;
+        let entry = call
+            .instance
+            .get_typed_func::<(i32, i32, i32, i32), i64>(&mut call.store, "nb_export")
+            .context("插件没有导出 nb_export")?;
+        let packed = entry.call(&mut call.store, (id_ptr, id_len, note_ptr, note_len))?;
+        call.read_bytes((packed >> 32) as u32, packed as u32)
+    }
+
+    fn instantiate(
+        &self,
+        plugin: &Plugin,
+        permissions: &[Permission],
+        mut notes: Vec<PluginNote>,
+    ) -> Result<PluginCall> {
+        if !permissions.contains(&Permission::Read) {
+            notes.clear();
+        }
+        let state = HostState {
+            name: plugin.manifest.name.clone(),
+            permissions: permissions.to_vec(),
+            notes,
+            writes: Vec::new(),
+            limits: StoreLimitsBuilder::new()
+                .memory_size(MAX_MEMORY_BYTES)
+                .build(),
+        };
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(FUEL_PER_CALL)?;
+        let instance = self.linker.instantiate(&mut store, &plugin.module)?;
+        Ok(PluginCall { store, instance })
+    }
+
+    fn linker(engine: &Engine) -> Result<Linker<HostState>> {
+        let mut linker = Linker::new(engine);
+
+        linker.func_wrap(
+            HOST_MODULE,
+            "log",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                let message = read_guest(&mut caller, ptr, len)?;
+                eprintln!(
+                    "[插件 {}] {}",
+                    caller.data().name,
+                    String::from_utf8_lossy(&message)
+                );
+                Ok(())
+            },
+        )?;
+
+        linker.func_wrap(
+            HOST_MODULE,
+            "notes_list",
+            |mut caller: Caller<'_, HostState>| -> wasmtime::Result<i64> {
+                let json = serde_json::to_vec(&caller.data().notes)?;
+                write_guest(&mut caller, &json)
+            },
+        )?;
+
+        linker.func_wrap(
+            HOST_MODULE,
+            "note_get",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> wasmtime::Result<i64> {
+                let id = read_guest(&mut caller, ptr, len)?;
+                let id = String::from_utf8_lossy(&id).to_string();
+                let note = caller
+                    .data()
+                    .notes
+                    .iter()
+                    .find(|note| note.id.as_deref() == Some(id.as_str()))
+                    .cloned();
+                match note {
+                    Some(note) => {
+                        let json = serde_json::to_vec(&note)?;
+                        write_guest(&mut caller, &json)
+                    }
+                    None => Ok(0),
+                }
+            },
+        )?;
+
+        linker.func_wrap(
+            HOST_MODULE,
+            "note_write",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> wasmtime::Result<i32> {
+                if !caller.data().permissions.contains(&Permission::Write) {
+                    return Ok(-1);
+                }
+                let json = read_guest(&mut caller, ptr, len)?;
+                match serde_json::from_slice::<PluginNote>(&json) {
+                    Ok(note) => {
+                        caller.data_mut().writes.push(note);
+                        Ok(0)
+                    }
+                    Err(_) => Ok(-2),
+                }
+            },
+        )?;
+
+        Ok(linker)
+    }
+}
+
+struct PluginCall {
+    store: Store<HostState>,
+    instance: wasmtime::Instance,
+}
+
+impl PluginCall {
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(i32, i32)> {
+        let alloc = self
+            .instance
+            .get_typed_func::<i32, i32>(&mut self.store, "nb_alloc")
+            .context("插件没有导出 nb_alloc")?;
+        let ptr = alloc.call(&mut self.store, bytes.len() as i32)?;
+        let memory = self
+            .instance
+            .get_memory(&mut self.store, "memory")
+            .context("插件没有导出 memory")?;
+        memory.write(&mut self.store, ptr as usize, bytes)?;
+        Ok((ptr, bytes.len() as i32))
+    }
+
+    fn read_bytes(&mut self, ptr: u32, len: u32) -> Result<Vec<u8>> {
+        let memory = self
+            .instance
+            .get_memory(&mut self.store, "memory")
+            .context("插件没有导出 memory")?;
+        guest_slice(memory.data(&self.store), ptr, len).map(<[u8]>::to_vec)
+    }
+}
+
+fn guest_memory(caller: &mut Caller<'_, HostState>) -> Result<wasmtime::Memory> {
+    match caller.get_export("memory") {
+        Some(Extern::Memory(memory)) => Ok(memory),
+        _ => bail!("插件没有导出 memory"),
+    }
+}
+
+fn read_guest(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> Result<Vec<u8>> {
+    let memory = guest_memory(caller)?;
+    guest_slice(memory.data(&*caller), ptr as u32, len as u32).map(<[u8]>::to_vec)
+}
+
+// 指针和长度由插件给出，先确认整段都在插件内存里，再复制
+fn guest_slice(memory: &[u8], ptr: u32, len: u32) -> Result<&[u8]> {
+    let start = ptr as usize;
+    start
+        .checked_add(len as usize)
+        .and_then(|end| memory.get(start..end))
+        .ok_or_else(|| anyhow!("插件给出的内存范围越界"))
+}
+
+// 通过插件的 nb_alloc 申请内存并写入数据，返回打包后的指针和长度
+fn write_guest(caller: &mut Caller<'_, HostState>, bytes: &[u8]) -> Result<i64> {
+    let alloc = caller
+        .get_export("nb_alloc")
+        .and_then(|export| export.into_func())
+        .ok_or_else(|| anyhow!("插件没有导出 nb_alloc"))?
+        .typed::<i32, i32>(&*caller)?;
+    let ptr = alloc.call(&mut *caller, bytes.len() as i32)?;
+    guest_memory(caller)?.write(&mut *caller, ptr as usize, bytes)?;
+    Ok(((ptr as u32 as i64) << 32) | bytes.len() as u32 as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guest_ranges_are_checked_before_copying() {
+        let memory = b"hello world";
+        assert_eq!(guest_slice(memory, 6, 5).unwrap(), b"world");
+        assert_eq!(guest_slice(memory, 11, 0).unwrap(), b"");
+        assert!(guest_slice(memory, 6, 6).is_err());
+        // 负数长度按 u32 解释后远超内存大小
+        assert!(guest_slice(memory, 0, -1i32 as u32).is_err());
+        assert!(guest_slice(memory, u32::MAX, u32::MAX).is_err());
+    }
+}
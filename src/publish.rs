@@ -0,0 +1,131 @@
+use crate::{curl, note::Note, settings::PublishSettings};
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+const GISTS_URL: &str = "https://api.github.com/gists";
+
+/// 笔记发布到的 GitHub Gist，取消发布时按 ID 删除
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Published {
+    pub gist_id: String,
+    pub url: String,
+    /// Gist 中的文件名，标题改了以后再发布时据此重命名
+    pub file_name: String,
+    pub published_at: DateTime<Utc>,
+}
+
+/// 把笔记渲染成 Markdown 上传为 Gist，返回可以分享的网址
+///
+/// 已经发布过的笔记更新原来的 Gist，链接保持不变。
+pub fn publish(note: &Note, settings: &PublishSettings) -> Result<Published> {
+    if settings.token.trim().is_empty() {
+        bail!("请先在设置 > 发布中填写 GitHub 令牌");
+    }
+    let file_name = file_name(note);
+    let file = json!({ "filename": file_name, "content": note.to_markdown() });
+    let (method, url, body) = match &note.published {
+        Some(published) => (
+            "PATCH",
+            format!("{}/{}", GISTS_URL, published.gist_id),
+            json!({
+                "description": note.title,
+                "files": { published.file_name.as_str(): file },
+            }),
+        ),
+        None => (
+            "POST",
+            GISTS_URL.to_string(),
+            json!({
+                "description": note.title,
+                "public": settings.public,
+                "files": { file_name.as_str(): file },
+            }),
+        ),
+    };
+    let mut config = request_config(method, &url, settings);
+    config.push_str(&format!(
+        "data-binary = {}\n",
+        curl::quote(&body.to_string())
+    ));
+    let output = curl::request(&settings.curl, &config)?;
+
+    let reply: Value = serde_json::from_slice(&output).context("解析 GitHub 回复失败")?;
+    Ok(Published {
+        gist_id: reply["id"]
+            .as_str()
+            .context("GitHub 回复中没有 Gist ID")?
+            .to_string(),
+        url: reply["html_url"]
+            .as_str()
+            .context("GitHub 回复中没有链接")?
+            .to_string(),
+        file_name,
+        published_at: Utc::now(),
+    })
+}
+
+/// 删除发布的 Gist，之后原来的链接无法再打开
+pub fn unpublish(published: &Published, settings: &PublishSettings) -> Result<()> {
+    let url = format!("{}/{}", GISTS_URL, published.gist_id);
+    curl::request(&settings.curl, &request_config("DELETE", &url, settings))?;
+    Ok(())
+}
+
+fn request_config(method: &str, url: &str, settings: &PublishSettings) -> String {
+    let mut config = String::new();
+    config.push_str(&format!("request = {}\n", method));
+    config.push_str(&format!("url = {}\n", curl::quote(url)));
+    config.push_str("header = \"Accept: application/vnd.github+json\"\n");
+    config.push_str("header = \"X-GitHub-Api-Version: 2022-11-28\"\n");
+    config.push_str(&format!(
+        "header = {}\n",
+        curl::quote(&format!("Authorization: Bearer {}", settings.token.trim()))
+    ));
+    config
+}
+
+// Gist 按扩展名渲染，.md 文件显示为排版后的网页；文件名里不能有斜杠
+fn file_name(note: &Note) -> String {
+    let title: String = note
+        .title
+        .trim()
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\') { '-' } else { c })
+        .collect();
+    if title.is_empty() {
+        "笔记.md".to_string()
+    } else {
+        format!("{}.md", title)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_names_come_from_titles() {
+        let mut note = Note::new();
+        note.title = " 周报/第 3 周 ".to_string();
+        assert_eq!(file_name(&note), "周报-第 3 周.md");
+        note.title = "  ".to_string();
+        assert_eq!(file_name(&note), "笔记.md");
+    }
+
+    #[test]
+    fn published_state_round_trips_with_the_note() {
+        let mut note = Note::new();
+        let json = serde_json::to_string(&note).unwrap();
+        assert!(!json.contains("published"));
+        note.published = Some(Published {
+            gist_id: "abc123".to_string(),
+            url: "https://gist.github.com/abc123".to_string(),
+            file_name: "新建笔记.md".to_string(),
+            published_at: Utc::now(),
+        });
+        let json = serde_json::to_string(&note).unwrap();
+        assert_eq!(serde_json::from_str::<Note>(&json).unwrap(), note);
+    }
+}
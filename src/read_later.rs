@@ -0,0 +1,632 @@
+use crate::{
+    curl,
+    note::{MAX_TITLE_CHARS, Note},
+    settings::ReadLaterSettings,
+    storage::Storage,
+};
+use anyhow::{Context, Result, bail};
+use html5ever::{ParseOpts, parse_document, tendril::TendrilSink};
+use markup5ever_rcdom::{Handle, NodeData, RcDom};
+use std::{collections::HashMap, rc::Rc};
+use url::Url;
+
+/// 稍后阅读的网页保存成的笔记都带这个标签
+pub const READ_LATER_TAG: &str = "read-later";
+
+// 网页和每张图片最多下载的字节数，超过时 curl 放弃下载
+const MAX_DOWNLOAD_BYTES: u64 = 20 << 20;
+
+// 这些元素里不会有正文
+const SKIPPED: [&str; 14] = [
+    "head", "script", "style", "noscript", "template", "nav", "header", "footer", "aside", "form",
+    "button", "iframe", "svg", "canvas",
+];
+
+// class 或 id 中出现这些词的元素多半是评论、分享按钮、推荐阅读等
+const BOILERPLATE: [&str; 17] = [
+    "comment",
+    "comments",
+    "sidebar",
+    "share",
+    "social",
+    "related",
+    "footer",
+    "nav",
+    "navbar",
+    "menu",
+    "ad",
+    "ads",
+    "advertisement",
+    "promo",
+    "subscribe",
+    "newsletter",
+    "breadcrumb",
+];
+
+const BLOCKS: [&str; 30] = [
+    "address",
+    "article",
+    "blockquote",
+    "body",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "html",
+    "li",
+    "main",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "tr",
+    "ul",
+    "center",
+];
+
+/// 从网页中提取的正文
+#[derive(Debug, PartialEq)]
+pub struct Article {
+    pub title: String,
+    pub markdown: String,
+    /// 正文中图片的绝对地址，按出现顺序，不重复
+    pub images: Vec<String>,
+}
+
+/// 下载网页、提取正文并转成 Markdown，图片下载到附件目录，返回带 read-later 标签的新笔记
+pub fn capture(url: &str, storage: &Storage, settings: &ReadLaterSettings) -> Result<Note> {
+    let url = Url::parse(url.trim()).context("无效的网址")?;
+    if !matches!(url.scheme(), "http" | "https") {
+        bail!("只能保存 http 或 https 网页: {}", url);
+    }
+    let html = fetch(&url, settings)?;
+    let mut article = extract(&String::from_utf8_lossy(&html), &url);
+    if article.markdown.trim().is_empty() {
+        bail!("没有在网页中找到正文");
+    }
+
+    if settings.download_images {
+        for image in &article.images {
+            // 下载失败的图片保留原来的网址
+            match download_image(image, storage, settings) {
+                Ok(name) => {
+                    article.markdown = article.markdown.replace(
+                        &format!("]({})", image),
+                        &format!("](attachments/{})", name),
+                    );
+                }
+                Err(e) => tracing::warn!("下载图片失败 {}: {:#}", image, e),
+            }
+        }
+    }
+
+    let mut note = Note::new();
+    note.title = article.title;
+    note.content = format!("原文：<{}>\n\n{}\n", url, article.markdown);
+    note.tags = vec![READ_LATER_TAG.to_string()];
+    Ok(note)
+}
+
+fn fetch(url: &Url, settings: &ReadLaterSettings) -> Result<Vec<u8>> {
+    let mut config = String::new();
+    config.push_str(&format!("url = {}\n", curl::quote(url.as_str())));
+    config.push_str("location\n");
+    config.push_str("compressed\n");
+    config.push_str("max-time = 30\n");
+    config.push_str(&format!("max-filesize = {}\n", MAX_DOWNLOAD_BYTES));
+    config.push_str("user-agent = \"Mozilla/5.0 (compatible; NothingBook)\"\n");
+    curl::request(&settings.curl, &config)
+}
+
+fn download_image(image: &str, storage: &Storage, settings: &ReadLaterSettings) -> Result<String> {
+    let url = Url::parse(image).context("无效的图片地址")?;
+    let bytes = fetch(&url, settings)?;
+    // 按内容判断类型，服务器返回的错误页不会被当作图片保存
+    let Some(extension) = image_extension(&bytes) else {
+        bail!("不是图片");
+    };
+    storage.write_attachment(&image_file_name(&url, extension), &bytes)
+}
+
+fn image_extension(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG") {
+        Some("png")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("jpg")
+    } else if bytes.starts_with(b"GIF8") {
+        Some("gif")
+    } else if bytes.len() > 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP" {
+        Some("webp")
+    } else {
+        let head = String::from_utf8_lossy(&bytes[..bytes.len().min(256)]);
+        let head = head.trim_start();
+        (head.starts_with("<svg") || head.starts_with("<?xml") && head.contains("<svg"))
+            .then_some("svg")
+    }
+}
+
+// 取网址最后一段作为文件名，去掉笔记中引用附件时会截断链接的字符
+fn image_file_name(url: &Url, extension: &str) -> String {
+    let last = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .unwrap_or_default();
+    let last = percent_encoding::percent_decode_str(last).decode_utf8_lossy();
+    let stem: String = last
+        .rsplit_once('.')
+        .map_or(last.as_ref(), |(stem, _)| stem)
+        .chars()
+        .map(|c| {
+            if c.is_whitespace() || "/\\()[]<>\"'?*:|#".contains(c) {
+                '-'
+            } else {
+                c
+            }
+        })
+        .take(60)
+        .collect();
+    let stem = stem.trim_matches(['-', '.']);
+    if stem.is_empty() {
+        format!("image.{}", extension)
+    } else {
+        format!("{}.{}", stem, extension)
+    }
+}
+
+/// 找出网页的正文并转成 Markdown
+///
+/// 和 Readability 一样按段落打分：每个段落的得分加给父元素，一半加给祖父元素，
+/// 得分最高的元素视为正文。有 `<article>` 时直接使用它。
+pub fn extract(html: &str, base: &Url) -> Article {
+    let dom = parse_document(RcDom::default(), ParseOpts::default()).one(html);
+    let root = dom.document.clone();
+
+    let title = meta_title(&root)
+        .or_else(|| find(&root, "title").map(|title| collapse(&text_content(&title))))
+        .or_else(|| find(&root, "h1").map(|h1| collapse(&text_content(&h1))))
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| base.host_str().unwrap_or("网页").to_string());
+    let title: String = title.chars().take(MAX_TITLE_CHARS).collect();
+
+    let content = find(&root, "article")
+        .filter(|article| text_content(article).chars().count() > 200)
+        .or_else(|| best_candidate(&root))
+        .or_else(|| find(&root, "body"))
+        .unwrap_or(root);
+
+    let mut converter = Converter {
+        base,
+        images: Vec::new(),
+    };
+    let blocks = converter.blocks(&content);
+    // 正文开头与标题相同的一级标题不再重复
+    let blocks: Vec<String> = blocks
+        .into_iter()
+        .enumerate()
+        .filter(|(ix, block)| *ix > 0 || block.trim_start_matches("# ") != title)
+        .map(|(_, block)| block)
+        .collect();
+    Article {
+        title: title.trim().to_string(),
+        markdown: blocks.join("\n\n"),
+        images: converter.images,
+    }
+}
+
+fn element_name(node: &Handle) -> Option<&str> {
+    match &node.data {
+        NodeData::Element { name, .. } => Some(name.local.as_ref()),
+        _ => None,
+    }
+}
+
+fn attr(node: &Handle, name: &str) -> Option<String> {
+    match &node.data {
+        NodeData::Element { attrs, .. } => attrs
+            .borrow()
+            .iter()
+            .find(|attr| attr.name.local.as_ref() == name)
+            .map(|attr| attr.value.to_string()),
+        _ => None,
+    }
+}
+
+fn is_skipped(node: &Handle) -> bool {
+    let Some(name) = element_name(node) else {
+        return matches!(node.data, NodeData::Comment { .. });
+    };
+    if SKIPPED.contains(&name) || attr(node, "hidden").is_some() {
+        return true;
+    }
+    let names = format!(
+        "{} {}",
+        attr(node, "class").unwrap_or_default(),
+        attr(node, "id").unwrap_or_default()
+    )
+    .to_lowercase();
+    names
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .any(|word| BOILERPLATE.contains(&word))
+}
+
+fn find(node: &Handle, tag: &str) -> Option<Handle> {
+    if element_name(node) == Some(tag) {
+        return Some(node.clone());
+    }
+    node.children
+        .borrow()
+        .iter()
+        .find_map(|child| find(child, tag))
+}
+
+fn meta_title(node: &Handle) -> Option<String> {
+    if element_name(node) == Some("meta") && attr(node, "property").as_deref() == Some("og:title") {
+        return attr(node, "content").map(|title| collapse(&title));
+    }
+    node.children.borrow().iter().find_map(meta_title)
+}
+
+fn text_content(node: &Handle) -> String {
+    let mut text = String::new();
+    collect_text(node, &mut text);
+    text
+}
+
+fn collect_text(node: &Handle, text: &mut String) {
+    if is_skipped(node) {
+        return;
+    }
+    if let NodeData::Text { contents } = &node.data {
+        text.push_str(&contents.borrow());
+    }
+    for child in node.children.borrow().iter() {
+        collect_text(child, text);
+    }
+}
+
+// 把连续的空白合并成一个空格
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn best_candidate(root: &Handle) -> Option<Handle> {
+    let mut scores: HashMap<*const markup5ever_rcdom::Node, (Handle, f32)> = HashMap::new();
+    score_paragraphs(root, &mut Vec::new(), &mut scores);
+    scores
+        .into_values()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(node, _)| node)
+}
+
+fn score_paragraphs(
+    node: &Handle,
+    ancestors: &mut Vec<Handle>,
+    scores: &mut HashMap<*const markup5ever_rcdom::Node, (Handle, f32)>,
+) {
+    if is_skipped(node) {
+        return;
+    }
+    if matches!(element_name(node), Some("p" | "pre")) {
+        let text = text_content(node);
+        let length = text.chars().count();
+        if length >= 25 {
+            let commas = text.matches([',', '，', '。']).count() as f32;
+            let score = 1.0 + commas + (length as f32 / 100.0).min(3.0);
+            for (depth, ancestor) in ancestors.iter().rev().take(2).enumerate() {
+                let entry = scores
+                    .entry(Rc::as_ptr(ancestor))
+                    .or_insert_with(|| (ancestor.clone(), 0.0));
+                entry.1 += if depth == 0 { score } else { score / 2.0 };
+            }
+        }
+        return;
+    }
+    ancestors.push(node.clone());
+    for child in node.children.borrow().iter() {
+        score_paragraphs(child, ancestors, scores);
+    }
+    ancestors.pop();
+}
+
+struct Converter<'a> {
+    base: &'a Url,
+    images: Vec<String>,
+}
+
+impl Converter<'_> {
+    fn resolve(&self, href: &str) -> Option<String> {
+        self.base.join(href.trim()).ok().map(|url| url.to_string())
+    }
+
+    /// 元素内的块级内容，每项是一段 Markdown
+    fn blocks(&mut self, node: &Handle) -> Vec<String> {
+        let mut blocks = Vec::new();
+        let mut inline = String::new();
+        for child in node.children.borrow().iter() {
+            if is_skipped(child) {
+                continue;
+            }
+            match element_name(child) {
+                Some(name) if BLOCKS.contains(&name) => {
+                    push_paragraph(&mut blocks, &mut inline);
+                    blocks.extend(self.block(child, name));
+                }
+                _ => inline.push_str(&self.inline(child)),
+            }
+        }
+        push_paragraph(&mut blocks, &mut inline);
+        blocks
+    }
+
+    fn block(&mut self, node: &Handle, name: &str) -> Vec<String> {
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = name[1..].parse().unwrap_or(1);
+                let text = collapse(&self.inline_children(node));
+                if text.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![format!("{} {}", "#".repeat(level), text)]
+                }
+            }
+            "p" => {
+                let mut inline = self.inline_children(node);
+                let mut blocks = Vec::new();
+                push_paragraph(&mut blocks, &mut inline);
+                blocks
+            }
+            "pre" => {
+                let code = text_content(node);
+                let code = code.trim_matches('\n');
+                if code.trim().is_empty() {
+                    Vec::new()
+                } else {
+                    vec![format!("```\n{}\n```", code)]
+                }
+            }
+            "hr" => vec!["---".to_string()],
+            "ul" | "ol" => {
+                let ordered = name == "ol";
+                let mut lines = Vec::new();
+                let items = node.children.borrow();
+                let items = items
+                    .iter()
+                    .filter(|item| element_name(item) == Some("li") && !is_skipped(item));
+                for (ix, item) in items.enumerate() {
+                    let marker = if ordered {
+                        format!("{}. ", ix + 1)
+                    } else {
+                        "- ".to_string()
+                    };
+                    let indent = " ".repeat(marker.chars().count());
+                    let content = self.blocks(item).join("\n");
+                    for (line_ix, line) in content.lines().enumerate() {
+                        if line_ix == 0 {
+                            lines.push(format!("{}{}", marker, line));
+                        } else if line.is_empty() {
+                            lines.push(String::new());
+                        } else {
+                            lines.push(format!("{}{}", indent, line));
+                        }
+                    }
+                }
+                if lines.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![lines.join("\n")]
+                }
+            }
+            "blockquote" => {
+                let content = self.blocks(node).join("\n\n");
+                if content.is_empty() {
+                    return Vec::new();
+                }
+                let quoted: Vec<String> = content
+                    .lines()
+                    .map(|line| format!("> {}", line).trim_end().to_string())
+                    .collect();
+                vec![quoted.join("\n")]
+            }
+            "table" => self.table(node),
+            _ => self.blocks(node),
+        }
+    }
+
+    fn table(&mut self, node: &Handle) -> Vec<String> {
+        let mut rows = Vec::new();
+        self.table_rows(node, &mut rows);
+        let Some(columns) = rows.iter().map(Vec::len).max().filter(|&n| n > 0) else {
+            return Vec::new();
+        };
+        let mut lines = Vec::new();
+        for (ix, mut row) in rows.into_iter().enumerate() {
+            row.resize(columns, String::new());
+            lines.push(format!("| {} |", row.join(" | ")));
+            if ix == 0 {
+                lines.push(format!("|{}", " --- |".repeat(columns)));
+            }
+        }
+        vec![lines.join("\n")]
+    }
+
+    fn table_rows(&mut self, node: &Handle, rows: &mut Vec<Vec<String>>) {
+        for child in node.children.borrow().iter() {
+            match element_name(child) {
+                Some("tr") => {
+                    let cells = child
+                        .children
+                        .borrow()
+                        .iter()
+                        .filter(|cell| matches!(element_name(cell), Some("td" | "th")))
+                        .map(|cell| collapse(&self.inline_children(cell)).replace('|', "\\|"))
+                        .collect();
+                    rows.push(cells);
+                }
+                Some("thead" | "tbody" | "tfoot") => self.table_rows(child, rows),
+                _ => {}
+            }
+        }
+    }
+
+    fn inline_children(&mut self, node: &Handle) -> String {
+        node.children
+            .borrow()
+            .iter()
+            .filter(|child| !is_skipped(child))
+            .map(|child| self.inline(child))
+            .collect()
+    }
+
+    fn inline(&mut self, node: &Handle) -> String {
+        if let NodeData::Text { contents } = &node.data {
+            let text = contents.borrow();
+            // 只合并空白，保留首尾的空格，免得相邻的文字粘在一起
+            let mut collapsed = String::new();
+            let mut space = false;
+            for c in text.chars() {
+                if c.is_whitespace() {
+                    space = true;
+                } else {
+                    if space {
+                        collapsed.push(' ');
+                        space = false;
+                    }
+                    collapsed.push(c);
+                }
+            }
+            if space {
+                collapsed.push(' ');
+            }
+            return collapsed;
+        }
+        let Some(name) = element_name(node) else {
+            return String::new();
+        };
+        match name {
+            "br" => "\n".to_string(),
+            "img" => self.image(node),
+            "a" => {
+                let text = self.inline_children(node);
+                let trimmed = text.trim();
+                let href = attr(node, "href").unwrap_or_default();
+                if trimmed.is_empty()
+                    || href.starts_with('#')
+                    || href.starts_with("javascript:")
+                    || trimmed.starts_with("![")
+                {
+                    return text;
+                }
+                match self.resolve(&href) {
+                    Some(href) => wrap(&text, "[", &format!("]({})", href)),
+                    None => text,
+                }
+            }
+            "strong" | "b" => wrap(&self.inline_children(node), "**", "**"),
+            "em" | "i" => wrap(&self.inline_children(node), "*", "*"),
+            "code" => wrap(&text_content(node), "`", "`"),
+            _ => self.inline_children(node),
+        }
+    }
+
+    fn image(&mut self, node: &Handle) -> String {
+        // 懒加载的图片真实地址常放在 data-src 里
+        let src = attr(node, "src")
+            .filter(|src| !src.is_empty() && !src.starts_with("data:"))
+            .or_else(|| attr(node, "data-src"));
+        let Some(src) = src.and_then(|src| self.resolve(&src)) else {
+            return String::new();
+        };
+        if !src.starts_with("http") {
+            return String::new();
+        }
+        let alt = collapse(&attr(node, "alt").unwrap_or_default()).replace(['[', ']'], "");
+        if !self.images.contains(&src) {
+            self.images.push(src.clone());
+        }
+        format!("![{}]({})", alt, src)
+    }
+}
+
+// 把空白以外的内容包上标记，首尾的空格留在标记外面
+fn wrap(text: &str, open: &str, close: &str) -> String {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return text.to_string();
+    }
+    let leading = &text[..text.len() - text.trim_start().len()];
+    let trailing = &text[text.trim_end().len()..];
+    format!("{}{}{}{}{}", leading, open, trimmed, close, trailing)
+}
+
+fn push_paragraph(blocks: &mut Vec<String>, inline: &mut String) {
+    let paragraph = inline
+        .lines()
+        .map(collapse)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    if !paragraph.is_empty() {
+        blocks.push(paragraph);
+    }
+    inline.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_article_body_as_markdown() {
+        let html = r#"<html><head><title>站点 | 标题</title>
+            <meta property="og:title" content="一篇文章"></head>
+            <body>
+              <nav><a href="/">首页</a></nav>
+              <div class="content">
+                <h1>一篇文章</h1>
+                <p>第一段，介绍这篇文章要讲的内容，并且足够长，可以被当作正文。</p>
+                <p>第二段有<strong>加粗</strong>和<a href="/more">相对链接</a>，也足够长，可以被当作正文。</p>
+                <img src="img/photo.png" alt="照片">
+                <ul><li>第一项</li><li>第二项</li></ul>
+                <pre>let x = 1;</pre>
+              </div>
+              <div class="comments"><p>评论区的内容也很长，但是不应该被保存到笔记里面去。</p></div>
+            </body></html>"#;
+        let base = Url::parse("https://example.com/posts/1").unwrap();
+        let article = extract(html, &base);
+        assert_eq!(article.title, "一篇文章");
+        assert_eq!(
+            article.markdown,
+            "第一段，介绍这篇文章要讲的内容，并且足够长，可以被当作正文。\n\n\
+             第二段有**加粗**和[相对链接](https://example.com/more)，也足够长，可以被当作正文。\n\n\
+             ![照片](https://example.com/posts/img/photo.png)\n\n\
+             - 第一项\n- 第二项\n\n\
+             ```\nlet x = 1;\n```"
+        );
+        assert_eq!(
+            article.images,
+            vec!["https://example.com/posts/img/photo.png"]
+        );
+    }
+
+    #[test]
+    fn image_names_and_types() {
+        let url = Url::parse("https://example.com/a/My%20Photo(1).jpeg?w=800").unwrap();
+        assert_eq!(image_file_name(&url, "jpg"), "My-Photo-1.jpg");
+        let url = Url::parse("https://example.com/").unwrap();
+        assert_eq!(image_file_name(&url, "png"), "image.png");
+        assert_eq!(image_extension(b"\x89PNG\r\n"), Some("png"));
+        assert_eq!(image_extension(b"<html>not found</html>"), None);
+    }
+}
@@ -0,0 +1,215 @@
+use crate::note::Note;
+use anyhow::{Context, Result, anyhow};
+use chrono::{Days, Local, NaiveDate, NaiveTime, Utc};
+use rhai::{Array, Dynamic, Engine, Map, Scope};
+use std::{cell::RefCell, collections::HashSet, fs, path::Path, rc::Rc};
+
+// 脚本单次运行的操作数上限，防止死循环一直占着后台线程
+const MAX_OPERATIONS: u64 = 50_000_000;
+const SCHEDULE_PREFIX: &str = "// schedule:";
+
+/// 脚本何时自动运行，写在脚本开头的 `// schedule: 08:00` 或 `// schedule: startup`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Schedule {
+    /// 每次启动时运行
+    Startup,
+    /// 每天到点后运行一次，错过时间则在下次启动后补跑
+    Daily(NaiveTime),
+}
+
+impl Schedule {
+    fn parse(source: &str) -> Option<Self> {
+        let value = source
+            .lines()
+            .take_while(|line| line.trim_start().starts_with("//"))
+            .find_map(|line| line.trim().strip_prefix(SCHEDULE_PREFIX))?
+            .trim();
+        if value == "startup" {
+            Some(Self::Startup)
+        } else {
+            NaiveTime::parse_from_str(value, "%H:%M")
+                .ok()
+                .map(Self::Daily)
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Self::Startup => "启动时".to_string(),
+            Self::Daily(time) => format!("每天 {}", time.format("%H:%M")),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Script {
+    /// 文件名去掉 .rhai 后缀
+    pub name: String,
+    pub schedule: Option<Schedule>,
+    source: String,
+}
+
+impl Script {
+    /// 按计划今天是否该运行，`last_run` 是上次运行的日期
+    pub fn is_due(&self, last_run: Option<NaiveDate>, now: chrono::NaiveDateTime) -> bool {
+        match self.schedule {
+            Some(Schedule::Daily(time)) => last_run != Some(now.date()) && now.time() >= time,
+            _ => false,
+        }
+    }
+}
+
+// 脚本运行期间的笔记副本，记录被修改过的笔记
+#[derive(Default)]
+struct Workspace {
+    notes: Vec<Note>,
+    changed: HashSet<u128>,
+}
+
+/// 从脚本目录加载 .rhai 脚本，脚本通过绑定函数读写笔记
+pub fn load_scripts(dir: &Path) -> Result<Vec<Script>> {
+    let mut scripts = Vec::new();
+    if !dir.exists() {
+        return Ok(scripts);
+    }
+    for entry in fs::read_dir(dir).context("无法读取脚本目录")? {
+        let path = entry.context("读取目录条目失败")?.path();
+        if path.extension().is_none_or(|ext| ext != "rhai") {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let source = fs::read_to_string(&path).context(format!("读取脚本失败: {:?}", path))?;
+        scripts.push(Script {
+            name: name.to_string(),
+            schedule: Schedule::parse(&source),
+            source,
+        });
+    }
+    scripts.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(scripts)
+}
+
+/// 运行脚本，返回新建或修改过的笔记
+///
+/// 脚本可能运行较久，界面上应在后台线程调用。
+pub fn run(script: &Script, notes: Vec<Note>) -> Result<Vec<Note>> {
+    let workspace = Rc::new(RefCell::new(Workspace {
+        notes,
+        changed: HashSet::new(),
+    }));
+    let engine = engine(workspace.clone(), script.name.clone());
+    engine
+        .run_with_scope(&mut Scope::new(), &script.source)
+        .map_err(|e| anyhow!("{}", e))?;
+
+    let workspace = workspace.take();
+    Ok(workspace
+        .notes
+        .into_iter()
+        .filter(|note| workspace.changed.contains(&note.id))
+        .collect())
+}
+
+fn engine(workspace: Rc<RefCell<Workspace>>, name: String) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.on_print(move |text| eprintln!("[脚本 {}] {}", name, text));
+
+    let ws = workspace.clone();
+    engine.register_fn("notes", move || -> Array {
+        ws.borrow()
+            .notes
+            .iter()
+            .map(|note| Dynamic::from_map(note_to_map(note)))
+            .collect()
+    });
+
+    let ws = workspace.clone();
+    engine.register_fn("find_note", move |title: &str| -> Dynamic {
+        ws.borrow()
+            .notes
+            .iter()
+            .find(|note| note.title == title)
+            .map(|note| Dynamic::from_map(note_to_map(note)))
+            .unwrap_or(Dynamic::UNIT)
+    });
+
+    let ws = workspace.clone();
+    engine.register_fn("create_note", move |title: &str, content: &str| -> String {
+        let mut note = Note::new();
+        note.title = title.to_string();
+        note.content = content.to_string();
+        let id = note.id;
+        let mut ws = ws.borrow_mut();
+        ws.notes.push(note);
+        ws.changed.insert(id);
+        id.to_string()
+    });
+
+    let ws = workspace.clone();
+    engine.register_fn("set_content", move |id: &str, content: &str| -> bool {
+        edit_note(&ws, id, |note| note.content = content.to_string())
+    });
+
+    let ws = workspace.clone();
+    engine.register_fn("set_title", move |id: &str, title: &str| -> bool {
+        edit_note(&ws, id, |note| note.title = title.to_string())
+    });
+
+    let ws = workspace;
+    engine.register_fn("add_tag", move |id: &str, tag: &str| -> bool {
+        edit_note(&ws, id, |note| {
+            if !note.tags.iter().any(|t| t == tag) {
+                note.tags.push(tag.to_string());
+            }
+        })
+    });
+
+    engine.register_fn("today", || Local::now().date_naive().to_string());
+    engine.register_fn("date_offset", |days: i64| {
+        let today = Local::now().date_naive();
+        let date = if days >= 0 {
+            today.checked_add_days(Days::new(days as u64))
+        } else {
+            today.checked_sub_days(Days::new(days.unsigned_abs()))
+        };
+        date.unwrap_or(today).to_string()
+    });
+    engine.register_fn("unchecked_tasks", |content: &str| -> Array {
+        content
+            .lines()
+            .filter(|line| line.trim_start().starts_with("- [ ]"))
+            .map(|line| Dynamic::from(line.trim_start().to_string()))
+            .collect()
+    });
+
+    engine
+}
+
+fn edit_note(workspace: &Rc<RefCell<Workspace>>, id: &str, f: impl FnOnce(&mut Note)) -> bool {
+    let Ok(id) = id.parse::<u128>() else {
+        return false;
+    };
+    let mut ws = workspace.borrow_mut();
+    let Some(note) = ws.notes.iter_mut().find(|note| note.id == id) else {
+        return false;
+    };
+    f(note);
+    note.updated_at = Utc::now();
+    ws.changed.insert(id);
+    true
+}
+
+fn note_to_map(note: &Note) -> Map {
+    let mut map = Map::new();
+    map.insert("id".into(), note.id.to_string().into());
+    map.insert("title".into(), note.title.clone().into());
+    map.insert("content".into(), note.content.clone().into());
+    map.insert(
+        "tags".into(),
+        Dynamic::from_array(note.tags.iter().cloned().map(Dynamic::from).collect()),
+    );
+    map
+}
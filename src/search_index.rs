@@ -0,0 +1,127 @@
+use crate::note::Note;
+use anyhow::{Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// 供系统搜索（Spotlight、Windows 搜索等）索引的笔记镜像
+///
+/// 每篇笔记写成一个 HTML 文件，正文可被系统全文索引；在搜索结果中打开文件时，
+/// 页面立即跳转到 `notes://open/<id>`，由系统交给本应用打开对应笔记。
+#[derive(Clone)]
+pub struct SearchIndex {
+    dir: PathBuf,
+}
+
+impl SearchIndex {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// 写入笔记，标题变化时删除旧文件
+    pub fn write(&self, note: &Note) -> Result<()> {
+        fs::create_dir_all(&self.dir).context("无法创建搜索索引目录")?;
+        self.remove(note.id)?;
+        let path = self.dir.join(format!(
+            "{} ({}).html",
+            crate::export::file_name(&note.title),
+            short_id(note.id)
+        ));
+        fs::write(&path, to_html(note)).context(format!("写入搜索索引失败: {:?}", path))
+    }
+
+    pub fn remove(&self, note_id: u128) -> Result<()> {
+        let suffix = format!(" ({}).html", short_id(note_id));
+        for path in self.files()? {
+            if path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.ends_with(&suffix))
+            {
+                fs::remove_file(&path).context(format!("删除搜索索引失败: {:?}", path))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 清空后重新写入全部笔记
+    pub fn rebuild(&self, notes: &[&Note]) -> Result<()> {
+        self.clear()?;
+        for note in notes {
+            self.write(note)?;
+        }
+        Ok(())
+    }
+
+    /// 删除索引目录中由本应用生成的文件
+    pub fn clear(&self) -> Result<()> {
+        for path in self.files()? {
+            fs::remove_file(&path).context(format!("删除搜索索引失败: {:?}", path))?;
+        }
+        Ok(())
+    }
+
+    fn files(&self) -> Result<Vec<PathBuf>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.dir).context("无法读取搜索索引目录")? {
+            let path = entry.context("读取目录条目失败")?.path();
+            if path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(is_index_file)
+            {
+                files.push(path);
+            }
+        }
+        Ok(files)
+    }
+}
+
+/// 未设置索引目录时使用文稿目录下的“NothingBook 搜索索引”，系统搜索默认会索引这里
+pub fn default_dir() -> PathBuf {
+    dirs::document_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_default()
+        .join("NothingBook 搜索索引")
+}
+
+// 文件名里带上 ID 的一部分，区分同名笔记，也用来找到改名前的旧文件
+fn short_id(id: u128) -> String {
+    format!("{:08x}", id as u32)
+}
+
+// 只处理“标题 (8 位十六进制).html”形式的文件，不碰目录里用户自己的文件
+fn is_index_file(name: &str) -> bool {
+    name.strip_suffix(").html")
+        .and_then(|rest| rest.rsplit_once(" ("))
+        .is_some_and(|(_, id)| id.len() == 8 && id.chars().all(|ch| ch.is_ascii_hexdigit()))
+}
+
+fn to_html(note: &Note) -> String {
+    let link = format!("notes://open/{}", note.id);
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta http-equiv=\"refresh\" content=\"0; url={link}\">\n\
+         <meta name=\"keywords\" content=\"{tags}\">\n<title>{title}</title>\n</head>\n<body>\n\
+         <h1><a href=\"{link}\">{title}</a></h1>\n<pre>{content}</pre>\n</body>\n</html>\n",
+        link = link,
+        title = escape(&note.title),
+        tags = escape(&note.tags.join(", ")),
+        content = escape(&note.content),
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,29 @@
+use chrono::NaiveDate;
+use gpui_component::input::Position;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 上次运行时的界面状态，下次启动时恢复
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    /// 退出时正在编辑的笔记
+    pub last_note_id: Option<u128>,
+    /// 每篇笔记最后的光标位置
+    pub cursors: HashMap<u128, Position>,
+    /// 定时脚本最后一次运行的日期，按脚本名记录
+    pub script_runs: HashMap<String, NaiveDate>,
+}
+
+impl Session {
+    pub fn cursor(&self, note_id: u128) -> Option<Position> {
+        self.cursors.get(&note_id).copied()
+    }
+
+    pub fn forget(&mut self, note_id: u128) {
+        self.cursors.remove(&note_id);
+        if self.last_note_id == Some(note_id) {
+            self.last_note_id = None;
+        }
+    }
+}
@@ -0,0 +1,907 @@
+use anyhow::{Context as _, Result, bail};
+use chrono::{DateTime, Local, Utc, format::StrftimeItems};
+use gpui::{App, Font, FontFallbacks, Global, Hsla, Pixels, Rgba, font, px};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq as _;
+use uuid::Uuid;
+
+use crate::{
+    card::CardSettings,
+    export::ExportFormat,
+    note::{DEFAULT_PREVIEW_CHARS, Note},
+    plugins::{Permission, Plugin},
+    search_index,
+    storage::Storage,
+};
+use std::{cmp::Ordering, collections::HashMap, path::PathBuf};
+
+// 中文字体后备链：按平台常见字体排列，前面的字体缺字时依次向后查找
+const DEFAULT_CJK_FALLBACKS: &[&str] = &[
+    "PingFang SC",
+    "Hiragino Sans GB",
+    "Microsoft YaHei",
+    "Noto Sans CJK SC",
+    "Source Han Sans SC",
+    "WenQuanYi Micro Hei",
+];
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct FontSettings {
+    pub ui_family: String,
+    pub editor_family: String,
+    pub editor_size: f32,
+    pub fallbacks: Vec<String>,
+}
+
+impl FontSettings {
+    pub fn ui_font(&self) -> Font {
+        self.with_fallbacks(&self.ui_family)
+    }
+
+    pub fn editor_font(&self) -> Font {
+        self.with_fallbacks(&self.editor_family)
+    }
+
+    pub fn editor_font_size(&self) -> Pixels {
+        px(self.editor_size)
+    }
+
+    fn with_fallbacks(&self, family: &str) -> Font {
+        let mut font = font(family.to_string());
+        // 用户手动清空后备链时仍保留默认值，避免中文显示成方块
+        let fallbacks = if self.fallbacks.is_empty() {
+            DEFAULT_CJK_FALLBACKS.iter().map(|f| f.to_string()).collect()
+        } else {
+            self.fallbacks.clone()
+        };
+        font.fallbacks = Some(FontFallbacks::from_fonts(fallbacks));
+        font
+    }
+}
+
+impl Default for FontSettings {
+    fn default() -> Self {
+        Self {
+            ui_family: ".SystemUIFont".to_string(),
+            editor_family: ".SystemUIFont".to_string(),
+            editor_size: 16.0,
+            fallbacks: DEFAULT_CJK_FALLBACKS.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct BoardSettings {
+    /// 看板的列，每一列对应一个状态标签
+    pub columns: Vec<String>,
+}
+
+impl Default for BoardSettings {
+    fn default() -> Self {
+        Self {
+            columns: vec!["todo".to_string(), "doing".to_string(), "done".to_string()],
+        }
+    }
+}
+
+/// 侧边栏等笔记列表的排列方式
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteSort {
+    /// 最近修改的在前
+    #[default]
+    Updated,
+    /// 最近创建的在前
+    Created,
+    /// 按标题
+    Title,
+}
+
+impl NoteSort {
+    pub const ALL: [Self; 3] = [Self::Updated, Self::Created, Self::Title];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Updated => "修改时间",
+            Self::Created => "创建时间",
+            Self::Title => "标题",
+        }
+    }
+
+    pub fn compare(&self, a: &Note, b: &Note) -> Ordering {
+        match self {
+            Self::Updated => b.updated_at.cmp(&a.updated_at),
+            Self::Created => b.created_at.cmp(&a.created_at),
+            Self::Title => a.title.cmp(&b.title),
+        }
+    }
+}
+
+/// 侧边栏时间的默认格式
+pub const DEFAULT_TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SidebarSettings {
+    pub sort: NoteSort,
+    /// 预览显示正文开头的字符数
+    pub preview_length: usize,
+    /// strftime 格式，例如 `%m月%d日 %H:%M`
+    pub time_format: String,
+    /// 一周内的时间显示为“5 分钟前”“昨天 14:30”
+    pub relative_time: bool,
+}
+
+impl Default for SidebarSettings {
+    fn default() -> Self {
+        Self {
+            sort: NoteSort::default(),
+            preview_length: DEFAULT_PREVIEW_CHARS,
+            time_format: DEFAULT_TIME_FORMAT.to_string(),
+            relative_time: false,
+        }
+    }
+}
+
+impl SidebarSettings {
+    /// 按设置格式化笔记时间，now 用于计算相对时间
+    pub fn format_time(&self, time: DateTime<Utc>, now: DateTime<Utc>) -> String {
+        if self.relative_time
+            && let Some(relative) = relative_time(time, now)
+        {
+            return relative;
+        }
+        // 格式写错时 chrono 会在格式化时 panic，先检查再使用
+        let format = if is_valid_time_format(&self.time_format) {
+            self.time_format.as_str()
+        } else {
+            DEFAULT_TIME_FORMAT
+        };
+        time.with_timezone(&Local).format(format).to_string()
+    }
+}
+
+pub fn is_valid_time_format(format: &str) -> bool {
+    !format.trim().is_empty()
+        && StrftimeItems::new(format).all(|item| item != chrono::format::Item::Error)
+}
+
+// 一周以外、或者时间在将来（其他设备时钟偏快）时返回 None
+fn relative_time(time: DateTime<Utc>, now: DateTime<Utc>) -> Option<String> {
+    let elapsed = now.signed_duration_since(time);
+    if elapsed.num_seconds() < -60 {
+        return None;
+    }
+    let minutes = elapsed.num_minutes();
+    if minutes < 1 {
+        return Some("刚刚".to_string());
+    }
+    if minutes < 60 {
+        return Some(format!("{} 分钟前", minutes));
+    }
+    let local = time.with_timezone(&Local);
+    let today = now.with_timezone(&Local).date_naive();
+    let days = (today - local.date_naive()).num_days();
+    match days {
+        0 => Some(format!("{} 小时前", elapsed.num_hours())),
+        1 => Some(format!("昨天 {}", local.format("%H:%M"))),
+        2..7 => Some(format!("{} 天前", days)),
+        _ => None,
+    }
+}
+
+/// 预览中高亮的默认颜色，浅黄色
+pub const DEFAULT_HIGHLIGHT_COLOR: &str = "#fde68a";
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PreviewSettings {
+    /// `==高亮==` 文字的背景色，十六进制写法
+    pub highlight_color: String,
+    /// 有子笔记的笔记在预览末尾列出子笔记
+    pub child_index: bool,
+    /// 自定义样式的 CSS 文件，用于预览和 HTML、EPUB 导出；留空使用默认样式
+    pub stylesheet: String,
+}
+
+impl Default for PreviewSettings {
+    fn default() -> Self {
+        Self {
+            highlight_color: DEFAULT_HIGHLIGHT_COLOR.to_string(),
+            child_index: true,
+            stylesheet: String::new(),
+        }
+    }
+}
+
+impl PreviewSettings {
+    /// 颜色写错时使用默认颜色
+    pub fn highlight_color(&self) -> Hsla {
+        parse_color(&self.highlight_color)
+            .or_else(|| parse_color(DEFAULT_HIGHLIGHT_COLOR))
+            .unwrap_or_else(gpui::yellow)
+    }
+
+    pub fn stylesheet_path(&self) -> Option<PathBuf> {
+        let path = self.stylesheet.trim();
+        (!path.is_empty()).then(|| PathBuf::from(path))
+    }
+}
+
+/// `#rrggbb` 或 `#rgb` 写法的颜色
+pub fn parse_color(value: &str) -> Option<Hsla> {
+    let value = value.trim();
+    if !value.starts_with('#') {
+        return None;
+    }
+    Rgba::try_from(value).ok().map(Hsla::from)
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SaveSettings {
+    /// 只在按 Ctrl/⌘+S 时保存，未保存的笔记在标题中显示圆点
+    pub manual: bool,
+    /// 停止输入多少秒后自动保存，0 表示随输入保存
+    pub autosave_seconds: u32,
+}
+
+impl SaveSettings {
+    /// 自动保存前等待的时间，手动保存时为 None
+    pub fn autosave_delay(&self) -> Option<std::time::Duration> {
+        (!self.manual).then(|| std::time::Duration::from_secs(self.autosave_seconds.into()))
+    }
+}
+
+/// 应用锁密码哈希的 PBKDF2 迭代次数，新设置的密码使用这个值；测试中用较小的值，免得调试构建太慢
+const PASSWORD_ITERATIONS: u32 = if cfg!(test) { 1_000 } else { 600_000 };
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct LockSettings {
+    /// 加盐后的密码摘要，为空表示没有开启应用锁
+    pub password_hash: String,
+    pub salt: String,
+    /// 计算摘要时 PBKDF2-SHA256 的迭代次数；0 是旧版本的单次 SHA-256，解锁后自动升级
+    pub iterations: u32,
+    /// 多少分钟没有操作后自动锁定，0 表示只在启动时锁定
+    pub idle_minutes: u32,
+}
+
+impl LockSettings {
+    pub fn is_enabled(&self) -> bool {
+        !self.password_hash.is_empty()
+    }
+
+    /// 设置新密码，空密码关闭应用锁
+    pub fn set_password(&mut self, password: &str) {
+        if password.is_empty() {
+            self.password_hash.clear();
+            self.salt.clear();
+            self.iterations = 0;
+            return;
+        }
+        self.salt = Uuid::new_v4().simple().to_string();
+        self.iterations = PASSWORD_ITERATIONS;
+        self.password_hash = password_hash(&self.salt, password, self.iterations);
+    }
+
+    pub fn verify(&self, password: &str) -> bool {
+        let hash = password_hash(&self.salt, password, self.iterations);
+        self.is_enabled() && bool::from(hash.as_bytes().ct_eq(self.password_hash.as_bytes()))
+    }
+
+    /// 换成 other 的密码摘要，空闲锁定等其他选项不变。摘要可以先在后台算好
+    pub fn copy_password(&mut self, other: &LockSettings) {
+        self.password_hash = other.password_hash.clone();
+        self.salt = other.salt.clone();
+        self.iterations = other.iterations;
+    }
+
+    /// 密码摘要的强度低于当前标准，应在验证通过后用同一密码重新设置
+    pub fn needs_rehash(&self) -> bool {
+        self.is_enabled() && self.iterations < PASSWORD_ITERATIONS
+    }
+
+    /// 开启应用锁且设置了空闲时间时返回
+    pub fn idle_timeout(&self) -> Option<std::time::Duration> {
+        (self.is_enabled() && self.idle_minutes > 0)
+            .then(|| std::time::Duration::from_secs(u64::from(self.idle_minutes) * 60))
+    }
+}
+
+fn password_hash(salt: &str, password: &str, iterations: u32) -> String {
+    let digest = if iterations == 0 {
+        Sha256::new()
+            .chain_update(salt)
+            .chain_update(password)
+            .finalize()
+            .into()
+    } else {
+        pbkdf2::pbkdf2_hmac_array::<Sha256, 32>(password.as_bytes(), salt.as_bytes(), iterations)
+    };
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct TrashSettings {
+    /// 回收站中的笔记保留的天数，到期后永久删除；0 表示一直保留
+    pub retention_days: u32,
+}
+
+impl Default for TrashSettings {
+    fn default() -> Self {
+        Self { retention_days: 30 }
+    }
+}
+
+impl TrashSettings {
+    pub fn retention(&self) -> Option<chrono::Duration> {
+        (self.retention_days > 0).then(|| chrono::Duration::days(self.retention_days.into()))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ApiSettings {
+    /// 是否在本机提供笔记接口，修改后重启生效
+    pub enabled: bool,
+    pub port: u16,
+    /// 请求需携带 `Authorization: Bearer <token>`
+    pub token: String,
+}
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 27124,
+            token: String::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PandocSettings {
+    /// pandoc 可执行文件，不在 PATH 中时填完整路径
+    pub path: String,
+    /// 各格式追加的命令行参数，按空白分隔，例如 `--reference-doc=模板.docx`
+    pub docx_args: String,
+    pub epub_args: String,
+    pub latex_args: String,
+    pub html_args: String,
+}
+
+impl PandocSettings {
+    pub fn args(&self, format: ExportFormat) -> Vec<String> {
+        let args = match format {
+            ExportFormat::Docx => &self.docx_args,
+            ExportFormat::Epub => &self.epub_args,
+            ExportFormat::Latex => &self.latex_args,
+            ExportFormat::Html => &self.html_args,
+        };
+        args.split_whitespace().map(str::to_string).collect()
+    }
+}
+
+impl Default for PandocSettings {
+    fn default() -> Self {
+        Self {
+            path: "pandoc".to_string(),
+            docx_args: String::new(),
+            epub_args: String::new(),
+            // ctexart 文档类自带中文排版支持，用 xelatex 编译即可
+            latex_args: "-V documentclass=ctexart".to_string(),
+            html_args: String::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct OcrSettings {
+    /// 添加图片附件时是否识别其中的文字
+    pub enabled: bool,
+    /// tesseract 可执行文件，不在 PATH 中时填完整路径
+    pub path: String,
+    /// 传给 tesseract -l 的语言，例如 chi_sim+eng
+    pub languages: String,
+}
+
+impl Default for OcrSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: "tesseract".to_string(),
+            languages: "chi_sim+eng".to_string(),
+        }
+    }
+}
+
+/// 朗读可选的语速倍数
+pub const SPEECH_RATES: [f32; 6] = [0.75, 1.0, 1.25, 1.5, 1.75, 2.0];
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SpeechSettings {
+    /// 朗读命令，{wpm} 替换为每分钟字数，文字从标准输入传入；留空时使用系统自带的语音
+    pub command: String,
+    /// 语速倍数，1 为正常语速
+    pub rate: f32,
+}
+
+impl Default for SpeechSettings {
+    fn default() -> Self {
+        Self {
+            command: String::new(),
+            rate: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct VoiceSettings {
+    /// ffmpeg 可执行文件，录音依赖它
+    pub ffmpeg: String,
+    /// ffmpeg 的录音输入参数，不同系统的音频设备写法不同
+    pub input_args: String,
+    /// 录音结束后是否转写为文字
+    pub transcribe: bool,
+    /// 转写命令，{file} 替换为录音文件路径，结果从标准输出读取
+    pub transcribe_command: String,
+}
+
+impl Default for VoiceSettings {
+    fn default() -> Self {
+        let input_args = if cfg!(target_os = "macos") {
+            "-f avfoundation -i :0"
+        } else if cfg!(target_os = "windows") {
+            "-f dshow -i audio=Microphone"
+        } else {
+            "-f pulse -i default"
+        };
+        Self {
+            ffmpeg: "ffmpeg".to_string(),
+            input_args: input_args.to_string(),
+            transcribe: false,
+            transcribe_command: "whisper-cli -nt -l auto -f {file}".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PublishSettings {
+    /// 有 gist 权限的 GitHub 令牌
+    pub token: String,
+    /// 公开的 Gist 会出现在个人主页上，默认只有拿到链接的人能看到
+    pub public: bool,
+    /// 发送请求用的 curl
+    pub curl: String,
+}
+
+impl Default for PublishSettings {
+    fn default() -> Self {
+        Self {
+            token: String::new(),
+            public: false,
+            curl: "curl".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ReadLaterSettings {
+    /// 下载网页和图片用的 curl
+    pub curl: String,
+    /// 把正文中的图片下载到附件目录，关闭时引用原网址
+    pub download_images: bool,
+}
+
+impl Default for ReadLaterSettings {
+    fn default() -> Self {
+        Self {
+            curl: "curl".to_string(),
+            download_images: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AiSettings {
+    /// 默认关闭，开启前不会向任何服务发送笔记内容
+    pub enabled: bool,
+    /// OpenAI 兼容接口的地址，本地模型例如 http://localhost:11434/v1
+    pub endpoint: String,
+    pub api_key: String,
+    pub model: String,
+    /// 发送请求用的 curl
+    pub curl: String,
+}
+
+impl Default for AiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: "https://api.openai.com/v1".to_string(),
+            api_key: String::new(),
+            model: "gpt-4o-mini".to_string(),
+            curl: "curl".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SearchIndexSettings {
+    /// 把笔记镜像到系统搜索会索引的目录
+    pub enabled: bool,
+    /// 镜像目录，留空时使用文稿目录下的默认位置
+    pub dir: String,
+}
+
+impl SearchIndexSettings {
+    /// 开启时返回镜像目录
+    pub fn dir(&self) -> Option<PathBuf> {
+        if !self.enabled {
+            None
+        } else if self.dir.trim().is_empty() {
+            Some(search_index::default_dir())
+        } else {
+            Some(PathBuf::from(self.dir.trim()))
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DiagnosticsSettings {
+    /// 把调试日志写入日志文件并在诊断面板中显示，默认关闭
+    pub enabled: bool,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PluginSettings {
+    /// 用户同意启用的插件及其权限，按 .wasm 文件的哈希记录，同名的新文件不会沿用旧授权
+    pub granted: HashMap<String, Vec<Permission>>,
+}
+
+impl PluginSettings {
+    /// 插件已被启用，且清单中的权限都已获得同意
+    pub fn is_granted(&self, plugin: &Plugin) -> bool {
+        self.permissions(plugin).is_some_and(|granted| {
+            plugin
+                .manifest
+                .permissions
+                .iter()
+                .all(|permission| granted.contains(permission))
+        })
+    }
+
+    pub fn permissions(&self, plugin: &Plugin) -> Option<&[Permission]> {
+        self.granted.get(&plugin.hash).map(Vec::as_slice)
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AppSettings {
+    pub fonts: FontSettings,
+    pub board: BoardSettings,
+    pub sidebar: SidebarSettings,
+    pub preview: PreviewSettings,
+    pub save: SaveSettings,
+    pub lock: LockSettings,
+    pub trash: TrashSettings,
+    /// 旧版本的设置中叫 intake
+    #[serde(alias = "intake")]
+    pub api: ApiSettings,
+    pub plugins: PluginSettings,
+    pub pandoc: PandocSettings,
+    pub card: CardSettings,
+    pub ocr: OcrSettings,
+    pub voice: VoiceSettings,
+    pub speech: SpeechSettings,
+    pub ai: AiSettings,
+    pub publish: PublishSettings,
+    pub read_later: ReadLaterSettings,
+    pub search_index: SearchIndexSettings,
+    pub diagnostics: DiagnosticsSettings,
+}
+
+impl Global for AppSettings {}
+
+/// 导出设置文件中的格式标记，导入时据此确认选中的是设置文件
+const SETTINGS_EXPORT_FORMAT: &str = "nothingbook-settings";
+
+#[derive(Serialize, Deserialize)]
+struct SettingsExport {
+    format: String,
+    settings: AppSettings,
+}
+
+// 设置保存在当前笔记库的数据目录里，每个笔记库各有一份
+struct SettingsFile(Storage);
+
+impl Global for SettingsFile {}
+
+impl AppSettings {
+    pub fn init(storage: &Storage, cx: &mut App) {
+        let settings = storage.load_settings().unwrap_or_else(|e| {
+            eprintln!("加载设置失败，使用默认设置: {}", e);
+            AppSettings::default()
+        });
+        cx.set_global(SettingsFile(storage.clone()));
+        cx.set_global(settings);
+
+        // 首次启动时生成随机令牌，用户也可以在设置中改成自己的
+        if Self::get(cx).api.token.is_empty() {
+            let token = Uuid::new_v4().simple().to_string();
+            Self::update(cx, |s| s.api.token = token);
+        }
+    }
+
+    pub fn get(cx: &App) -> &Self {
+        cx.global::<Self>()
+    }
+
+    /// 导出设置。应用锁、插件授权、各种令牌和外部程序只对本机有效，不导出
+    pub fn export_json(&self) -> Result<String> {
+        let export = SettingsExport {
+            format: SETTINGS_EXPORT_FORMAT.to_string(),
+            settings: self.clone().keep_local(&AppSettings::default()),
+        };
+        serde_json::to_string_pretty(&export).context("序列化设置失败")
+    }
+
+    /// 用导出的设置替换当前设置，本机的应用锁、插件授权、令牌和外部程序保持不变
+    pub fn import_json(&self, json: &str) -> Result<Self> {
+        let export: SettingsExport = serde_json::from_str(json).context("无法解析设置文件")?;
+        if export.format != SETTINGS_EXPORT_FORMAT {
+            bail!("不是 NothingBook 导出的设置文件");
+        }
+        Ok(export.settings.keep_local(self))
+    }
+
+    // 换成 local 中只属于本机的设置：密钥不能离开本机，会被执行的程序也不能由导入的文件指定
+    fn keep_local(self, local: &AppSettings) -> Self {
+        let local = local.clone();
+        AppSettings {
+            lock: local.lock,
+            plugins: local.plugins,
+            api: ApiSettings {
+                token: local.api.token,
+                ..self.api
+            },
+            // 附加参数可以用 --filter 指定程序
+            pandoc: local.pandoc,
+            ocr: OcrSettings {
+                path: local.ocr.path,
+                ..self.ocr
+            },
+            voice: VoiceSettings {
+                ffmpeg: local.voice.ffmpeg,
+                input_args: local.voice.input_args,
+                transcribe_command: local.voice.transcribe_command,
+                ..self.voice
+            },
+            speech: SpeechSettings {
+                command: local.speech.command,
+                ..self.speech
+            },
+            // 接口地址换掉的话，本机的密钥和笔记内容会发到别处
+            ai: AiSettings {
+                endpoint: local.ai.endpoint,
+                api_key: local.ai.api_key,
+                curl: local.ai.curl,
+                ..self.ai
+            },
+            publish: PublishSettings {
+                token: local.publish.token,
+                curl: local.publish.curl,
+                ..self.publish
+            },
+            read_later: ReadLaterSettings {
+                curl: local.read_later.curl,
+                ..self.read_later
+            },
+            ..self
+        }
+    }
+
+    /// 修改设置并立即写盘，同时刷新所有窗口使新设置生效
+    pub fn update(cx: &mut App, f: impl FnOnce(&mut Self)) {
+        f(cx.global_mut::<Self>());
+        // 测试中没有调用 init，只改内存中的设置
+        if let Some(SettingsFile(storage)) = cx.try_global::<SettingsFile>()
+            && let Err(e) = storage.save_settings(Self::get(cx))
+        {
+            eprintln!("保存设置失败: {}", e);
+        }
+        cx.refresh_windows();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn relative() -> SidebarSettings {
+        SidebarSettings {
+            relative_time: true,
+            ..SidebarSettings::default()
+        }
+    }
+
+    #[test]
+    fn export_and_import_keep_local_secrets() {
+        let mut settings = AppSettings::default();
+        settings.sidebar.sort = NoteSort::Title;
+        settings.fonts.editor_size = 18.0;
+        settings.lock.set_password("本机密码");
+        settings
+            .plugins
+            .granted
+            .insert("字数统计".to_string(), vec![Permission::Read]);
+        settings.api.token = "api-token-本机".to_string();
+        settings.publish.token = "ghp_gist_token".to_string();
+        settings.ai.api_key = "sk-ai-key".to_string();
+        settings.speech.command = "/tmp/evil-speak".to_string();
+        settings.voice.transcribe_command = "/tmp/evil-transcribe {file}".to_string();
+        let json = settings.export_json().unwrap();
+        assert!(!json.contains(&settings.lock.password_hash));
+        assert!(!json.contains("字数统计"));
+        for secret in ["api-token-本机", "ghp_gist_token", "sk-ai-key", "/tmp/evil"] {
+            assert!(!json.contains(secret), "{secret}");
+        }
+
+        // 另一台机器上有自己的应用锁、令牌和程序路径
+        let mut other = AppSettings::default();
+        other.lock.set_password("另一台");
+        other.api.token = "另一台的令牌".to_string();
+        other.pandoc.path = "/opt/pandoc".to_string();
+        // 即使导入的文件里写了程序路径，也不会被采用
+        let tampered = json.replace(r#""command": """#, r#""command": "/tmp/evil-speak""#);
+        assert_ne!(tampered, json);
+        let imported = other.import_json(&tampered).unwrap();
+        assert_eq!(imported.sidebar.sort, NoteSort::Title);
+        assert_eq!(imported.fonts.editor_size, 18.0);
+        assert!(imported.lock.verify("另一台"));
+        assert!(imported.plugins.granted.is_empty());
+        assert_eq!(imported.api.token, "另一台的令牌");
+        assert_eq!(imported.pandoc.path, "/opt/pandoc");
+        assert_eq!(imported.speech.command, "");
+
+        assert!(other.import_json("{}").is_err());
+        assert!(other.import_json("不是 JSON").is_err());
+    }
+
+    #[test]
+    fn relative_minutes_and_hours() {
+        // 取当天中午，避免跨过午夜影响“小时前”和“昨天”的判断
+        let now = Local::now()
+            .date_naive()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_local_timezone(Local)
+            .unwrap()
+            .with_timezone(&Utc);
+        let settings = relative();
+        assert_eq!(settings.format_time(now, now), "刚刚");
+        assert_eq!(
+            settings.format_time(now - Duration::minutes(5), now),
+            "5 分钟前"
+        );
+        assert_eq!(
+            settings.format_time(now - Duration::hours(3), now),
+            "3 小时前"
+        );
+        assert_eq!(settings.format_time(now - Duration::days(3), now), "3 天前");
+    }
+
+    #[test]
+    fn old_times_use_absolute_format() {
+        let now = Utc::now();
+        let time = now - Duration::days(30);
+        let expected = time
+            .with_timezone(&Local)
+            .format(DEFAULT_TIME_FORMAT)
+            .to_string();
+        assert_eq!(relative().format_time(time, now), expected);
+        assert_eq!(SidebarSettings::default().format_time(time, now), expected);
+    }
+
+    #[test]
+    fn lock_password_is_salted_and_verified() {
+        let mut lock = LockSettings::default();
+        assert!(!lock.is_enabled());
+        assert!(!lock.verify(""));
+
+        lock.set_password("芝麻开门");
+        assert!(lock.is_enabled());
+        assert!(lock.verify("芝麻开门"));
+        assert!(!lock.verify("芝麻"));
+        assert!(!lock.password_hash.contains("芝麻开门"));
+        assert_eq!(lock.iterations, PASSWORD_ITERATIONS);
+        assert!(!lock.needs_rehash());
+        assert_eq!(lock.idle_timeout(), None);
+        lock.idle_minutes = 5;
+        assert_eq!(
+            lock.idle_timeout(),
+            Some(std::time::Duration::from_secs(300))
+        );
+
+        lock.set_password("");
+        assert!(!lock.is_enabled());
+        assert_eq!(lock.idle_timeout(), None);
+    }
+
+    #[test]
+    fn legacy_sha256_password_still_unlocks_and_needs_rehash() {
+        // 旧版本保存的设置没有 iterations 字段
+        let mut lock: LockSettings = serde_json::from_value(serde_json::json!({
+            "password_hash": password_hash("盐", "旧密码", 0),
+            "salt": "盐",
+        }))
+        .unwrap();
+        assert!(lock.verify("旧密码"));
+        assert!(!lock.verify("新密码"));
+        assert!(lock.needs_rehash());
+
+        lock.set_password("旧密码");
+        assert!(lock.verify("旧密码"));
+        assert!(!lock.needs_rehash());
+    }
+
+    #[test]
+    fn invalid_highlight_color_falls_back_to_default() {
+        let default = PreviewSettings::default().highlight_color();
+        let custom = PreviewSettings {
+            highlight_color: "#ff0000".to_string(),
+            ..PreviewSettings::default()
+        };
+        assert_ne!(custom.highlight_color(), default);
+        for invalid in ["红色", "ff0000", "#zzzzzz"] {
+            let settings = PreviewSettings {
+                highlight_color: invalid.to_string(),
+                ..PreviewSettings::default()
+            };
+            assert_eq!(settings.highlight_color(), default);
+        }
+    }
+
+    #[test]
+    fn custom_and_invalid_time_formats() {
+        let now = Utc::now();
+        let custom = SidebarSettings {
+            time_format: "%m月%d日".to_string(),
+            ..SidebarSettings::default()
+        };
+        assert_eq!(
+            custom.format_time(now, now),
+            now.with_timezone(&Local).format("%m月%d日").to_string()
+        );
+
+        let invalid = SidebarSettings {
+            time_format: "%Q".to_string(),
+            ..SidebarSettings::default()
+        };
+        assert!(!is_valid_time_format("%Q"));
+        assert_eq!(
+            invalid.format_time(now, now),
+            now.with_timezone(&Local)
+                .format(DEFAULT_TIME_FORMAT)
+                .to_string()
+        );
+    }
+}
@@ -0,0 +1,13 @@
+use crate::note::Note;
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+
+/// 用默认邮件客户端发送笔记的 mailto 链接
+///
+/// 空格编码为 %20 而不是 +，否则部分邮件客户端会原样显示加号
+pub fn mailto_url(note: &Note) -> String {
+    format!(
+        "mailto:?subject={}&body={}",
+        utf8_percent_encode(&note.title, NON_ALPHANUMERIC),
+        utf8_percent_encode(&note.content, NON_ALPHANUMERIC)
+    )
+}
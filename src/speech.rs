@@ -0,0 +1,151 @@
+use crate::{markup, settings::SpeechSettings};
+use anyhow::{Context, Result};
+use std::{
+    io::Write,
+    ops::Range,
+    process::{Child, Command, Stdio},
+};
+
+/// 1 倍语速时每分钟的字数，与各系统语音的默认语速接近
+const BASE_WPM: f32 = 180.0;
+
+/// 把正文切成句子，返回每句在正文中的字节范围
+///
+/// 在句末标点和换行处断开，句子不会跨行；只有空白或标记符号的部分跳过。
+pub fn sentences(text: &str) -> Vec<Range<usize>> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut push = |range: Range<usize>| {
+        if !speakable(&text[range.clone()]).is_empty() {
+            sentences.push(trim_range(text, range));
+        }
+    };
+    for (ix, ch) in text.char_indices() {
+        let end = ix + ch.len_utf8();
+        match ch {
+            '\n' => {
+                push(start..ix);
+                start = end;
+            }
+            '。' | '！' | '？' | '；' | '!' | '?' | '.' | ';' => {
+                // 小数点和省略号中间不断开
+                let next = text[end..].chars().next();
+                if ch == '.' && next.is_some_and(|next| !next.is_whitespace()) {
+                    continue;
+                }
+                push(start..end);
+                start = end;
+            }
+            _ => {}
+        }
+    }
+    push(start..text.len());
+    sentences
+}
+
+fn trim_range(text: &str, range: Range<usize>) -> Range<usize> {
+    let slice = &text[range.clone()];
+    let start = range.start + (slice.len() - slice.trim_start().len());
+    let end = range.end - (slice.len() - slice.trim_end().len());
+    start..end
+}
+
+/// 送给语音引擎的文字：去掉标题、列表、引用的前缀和行内格式标记
+pub fn speakable(sentence: &str) -> String {
+    let line = sentence
+        .trim()
+        .trim_start_matches(['#', '>', '-', '*', '+', '`', '|', '=', '_'])
+        .trim_start();
+    let text = markup::parse_inline(line).text;
+    if text.chars().any(char::is_alphanumeric) {
+        text
+    } else {
+        String::new()
+    }
+}
+
+/// 用语音引擎读出一段文字，进程结束即读完；结束进程可以中途停下
+pub fn speak(text: &str, settings: &SpeechSettings) -> Result<Child> {
+    let mut command = engine(settings)?;
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("无法启动语音引擎，请在设置中检查朗读命令")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(text.as_bytes())
+            .context("向语音引擎传送文字失败")?;
+    }
+    Ok(child)
+}
+
+fn engine(settings: &SpeechSettings) -> Result<Command> {
+    let wpm = (BASE_WPM * settings.rate).round().to_string();
+    if !settings.command.trim().is_empty() {
+        let mut args = settings
+            .command
+            .split_whitespace()
+            .map(|arg| arg.replace("{wpm}", &wpm));
+        let program = args.next().context("朗读命令为空")?;
+        let mut command = Command::new(program);
+        command.args(args);
+        return Ok(command);
+    }
+    let command = if cfg!(target_os = "macos") {
+        // 不带文字参数时 say 从标准输入读取
+        let mut command = Command::new("say");
+        command.args(["-r", &wpm]);
+        command
+    } else if cfg!(target_os = "windows") {
+        // SAPI 的语速从 -10 到 10，0 为正常语速
+        let rate = ((settings.rate - 1.0) * 10.0).round().clamp(-10.0, 10.0);
+        let mut command = Command::new("powershell");
+        command.args([
+            "-NoProfile",
+            "-Command",
+            &format!(
+                "Add-Type -AssemblyName System.Speech; \
+                 $voice = New-Object System.Speech.Synthesis.SpeechSynthesizer; \
+                 $voice.Rate = {}; \
+                 $voice.Speak([Console]::In.ReadToEnd())",
+                rate
+            ),
+        ]);
+        command
+    } else {
+        let mut command = Command::new("espeak-ng");
+        command.args(["-s", &wpm, "--stdin"]);
+        command
+    };
+    Ok(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_sentences_by_punctuation_and_lines() {
+        let text = "# 标题\n第一句。第二句！Version 1.5 is out. Next?\n\n---\n- **列表**项";
+        let sentences: Vec<&str> = sentences(text)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect();
+        assert_eq!(
+            sentences,
+            vec![
+                "# 标题",
+                "第一句。",
+                "第二句！",
+                "Version 1.5 is out.",
+                "Next?",
+                "- **列表**项"
+            ]
+        );
+        assert_eq!(speakable("# 标题"), "标题");
+        assert_eq!(speakable("- **列表**项"), "列表项");
+        assert_eq!(speakable("---"), "");
+    }
+}
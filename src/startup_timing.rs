@@ -0,0 +1,82 @@
+use gpui::{App, Global};
+use std::time::{Duration, Instant};
+
+/// 从进程启动到侧边栏可以交互的目标耗时
+const INTERACTIVE_TARGET: Duration = Duration::from_millis(200);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum StartupPhase {
+    WindowOpened,
+    FirstFrame,
+    /// 侧边栏显示出笔记标题，可以点击
+    Interactive,
+    NotesLoaded,
+    PreviewsReady,
+}
+
+impl StartupPhase {
+    fn label(self) -> &'static str {
+        match self {
+            StartupPhase::WindowOpened => "窗口创建",
+            StartupPhase::FirstFrame => "首帧绘制",
+            StartupPhase::Interactive => "侧边栏可交互",
+            StartupPhase::NotesLoaded => "正文读取完成",
+            StartupPhase::PreviewsReady => "预览计算完成",
+        }
+    }
+}
+
+/// 启动各阶段距进程启动的耗时，带 `--startup-timing` 启动时在预览算完后打印到终端
+pub struct StartupTiming {
+    started: Instant,
+    marks: Vec<(StartupPhase, Duration)>,
+    report: bool,
+}
+
+impl Global for StartupTiming {}
+
+impl StartupTiming {
+    pub fn init(started: Instant, report: bool, cx: &mut App) {
+        cx.set_global(Self {
+            started,
+            marks: Vec::new(),
+            report,
+        });
+    }
+
+    /// 记录到达某个阶段的时间，同一阶段只记第一次
+    pub fn mark(phase: StartupPhase, cx: &mut App) {
+        if !cx.has_global::<Self>() {
+            return;
+        }
+        let timing = cx.global_mut::<Self>();
+        if timing.marks.iter().any(|(other, _)| *other == phase) {
+            return;
+        }
+        let elapsed = timing.started.elapsed();
+        timing.marks.push((phase, elapsed));
+        tracing::info!(
+            elapsed_ms = elapsed.as_millis() as u64,
+            "启动阶段: {}",
+            phase.label()
+        );
+        if phase == StartupPhase::PreviewsReady && timing.report {
+            eprintln!("{}", timing.report());
+        }
+    }
+
+    fn report(&self) -> String {
+        let mut report = String::from("启动耗时（从进程启动算起）:");
+        for (phase, elapsed) in &self.marks {
+            report.push_str(&format!(
+                "\n  {:<8} {:>6} ms",
+                phase.label(),
+                elapsed.as_millis()
+            ));
+            if *phase == StartupPhase::Interactive && *elapsed > INTERACTIVE_TARGET {
+                report.push_str(&format!("  超出目标 {} ms", INTERACTIVE_TARGET.as_millis()));
+            }
+        }
+        report
+    }
+}
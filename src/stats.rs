@@ -0,0 +1,103 @@
+use crate::note::Note;
+use chrono::{Datelike, Days, NaiveDate};
+use std::collections::{HashMap, HashSet};
+
+/// 折线图覆盖的周数
+pub const WEEKS: usize = 12;
+const TOP_TAGS: usize = 5;
+const LONGEST_NOTES: usize = 5;
+
+pub struct NoteStats {
+    pub total_notes: usize,
+    pub total_words: usize,
+    /// 最近 WEEKS 周每周新建的笔记数，最后一项是本周
+    pub created_per_week: Vec<usize>,
+    pub top_tags: Vec<(String, usize)>,
+    /// (笔记 ID, 标题, 字数)
+    pub longest_notes: Vec<(u128, String, usize)>,
+    /// 截止到今天（今天还没写则截止到昨天）连续写作的天数
+    pub streak: usize,
+}
+
+impl NoteStats {
+    /// 字数由调用方提供，通常来自后台算好的 NoteIndex
+    pub fn compute(notes: &[&Note], word_count: impl Fn(&Note) -> usize, today: NaiveDate) -> Self {
+        let word_counts: Vec<usize> = notes.iter().map(|note| word_count(note)).collect();
+
+        let this_week = today - Days::new(today.weekday().num_days_from_monday() as u64);
+        let mut created_per_week = vec![0; WEEKS];
+        for note in notes {
+            let created = note.created_local().date_naive();
+            if created > today {
+                continue;
+            }
+            let weeks_ago = if created >= this_week {
+                0
+            } else {
+                ((this_week - created).num_days() as usize).div_ceil(7)
+            };
+            if weeks_ago < WEEKS {
+                created_per_week[WEEKS - 1 - weeks_ago] += 1;
+            }
+        }
+
+        let mut tag_counts: HashMap<&str, usize> = HashMap::new();
+        for tag in notes.iter().flat_map(|note| note.tags.iter()) {
+            *tag_counts.entry(tag.as_str()).or_default() += 1;
+        }
+        let mut top_tags: Vec<(String, usize)> = tag_counts
+            .into_iter()
+            .map(|(tag, count)| (tag.to_string(), count))
+            .collect();
+        top_tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top_tags.truncate(TOP_TAGS);
+
+        let mut longest_notes: Vec<(u128, String, usize)> = notes
+            .iter()
+            .zip(&word_counts)
+            .map(|(note, &words)| (note.id, note.title.clone(), words))
+            .collect();
+        longest_notes.sort_by_key(|(_, _, words)| std::cmp::Reverse(*words));
+        longest_notes.truncate(LONGEST_NOTES);
+
+        Self {
+            total_notes: notes.len(),
+            total_words: word_counts.iter().sum(),
+            created_per_week,
+            top_tags,
+            longest_notes,
+            streak: writing_streak(notes, today),
+        }
+    }
+}
+
+// 新建或修改过笔记的日子都算作写作日
+fn writing_streak(notes: &[&Note], today: NaiveDate) -> usize {
+    let active: HashSet<NaiveDate> = notes
+        .iter()
+        .flat_map(|note| {
+            [
+                note.created_local().date_naive(),
+                note.updated_local().date_naive(),
+            ]
+        })
+        .collect();
+
+    let mut day = if active.contains(&today) {
+        today
+    } else {
+        match today.pred_opt() {
+            Some(yesterday) => yesterday,
+            None => return 0,
+        }
+    };
+    let mut streak = 0;
+    while active.contains(&day) {
+        streak += 1;
+        match day.pred_opt() {
+            Some(previous) => day = previous,
+            None => break,
+        }
+    }
+    streak
+}
@@ -1,60 +1,813 @@
-use crate::note::Note;
+use crate::note::{Note, NoteMeta};
+use crate::session::Session;
+use crate::settings::AppSettings;
 use anyhow::{Context, Result};
-use std::fs;
-use std::path::PathBuf;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use futures::channel::{mpsc, oneshot};
+use gpui::BackgroundExecutor;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+#[derive(Clone)]
 pub struct Storage {
     data_dir: PathBuf,
+    /// 本进程最近一次读到或写入的每篇笔记文件的校验和。
+    /// 写入前文件内容与之不同，说明笔记在另一台机器上被修改过
+    versions: Arc<Mutex<HashMap<u128, String>>>,
+}
+
+/// 同一 ID 的笔记出现两个不同版本时的处理结果
+#[derive(Clone, Debug)]
+pub struct NoteConflict {
+    /// 修改时间较新的版本，保留原来的 ID
+    pub kept: Note,
+    /// 另一个版本，以新 ID 另存为冲突副本
+    pub copy: Note,
+}
+
+impl NoteConflict {
+    /// 两个版本相同时返回 None。修改时间相同时按正文和标题比较，结果与读取顺序无关
+    pub fn resolve(a: &Note, b: &Note) -> Option<Self> {
+        if a.same_version(b) {
+            return None;
+        }
+        let key = |note: &Note| (note.updated_at, note.content.clone(), note.title.clone());
+        let (kept, other) = if key(a) >= key(b) { (a, b) } else { (b, a) };
+        Some(Self {
+            kept: kept.clone(),
+            copy: other.conflict_copy(),
+        })
+    }
 }
 
 impl Storage {
+    /// 默认笔记库的数据目录
     pub fn new() -> Result<Self> {
+        Self::with_dir(Self::default_dir()?)
+    }
+
+    pub fn default_dir() -> Result<PathBuf> {
         // 获取用户的配置目录
         // Linux: ~/.config
         // macOS: ~/Library/Application Support
         // Windows: C:\Users\用户名\AppData\Roaming
         let config_dir = dirs::config_dir().context("无法获取配置目录")?;
-        let data_dir = config_dir.join("notes-app");
+        Ok(config_dir.join("notes-app"))
+    }
+
+    /// 使用指定的数据目录，基准测试和压力测试用临时目录
+    pub fn with_dir(data_dir: PathBuf) -> Result<Self> {
         fs::create_dir_all(&data_dir).context("无法创建数据目录")?;
-        Ok(Self { data_dir })
+        Ok(Self {
+            data_dir,
+            versions: Arc::default(),
+        })
     }
 
     pub fn save_note(&self, note: &Note) -> Result<()> {
-        let file_path = self.data_dir.join(format!("{}.json", note.id));
         let json = serde_json::to_string_pretty(note).context("序列化笔记失败")?;
-        fs::write(&file_path, json).context("写入笔记文件失败")?;
+        self.write_note_json(note.id, &json)
+    }
+
+    // 覆盖前把校验通过的旧文件留作备份；先写临时文件再替换，写到一半不会损坏原文件
+    fn write_note_json(&self, note_id: u128, json: &str) -> Result<()> {
+        let file_path = self.note_path(note_id);
+        if let Ok(old) = fs::read(&file_path)
+            && self.check_note(note_id, &old).is_none()
+        {
+            let backups = self.backups_dir();
+            fs::create_dir_all(&backups).context("无法创建备份目录")?;
+            fs::write(backups.join(format!("{}.json", note_id)), &old)
+                .context("写入笔记备份失败")?;
+        }
+        let temp_path = file_path.with_extension("json.tmp");
+        fs::write(&temp_path, json).context("写入笔记文件失败")?;
+        fs::rename(&temp_path, &file_path).context("写入笔记文件失败")?;
+        let checksum = checksum(json.as_bytes());
+        fs::write(self.checksum_path(note_id), &checksum).context("写入校验和失败")?;
+        self.remember_version(note_id, checksum);
         Ok(())
     }
 
-    pub fn load_all_notes(&self, notes: &mut HashMap<u128, Note>) -> Result<()> {
+    fn remember_version(&self, note_id: u128, checksum: String) {
+        if let Ok(mut versions) = self.versions.lock() {
+            versions.insert(note_id, checksum);
+        }
+    }
+
+    /// 写入前确认笔记文件没有在别处被修改过；被修改过且内容不同时，
+    /// 较新的版本保留原 ID，另一个版本另存为冲突副本，两者都写盘
+    fn write_note_checked(&self, note_id: u128, json: &str) -> Result<Option<NoteConflict>> {
+        if let Some(theirs) = self.changed_elsewhere(note_id) {
+            let ours: Note = serde_json::from_str(json).context("解析笔记失败")?;
+            if let Some(conflict) = NoteConflict::resolve(&ours, &theirs) {
+                tracing::warn!(
+                    note_id,
+                    copy = conflict.copy.id,
+                    "笔记在别处被修改，已另存冲突副本"
+                );
+                self.save_note(&conflict.kept)?;
+                self.save_note(&conflict.copy)?;
+                return Ok(Some(conflict));
+            }
+        }
+        self.write_note_json(note_id, json)?;
+        Ok(None)
+    }
+
+    // 本进程读写过这篇笔记、而文件现在的内容不是当时的内容
+    fn changed_elsewhere(&self, note_id: u128) -> Option<Note> {
+        let known = self.versions.lock().ok()?.get(&note_id).cloned()?;
+        let content = fs::read(self.note_path(note_id)).ok()?;
+        if checksum(&content) == known {
+            return None;
+        }
+        serde_json::from_slice::<Note>(&content)
+            .ok()
+            .filter(|note| note.id == note_id)
+    }
+
+    fn note_path(&self, note_id: u128) -> PathBuf {
+        self.data_dir.join(format!("{}.json", note_id))
+    }
+
+    // 与笔记文件放在一起，扩展名不是 json，读取笔记时会跳过
+    fn checksum_path(&self, note_id: u128) -> PathBuf {
+        self.data_dir.join(format!("{}.sha256", note_id))
+    }
+
+    /// 每篇笔记最后一个校验通过的版本，用于恢复损坏的笔记
+    pub fn backups_dir(&self) -> PathBuf {
+        self.data_dir.join("backups")
+    }
+
+    // 校验笔记文件内容，没有问题时返回 None。旧版本保存的文件没有校验和，只检查能否解析
+    fn check_note(&self, note_id: u128, content: &[u8]) -> Option<NoteProblem> {
+        let expected = fs::read_to_string(self.checksum_path(note_id)).ok();
+        if expected
+            .as_deref()
+            .is_some_and(|expected| expected.trim() == checksum(content))
+        {
+            return None;
+        }
+        match serde_json::from_slice::<Note>(content) {
+            Err(e) if e.is_eof() => Some(NoteProblem::Truncated),
+            _ if expected.is_some() => Some(NoteProblem::ChecksumMismatch),
+            Ok(_) => None,
+            Err(e) => Some(NoteProblem::Unreadable(e.to_string())),
+        }
+    }
+
+    /// 扫描所有笔记文件，找出校验和不符、不完整或无法解析的文件
+    pub fn verify(&self) -> Result<VerifyReport> {
+        let mut report = VerifyReport::default();
         let entries = fs::read_dir(&self.data_dir).context("无法读取数据目录")?;
+        for entry in entries {
+            let path = entry.context("读取目录条目失败")?.path();
+            let Some(note_id) = Self::note_id_from_path(&path) else {
+                continue;
+            };
+            report.checked += 1;
+            if !self.checksum_path(note_id).exists() {
+                report.unverified += 1;
+            }
+            let problem = match fs::read(&path) {
+                Ok(content) => self.check_note(note_id, &content),
+                Err(e) => Some(NoteProblem::Unreadable(e.to_string())),
+            };
+            if let Some(problem) = problem {
+                report.damaged.push(DamagedNote {
+                    note_id,
+                    path,
+                    problem,
+                    backup: self.load_backup(note_id),
+                });
+            }
+        }
+        report.damaged.sort_by_key(|damaged| damaged.note_id);
+        Ok(report)
+    }
+
+    // 备份同样可能损坏，只返回能解析的备份
+    fn load_backup(&self, note_id: u128) -> Option<Note> {
+        let content = fs::read(self.backups_dir().join(format!("{}.json", note_id))).ok()?;
+        serde_json::from_slice(&content).ok()
+    }
 
+    /// 读取全部笔记，返回读取时发现并已处理的冲突
+    ///
+    /// 文件被复制或改名后，文件名与其中的 ID 不一致，可能与另一篇笔记重复。
+    /// 这样的文件会移到以自身 ID 命名的位置；ID 重复且内容不同时，
+    /// 较新的版本保留 ID，另一个版本另存为冲突副本。
+    pub fn load_all_notes(&self, notes: &mut HashMap<u128, Note>) -> Result<Vec<NoteConflict>> {
+        let entries = fs::read_dir(&self.data_dir).context("无法读取数据目录")?;
+        // 数据目录里还有 settings.json 等非笔记文件，只读取以笔记 ID 命名的文件
+        let mut paths = Vec::new();
         for entry in entries {
-            let entry = entry.context("读取目录条目失败")?;
-            let path = entry.path();
-
-            if path.extension().map_or(false, |ext| ext == "json") {
-                let content =
-                    fs::read_to_string(&path).context(format!("读取文件失败: {:?}", path))?;
-                match serde_json::from_str::<Note>(&content) {
-                    Ok(note) => {
-                        notes.insert(note.id, note);
-                    },
-                    Err(e) => {
-                        eprintln!("解析笔记文件失败 {:?}: {}", path, e);
+            let path = entry.context("读取目录条目失败")?.path();
+            if let Some(file_id) = Self::note_id_from_path(&path) {
+                paths.push((file_id, path));
+            }
+        }
+        // 先处理文件名与 ID 一致的文件，移动的文件不会覆盖还没读到的笔记；
+        // 再按文件名排序，重复 ID 的处理结果不受目录遍历顺序影响
+        let mut loaded = Vec::new();
+        for (file_id, path) in paths {
+            let content = fs::read(&path).context(format!("读取文件失败: {:?}", path))?;
+            match serde_json::from_slice::<Note>(&content) {
+                Ok(note) => {
+                    if note.id == file_id {
+                        self.remember_version(file_id, checksum(&content));
                     }
+                    loaded.push((note.id != file_id, file_id, path, note));
+                }
+                Err(e) => tracing::warn!("解析笔记文件失败 {:?}: {}", path, e),
+            }
+        }
+        loaded.sort_by_key(|(moved, file_id, ..)| (*moved, *file_id));
+
+        let mut conflicts = Vec::new();
+        for (moved, file_id, path, note) in loaded {
+            let conflict = notes
+                .get(&note.id)
+                .and_then(|existing| NoteConflict::resolve(existing, &note));
+            if let Some(conflict) = conflict {
+                tracing::warn!(
+                    note_id = conflict.kept.id,
+                    copy = conflict.copy.id,
+                    "笔记 ID 重复，已另存冲突副本"
+                );
+                self.save_note(&conflict.kept)?;
+                self.save_note(&conflict.copy)?;
+                notes.insert(conflict.kept.id, conflict.kept.clone());
+                notes.insert(conflict.copy.id, conflict.copy.clone());
+                conflicts.push(conflict);
+            } else if notes
+                .get(&note.id)
+                .is_none_or(|existing| note.updated_at > existing.updated_at)
+            {
+                if moved {
+                    self.save_note(&note)?;
                 }
+                notes.insert(note.id, note);
+            }
+            if moved {
+                fs::remove_file(&path).context("移动笔记文件失败")?;
+                fs::remove_file(self.checksum_path(file_id)).ok();
             }
         }
+        Ok(conflicts)
+    }
+
+    /// 上次退出时保存的笔记元数据，文件不存在时返回 None
+    ///
+    /// 只用于快速显示侧边栏，可能落后于笔记文件，正文读完后以笔记文件为准。
+    pub fn load_note_index(&self) -> Result<Option<Vec<NoteMeta>>> {
+        let file_path = self.note_index_path();
+        if !file_path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&file_path).context("读取笔记索引失败")?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .context("解析笔记索引失败")
+    }
+
+    fn write_note_index(&self, notes: &[NoteMeta]) -> Result<()> {
+        let json = serde_json::to_string(notes).context("序列化笔记索引失败")?;
+        fs::write(self.note_index_path(), json).context("写入笔记索引失败")?;
+        Ok(())
+    }
+
+    fn note_index_path(&self) -> PathBuf {
+        self.data_dir.join("note-index.json")
+    }
+
+    pub fn load_settings(&self) -> Result<AppSettings> {
+        let file_path = self.settings_path();
+        if !file_path.exists() {
+            return Ok(AppSettings::default());
+        }
+        let content = fs::read_to_string(&file_path).context("读取设置文件失败")?;
+        serde_json::from_str(&content).context("解析设置文件失败")
+    }
+
+    pub fn save_settings(&self, settings: &AppSettings) -> Result<()> {
+        let json = serde_json::to_string_pretty(settings).context("序列化设置失败")?;
+        fs::write(self.settings_path(), json).context("写入设置文件失败")?;
         Ok(())
     }
 
+    fn settings_path(&self) -> PathBuf {
+        self.data_dir.join("settings.json")
+    }
+
+    pub fn load_session(&self) -> Result<Session> {
+        let file_path = self.session_path();
+        if !file_path.exists() {
+            return Ok(Session::default());
+        }
+        let content = fs::read_to_string(&file_path).context("读取会话文件失败")?;
+        serde_json::from_str(&content).context("解析会话文件失败")
+    }
+
+    fn write_session_json(&self, json: &str) -> Result<()> {
+        fs::write(self.session_path(), json).context("写入会话文件失败")?;
+        Ok(())
+    }
+
+    /// 存放 .wasm 插件及其清单的目录
+    pub fn plugins_dir(&self) -> PathBuf {
+        self.data_dir.join("plugins")
+    }
+
+    /// 开启诊断后日志文件所在的目录
+    pub fn logs_dir(&self) -> PathBuf {
+        self.data_dir.join("logs")
+    }
+
+    /// 存放 .rhai 自动化脚本的目录
+    pub fn scripts_dir(&self) -> PathBuf {
+        self.data_dir.join("scripts")
+    }
+
+    /// 存放图片等附件的目录，笔记里用 `attachments/<文件名>` 引用
+    pub fn attachments_dir(&self) -> PathBuf {
+        self.data_dir.join("attachments")
+    }
+
+    /// 把文件复制到附件目录，重名时在文件名后加序号，返回保存后的文件名
+    pub fn save_attachment(&self, source: &Path) -> Result<String> {
+        let name = self.unused_attachment_name(source)?;
+        fs::copy(source, self.attachments_dir().join(&name))
+            .context(format!("复制附件失败: {:?}", source))?;
+        Ok(name)
+    }
+
+    /// 把下载的内容写成附件，重名时同样加序号，返回保存后的文件名
+    pub fn write_attachment(&self, name: &str, bytes: &[u8]) -> Result<String> {
+        if name.contains(['/', '\\']) || name == ".." {
+            anyhow::bail!("无效的附件名: {}", name);
+        }
+        let name = self.unused_attachment_name(Path::new(name))?;
+        fs::write(self.attachments_dir().join(&name), bytes)
+            .context(format!("写入附件失败: {}", name))?;
+        Ok(name)
+    }
+
+    /// 附件目录中还没有被占用的文件名，与 source 的文件名同名时在后面加序号
+    pub fn unused_attachment_name(&self, source: &Path) -> Result<String> {
+        let dir = self.attachments_dir();
+        fs::create_dir_all(&dir).context("无法创建附件目录")?;
+        let stem = source
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("attachment");
+        // 没有扩展名的文件不加点，免得得到 name-2. 这样的名字
+        let extension = source
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .filter(|ext| !ext.is_empty())
+            .map(|ext| format!(".{}", ext))
+            .unwrap_or_default();
+        let mut name = source
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("attachment")
+            .to_string();
+        let mut index = 1;
+        while dir.join(&name).exists() {
+            index += 1;
+            name = format!("{}-{}{}", stem, index, extension);
+        }
+        Ok(name)
+    }
+
+    pub fn delete_attachment(&self, name: &str) -> Result<()> {
+        // 只删除附件目录里的文件，名称来自附件列表，防止带路径的名称删到别处
+        if name.contains(['/', '\\']) || name == ".." {
+            anyhow::bail!("无效的附件名: {}", name);
+        }
+        fs::remove_file(self.attachments_dir().join(name))
+            .context(format!("删除附件失败: {}", name))
+    }
+
+    fn session_path(&self) -> PathBuf {
+        self.data_dir.join("session.json")
+    }
+
+    fn note_id_from_path(path: &Path) -> Option<u128> {
+        if path.extension()? != "json" {
+            return None;
+        }
+        path.file_stem()?.to_str()?.parse().ok()
+    }
+
+    /// 把笔记移到回收站并记下删除时间
+    pub fn trash_note(&self, note: &Note, deleted_at: DateTime<Utc>) -> Result<()> {
+        self.write_trashed_json(note.id, &trashed_json(note, deleted_at)?)
+    }
+
+    // 回收站里存的是删除时内存中的笔记，还没写盘的修改也不会丢
+    fn write_trashed_json(&self, note_id: u128, json: &str) -> Result<()> {
+        fs::create_dir_all(self.trash_dir()).context("无法创建回收站目录")?;
+        fs::write(self.trash_path(note_id), json).context("写入回收站失败")?;
+        self.delete_note(note_id)
+    }
+
+    /// 回收站中的笔记，最近删除的在前
+    pub fn load_trash(&self) -> Result<Vec<TrashedNote>> {
+        let dir = self.trash_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut trashed = Vec::new();
+        for entry in fs::read_dir(&dir).context("无法读取回收站目录")? {
+            let path = entry.context("读取目录条目失败")?.path();
+            if Self::note_id_from_path(&path).is_none() {
+                continue;
+            }
+            let content = fs::read(&path).context(format!("读取文件失败: {:?}", path))?;
+            match serde_json::from_slice::<TrashedNote>(&content) {
+                Ok(note) => trashed.push(note),
+                Err(e) => tracing::warn!("解析回收站文件失败 {:?}: {}", path, e),
+            }
+        }
+        trashed.sort_by_key(|trashed| std::cmp::Reverse(trashed.deleted_at));
+        Ok(trashed)
+    }
+
+    /// 从回收站中移除，恢复笔记和永久删除都会调用
+    pub fn remove_from_trash(&self, note_id: u128) -> Result<()> {
+        let path = self.trash_path(note_id);
+        if path.exists() {
+            fs::remove_file(&path).context("删除回收站中的笔记失败")?;
+        }
+        Ok(())
+    }
+
+    /// 永久删除在 `now` 之前已到期的笔记，返回删除的篇数
+    pub fn purge_trash(&self, retention: chrono::Duration, now: DateTime<Utc>) -> Result<usize> {
+        let mut purged = 0;
+        for trashed in self.load_trash()? {
+            if trashed.deleted_at + retention <= now {
+                self.remove_from_trash(trashed.note.id)?;
+                purged += 1;
+            }
+        }
+        Ok(purged)
+    }
+
+    pub fn trash_dir(&self) -> PathBuf {
+        self.data_dir.join("trash")
+    }
+
+    fn trash_path(&self, note_id: u128) -> PathBuf {
+        self.trash_dir().join(format!("{}.json", note_id))
+    }
+
     pub fn delete_note(&self, note_id: u128) -> Result<()> {
-        let file_path = self.data_dir.join(format!("{}.json", note_id));
+        if let Ok(mut versions) = self.versions.lock() {
+            versions.remove(&note_id);
+        }
+        let file_path = self.note_path(note_id);
         if file_path.exists() {
             fs::remove_file(&file_path).context("删除笔记文件失败")?;
         }
+        // 校验和与备份随笔记一起删除，免得验证数据时把已删除的笔记恢复回来
+        let backup_path = self.backups_dir().join(format!("{}.json", note_id));
+        for path in [self.checksum_path(note_id), backup_path] {
+            if path.exists() {
+                fs::remove_file(&path).context("删除笔记文件失败")?;
+            }
+        }
         Ok(())
     }
 }
+
+fn trashed_json(note: &Note, deleted_at: DateTime<Utc>) -> Result<String> {
+    let trashed = TrashedNote {
+        note: note.clone(),
+        deleted_at,
+    };
+    serde_json::to_string_pretty(&trashed).context("序列化笔记失败")
+}
+
+fn checksum(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// 回收站中的一篇笔记
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrashedNote {
+    pub note: Note,
+    pub deleted_at: DateTime<Utc>,
+}
+
+impl TrashedNote {
+    /// 按保留时长计算的永久删除时间，一直保留时为 None
+    pub fn purge_at(&self, retention: Option<chrono::Duration>) -> Option<DateTime<Utc>> {
+        retention.map(|retention| self.deleted_at + retention)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum NoteProblem {
+    /// 内容与保存时记录的校验和不符
+    ChecksumMismatch,
+    /// 文件在写入途中被截断
+    Truncated,
+    Unreadable(String),
+}
+
+impl NoteProblem {
+    pub fn label(&self) -> String {
+        match self {
+            NoteProblem::ChecksumMismatch => "内容与校验和不符".to_string(),
+            NoteProblem::Truncated => "文件不完整".to_string(),
+            NoteProblem::Unreadable(error) => format!("无法读取: {}", error),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DamagedNote {
+    pub note_id: u128,
+    pub path: PathBuf,
+    pub problem: NoteProblem,
+    /// 最后一个校验通过的版本，没有备份时为 None
+    pub backup: Option<Note>,
+}
+
+/// 验证数据的结果
+#[derive(Clone, Debug, Default)]
+pub struct VerifyReport {
+    pub checked: usize,
+    /// 旧版本保存、还没有校验和的文件数，下次保存时补上
+    pub unverified: usize,
+    pub damaged: Vec<DamagedNote>,
+}
+
+enum WriteOp {
+    SaveNote {
+        note_id: u128,
+        json: String,
+    },
+    /// 移到回收站，json 是带删除时间的 TrashedNote
+    TrashNote {
+        note_id: u128,
+        json: String,
+    },
+    SaveSession(String),
+    /// 元数据在后台线程序列化，笔记很多时也不占用界面线程
+    SaveNoteIndex(Vec<NoteMeta>),
+    /// 其他需要与笔记写入保持先后顺序的文件操作，例如搜索索引。
+    /// 带笔记 ID 的操作与同一笔记的后续操作合并
+    Run {
+        note_id: Option<u128>,
+        f: Box<dyn Fn() -> Result<()> + Send>,
+    },
+    /// 重新写入之前失败的操作
+    Retry,
+    /// 前面的写操作都完成后回复，并跳过等待立即写盘
+    Flush(oneshot::Sender<()>),
+}
+
+// 合并写操作时的依据，键相同的操作只保留最后一个
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum WriteKey {
+    Note(u128),
+    SearchIndex(u128),
+    Session,
+    NoteIndex,
+    Once(usize),
+}
+
+/// 收到写操作后等这么久再写盘，期间同一笔记的多次保存只写最后一次
+pub(crate) const WRITE_DELAY: Duration = Duration::from_millis(300);
+
+/// 后台写盘的结果，界面据此提示用户
+pub enum WriteStatus {
+    /// 有写操作失败。失败的操作留在内存里，重试或同一文件有新的写入时再写
+    Failed { pending: usize, error: String },
+    /// 之前失败的操作都已写入
+    Recovered,
+    /// 笔记文件在别处被修改过，写入时另存了冲突副本，内存中的笔记需要随之更新
+    Conflict(Box<NoteConflict>),
+}
+
+/// 在后台线程写盘，界面线程只负责序列化
+///
+/// 写操作攒一小段时间后成批处理：同一篇笔记只写最后提交的内容，
+/// 不同文件按首次提交的顺序写入。
+#[derive(Clone)]
+pub struct StorageWriter {
+    tx: mpsc::UnboundedSender<WriteOp>,
+}
+
+// 后台写盘任务的状态
+struct WriteQueue {
+    storage: Storage,
+    /// 写入失败、等待重试的操作
+    failed: IndexMap<WriteKey, WriteOp>,
+    next_once: usize,
+    status: mpsc::UnboundedSender<WriteStatus>,
+}
+
+impl StorageWriter {
+    pub fn spawn(
+        storage: Storage,
+        executor: &BackgroundExecutor,
+        status: mpsc::UnboundedSender<WriteStatus>,
+    ) -> Self {
+        let (tx, mut rx) = mpsc::unbounded::<WriteOp>();
+        let timer = executor.clone();
+        let mut queue = WriteQueue {
+            storage,
+            failed: IndexMap::new(),
+            next_once: 0,
+            status,
+        };
+        executor
+            .spawn(async move {
+                while let Some(first) = rx.next().await {
+                    if !matches!(first, WriteOp::Flush(_) | WriteOp::Retry) {
+                        timer.timer(WRITE_DELAY).await;
+                    }
+                    let mut batch = vec![first];
+                    while let Ok(Some(op)) = rx.try_next() {
+                        batch.push(op);
+                    }
+                    queue.write_batch(batch);
+                }
+            })
+            .detach();
+        Self { tx }
+    }
+
+    pub fn save_note(&self, note: &Note) -> Result<()> {
+        let json = serde_json::to_string_pretty(note).context("序列化笔记失败")?;
+        self.send(WriteOp::SaveNote {
+            note_id: note.id,
+            json,
+        })
+    }
+
+    pub fn trash_note(&self, note: &Note) -> Result<()> {
+        let json = trashed_json(note, Utc::now())?;
+        self.send(WriteOp::TrashNote {
+            note_id: note.id,
+            json,
+        })
+    }
+
+    pub fn save_session(&self, session: &Session) -> Result<()> {
+        let json = serde_json::to_string_pretty(session).context("序列化会话失败")?;
+        self.send(WriteOp::SaveSession(json))
+    }
+
+    pub fn save_note_index(&self, notes: Vec<NoteMeta>) -> Result<()> {
+        self.send(WriteOp::SaveNoteIndex(notes))
+    }
+
+    pub fn run(&self, f: impl Fn() -> Result<()> + Send + 'static) -> Result<()> {
+        self.send(WriteOp::Run {
+            note_id: None,
+            f: Box::new(f),
+        })
+    }
+
+    /// 与某篇笔记相关的文件操作，同一笔记未写盘的旧操作会被丢弃
+    pub fn run_for_note(
+        &self,
+        note_id: u128,
+        f: impl Fn() -> Result<()> + Send + 'static,
+    ) -> Result<()> {
+        self.send(WriteOp::Run {
+            note_id: Some(note_id),
+            f: Box::new(f),
+        })
+    }
+
+    /// 重新写入之前失败的操作
+    pub fn retry(&self) -> Result<()> {
+        self.send(WriteOp::Retry)
+    }
+
+    /// 立即写入所有未写盘的操作，返回的 Future 在写完后完成。退出和窗口失去焦点时调用
+    pub fn flush(&self) -> impl Future<Output = ()> + use<> {
+        let (done, wait) = oneshot::channel();
+        let sent = self.send(WriteOp::Flush(done)).is_ok();
+        async move {
+            if sent {
+                wait.await.ok();
+            }
+        }
+    }
+
+    fn send(&self, op: WriteOp) -> Result<()> {
+        self.tx
+            .unbounded_send(op)
+            .map_err(|_| anyhow::anyhow!("后台写入任务已停止"))
+    }
+}
+
+impl WriteQueue {
+    fn write_batch(&mut self, batch: Vec<WriteOp>) {
+        let started = Instant::now();
+        let submitted = batch.len();
+        let had_failures = !self.failed.is_empty();
+        let retry = batch.iter().any(|op| matches!(op, WriteOp::Retry));
+        // 重试时失败的操作排在最前面，同一文件有更新的写入时以新的为准
+        let mut pending = if retry {
+            std::mem::take(&mut self.failed)
+        } else {
+            IndexMap::new()
+        };
+        let mut flushes = Vec::new();
+        for op in batch {
+            let key = match &op {
+                WriteOp::SaveNote { note_id, .. } | WriteOp::TrashNote { note_id, .. } => {
+                    WriteKey::Note(*note_id)
+                }
+                WriteOp::SaveSession(_) => WriteKey::Session,
+                WriteOp::SaveNoteIndex(_) => WriteKey::NoteIndex,
+                WriteOp::Run {
+                    note_id: Some(note_id),
+                    ..
+                } => WriteKey::SearchIndex(*note_id),
+                WriteOp::Run { note_id: None, .. } => {
+                    self.next_once += 1;
+                    WriteKey::Once(self.next_once)
+                }
+                WriteOp::Retry => continue,
+                WriteOp::Flush(_) => {
+                    if let WriteOp::Flush(done) = op {
+                        flushes.push(done);
+                    }
+                    continue;
+                }
+            };
+            self.failed.shift_remove(&key);
+            pending.insert(key, op);
+        }
+
+        let written = pending.len();
+        let mut last_error = None;
+        for (key, op) in pending {
+            if let Err(e) = self.write(&op) {
+                tracing::error!("写入数据失败: {:#}", e);
+                last_error = Some(format!("{:#}", e));
+                self.failed.insert(key, op);
+            }
+        }
+        if written > 0 {
+            tracing::debug!(
+                submitted,
+                written,
+                failed = self.failed.len(),
+                elapsed_ms = started.elapsed().as_millis() as u64,
+                "写盘完成"
+            );
+        }
+        if let Some(error) = last_error {
+            let pending = self.failed.len();
+            self.status
+                .unbounded_send(WriteStatus::Failed { pending, error })
+                .ok();
+        } else if had_failures && self.failed.is_empty() {
+            self.status.unbounded_send(WriteStatus::Recovered).ok();
+        }
+        for done in flushes {
+            done.send(()).ok();
+        }
+    }
+
+    fn write(&self, op: &WriteOp) -> Result<()> {
+        match op {
+            WriteOp::SaveNote { note_id, json } => {
+                if let Some(conflict) = self.storage.write_note_checked(*note_id, json)? {
+                    self.status
+                        .unbounded_send(WriteStatus::Conflict(Box::new(conflict)))
+                        .ok();
+                }
+                Ok(())
+            }
+            WriteOp::TrashNote { note_id, json } => self.storage.write_trashed_json(*note_id, json),
+            WriteOp::SaveSession(json) => self.storage.write_session_json(json),
+            WriteOp::SaveNoteIndex(notes) => self.storage.write_note_index(notes),
+            WriteOp::Run { f, .. } => f(),
+            WriteOp::Retry | WriteOp::Flush(_) => Ok(()),
+        }
+    }
+}
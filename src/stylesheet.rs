@@ -0,0 +1,204 @@
+use crate::settings::{AppSettings, parse_color};
+use anyhow::{Context as _, Result};
+use gpui::{App, Global, Hsla};
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::SystemTime,
+};
+
+/// 样式表中一类元素的样式，没写的属性沿用默认样式
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextStyle {
+    pub font_family: Option<String>,
+    /// 像素，em 和 rem 按 16px 换算
+    pub font_size: Option<f32>,
+    pub color: Option<Hsla>,
+    pub background: Option<Hsla>,
+    pub border_color: Option<Hsla>,
+    pub italic: bool,
+}
+
+/// 预览样式表，支持 CSS 中 body、h1–h6、pre/code 和 blockquote 的常用属性
+///
+/// 颜色只认十六进制写法；不认识的选择器和属性忽略，同一个 CSS 文件也能直接用于导出。
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stylesheet {
+    pub body: TextStyle,
+    pub headings: [TextStyle; 6],
+    pub code: TextStyle,
+    pub quote: TextStyle,
+}
+
+impl Stylesheet {
+    pub fn load(path: &Path) -> Result<Self> {
+        let css = std::fs::read_to_string(path).context(format!("无法读取样式表 {:?}", path))?;
+        Ok(Self::parse(&css))
+    }
+
+    pub fn parse(css: &str) -> Self {
+        let mut sheet = Self::default();
+        let css = strip_comments(css);
+        let mut rest = css.as_str();
+        while let Some((selectors, after)) = rest.split_once('{') {
+            let Some((body, after)) = after.split_once('}') else {
+                break;
+            };
+            rest = after;
+            for selector in selectors.split(',') {
+                // `.preview h1` 这样带前缀的选择器按最后一段匹配
+                let element = selector.split_whitespace().last().unwrap_or_default();
+                if let Some(style) = sheet.style_mut(&element.to_ascii_lowercase()) {
+                    apply_declarations(style, body);
+                }
+            }
+        }
+        sheet
+    }
+
+    fn style_mut(&mut self, element: &str) -> Option<&mut TextStyle> {
+        match element {
+            "html" | "body" => Some(&mut self.body),
+            "pre" | "code" => Some(&mut self.code),
+            "blockquote" => Some(&mut self.quote),
+            _ => {
+                let level: usize = element.strip_prefix('h')?.parse().ok()?;
+                self.headings.get_mut(level.checked_sub(1)?)
+            }
+        }
+    }
+
+    pub fn heading(&self, level: usize) -> &TextStyle {
+        &self.headings[level.clamp(1, 6) - 1]
+    }
+}
+
+fn strip_comments(css: &str) -> String {
+    let mut out = String::new();
+    let mut rest = css;
+    while let Some(start) = rest.find("/*") {
+        out.push_str(&rest[..start]);
+        rest = rest[start + 2..]
+            .split_once("*/")
+            .map_or("", |(_, after)| after);
+    }
+    out.push_str(rest);
+    out
+}
+
+fn apply_declarations(style: &mut TextStyle, body: &str) {
+    for declaration in body.split(';') {
+        let Some((property, value)) = declaration.split_once(':') else {
+            continue;
+        };
+        let value = value.trim().trim_end_matches("!important").trim();
+        match property.trim().to_ascii_lowercase().as_str() {
+            "font-family" => {
+                let family = value.split(',').next().unwrap_or_default();
+                let family = family.trim().trim_matches(['"', '\'']);
+                if !family.is_empty() {
+                    style.font_family = Some(family.to_string());
+                }
+            }
+            "font-size" => style.font_size = parse_size(value).or(style.font_size),
+            "color" => style.color = parse_color(value).or(style.color),
+            "background" | "background-color" => {
+                style.background = first_color(value).or(style.background)
+            }
+            "border-color" | "border-left-color" => {
+                style.border_color = parse_color(value).or(style.border_color)
+            }
+            "border-left" | "border" => {
+                style.border_color = first_color(value).or(style.border_color)
+            }
+            "font-style" => style.italic = value == "italic",
+            _ => {}
+        }
+    }
+}
+
+// `background: #fff url(...)` 这样的简写取其中的颜色
+fn first_color(value: &str) -> Option<Hsla> {
+    value.split_whitespace().find_map(parse_color)
+}
+
+fn parse_size(value: &str) -> Option<f32> {
+    let (number, scale) = if let Some(number) = value.strip_suffix("px") {
+        (number, 1.0)
+    } else if let Some(number) = value
+        .strip_suffix("rem")
+        .or_else(|| value.strip_suffix("em"))
+    {
+        (number, 16.0)
+    } else if let Some(number) = value.strip_suffix("pt") {
+        (number, 4.0 / 3.0)
+    } else {
+        return None;
+    };
+    let size = number.trim().parse::<f32>().ok()? * scale;
+    (size > 0.0).then_some(size)
+}
+
+// 上次读取的样式表，文件路径或修改时间变了才重新读取
+struct StylesheetCache {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    sheet: Rc<Stylesheet>,
+}
+
+impl Global for StylesheetCache {}
+
+/// 设置中指定的预览样式表，没有设置或读取失败时为默认样式
+pub fn current(cx: &mut App) -> Rc<Stylesheet> {
+    let Some(path) = AppSettings::get(cx).preview.stylesheet_path() else {
+        return Rc::default();
+    };
+    let modified = std::fs::metadata(&path)
+        .and_then(|meta| meta.modified())
+        .ok();
+    if let Some(cache) = cx.try_global::<StylesheetCache>()
+        && cache.path == path
+        && cache.modified == modified
+    {
+        return cache.sheet.clone();
+    }
+    let sheet = Rc::new(Stylesheet::load(&path).unwrap_or_else(|e| {
+        tracing::warn!("{:#}", e);
+        Stylesheet::default()
+    }));
+    cx.set_global(StylesheetCache {
+        path,
+        modified,
+        sheet: sheet.clone(),
+    });
+    sheet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_supported_selectors_and_properties() {
+        let sheet = Stylesheet::parse(
+            r#"
+            /* 公司模板 */
+            body { font-family: "Source Han Serif", serif; color: #333333; }
+            .preview h1, h2 { font-size: 2em; color: #1d4ed8 !important }
+            pre, code { background: #0f172a; color: #e2e8f0; font-family: 'JetBrains Mono' }
+            blockquote { border-left: 4px solid #f59e0b; font-style: italic }
+            table { color: #ff0000 }
+            h7 { color: #ff0000 }
+            "#,
+        );
+        assert_eq!(sheet.body.font_family.as_deref(), Some("Source Han Serif"));
+        assert_eq!(sheet.body.color, parse_color("#333333"));
+        assert_eq!(sheet.heading(1).font_size, Some(32.0));
+        assert_eq!(sheet.heading(2).color, parse_color("#1d4ed8"));
+        assert_eq!(sheet.heading(3), &TextStyle::default());
+        assert_eq!(sheet.code.background, parse_color("#0f172a"));
+        assert_eq!(sheet.code.font_family.as_deref(), Some("JetBrains Mono"));
+        assert_eq!(sheet.quote.border_color, parse_color("#f59e0b"));
+        assert!(sheet.quote.italic);
+    }
+}
@@ -0,0 +1,102 @@
+use crate::note::Note;
+use chrono::{Duration, Utc};
+
+/// 合成笔记都带这个标签，方便压力测试后找出来删除
+pub const SYNTHETIC_TAG: &str = "压测";
+
+const WORDS: &[&str] = &[
+    "笔记", "会议", "想法", "计划", "读书", "项目", "复盘", "灵感", "待办", "周报", "rust", "gpui",
+    "editor", "storage", "render", "search", "draft", "todo", "release", "bench",
+];
+
+/// 正文长度（字符数）的分布：在 [min, max] 之间，skew 越大短笔记越多、长笔记越少
+#[derive(Clone, Copy, Debug)]
+pub struct SizeDistribution {
+    pub min: usize,
+    pub max: usize,
+    pub skew: f64,
+}
+
+impl Default for SizeDistribution {
+    fn default() -> Self {
+        Self {
+            min: 200,
+            max: 20_000,
+            skew: 3.0,
+        }
+    }
+}
+
+impl SizeDistribution {
+    fn sample(&self, rng: &mut Rng) -> usize {
+        let max = self.max.max(self.min);
+        let t = rng.next_f64().powf(self.skew.max(0.1));
+        self.min + ((max - self.min) as f64 * t) as usize
+    }
+}
+
+/// 生成 count 篇合成笔记，同样的 seed 每次得到同样的内容
+///
+/// 正文混合中英文、换行和指向其他合成笔记的双链，时间分布在最近一年内。
+pub fn generate(count: usize, sizes: SizeDistribution, seed: u64) -> Vec<Note> {
+    let mut rng = Rng::new(seed);
+    let now = Utc::now();
+    let titles: Vec<String> = (0..count)
+        .map(|ix| format!("合成笔记 {}", ix + 1))
+        .collect();
+
+    titles
+        .iter()
+        .map(|title| {
+            let mut note = Note::new();
+            note.title = title.clone();
+            note.tags = vec![
+                SYNTHETIC_TAG.to_string(),
+                WORDS[rng.below(WORDS.len())].to_string(),
+            ];
+            note.created_at = now - Duration::minutes(rng.below(365 * 24 * 60) as i64);
+            note.updated_at = note.created_at + Duration::minutes(rng.below(30 * 24 * 60) as i64);
+            note.content = content(sizes.sample(&mut rng), &titles, &mut rng);
+            note
+        })
+        .collect()
+}
+
+fn content(size: usize, titles: &[String], rng: &mut Rng) -> String {
+    let mut content = String::new();
+    let mut chars = 0;
+    while chars < size {
+        let piece = match rng.below(40) {
+            0 => "\n\n".to_string(),
+            1 if !titles.is_empty() => format!("[[{}]]", titles[rng.below(titles.len())]),
+            _ => format!("{} ", WORDS[rng.below(WORDS.len())]),
+        };
+        chars += piece.chars().count();
+        content.push_str(&piece);
+    }
+    content
+}
+
+// xorshift64*，只用来生成测试数据，不需要密码学强度
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+}
@@ -0,0 +1,163 @@
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// 默认笔记库就是原来的数据目录，升级前的笔记都在这里
+pub const DEFAULT_VAULT: &str = "默认";
+
+/// 一个独立的数据目录，笔记、设置、附件和回收站各自分开，例如“工作”和“个人”
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Vault {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// 已知的笔记库，保存在默认数据目录的 vaults.json
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Vaults {
+    #[serde(skip)]
+    root: PathBuf,
+    /// 用户添加的笔记库，不含默认笔记库
+    vaults: Vec<Vault>,
+    /// 上次打开的笔记库，不带 --vault 启动时打开它
+    last: Option<String>,
+}
+
+impl Vaults {
+    /// root 是默认笔记库的数据目录
+    pub fn load(root: PathBuf) -> Result<Self> {
+        let path = root.join("vaults.json");
+        let mut vaults: Self = if path.exists() {
+            let content = fs::read_to_string(&path).context("读取笔记库列表失败")?;
+            serde_json::from_str(&content).context("解析笔记库列表失败")?
+        } else {
+            Self::default()
+        };
+        vaults.root = root;
+        Ok(vaults)
+    }
+
+    fn save(&self) -> Result<()> {
+        fs::create_dir_all(&self.root).context("无法创建数据目录")?;
+        let json = serde_json::to_string_pretty(self).context("序列化笔记库列表失败")?;
+        fs::write(self.root.join("vaults.json"), json).context("写入笔记库列表失败")?;
+        Ok(())
+    }
+
+    /// 全部笔记库，默认笔记库在最前
+    pub fn all(&self) -> Vec<Vault> {
+        let default = Vault {
+            name: DEFAULT_VAULT.to_string(),
+            path: self.root.clone(),
+        };
+        std::iter::once(default)
+            .chain(self.vaults.iter().cloned())
+            .collect()
+    }
+
+    /// 按名称或目录找到要打开的笔记库，不指定时打开上次的笔记库
+    ///
+    /// 不在列表中的目录也可以直接打开，名称取目录名，用 [`Vaults::add`] 加入列表。
+    pub fn resolve(&self, name_or_path: Option<&str>) -> Result<Vault> {
+        let all = self.all();
+        let Some(wanted) = name_or_path.or(self.last.as_deref()) else {
+            return Ok(all[0].clone());
+        };
+        if let Some(vault) = all.iter().find(|vault| vault.name == wanted) {
+            return Ok(vault.clone());
+        }
+        let path = Path::new(wanted);
+        if path.is_dir() {
+            let path = path.canonicalize().context("无法读取笔记库目录")?;
+            if let Some(vault) = all.iter().find(|vault| vault.path == path) {
+                return Ok(vault.clone());
+            }
+            return Ok(Vault {
+                name: dir_name(&path),
+                path,
+            });
+        }
+        if name_or_path.is_none() {
+            // 上次的笔记库已被删除或改名，回到默认笔记库
+            return Ok(all[0].clone());
+        }
+        bail!("找不到笔记库: {}", wanted)
+    }
+
+    /// 把目录加入列表，重名时在名称后加序号
+    pub fn add(&mut self, path: PathBuf) -> Result<Vault> {
+        if let Some(vault) = self.all().into_iter().find(|vault| vault.path == path) {
+            return Ok(vault);
+        }
+        let base = dir_name(&path);
+        let mut name = base.clone();
+        let mut index = 1;
+        while self.all().iter().any(|vault| vault.name == name) {
+            index += 1;
+            name = format!("{} {}", base, index);
+        }
+        let vault = Vault { name, path };
+        self.vaults.push(vault.clone());
+        self.save()?;
+        Ok(vault)
+    }
+
+    /// 记下打开的笔记库，下次启动时打开。不在列表中的目录先加入列表，返回列表中的笔记库
+    pub fn remember(&mut self, vault: &Vault) -> Result<Vault> {
+        let vault = self.add(vault.path.clone())?;
+        self.last = Some(vault.name.clone());
+        self.save()?;
+        Ok(vault)
+    }
+}
+
+fn dir_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("笔记库")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_default_last_and_named_vaults() {
+        let root = tempfile::tempdir().unwrap();
+        let work = tempfile::tempdir().unwrap();
+        let mut vaults = Vaults::load(root.path().to_path_buf()).unwrap();
+        assert_eq!(vaults.resolve(None).unwrap().name, DEFAULT_VAULT);
+
+        let added = vaults.add(work.path().to_path_buf()).unwrap();
+        vaults.remember(&added).unwrap();
+        let vaults = Vaults::load(root.path().to_path_buf()).unwrap();
+        assert_eq!(vaults.resolve(None).unwrap(), added);
+        assert_eq!(
+            vaults.resolve(Some(DEFAULT_VAULT)).unwrap().path,
+            root.path()
+        );
+        assert!(vaults.resolve(Some("不存在")).is_err());
+    }
+
+    #[test]
+    fn duplicate_names_get_a_number() {
+        let root = tempfile::tempdir().unwrap();
+        let parent = tempfile::tempdir().unwrap();
+        let first = parent.path().join("工作");
+        let second = parent.path().join("其他").join("工作");
+        fs::create_dir_all(&first).unwrap();
+        fs::create_dir_all(&second).unwrap();
+
+        let mut vaults = Vaults::load(root.path().to_path_buf()).unwrap();
+        assert_eq!(vaults.add(first.clone()).unwrap().name, "工作");
+        assert_eq!(vaults.add(second).unwrap().name, "工作 2");
+        // 同一个目录只加一次
+        assert_eq!(vaults.add(first).unwrap().name, "工作");
+        assert_eq!(vaults.all().len(), 3);
+    }
+}
@@ -0,0 +1,286 @@
+use crate::attachments::{self, Attachment, AttachmentKind};
+use gpui::{prelude::FluentBuilder as _, *};
+use gpui_component::{
+    button::{Button, ButtonVariants},
+    h_flex, v_flex,
+};
+
+pub enum AttachmentsEvent {
+    OpenNote(u128),
+    Delete(String),
+    /// 删除所有没有笔记引用的附件
+    CleanOrphans,
+    Close,
+}
+
+/// 附件管理：列出附件目录中的所有文件、大小和引用它们的笔记
+pub struct AttachmentsView {
+    attachments: Vec<Attachment>,
+    selected: Option<String>,
+}
+
+impl AttachmentsView {
+    pub fn new(attachments: Vec<Attachment>) -> Self {
+        Self {
+            attachments,
+            selected: None,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Attachment> {
+        self.attachments
+            .iter()
+            .find(|attachment| attachment.name == name)
+    }
+
+    pub fn orphans(&self) -> Vec<String> {
+        self.attachments
+            .iter()
+            .filter(|attachment| attachment.is_orphaned())
+            .map(|attachment| attachment.name.clone())
+            .collect()
+    }
+
+    /// 删除后从列表中去掉
+    pub fn remove(&mut self, names: &[String], cx: &mut Context<Self>) {
+        self.attachments
+            .retain(|attachment| !names.contains(&attachment.name));
+        if self
+            .selected
+            .as_ref()
+            .is_some_and(|selected| names.contains(selected))
+        {
+            self.selected = None;
+        }
+        cx.notify();
+    }
+
+    fn render_row(
+        &self,
+        ix: usize,
+        attachment: &Attachment,
+        cx: &mut Context<Self>,
+    ) -> Stateful<Div> {
+        let name = attachment.name.clone();
+        let selected = self.selected.as_deref() == Some(name.as_str());
+        let owners = if attachment.is_orphaned() {
+            div()
+                .text_xs()
+                .text_color(gpui::rgb(0xdc2626))
+                .child("未被引用")
+        } else {
+            h_flex()
+                .gap_2()
+                .flex_wrap()
+                .children(
+                    attachment
+                        .owners
+                        .iter()
+                        .enumerate()
+                        .map(|(owner_ix, owner)| {
+                            let note_id = owner.note_id;
+                            let label = if owner.trashed {
+                                format!("回收站中的「{}」", owner.title)
+                            } else {
+                                format!("「{}」", owner.title)
+                            };
+                            div()
+                                .id(("attachment-owner", owner_ix))
+                                .text_xs()
+                                .text_color(gpui::rgb(if owner.trashed {
+                                    0x9ca3af
+                                } else {
+                                    0x2563eb
+                                }))
+                                .when(!owner.trashed, |this| {
+                                    this.cursor_pointer().on_click(cx.listener(
+                                        move |_, _, _window, cx| {
+                                            cx.stop_propagation();
+                                            cx.emit(AttachmentsEvent::OpenNote(note_id));
+                                        },
+                                    ))
+                                })
+                                .child(label)
+                        }),
+                )
+        };
+
+        h_flex()
+            .id(("attachment-row", ix))
+            .px_2()
+            .py_2()
+            .gap_4()
+            .justify_between()
+            .border_b_1()
+            .border_color(gpui::rgb(0xf3f4f6))
+            .cursor_pointer()
+            .when(selected, |this| this.bg(gpui::rgb(0xeff6ff)))
+            .on_click(cx.listener(move |this, _, _window, cx| {
+                this.selected = Some(name.clone());
+                cx.notify();
+            }))
+            .child(
+                v_flex()
+                    .gap_1()
+                    .min_w_0()
+                    .child(
+                        div()
+                            .font_weight(FontWeight::SEMIBOLD)
+                            .truncate()
+                            .child(attachment.name.clone()),
+                    )
+                    .child(owners),
+            )
+            .child(
+                div()
+                    .flex_none()
+                    .text_xs()
+                    .text_color(gpui::rgb(0x6b7280))
+                    .child(format!(
+                        "{} · {}",
+                        attachment.kind.label(),
+                        attachments::format_size(attachment.size)
+                    )),
+            )
+    }
+
+    fn render_preview(&self, attachment: &Attachment, cx: &mut Context<Self>) -> Div {
+        let path = attachment.path.clone();
+        let name = attachment.name.clone();
+        let preview = match attachment.kind {
+            AttachmentKind::Image => img(attachment.path.clone())
+                .max_w_full()
+                .max_h(px(360.0))
+                .object_fit(ObjectFit::Contain)
+                .into_any_element(),
+            AttachmentKind::Audio | AttachmentKind::Other => div()
+                .text_sm()
+                .text_color(gpui::rgb(0x9ca3af))
+                .child("无法预览，可在系统中打开")
+                .into_any_element(),
+        };
+
+        v_flex()
+            .w(px(320.0))
+            .flex_none()
+            .p_4()
+            .gap_3()
+            .border_l_1()
+            .border_color(gpui::rgb(0xe5e7eb))
+            .child(
+                div()
+                    .font_weight(FontWeight::SEMIBOLD)
+                    .child(attachment.name.clone()),
+            )
+            .child(preview)
+            .child(
+                h_flex()
+                    .gap_2()
+                    .child(
+                        Button::new("open-attachment")
+                            .label("在系统中打开")
+                            .on_click(move |_, _window, cx| cx.open_with_system(&path)),
+                    )
+                    .child(
+                        Button::new("delete-attachment")
+                            .label("删除")
+                            .danger()
+                            .on_click(cx.listener(move |_, _, _window, cx| {
+                                cx.emit(AttachmentsEvent::Delete(name.clone()));
+                            })),
+                    ),
+            )
+    }
+}
+
+impl EventEmitter<AttachmentsEvent> for AttachmentsView {}
+
+impl Render for AttachmentsView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let total: u64 = self.attachments.iter().map(|a| a.size).sum();
+        let orphans = self.orphans().len();
+        let summary = format!(
+            "共 {} 个附件，{}，{} 个未被任何笔记引用",
+            self.attachments.len(),
+            attachments::format_size(total),
+            orphans
+        );
+        let rows: Vec<Stateful<Div>> = self
+            .attachments
+            .iter()
+            .enumerate()
+            .map(|(ix, attachment)| self.render_row(ix, attachment, cx))
+            .collect();
+        let preview = self
+            .selected
+            .as_deref()
+            .and_then(|name| self.get(name))
+            .map(|attachment| self.render_preview(attachment, cx));
+
+        v_flex()
+            .size_full()
+            .bg(gpui::rgb(0xffffff))
+            .child(
+                h_flex()
+                    .px_6()
+                    .py_3()
+                    .border_b_1()
+                    .border_color(gpui::rgb(0xe5e7eb))
+                    .items_center()
+                    .justify_between()
+                    .child(
+                        div()
+                            .text_lg()
+                            .font_weight(FontWeight::SEMIBOLD)
+                            .child("附件"),
+                    )
+                    .child(
+                        h_flex()
+                            .gap_2()
+                            .when(orphans > 0, |this| {
+                                this.child(
+                                    Button::new("clean-orphans")
+                                        .label(format!("清理未引用的附件（{}）", orphans))
+                                        .on_click(cx.listener(|_, _, _window, cx| {
+                                            cx.emit(AttachmentsEvent::CleanOrphans);
+                                        })),
+                                )
+                            })
+                            .child(Button::new("close-attachments").label("返回").on_click(
+                                cx.listener(|_, _, _window, cx| {
+                                    cx.emit(AttachmentsEvent::Close);
+                                }),
+                            )),
+                    ),
+            )
+            .child(
+                h_flex()
+                    .flex_1()
+                    .min_h_0()
+                    .items_start()
+                    .child(
+                        v_flex()
+                            .id("attachment-list")
+                            .flex_1()
+                            .h_full()
+                            .px_6()
+                            .py_3()
+                            .overflow_y_scroll()
+                            .child(
+                                div()
+                                    .pb_2()
+                                    .text_sm()
+                                    .text_color(gpui::rgb(0x6b7280))
+                                    .child(summary),
+                            )
+                            .when(rows.is_empty(), |this| {
+                                this.child(
+                                    div().text_color(gpui::rgb(0x9ca3af)).child("还没有附件"),
+                                )
+                            })
+                            .children(rows),
+                    )
+                    .children(preview),
+            )
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{note_list::NoteList, settings::AppSettings};
+use gpui::{prelude::FluentBuilder as _, *};
+use gpui_component::{button::Button, h_flex, v_flex};
+
+pub enum BoardEvent {
+    OpenNote(u128),
+    /// 卡片被拖到了另一列，需要把笔记的状态标签改为该列
+    MoveNote {
+        note_id: u128,
+        status: String,
+    },
+    Close,
+}
+
+#[derive(Clone)]
+struct DraggedCard {
+    note_id: u128,
+    title: SharedString,
+}
+
+impl Render for DraggedCard {
+    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .w(px(200.))
+            .p_2()
+            .rounded_md()
+            .bg(gpui::rgb(0xffffff))
+            .border_1()
+            .border_color(gpui::rgb(0x6366f1))
+            .shadow_md()
+            .text_sm()
+            .child(self.title.clone())
+    }
+}
+
+pub struct BoardView {
+    notes: Entity<NoteList>,
+    _notes_changed: Subscription,
+}
+
+impl BoardView {
+    pub fn new(notes: Entity<NoteList>, cx: &mut Context<Self>) -> Self {
+        Self {
+            _notes_changed: cx.observe(&notes, |_, _, cx| cx.notify()),
+            notes,
+        }
+    }
+
+    fn render_column(&self, status: String, cx: &mut Context<Self>) -> Stateful<Div> {
+        let mut cards: Vec<_> = self
+            .notes
+            .read(cx)
+            .get_all()
+            .into_iter()
+            .filter(|note| note.tags.contains(&status))
+            .map(|note| (note.id, note.title.clone(), note.preview()))
+            .collect();
+        cards.sort_by(|a, b| a.1.cmp(&b.1));
+
+        let drop_status = status.clone();
+        v_flex()
+            .id(SharedString::from(format!("board-column-{}", status)))
+            .flex_1()
+            .h_full()
+            .min_w(px(200.))
+            .p_2()
+            .gap_2()
+            .rounded_md()
+            .bg(gpui::rgb(0xf3f4f6))
+            .drag_over::<DraggedCard>(|this, _, _, _| this.bg(gpui::rgb(0xe0e7ff)))
+            .on_drop(cx.listener(move |_, card: &DraggedCard, _window, cx| {
+                cx.emit(BoardEvent::MoveNote {
+                    note_id: card.note_id,
+                    status: drop_status.clone(),
+                });
+            }))
+            .child(
+                h_flex()
+                    .justify_between()
+                    .px_1()
+                    .child(
+                        div()
+                            .text_sm()
+                            .font_weight(FontWeight::SEMIBOLD)
+                            .child(status.clone()),
+                    )
+                    .child(
+                        div()
+                            .text_xs()
+                            .text_color(gpui::rgb(0x6b7280))
+                            .child(cards.len().to_string()),
+                    ),
+            )
+            .children(cards.into_iter().map(|(note_id, title, preview)| {
+                let dragged = DraggedCard {
+                    note_id,
+                    title: title.clone().into(),
+                };
+                v_flex()
+                    .id(SharedString::from(format!("board-card-{}", note_id)))
+                    .p_2()
+                    .gap_1()
+                    .rounded_md()
+                    .bg(gpui::rgb(0xffffff))
+                    .border_1()
+                    .border_color(gpui::rgb(0xe5e7eb))
+                    .cursor_move()
+                    .on_drag(dragged, |card: &DraggedCard, _, _, cx| {
+                        cx.new(|_| card.clone())
+                    })
+                    .on_click(cx.listener(move |_, _, _window, cx| {
+                        cx.emit(BoardEvent::OpenNote(note_id));
+                    }))
+                    .child(div().text_sm().font_weight(FontWeight::MEDIUM).child(title))
+                    .child(
+                        div()
+                            .text_xs()
+                            .text_color(gpui::rgb(0x6b7280))
+                            .child(preview),
+                    )
+            }))
+    }
+}
+
+impl EventEmitter<BoardEvent> for BoardView {}
+
+impl Render for BoardView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let columns = AppSettings::get(cx).board.columns.clone();
+        let mut rendered = Vec::with_capacity(columns.len());
+        for status in columns {
+            rendered.push(self.render_column(status, cx));
+        }
+
+        v_flex()
+            .size_full()
+            .bg(gpui::rgb(0xffffff))
+            .child(
+                h_flex()
+                    .px_6()
+                    .py_3()
+                    .border_b_1()
+                    .border_color(gpui::rgb(0xe5e7eb))
+                    .items_center()
+                    .justify_between()
+                    .child(
+                        div()
+                            .text_lg()
+                            .font_weight(FontWeight::SEMIBOLD)
+                            .child("看板"),
+                    )
+                    .child(
+                        Button::new("close-board")
+                            .label("返回")
+                            .on_click(cx.listener(|_, _, _window, cx| {
+                                cx.emit(BoardEvent::Close);
+                            })),
+                    ),
+            )
+            .child(
+                h_flex()
+                    .flex_1()
+                    .p_4()
+                    .gap_4()
+                    .items_start()
+                    .overflow_hidden()
+                    .when(rendered.is_empty(), |this| {
+                        this.child(
+                            div()
+                                .text_color(gpui::rgb(0x6b7280))
+                                .child("请先在设置中配置看板列"),
+                        )
+                    })
+                    .children(rendered),
+            )
+    }
+}
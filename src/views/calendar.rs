@@ -0,0 +1,200 @@
+use crate::note_list::NoteList;
+use chrono::{Datelike, Local, Months, NaiveDate};
+use gpui::{prelude::FluentBuilder as _, *};
+use gpui_component::{button::Button, h_flex, v_flex};
+use std::collections::HashSet;
+
+const WEEKDAYS: [&str; 7] = ["一", "二", "三", "四", "五", "六", "日"];
+
+pub enum CalendarEvent {
+    OpenDay(NaiveDate),
+    Close,
+}
+
+pub struct CalendarView {
+    notes: Entity<NoteList>,
+    /// 当前显示月份的第一天
+    month: NaiveDate,
+    _notes_changed: Subscription,
+}
+
+impl CalendarView {
+    pub fn new(notes: Entity<NoteList>, cx: &mut Context<Self>) -> Self {
+        let today = Local::now().date_naive();
+        Self {
+            _notes_changed: cx.observe(&notes, |_, _, cx| cx.notify()),
+            notes,
+            month: today.with_day(1).unwrap_or(today),
+        }
+    }
+
+    fn shift_month(&mut self, forward: bool, cx: &mut Context<Self>) {
+        let shifted = if forward {
+            self.month.checked_add_months(Months::new(1))
+        } else {
+            self.month.checked_sub_months(Months::new(1))
+        };
+        if let Some(month) = shifted {
+            self.month = month;
+            cx.notify();
+        }
+    }
+
+    /// 本月中有日记的日期和有截止日期的日期
+    fn marked_days(&self, cx: &App) -> (HashSet<NaiveDate>, HashSet<NaiveDate>) {
+        let mut daily = HashSet::new();
+        let mut due = HashSet::new();
+        for note in self.notes.read(cx).get_all() {
+            if let Some(date) = note.daily_date() {
+                daily.insert(date);
+            }
+            if let Some(date) = note.due_date {
+                due.insert(date);
+            }
+        }
+        (daily, due)
+    }
+
+    fn render_day(
+        &self,
+        date: NaiveDate,
+        has_daily: bool,
+        has_due: bool,
+        cx: &mut Context<Self>,
+    ) -> Stateful<Div> {
+        let in_month = date.month() == self.month.month();
+        let is_today = date == Local::now().date_naive();
+
+        v_flex()
+            .id(ElementId::Name(format!("day-{}", date).into()))
+            .flex_1()
+            .h(px(64.))
+            .p_1()
+            .gap_1()
+            .border_1()
+            .border_color(gpui::rgb(0xf3f4f6))
+            .cursor_pointer()
+            .hover(|this| this.bg(gpui::rgb(0xf9fafb)))
+            .when(is_today, |this| this.bg(gpui::rgb(0xe0e7ff)))
+            .on_click(cx.listener(move |_, _, _window, cx| {
+                cx.emit(CalendarEvent::OpenDay(date));
+            }))
+            .child(
+                div()
+                    .text_sm()
+                    .text_color(if in_month {
+                        gpui::rgb(0x111827)
+                    } else {
+                        gpui::rgb(0xd1d5db)
+                    })
+                    .child(date.day().to_string()),
+            )
+            .child(
+                h_flex()
+                    .gap_1()
+                    .when(has_daily, |this| {
+                        this.child(div().size(px(6.)).rounded_full().bg(gpui::rgb(0x6366f1)))
+                    })
+                    .when(has_due, |this| {
+                        this.child(div().size(px(6.)).rounded_full().bg(gpui::rgb(0xf59e0b)))
+                    }),
+            )
+    }
+}
+
+impl EventEmitter<CalendarEvent> for CalendarView {}
+
+impl Render for CalendarView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let (daily, due) = self.marked_days(cx);
+
+        // 从本月第一天所在周的周一开始，固定画 6 周
+        let offset = self.month.weekday().num_days_from_monday() as u64;
+        let start = self.month - chrono::Days::new(offset);
+        let mut weeks = Vec::with_capacity(6);
+        for week in 0..6 {
+            let mut days = Vec::with_capacity(7);
+            for day in 0..7 {
+                let date = start + chrono::Days::new(week * 7 + day);
+                days.push(self.render_day(date, daily.contains(&date), due.contains(&date), cx));
+            }
+            weeks.push(h_flex().children(days));
+        }
+
+        v_flex()
+            .size_full()
+            .bg(gpui::rgb(0xffffff))
+            .child(
+                h_flex()
+                    .px_6()
+                    .py_3()
+                    .border_b_1()
+                    .border_color(gpui::rgb(0xe5e7eb))
+                    .items_center()
+                    .justify_between()
+                    .child(
+                        h_flex()
+                            .gap_2()
+                            .items_center()
+                            .child(Button::new("prev-month").label("‹").compact().on_click(
+                                cx.listener(|this, _, _window, cx| this.shift_month(false, cx)),
+                            ))
+                            .child(
+                                div()
+                                    .text_lg()
+                                    .font_weight(FontWeight::SEMIBOLD)
+                                    .child(self.month.format("%Y 年 %m 月").to_string()),
+                            )
+                            .child(Button::new("next-month").label("›").compact().on_click(
+                                cx.listener(|this, _, _window, cx| this.shift_month(true, cx)),
+                            )),
+                    )
+                    .child(
+                        Button::new("close-calendar")
+                            .label("返回")
+                            .on_click(cx.listener(|_, _, _window, cx| {
+                                cx.emit(CalendarEvent::Close);
+                            })),
+                    ),
+            )
+            .child(
+                v_flex()
+                    .p_4()
+                    .child(h_flex().children(WEEKDAYS.iter().map(|day| {
+                        div()
+                            .flex_1()
+                            .py_1()
+                            .text_xs()
+                            .text_center()
+                            .text_color(gpui::rgb(0x6b7280))
+                            .child(*day)
+                    })))
+                    .children(weeks)
+                    .child(
+                        h_flex()
+                            .mt_3()
+                            .gap_4()
+                            .text_xs()
+                            .text_color(gpui::rgb(0x6b7280))
+                            .child(
+                                h_flex()
+                                    .gap_1()
+                                    .items_center()
+                                    .child(
+                                        div().size(px(6.)).rounded_full().bg(gpui::rgb(0x6366f1)),
+                                    )
+                                    .child("日记"),
+                            )
+                            .child(
+                                h_flex()
+                                    .gap_1()
+                                    .items_center()
+                                    .child(
+                                        div().size(px(6.)).rounded_full().bg(gpui::rgb(0xf59e0b)),
+                                    )
+                                    .child("截止"),
+                            ),
+                    ),
+            )
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{
+    diagnostics::{self, LogEntry},
+    settings::AppSettings,
+};
+use gpui::{prelude::FluentBuilder as _, *};
+use gpui_component::{
+    button::{Button, ButtonVariants},
+    h_flex, v_flex,
+};
+use std::time::Duration;
+use tracing::Level;
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+pub enum DiagnosticsEvent {
+    Close,
+}
+
+/// 诊断面板：显示最近的日志，方便复制后附在问题反馈里
+pub struct DiagnosticsView {
+    entries: Vec<LogEntry>,
+    _refresh: Task<()>,
+}
+
+impl DiagnosticsView {
+    pub fn new(cx: &mut Context<Self>) -> Self {
+        // 面板打开期间定时读取新日志，关闭面板即丢弃任务
+        let refresh = cx.spawn(async move |this, cx| {
+            loop {
+                cx.background_executor().timer(REFRESH_INTERVAL).await;
+                let updated = this.update(cx, |this, cx| this.refresh(cx));
+                if updated.is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            entries: diagnostics::recent(),
+            _refresh: refresh,
+        }
+    }
+
+    fn refresh(&mut self, cx: &mut Context<Self>) {
+        self.entries = diagnostics::recent();
+        cx.notify();
+    }
+
+    fn copy_all(&mut self, cx: &mut Context<Self>) {
+        let text = self
+            .entries
+            .iter()
+            .map(LogEntry::to_line)
+            .collect::<Vec<_>>()
+            .join("\n");
+        cx.write_to_clipboard(ClipboardItem::new_string(text));
+    }
+
+    fn level_color(level: Level) -> Rgba {
+        match level {
+            Level::ERROR => gpui::rgb(0xdc2626),
+            Level::WARN => gpui::rgb(0xd97706),
+            Level::INFO => gpui::rgb(0x374151),
+            _ => gpui::rgb(0x9ca3af),
+        }
+    }
+
+    fn render_disabled(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        v_flex()
+            .flex_1()
+            .items_center()
+            .justify_center()
+            .gap_3()
+            .text_color(gpui::rgb(0x6b7280))
+            .child("诊断日志未开启。开启后会记录存储错误、耗时和同步事件，日志只保存在本机。")
+            .child(
+                Button::new("enable-diagnostics")
+                    .label("开启诊断日志")
+                    .primary()
+                    .on_click(cx.listener(|this, _, _window, cx| {
+                        AppSettings::update(cx, |s| s.diagnostics.enabled = true);
+                        this.refresh(cx);
+                    })),
+            )
+    }
+}
+
+impl EventEmitter<DiagnosticsEvent> for DiagnosticsView {}
+
+impl Render for DiagnosticsView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let enabled = diagnostics::is_enabled();
+        let log_path = diagnostics::log_path();
+
+        v_flex()
+            .size_full()
+            .bg(gpui::rgb(0xffffff))
+            .child(
+                h_flex()
+                    .px_6()
+                    .py_3()
+                    .border_b_1()
+                    .border_color(gpui::rgb(0xe5e7eb))
+                    .items_center()
+                    .justify_between()
+                    .child(
+                        div()
+                            .text_lg()
+                            .font_weight(FontWeight::SEMIBOLD)
+                            .child("诊断日志"),
+                    )
+                    .child(
+                        h_flex()
+                            .gap_2()
+                            .child(Button::new("copy-logs").label("复制全部").on_click(
+                                cx.listener(|this, _, _window, cx| {
+                                    this.copy_all(cx);
+                                }),
+                            ))
+                            .when_some(log_path, |this, path| {
+                                this.child(
+                                    Button::new("open-log-file")
+                                        .label("打开日志文件")
+                                        .on_click(move |_, _window, cx| cx.open_with_system(&path)),
+                                )
+                            })
+                            .child(Button::new("close-diagnostics").label("返回").on_click(
+                                cx.listener(|_, _, _window, cx| {
+                                    cx.emit(DiagnosticsEvent::Close);
+                                }),
+                            )),
+                    ),
+            )
+            .child(if !enabled && self.entries.is_empty() {
+                self.render_disabled(cx).into_any_element()
+            } else {
+                v_flex()
+                    .id("diagnostics-entries")
+                    .flex_1()
+                    .px_6()
+                    .py_3()
+                    .gap_1()
+                    .overflow_y_scroll()
+                    .text_xs()
+                    .font_family("monospace")
+                    // 最新的日志在最上面
+                    .children(self.entries.iter().rev().map(|entry| {
+                        h_flex()
+                            .gap_2()
+                            .text_color(Self::level_color(entry.level))
+                            .child(entry.time.format("%H:%M:%S%.3f").to_string())
+                            .child(format!("{:<5}", entry.level))
+                            .child(entry.message.clone())
+                    }))
+                    .into_any_element()
+            })
+    }
+}
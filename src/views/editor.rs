@@ -1,27 +1,809 @@
-use crate::note::Note;
-use gpui::*;
-use gpui_component::v_flex;
+use crate::{
+    markup,
+    note::{self, Note, NoteLink},
+    note_list::NoteList,
+    settings::AppSettings,
+    views::{
+        preview::NotePreview,
+        read_aloud::{ReadAloudEvent, ReadAloudView},
+    },
+};
+use chrono::NaiveDate;
+use gpui::{prelude::FluentBuilder as _, *};
+use gpui_component::{
+    Disableable as _, Sizable as _,
+    button::{Button, ButtonVariants},
+    calendar::Date,
+    date_picker::{DatePicker, DatePickerEvent, DatePickerState},
+    h_flex,
+    input::{
+        Escape, HoverProvider, Input, InputEvent, InputState, Position, RopeExt as _, SelectAll,
+    },
+    v_flex,
+};
+use ropey::{LineType, Rope};
+use std::{collections::HashMap, ops::Range, path::PathBuf, rc::Rc};
+
+/// 链接预览卡片中正文第一段最多显示的字符数
+const LINK_PREVIEW_CHARS: usize = 200;
+
+pub enum EditorEvent {
+    /// 正文缓冲区的快照，克隆 Rope 不复制文本
+    ContentChanged {
+        note_id: u128,
+        content: Rope,
+    },
+    /// 标题已通过校验，重名时已经加上序号
+    Rename {
+        note_id: u128,
+        title: String,
+    },
+    ToggleRecording,
+    /// 用系统播放器打开录音附件
+    PlayAudio(String),
+    /// 按住 Ctrl/⌘ 点击了笔记链接
+    OpenLink(NoteLink),
+    /// 当前笔记有了未保存的更改，或者更改已保存
+    DirtyChanged(bool),
+    /// 添加或删除了标签
+    TagsChanged {
+        note_id: u128,
+        tags: Vec<String>,
+    },
+    /// 设置或清除了截止日期
+    DueDateChanged {
+        note_id: u128,
+        due_date: Option<NaiveDate>,
+    },
+}
 
 pub struct EditorView {
     current_note: Option<Note>,
+    /// 当前正文，与输入框内部的 Rope 共享节点
+    buffer: Rope,
+    /// load_note 替换内容时输入框也会发出 Change，不应当作用户编辑
+    loading: bool,
+    /// 当前正文有还没交给 ContentChanged 保存的修改
+    dirty: bool,
+    /// 手动保存模式下切换笔记时留下的未保存正文，切回来时继续编辑
+    unsaved: HashMap<u128, Rope>,
+    /// 自动保存间隔不为 0 时等待保存的任务，继续输入会重新计时
+    _autosave: Option<Task<()>>,
+    recording: bool,
+    /// 显示预览而不是编辑区
+    previewing: bool,
+    /// 正在朗读时代替编辑区显示
+    reading: Option<(Entity<ReadAloudView>, Subscription)>,
+    input: Entity<InputState>,
+    notes: Entity<NoteList>,
+    attachments_dir: PathBuf,
+    title_input: Entity<InputState>,
+    renaming: bool,
+    /// 重命名输入的校验结果：错误时不能保存，重名时提示将要使用的标题
+    title_check: Option<Result<String, String>>,
+    tag_input: Entity<InputState>,
+    due_date: Entity<DatePickerState>,
+    _subscriptions: Vec<Subscription>,
 }
 
 impl EditorView {
-    pub fn new(_cx: &mut Context<Self>) -> Self {
-        Self { current_note: None }
+    pub fn new(
+        notes: Entity<NoteList>,
+        attachments_dir: PathBuf,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let input = cx.new(|cx| {
+            InputState::new(window, cx)
+                .multi_line(true)
+                .soft_wrap(true)
+                .placeholder("开始写作…")
+        });
+        input.update(cx, |input, _| {
+            input.lsp.hover_provider = Some(Rc::new(LinkPreview {
+                notes: notes.clone(),
+            }));
+        });
+        let title_input = cx.new(|cx| InputState::new(window, cx).placeholder("笔记标题"));
+        let tag_input = cx.new(|cx| InputState::new(window, cx).placeholder("添加标签"));
+        let due_date = cx.new(|cx| DatePickerState::new(window, cx));
+        let subscriptions = vec![
+            cx.subscribe_in(&input, window, Self::on_input_event),
+            cx.subscribe_in(&title_input, window, Self::on_title_event),
+            cx.subscribe_in(&tag_input, window, Self::on_tag_event),
+            cx.subscribe(&due_date, Self::on_due_date_event),
+            // 预览中的子笔记目录随笔记增删改名更新
+            cx.observe(&notes, |this, _, cx| {
+                if this.previewing {
+                    cx.notify();
+                }
+            }),
+        ];
+
+        Self {
+            current_note: None,
+            buffer: Rope::new(),
+            loading: false,
+            dirty: false,
+            unsaved: HashMap::new(),
+            _autosave: None,
+            recording: false,
+            previewing: false,
+            reading: None,
+            input,
+            notes,
+            attachments_dir,
+            title_input,
+            renaming: false,
+            title_check: None,
+            tag_input,
+            due_date,
+            _subscriptions: subscriptions,
+        }
+    }
+
+    fn on_input_event(
+        &mut self,
+        input: &Entity<InputState>,
+        event: &InputEvent,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if !matches!(event, InputEvent::Change) || self.loading || self.current_note.is_none() {
+            return;
+        }
+        // set_value 发出的 Change 在 load_note 返回后才送到，内容没变的不算编辑
+        let text = input.read(cx).text();
+        if *text == self.buffer {
+            return;
+        }
+        self.buffer = text.clone();
+        match AppSettings::get(cx).save.autosave_delay() {
+            Some(delay) if delay.is_zero() => self.save(cx),
+            Some(delay) => {
+                self.set_dirty(true, cx);
+                self._autosave = Some(cx.spawn(async move |this, cx| {
+                    cx.background_executor().timer(delay).await;
+                    this.update(cx, |this, cx| this.save(cx)).ok();
+                }));
+            }
+            None => self.set_dirty(true, cx),
+        }
+    }
+
+    fn set_dirty(&mut self, dirty: bool, cx: &mut Context<Self>) {
+        if self.dirty != dirty {
+            self.dirty = dirty;
+            cx.emit(EditorEvent::DirtyChanged(dirty));
+            cx.notify();
+        }
+    }
+
+    /// 把当前正文交给 ContentChanged 保存
+    pub fn save(&mut self, cx: &mut Context<Self>) {
+        self._autosave = None;
+        let Some(note) = self.current_note.as_ref() else {
+            return;
+        };
+        cx.emit(EditorEvent::ContentChanged {
+            note_id: note.id,
+            content: self.buffer.clone(),
+        });
+        self.set_dirty(false, cx);
+    }
+
+    /// 取出所有未保存的正文，包括切换笔记时留下的，调用方负责保存
+    pub fn take_unsaved(&mut self, cx: &mut Context<Self>) -> Vec<(u128, Rope)> {
+        self._autosave = None;
+        let mut unsaved: Vec<(u128, Rope)> = self.unsaved.drain().collect();
+        if self.dirty
+            && let Some(note) = self.current_note.as_ref()
+        {
+            unsaved.push((note.id, self.buffer.clone()));
+        }
+        self.set_dirty(false, cx);
+        unsaved
+    }
+
+    /// 有未保存更改的笔记数
+    pub fn unsaved_count(&self) -> usize {
+        self.unsaved.len() + usize::from(self.dirty)
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn is_renaming(&self) -> bool {
+        self.renaming
+    }
+
+    /// 把标题变成输入框，回车或移开焦点时保存，Esc 取消
+    pub fn start_rename(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(note) = self.current_note.as_ref() else {
+            return;
+        };
+        let title = note.title.clone();
+        self.title_input.update(cx, |input, cx| {
+            input.set_value(title, window, cx);
+            input.focus(window, cx);
+        });
+        // 选中原标题，直接输入即可替换
+        window.dispatch_action(Box::new(SelectAll), cx);
+        self.renaming = true;
+        self.title_check = None;
+        cx.notify();
+    }
+
+    fn on_title_event(
+        &mut self,
+        _: &Entity<InputState>,
+        event: &InputEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if !self.renaming {
+            return;
+        }
+        match event {
+            InputEvent::Change => {
+                self.title_check = Some(self.check_title(cx));
+                cx.notify();
+            }
+            InputEvent::PressEnter { .. } => self.finish_rename(false, window, cx),
+            InputEvent::Blur => self.finish_rename(true, window, cx),
+            InputEvent::Focus => {}
+        }
+    }
+
+    fn check_title(&self, cx: &App) -> Result<String, String> {
+        let note_id = self.note_id().unwrap_or_default();
+        let input = self.title_input.read(cx).value();
+        self.notes.read(cx).check_title(note_id, &input)
+    }
+
+    // 标题无效时，回车保持编辑状态并显示错误，失去焦点则放弃修改
+    fn finish_rename(
+        &mut self,
+        cancel_if_invalid: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let check = self.check_title(cx);
+        match check {
+            Ok(title) => {
+                if let Some(note) = self.current_note.as_mut()
+                    && note.title != title
+                {
+                    note.title = title.clone();
+                    cx.emit(EditorEvent::Rename {
+                        note_id: note.id,
+                        title,
+                    });
+                }
+                self.cancel_rename(window, cx);
+            }
+            Err(_) if cancel_if_invalid => self.cancel_rename(window, cx),
+            Err(message) => {
+                self.title_check = Some(Err(message));
+                cx.notify();
+            }
+        }
+    }
+
+    fn cancel_rename(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if !self.renaming {
+            return;
+        }
+        self.renaming = false;
+        self.title_check = None;
+        self.focus(window, cx);
+        cx.notify();
+    }
+
+    fn on_escape(&mut self, _: &Escape, window: &mut Window, cx: &mut Context<Self>) {
+        if self.renaming {
+            self.cancel_rename(window, cx);
+        } else {
+            cx.propagate();
+        }
+    }
+
+    fn on_tag_event(
+        &mut self,
+        input: &Entity<InputState>,
+        event: &InputEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if !matches!(event, InputEvent::PressEnter { .. }) {
+            return;
+        }
+        let value = input.read(cx).value();
+        let tag = value.trim().trim_start_matches('#').trim().to_string();
+        input.update(cx, |input, cx| input.set_value("", window, cx));
+        if tag.is_empty() {
+            return;
+        }
+        self.set_tags(
+            |tags| {
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            },
+            cx,
+        );
+    }
+
+    fn on_due_date_event(
+        &mut self,
+        _: Entity<DatePickerState>,
+        event: &DatePickerEvent,
+        cx: &mut Context<Self>,
+    ) {
+        let DatePickerEvent::Change(Date::Single(due_date)) = event else {
+            return;
+        };
+        let Some(note) = self.current_note.as_mut() else {
+            return;
+        };
+        if note.due_date == *due_date {
+            return;
+        }
+        note.due_date = *due_date;
+        cx.emit(EditorEvent::DueDateChanged {
+            note_id: note.id,
+            due_date: *due_date,
+        });
+    }
+
+    /// 和拖动看板卡片一样，换掉其他列的标签
+    fn set_status(&mut self, status: &str, statuses: &[String], cx: &mut Context<Self>) {
+        let Some(mut note) = self.current_note.clone() else {
+            return;
+        };
+        note.set_status(status, statuses);
+        self.set_tags(|tags| *tags = note.tags, cx);
     }
 
-    pub fn load_note(&mut self, note: &Note, _cx: &mut Context<Self>) {
+    fn set_tags(&mut self, edit: impl FnOnce(&mut Vec<String>), cx: &mut Context<Self>) {
+        let Some(note) = self.current_note.as_mut() else {
+            return;
+        };
+        let mut tags = note.tags.clone();
+        edit(&mut tags);
+        if tags == note.tags {
+            return;
+        }
+        note.tags = tags.clone();
+        cx.emit(EditorEvent::TagsChanged {
+            note_id: note.id,
+            tags,
+        });
+        cx.notify();
+    }
+
+    fn render_tags(&self, cx: &mut Context<Self>) -> AnyElement {
+        let tags = self
+            .current_note
+            .as_ref()
+            .map(|note| note.tags.clone())
+            .unwrap_or_default();
+        let statuses = AppSettings::get(cx).board.columns.clone();
+        h_flex()
+            .mt_2()
+            .gap_1()
+            .flex_wrap()
+            .items_center()
+            .children(tags.iter().cloned().enumerate().map(|(ix, tag)| {
+                Button::new(("remove-tag", ix))
+                    .label(format!("#{} ×", tag))
+                    .compact()
+                    .on_click(cx.listener(move |this, _, _window, cx| {
+                        this.set_tags(|tags| tags.retain(|t| *t != tag), cx);
+                    }))
+            }))
+            .child(div().w(px(120.)).child(Input::new(&self.tag_input).small()))
+            .when(!statuses.is_empty(), |this| {
+                this.child(
+                    div()
+                        .ml_2()
+                        .text_xs()
+                        .text_color(gpui::rgb(0x6b7280))
+                        .child("看板状态"),
+                )
+                .children(statuses.iter().enumerate().map(|(ix, status)| {
+                    let selected = tags.contains(status);
+                    let (status, statuses) = (status.clone(), statuses.clone());
+                    Button::new(("set-status", ix))
+                        .label(status.clone())
+                        .compact()
+                        .when(selected, |this| this.primary())
+                        .on_click(cx.listener(move |this, _, _window, cx| {
+                            this.set_status(&status, &statuses, cx);
+                        }))
+                }))
+            })
+            .child(
+                div().w(px(160.)).child(
+                    DatePicker::new(&self.due_date)
+                        .placeholder("截止日期")
+                        .cleanable(true)
+                        .small(),
+                ),
+            )
+            .into_any_element()
+    }
+
+    fn render_title(&self, cx: &mut Context<Self>) -> AnyElement {
+        if !self.renaming {
+            let title = self
+                .current_note
+                .as_ref()
+                .map(|note| note.title.clone())
+                .unwrap_or_default();
+            return h_flex()
+                .gap_2()
+                .items_center()
+                .child(
+                    div()
+                        .id("note-title")
+                        .text_xl()
+                        .font_weight(FontWeight::BOLD)
+                        .cursor_text()
+                        .on_click(cx.listener(|this, _, window, cx| this.start_rename(window, cx)))
+                        .child(title),
+                )
+                .when(self.dirty, |this| {
+                    this.child(
+                        div()
+                            .text_xs()
+                            .text_color(gpui::rgb(0xd97706))
+                            .child("● 未保存"),
+                    )
+                })
+                .into_any_element();
+        }
+
+        let hint = match &self.title_check {
+            Some(Err(message)) => Some((message.clone(), gpui::rgb(0xdc2626))),
+            Some(Ok(title)) if title.as_str() != self.title_input.read(cx).value().trim() => {
+                Some((
+                    format!("已有同名笔记，将保存为「{}」", title),
+                    gpui::rgb(0xd97706),
+                ))
+            }
+            _ => None,
+        };
+        v_flex()
+            .flex_1()
+            .gap_1()
+            .on_action(cx.listener(Self::on_escape))
+            .child(
+                div()
+                    .text_xl()
+                    .font_weight(FontWeight::BOLD)
+                    .child(Input::new(&self.title_input)),
+            )
+            .when_some(hint, |this, (message, color)| {
+                this.child(div().text_xs().text_color(color).child(message))
+            })
+            .into_any_element()
+    }
+
+    /// 载入笔记。当前笔记有未保存的更改时，自动保存模式下先保存，手动保存模式下先留着
+    ///
+    /// 重新载入正在编辑的笔记时，未保存的正文优先于传入的内容。
+    pub fn load_note(&mut self, note: &Note, window: &mut Window, cx: &mut Context<Self>) {
+        if self.dirty
+            && let Some(current) = self.current_note.as_ref()
+        {
+            if AppSettings::get(cx).save.manual || current.id == note.id {
+                self.unsaved.insert(current.id, self.buffer.clone());
+            } else {
+                self.save(cx);
+            }
+        }
+        if self.current_note.as_ref().map(|current| current.id) != Some(note.id) {
+            self.stop_read_aloud(cx);
+        }
+        // 标题框随重命名一起隐藏，焦点在它上面时交给正文，否则快捷键无处分发
+        if self.renaming && self.title_input.focus_handle(cx).is_focused(window) {
+            self.focus(window, cx);
+        }
+        self.renaming = false;
+        self.title_check = None;
         self.current_note = Some(note.clone());
+        self.due_date.update(cx, |picker, cx| {
+            picker.set_date(Date::Single(note.due_date), window, cx)
+        });
+        match self.unsaved.remove(&note.id) {
+            Some(content) => {
+                self.set_text(content.to_string(), window, cx);
+                self.set_dirty(true, cx);
+            }
+            None => {
+                self.set_text(note.content.clone(), window, cx);
+                self.set_dirty(false, cx);
+            }
+        }
+        cx.notify();
     }
 
-    pub fn clear(&mut self) {
+    /// 关闭当前笔记，未保存的更改一并丢弃，用于笔记被删除时
+    pub fn clear(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self._autosave = None;
+        if let Some(note) = self.current_note.as_ref() {
+            self.unsaved.remove(&note.id);
+        }
+        self.set_dirty(false, cx);
+        self.stop_read_aloud(cx);
+        self.renaming = false;
+        self.title_check = None;
         self.current_note = None;
+        self.due_date.update(cx, |picker, cx| {
+            picker.set_date(Date::Single(None), window, cx)
+        });
+        self.set_text(String::new(), window, cx);
+        cx.notify();
+    }
+
+    fn set_text(&mut self, text: String, window: &mut Window, cx: &mut Context<Self>) {
+        self.loading = true;
+        self.input.update(cx, |input, cx| {
+            input.set_value(text, window, cx);
+        });
+        self.buffer = self.input.read(cx).text().clone();
+        self.loading = false;
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.buffer.len_lines(LineType::LF)
+    }
+
+    /// 在字节偏移处插入文本，和键盘输入一样进入撤销历史并触发保存
+    pub fn insert(
+        &mut self,
+        offset: usize,
+        text: &str,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.replace(offset..offset, text, window, cx);
+    }
+
+    /// 把字节范围替换为新文本，范围会收拢到字符边界
+    pub fn replace(
+        &mut self,
+        range: Range<usize>,
+        text: &str,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let start = self
+            .buffer
+            .floor_char_boundary(range.start.min(self.buffer.len()));
+        let end = self
+            .buffer
+            .ceil_char_boundary(range.end.clamp(start, self.buffer.len()));
+        let range_utf16 = self.buffer.byte_to_utf16_idx(start)..self.buffer.byte_to_utf16_idx(end);
+        self.input.update(cx, |input, cx| {
+            input.replace_text_in_range(Some(range_utf16), text, window, cx);
+        });
+    }
+
+    // 只把以 < 开头的行转成字符串检查，其余行不复制
+    fn audio_attachments(&self) -> Vec<String> {
+        self.buffer
+            .lines(LineType::LF)
+            .filter(|line| line.chars().find(|ch| !ch.is_whitespace()) == Some('<'))
+            .filter_map(|line| note::audio_attachment(&line.to_string()).map(str::to_string))
+            .collect()
+    }
+
+    /// 在正文末尾另起一行追加文本，光标保持原位
+    pub fn append_line(&mut self, text: &str, window: &mut Window, cx: &mut Context<Self>) {
+        let end = self.buffer.len();
+        let text = if end == 0 || self.buffer.byte(end - 1) == b'\n' {
+            text.to_string()
+        } else {
+            format!("\n{}", text)
+        };
+        let position = self.cursor_position(cx);
+        self.insert(end, &text, window, cx);
+        self.set_cursor_position(position, window, cx);
+    }
+
+    pub fn set_recording(&mut self, recording: bool, cx: &mut Context<Self>) {
+        self.recording = recording;
+        cx.notify();
+    }
+
+    pub fn toggle_preview(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.current_note.is_none() {
+            return;
+        }
+        self.previewing = !self.previewing;
+        if !self.previewing {
+            self.focus(window, cx);
+        }
+        cx.notify();
+    }
+
+    /// 开始朗读当前正文，正在朗读时停下并回到编辑
+    pub fn toggle_read_aloud(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.current_note.is_none() {
+            return;
+        }
+        if self.reading.is_some() {
+            self.stop_read_aloud(cx);
+            self.focus(window, cx);
+            return;
+        }
+        let reader = cx.new(|cx| ReadAloudView::new(self.buffer.to_string(), cx));
+        let subscription =
+            cx.subscribe_in(&reader, window, |this, _, event, window, cx| match event {
+                ReadAloudEvent::Closed => {
+                    this.stop_read_aloud(cx);
+                    this.focus(window, cx);
+                }
+            });
+        reader.update(cx, |reader, cx| reader.play(cx));
+        self.reading = Some((reader, subscription));
+        cx.notify();
+    }
+
+    pub fn stop_read_aloud(&mut self, cx: &mut Context<Self>) {
+        if let Some((reader, _)) = self.reading.take() {
+            reader.update(cx, |reader, cx| reader.pause(cx));
+            cx.notify();
+        }
+    }
+
+    /// 给选中的文字加上或去掉 `==高亮==`，没有选中文字时插入一对标记并把光标放在中间
+    pub fn toggle_highlight(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.current_note.is_none() || self.previewing {
+            return;
+        }
+        let selection = self
+            .input
+            .update(cx, |input, cx| input.selected_text_range(false, window, cx));
+        let Some(selection) = selection else {
+            return;
+        };
+        let start = self.buffer.utf16_to_byte_idx(selection.range.start);
+        let end = self.buffer.utf16_to_byte_idx(selection.range.end);
+        let selected = self.buffer.slice(start..end).to_string();
+        self.replace(start..end, &markup::toggle_highlight(&selected), window, cx);
+        if selected.is_empty() {
+            let mut position = self.cursor_position(cx);
+            position.character = position
+                .character
+                .saturating_sub(markup::HIGHLIGHT_MARKER.len() as u32);
+            self.set_cursor_position(position, window, cx);
+        }
+    }
+
+    pub fn note_id(&self) -> Option<u128> {
+        self.current_note.as_ref().map(|note| note.id)
+    }
+
+    pub fn cursor_position(&self, cx: &App) -> Position {
+        self.input.read(cx).cursor_position()
+    }
+
+    /// 恢复光标位置，编辑区会随之滚动到光标所在行
+    pub fn set_cursor_position(
+        &mut self,
+        position: Position,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        // set_cursor_position 会顺带聚焦输入框，恢复光标时不应抢走侧边栏的焦点
+        let focused = window.focused(cx);
+        self.input.update(cx, |input, cx| {
+            input.set_cursor_position(position, window, cx);
+        });
+        if let Some(focused) = focused {
+            window.focus(&focused);
+        }
+    }
+
+    pub fn focus(&self, window: &mut Window, cx: &mut Context<Self>) {
+        self.input.update(cx, |input, cx| input.focus(window, cx));
+    }
+
+    // 输入框先处理按下事件把光标移到点击处，这里再按光标位置找链接
+    fn on_mouse_down(&mut self, event: &MouseDownEvent, _: &mut Window, cx: &mut Context<Self>) {
+        if !event.modifiers.secondary() {
+            return;
+        }
+        let offset = self.input.read(cx).cursor();
+        if let Some((_, link)) = link_at(&self.buffer, offset) {
+            cx.emit(EditorEvent::OpenLink(link));
+        }
+    }
+}
+
+/// 找到偏移所在行中的笔记链接，返回链接在整篇正文中的字节范围
+fn link_at(text: &Rope, offset: usize) -> Option<(Range<usize>, NoteLink)> {
+    let offset = offset.min(text.len());
+    let row = text.byte_to_line_idx(offset, LineType::LF);
+    let line_start = text.line_to_byte_idx(row, LineType::LF);
+    let line = text.line(row, LineType::LF).to_string();
+    let (range, link) = note::note_link_at(&line, offset - line_start)?;
+    Some((line_start + range.start..line_start + range.end, link))
+}
+
+/// 鼠标停在 `[[双链]]` 或 `notes://open/<id>` 上时，弹出目标笔记的标题和第一段
+struct LinkPreview {
+    notes: Entity<NoteList>,
+}
+
+impl LinkPreview {
+    fn card(&self, link: &NoteLink, cx: &App) -> String {
+        let open_key = if cfg!(target_os = "macos") {
+            "⌘"
+        } else {
+            "Ctrl"
+        };
+        let notes = self.notes.read(cx);
+        let note = match link {
+            NoteLink::Title(title) => notes.find_by_title(title),
+            NoteLink::Id(id) => notes.get(*id),
+        };
+        match (note, link) {
+            (Some(note), _) => {
+                let paragraph = note.first_paragraph();
+                let mut excerpt: String = paragraph.chars().take(LINK_PREVIEW_CHARS).collect();
+                if paragraph.chars().nth(LINK_PREVIEW_CHARS).is_some() {
+                    excerpt.push('…');
+                }
+                if excerpt.is_empty() {
+                    excerpt.push_str("无内容");
+                }
+                format!(
+                    "**{}**\n\n{}\n\n*按住 {} 点击打开*",
+                    note.title, excerpt, open_key
+                )
+            }
+            (None, NoteLink::Title(title)) => {
+                format!(
+                    "还没有「{}」这篇笔记\n\n*按住 {} 点击新建*",
+                    title, open_key
+                )
+            }
+            (None, NoteLink::Id(_)) => "找不到这篇笔记，可能已被删除".to_string(),
+        }
+    }
+}
+
+impl HoverProvider for LinkPreview {
+    fn hover(
+        &self,
+        text: &Rope,
+        offset: usize,
+        _window: &mut Window,
+        cx: &mut App,
+    ) -> Task<anyhow::Result<Option<lsp_types::Hover>>> {
+        let hover = link_at(text, offset).map(|(range, link)| lsp_types::Hover {
+            contents: lsp_types::HoverContents::Markup(lsp_types::MarkupContent {
+                kind: lsp_types::MarkupKind::Markdown,
+                value: self.card(&link, cx),
+            }),
+            range: Some(lsp_types::Range::new(
+                text.offset_to_position(range.start),
+                text.offset_to_position(range.end),
+            )),
+        });
+        Task::ready(Ok(hover))
     }
 }
 
+impl EventEmitter<EditorEvent> for EditorView {}
+
 impl Render for EditorView {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         if self.current_note.is_none() {
             return div()
                 .h_full()
@@ -38,7 +820,21 @@ impl Render for EditorView {
                 .into_any_element();
         }
 
+        let title = self.render_title(cx);
+        let tags = self.render_tags(cx);
         let note = self.current_note.as_ref().unwrap();
+        let fonts = &AppSettings::get(cx).fonts;
+        let mut recordings = Vec::new();
+        for (ix, name) in self.audio_attachments().into_iter().enumerate() {
+            recordings.push(
+                Button::new(("play-audio", ix))
+                    .label(format!("▶ {}", name))
+                    .compact()
+                    .on_click(cx.listener(move |_, _, _window, cx| {
+                        cx.emit(EditorEvent::PlayAudio(name.clone()));
+                    })),
+            );
+        }
 
         v_flex()
             .h_full()
@@ -51,10 +847,60 @@ impl Render for EditorView {
                     .border_b_1()
                     .border_color(gpui::rgb(0xe5e7eb))
                     .child(
-                        div()
-                            .text_xl()
-                            .font_weight(FontWeight::BOLD)
-                            .child(note.title.clone()),
+                        h_flex()
+                            .justify_between()
+                            .items_center()
+                            .child(title)
+                            .child(
+                                h_flex()
+                                    .gap_2()
+                                    .child(
+                                        Button::new("toggle-highlight")
+                                            .label("高亮")
+                                            .compact()
+                                            .disabled(self.previewing)
+                                            .on_click(cx.listener(|this, _, window, cx| {
+                                                this.toggle_highlight(window, cx);
+                                            })),
+                                    )
+                                    .child(
+                                        Button::new("toggle-preview")
+                                            .label(if self.previewing {
+                                                "编辑"
+                                            } else {
+                                                "预览"
+                                            })
+                                            .compact()
+                                            .on_click(cx.listener(|this, _, window, cx| {
+                                                this.toggle_preview(window, cx);
+                                            })),
+                                    )
+                                    .child(
+                                        Button::new("toggle-read-aloud")
+                                            .label(if self.reading.is_some() {
+                                                "停止朗读"
+                                            } else {
+                                                "朗读"
+                                            })
+                                            .compact()
+                                            .on_click(cx.listener(|this, _, window, cx| {
+                                                this.toggle_read_aloud(window, cx);
+                                            })),
+                                    )
+                                    .child(
+                                        Button::new("toggle-recording")
+                                            .label(if self.recording {
+                                                "■ 停止录音"
+                                            } else {
+                                                "● 录音"
+                                            })
+                                            .compact()
+                                            .when(self.recording, |this| this.danger())
+                                            .on_click(cx.listener(|_, _, _window, cx| {
+                                                cx.emit(EditorEvent::ToggleRecording);
+                                            })),
+                                    ),
+                            ),
                     )
                     .child(
                         div()
@@ -62,14 +908,66 @@ impl Render for EditorView {
                             .text_xs()
                             .text_color(gpui::rgb(0x6b7280))
                             .child(format!("创建于 {}", note.formatted_time())),
-                    ),
-            )
-            .child(
-                div()
-                    .flex_1()
-                    .p_6()
-                    .child(div().text_base().child(note.content.clone())),
+                    )
+                    .child(tags),
             )
+            .map(|this| {
+                if let Some((reader, _)) = &self.reading {
+                    this.child(reader.clone())
+                } else if self.previewing {
+                    this.child(
+                        div()
+                            .id("note-preview")
+                            .flex_1()
+                            .px_6()
+                            .py_4()
+                            .overflow_y_scroll()
+                            .text_size(fonts.editor_font_size())
+                            .child(
+                                NotePreview::new(
+                                    &self.buffer.to_string(),
+                                    self.attachments_dir.clone(),
+                                )
+                                .embeds(self.notes.clone(), note.id)
+                                .child_index(
+                                    self.notes
+                                        .read(cx)
+                                        .children_of(note.id)
+                                        .into_iter()
+                                        .map(|child| (child.id, child.title.clone().into()))
+                                        .collect(),
+                                    cx.listener(|_, note_id: &u128, _window, cx| {
+                                        cx.emit(EditorEvent::OpenLink(NoteLink::Id(*note_id)));
+                                    }),
+                                ),
+                            ),
+                    )
+                } else {
+                    this.child(
+                        div()
+                            .flex_1()
+                            .px_4()
+                            .py_2()
+                            .overflow_hidden()
+                            .font(fonts.editor_font())
+                            .text_size(fonts.editor_font_size())
+                            .on_mouse_down(MouseButton::Left, cx.listener(Self::on_mouse_down))
+                            .child(Input::new(&self.input).appearance(false).h_full()),
+                    )
+                }
+            })
+            .when(!recordings.is_empty(), |this| {
+                this.child(
+                    h_flex()
+                        .px_6()
+                        .py_2()
+                        .gap_2()
+                        .flex_wrap()
+                        .border_t_1()
+                        .border_color(gpui::rgb(0xe5e7eb))
+                        .children(recordings),
+                )
+            })
             .child(
                 div()
                     .px_6()
@@ -77,10 +975,12 @@ impl Render for EditorView {
                     .border_t_1()
                     .border_color(gpui::rgb(0xe5e7eb))
                     .child(
-                        div()
+                        h_flex()
+                            .justify_between()
                             .text_xs()
                             .text_color(gpui::rgb(0x6b7280))
-                            .child("提示：使用 Markdown 语法格式化文本"),
+                            .child("提示：使用 Markdown 语法格式化文本")
+                            .child(format!("共 {} 行", self.line_count())),
                     ),
             )
             .into_any_element()
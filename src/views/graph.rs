@@ -0,0 +1,233 @@
+use crate::{graph::NoteGraph, note::Note, note_list::NoteList};
+use gpui::{prelude::FluentBuilder as _, *};
+use gpui_component::{
+    button::{Button, ButtonVariants},
+    h_flex, v_flex,
+};
+
+pub enum GraphEvent {
+    OpenNote(u128),
+    Close,
+}
+
+pub struct GraphView {
+    notes: Entity<NoteList>,
+    tag_filter: Option<String>,
+    graph: NoteGraph,
+    /// 后台正在计算的布局，重新构建时丢弃旧任务
+    building: Option<Task<()>>,
+    _notes_changed: Subscription,
+}
+
+impl GraphView {
+    pub fn new(notes: Entity<NoteList>, cx: &mut Context<Self>) -> Self {
+        let mut view = Self {
+            _notes_changed: cx.observe(&notes, |this, _, cx| {
+                this.rebuild(cx);
+                cx.notify();
+            }),
+            notes,
+            tag_filter: None,
+            graph: NoteGraph {
+                nodes: Vec::new(),
+                edges: Vec::new(),
+            },
+            building: None,
+        };
+        view.rebuild(cx);
+        view
+    }
+
+    fn set_tag_filter(&mut self, tag: Option<String>, cx: &mut Context<Self>) {
+        self.tag_filter = tag;
+        self.rebuild(cx);
+        cx.notify();
+    }
+
+    // 力导向布局的计算量随笔记数平方增长，放到后台线程，算完再替换
+    fn rebuild(&mut self, cx: &mut Context<Self>) {
+        let notes = self.notes.read(cx).get_all();
+        let visible: Vec<Note> = notes
+            .into_iter()
+            .filter(|note| match &self.tag_filter {
+                Some(tag) => note.tags.contains(tag),
+                None => true,
+            })
+            .cloned()
+            .collect();
+        let graph = cx
+            .background_executor()
+            .spawn(async move { NoteGraph::build(&visible.iter().collect::<Vec<_>>()) });
+        self.building = Some(cx.spawn(async move |this, cx| {
+            let graph = graph.await;
+            this.update(cx, |this, cx| {
+                this.graph = graph;
+                this.building = None;
+                cx.notify();
+            })
+            .ok();
+        }));
+    }
+
+    fn all_tags(&self, cx: &App) -> Vec<String> {
+        let mut tags: Vec<String> = self
+            .notes
+            .read(cx)
+            .get_all()
+            .iter()
+            .flat_map(|note| note.tags.iter().cloned())
+            .collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    fn render_filter_bar(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let tags = self.all_tags(cx);
+        let all_button = Button::new("graph-tag-all")
+            .label("全部")
+            .compact()
+            .when(self.tag_filter.is_none(), |this| this.primary())
+            .on_click(cx.listener(|this, _, _window, cx| this.set_tag_filter(None, cx)));
+
+        h_flex()
+            .gap_2()
+            .flex_wrap()
+            .child(all_button)
+            .children(tags.into_iter().enumerate().map(|(ix, tag)| {
+                let selected = self.tag_filter.as_ref() == Some(&tag);
+                Button::new(("graph-tag", ix))
+                    .label(format!("#{}", tag))
+                    .compact()
+                    .when(selected, |this| this.primary())
+                    .on_click(cx.listener(move |this, _, _window, cx| {
+                        this.set_tag_filter(Some(tag.clone()), cx)
+                    }))
+            }))
+    }
+
+    fn render_canvas(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let segments: Vec<_> = self
+            .graph
+            .edges
+            .iter()
+            .map(|&(a, b)| {
+                let (a, b) = (&self.graph.nodes[a], &self.graph.nodes[b]);
+                ((a.x, a.y), (b.x, b.y))
+            })
+            .collect();
+
+        let edges = canvas(
+            |_, _, _| {},
+            move |bounds, _, window, _cx| {
+                let to_point = |(x, y): (f32, f32)| {
+                    point(
+                        bounds.origin.x + bounds.size.width * x,
+                        bounds.origin.y + bounds.size.height * y,
+                    )
+                };
+                for (from, to) in segments {
+                    let mut builder = PathBuilder::stroke(px(1.));
+                    builder.move_to(to_point(from));
+                    builder.line_to(to_point(to));
+                    if let Ok(path) = builder.build() {
+                        window.paint_path(path, gpui::rgb(0xc7d2fe));
+                    }
+                }
+            },
+        )
+        .absolute()
+        .size_full();
+
+        div()
+            .relative()
+            .flex_1()
+            .w_full()
+            .overflow_hidden()
+            .child(edges)
+            .children(self.graph.nodes.iter().map(|node| {
+                let note_id = node.id;
+                div()
+                    .id(ElementId::Name(format!("graph-node-{}", note_id).into()))
+                    .absolute()
+                    .left(relative(node.x))
+                    .top(relative(node.y))
+                    // 以节点坐标为圆点中心向右排布标题
+                    .ml(px(-6.))
+                    .mt(px(-6.))
+                    .flex()
+                    .flex_row()
+                    .items_center()
+                    .gap_1()
+                    .cursor_pointer()
+                    .on_click(cx.listener(move |_, _, _window, cx| {
+                        cx.emit(GraphEvent::OpenNote(note_id));
+                    }))
+                    .child(div().size(px(12.)).rounded_full().bg(gpui::rgb(0x6366f1)))
+                    .child(
+                        div()
+                            .text_xs()
+                            .text_color(gpui::rgb(0x374151))
+                            .child(node.title.clone()),
+                    )
+            }))
+    }
+}
+
+impl EventEmitter<GraphEvent> for GraphView {}
+
+impl Render for GraphView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        v_flex()
+            .size_full()
+            .bg(gpui::rgb(0xffffff))
+            .child(
+                v_flex()
+                    .px_6()
+                    .py_3()
+                    .gap_2()
+                    .border_b_1()
+                    .border_color(gpui::rgb(0xe5e7eb))
+                    .child(
+                        h_flex()
+                            .items_center()
+                            .justify_between()
+                            .child(
+                                div()
+                                    .text_lg()
+                                    .font_weight(FontWeight::SEMIBOLD)
+                                    .child("关系图谱"),
+                            )
+                            .child(
+                                Button::new("close-graph")
+                                    .label("返回")
+                                    .on_click(cx.listener(|_, _, _window, cx| {
+                                        cx.emit(GraphEvent::Close);
+                                    })),
+                            ),
+                    )
+                    .child(self.render_filter_bar(cx)),
+            )
+            .child(if self.graph.nodes.is_empty() && self.building.is_some() {
+                div()
+                    .flex_1()
+                    .flex()
+                    .items_center()
+                    .justify_center()
+                    .text_color(gpui::rgb(0x6b7280))
+                    .child("正在生成图谱…")
+                    .into_any_element()
+            } else if self.graph.nodes.is_empty() {
+                div()
+                    .flex_1()
+                    .flex()
+                    .items_center()
+                    .justify_center()
+                    .text_color(gpui::rgb(0x6b7280))
+                    .child("没有符合条件的笔记")
+                    .into_any_element()
+            } else {
+                self.render_canvas(cx).into_any_element()
+            })
+    }
+}
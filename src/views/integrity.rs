@@ -0,0 +1,173 @@
+use crate::{
+    note::Note,
+    storage::{DamagedNote, VerifyReport},
+};
+use gpui::{prelude::FluentBuilder as _, *};
+use gpui_component::{
+    button::{Button, ButtonVariants},
+    h_flex, v_flex,
+};
+use std::collections::HashSet;
+
+pub enum IntegrityEvent {
+    /// 用备份替换损坏的笔记
+    Restore(u128),
+    Close,
+}
+
+/// 验证数据的结果：列出损坏的笔记文件，有备份的可以一键恢复
+pub struct IntegrityView {
+    report: VerifyReport,
+    restored: HashSet<u128>,
+}
+
+impl IntegrityView {
+    pub fn new(report: VerifyReport) -> Self {
+        Self {
+            report,
+            restored: HashSet::new(),
+        }
+    }
+
+    pub fn backup(&self, note_id: u128) -> Option<&Note> {
+        self.report
+            .damaged
+            .iter()
+            .find(|damaged| damaged.note_id == note_id)
+            .and_then(|damaged| damaged.backup.as_ref())
+    }
+
+    pub fn mark_restored(&mut self, note_id: u128, cx: &mut Context<Self>) {
+        self.restored.insert(note_id);
+        cx.notify();
+    }
+
+    fn render_row(&self, ix: usize, damaged: &DamagedNote, cx: &mut Context<Self>) -> Div {
+        let note_id = damaged.note_id;
+        let title = damaged
+            .backup
+            .as_ref()
+            .map_or_else(|| note_id.to_string(), |note| note.title.clone());
+        let action = if self.restored.contains(&note_id) {
+            div()
+                .text_sm()
+                .text_color(gpui::rgb(0x16a34a))
+                .child("已恢复")
+                .into_any_element()
+        } else if let Some(backup) = &damaged.backup {
+            Button::new(("restore-note", ix))
+                .label(format!("恢复到 {} 的版本", backup.formatted_time()))
+                .primary()
+                .compact()
+                .on_click(cx.listener(move |_, _, _window, cx| {
+                    cx.emit(IntegrityEvent::Restore(note_id));
+                }))
+                .into_any_element()
+        } else {
+            div()
+                .text_sm()
+                .text_color(gpui::rgb(0x9ca3af))
+                .child("没有可用的备份")
+                .into_any_element()
+        };
+
+        h_flex()
+            .py_2()
+            .gap_4()
+            .justify_between()
+            .border_b_1()
+            .border_color(gpui::rgb(0xf3f4f6))
+            .child(
+                v_flex()
+                    .gap_1()
+                    .child(div().font_weight(FontWeight::SEMIBOLD).child(title))
+                    .child(
+                        div()
+                            .text_xs()
+                            .text_color(gpui::rgb(0xdc2626))
+                            .child(damaged.problem.label()),
+                    )
+                    .child(
+                        div()
+                            .text_xs()
+                            .text_color(gpui::rgb(0x9ca3af))
+                            .child(damaged.path.display().to_string()),
+                    ),
+            )
+            .child(action)
+    }
+}
+
+impl EventEmitter<IntegrityEvent> for IntegrityView {}
+
+impl Render for IntegrityView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let report = &self.report;
+        let mut summary = format!(
+            "共检查 {} 篇笔记，{} 篇有问题。",
+            report.checked,
+            report.damaged.len()
+        );
+        if report.unverified > 0 {
+            summary.push_str(&format!(
+                "{} 篇由旧版本保存、还没有校验和，下次保存时补上。",
+                report.unverified
+            ));
+        }
+        let rows: Vec<Div> = report
+            .damaged
+            .iter()
+            .enumerate()
+            .map(|(ix, damaged)| self.render_row(ix, damaged, cx))
+            .collect();
+
+        v_flex()
+            .size_full()
+            .bg(gpui::rgb(0xffffff))
+            .child(
+                h_flex()
+                    .px_6()
+                    .py_3()
+                    .border_b_1()
+                    .border_color(gpui::rgb(0xe5e7eb))
+                    .items_center()
+                    .justify_between()
+                    .child(
+                        div()
+                            .text_lg()
+                            .font_weight(FontWeight::SEMIBOLD)
+                            .child("验证数据"),
+                    )
+                    .child(
+                        Button::new("close-integrity")
+                            .label("返回")
+                            .on_click(cx.listener(|_, _, _window, cx| {
+                                cx.emit(IntegrityEvent::Close);
+                            })),
+                    ),
+            )
+            .child(
+                v_flex()
+                    .id("integrity-report")
+                    .flex_1()
+                    .px_6()
+                    .py_3()
+                    .overflow_y_scroll()
+                    .child(
+                        div()
+                            .pb_2()
+                            .text_sm()
+                            .text_color(gpui::rgb(0x6b7280))
+                            .child(summary),
+                    )
+                    .when(rows.is_empty(), |this| {
+                        this.child(
+                            div()
+                                .text_color(gpui::rgb(0x16a34a))
+                                .child("没有发现损坏的笔记"),
+                        )
+                    })
+                    .children(rows),
+            )
+    }
+}
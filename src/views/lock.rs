@@ -0,0 +1,196 @@
+use crate::{actions::APP_NAME, settings::AppSettings};
+use gpui::{prelude::FluentBuilder as _, *};
+use gpui_component::{
+    Disableable as _,
+    button::{Button, ButtonVariants as _},
+    input::{Input, InputEvent, InputState},
+    v_flex,
+};
+use std::time::Duration;
+
+/// 连续输错这么多次后开始等待
+const FREE_ATTEMPTS: u32 = 3;
+/// 每多输错一次等待时间翻倍，最长等这么久
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+pub enum LockEvent {
+    Unlocked,
+}
+
+/// 应用锁定时盖住全部内容的解锁界面
+pub struct LockView {
+    input: Entity<InputState>,
+    /// 上次输入的密码不对
+    wrong: bool,
+    /// 连续输错的次数
+    failures: u32,
+    /// 正在后台校验密码
+    verifying: bool,
+    /// 输错太多次后等待结束才能再试，任务结束时清空
+    _backoff: Option<Task<()>>,
+    _subscription: Subscription,
+}
+
+impl LockView {
+    pub fn new(window: &mut Window, cx: &mut Context<Self>) -> Self {
+        let input = cx.new(|cx| {
+            InputState::new(window, cx)
+                .masked(true)
+                .placeholder("输入密码")
+        });
+        let subscription =
+            cx.subscribe_in(&input, window, |this, _, event, window, cx| match event {
+                InputEvent::PressEnter { .. } => this.unlock(window, cx),
+                // 输错后清空输入框也会发出 Change，重新输入时才去掉提示
+                InputEvent::Change if !this.input.read(cx).value().is_empty() => {
+                    this.wrong = false;
+                    cx.notify();
+                }
+                _ => {}
+            });
+        Self {
+            input,
+            wrong: false,
+            failures: 0,
+            verifying: false,
+            _backoff: None,
+            _subscription: subscription,
+        }
+    }
+
+    pub fn focus(&self, window: &mut Window, cx: &mut Context<Self>) {
+        self.input.update(cx, |input, cx| input.focus(window, cx));
+    }
+
+    fn can_submit(&self) -> bool {
+        !self.verifying && self._backoff.is_none()
+    }
+
+    // 密钥派生要几百毫秒，放到后台算，界面保持响应
+    fn unlock(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if !self.can_submit() {
+            return;
+        }
+        let password = self.input.read(cx).value().to_string();
+        let lock = AppSettings::get(cx).lock.clone();
+        self.verifying = true;
+        cx.notify();
+        let verify = cx.background_executor().spawn(async move {
+            if !lock.verify(&password) {
+                return None;
+            }
+            // 旧摘要在验证通过后顺便升级
+            Some(lock.needs_rehash().then(|| {
+                let mut upgraded = lock.clone();
+                upgraded.set_password(&password);
+                upgraded
+            }))
+        });
+        cx.spawn_in(window, async move |this, cx| {
+            let verified = verify.await;
+            this.update_in(cx, |this, window, cx| {
+                this.verifying = false;
+                match verified {
+                    Some(upgraded) => {
+                        this.failures = 0;
+                        if let Some(upgraded) = upgraded {
+                            AppSettings::update(cx, |s| s.lock.copy_password(&upgraded));
+                        }
+                        cx.emit(LockEvent::Unlocked);
+                    }
+                    None => this.fail(window, cx),
+                }
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn fail(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.wrong = true;
+        self.failures += 1;
+        self.input
+            .update(cx, |input, cx| input.set_value("", window, cx));
+        if let Some(delay) = backoff(self.failures) {
+            self._backoff = Some(cx.spawn(async move |this, cx| {
+                cx.background_executor().timer(delay).await;
+                this.update(cx, |this, cx| {
+                    this._backoff = None;
+                    cx.notify();
+                })
+                .ok();
+            }));
+        }
+    }
+}
+
+/// 连续输错 failures 次后需要等待的时间
+fn backoff(failures: u32) -> Option<Duration> {
+    let extra = failures.checked_sub(FREE_ATTEMPTS)?;
+    let delay = Duration::from_secs(1u64 << extra.min(6));
+    Some(delay.min(MAX_BACKOFF))
+}
+
+impl EventEmitter<LockEvent> for LockView {}
+
+impl Render for LockView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .size_full()
+            .flex()
+            .items_center()
+            .justify_center()
+            .bg(gpui::rgb(0xf3f4f6))
+            .child(
+                v_flex()
+                    .w(px(280.0))
+                    .gap_3()
+                    .items_center()
+                    .child(div().text_3xl().child("🔒"))
+                    .child(
+                        div()
+                            .text_lg()
+                            .font_weight(FontWeight::SEMIBOLD)
+                            .child(format!("{} 已锁定", APP_NAME)),
+                    )
+                    .child(Input::new(&self.input).w_full())
+                    .when(self.wrong, |this| {
+                        this.child(div().text_sm().text_color(gpui::rgb(0xdc2626)).child(
+                            if self._backoff.is_some() {
+                                "密码不正确，输错次数过多，请稍后再试"
+                            } else {
+                                "密码不正确"
+                            },
+                        ))
+                    })
+                    .child(
+                        Button::new("unlock")
+                            .label(if self.verifying {
+                                "正在验证…"
+                            } else {
+                                "解锁"
+                            })
+                            .primary()
+                            .w_full()
+                            .disabled(!self.can_submit())
+                            .on_click(cx.listener(|this, _, window, cx| this.unlock(window, cx))),
+                    ),
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FREE_ATTEMPTS, MAX_BACKOFF, backoff};
+    use std::time::Duration;
+
+    #[test]
+    fn backoff_starts_after_free_attempts_and_is_capped() {
+        assert_eq!(backoff(1), None);
+        assert_eq!(backoff(FREE_ATTEMPTS - 1), None);
+        assert_eq!(backoff(FREE_ATTEMPTS), Some(Duration::from_secs(1)));
+        assert_eq!(backoff(FREE_ATTEMPTS + 2), Some(Duration::from_secs(4)));
+        assert_eq!(backoff(FREE_ATTEMPTS + 20), Some(MAX_BACKOFF));
+    }
+}
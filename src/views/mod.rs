@@ -1,2 +1,16 @@
+pub mod attachments;
+pub mod board;
+pub mod calendar;
+pub mod diagnostics;
 pub mod editor;
+pub mod graph;
+pub mod integrity;
+pub mod lock;
+pub mod plugins;
+pub mod preview;
+pub mod read_aloud;
+pub mod settings;
 pub mod sidebar;
+pub mod stats;
+pub mod title_bar;
+pub mod trash;
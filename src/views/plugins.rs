@@ -0,0 +1,263 @@
+use crate::{
+    plugins::{PluginCommand, PluginExporter, PluginHost},
+    scripting::Script,
+    settings::AppSettings,
+};
+use gpui::*;
+use gpui_component::{
+    button::{Button, ButtonVariants},
+    h_flex, v_flex,
+};
+
+pub enum PluginsEvent {
+    RunCommand {
+        plugin: String,
+        command: String,
+    },
+    Export {
+        plugin: String,
+        exporter: String,
+    },
+    /// 重新询问是否启用插件
+    Review(String),
+    RunScript(String),
+    Close,
+}
+
+struct PluginRow {
+    name: String,
+    description: String,
+    enabled: bool,
+    commands: Vec<PluginCommand>,
+    exporters: Vec<PluginExporter>,
+}
+
+struct ScriptRow {
+    name: String,
+    schedule: Option<String>,
+}
+
+pub struct PluginsView {
+    plugins: Vec<PluginRow>,
+    scripts: Vec<ScriptRow>,
+}
+
+impl PluginsView {
+    pub fn new(host: Option<&PluginHost>, scripts: &[Script], cx: &mut Context<Self>) -> Self {
+        let settings = &AppSettings::get(cx).plugins;
+        let plugins = host
+            .map(|host| host.plugins.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .map(|plugin| PluginRow {
+                name: plugin.manifest.name.clone(),
+                description: plugin.manifest.description.clone(),
+                enabled: settings.is_granted(plugin),
+                commands: plugin.manifest.commands.clone(),
+                exporters: plugin.manifest.exporters.clone(),
+            })
+            .collect();
+        let scripts = scripts
+            .iter()
+            .map(|script| ScriptRow {
+                name: script.name.clone(),
+                schedule: script.schedule.map(|schedule| schedule.label()),
+            })
+            .collect();
+        Self { plugins, scripts }
+    }
+
+    fn render_script(&self, ix: usize, row: &ScriptRow, cx: &mut Context<Self>) -> Div {
+        let name = row.name.clone();
+        h_flex()
+            .px_4()
+            .py_2()
+            .gap_3()
+            .items_center()
+            .rounded_md()
+            .border_1()
+            .border_color(gpui::rgb(0xe5e7eb))
+            .child(div().flex_1().text_sm().child(format!("{}.rhai", row.name)))
+            .children(row.schedule.clone().map(|schedule| {
+                div()
+                    .text_xs()
+                    .text_color(gpui::rgb(0x6b7280))
+                    .child(schedule)
+            }))
+            .child(
+                Button::new(("run-script", ix))
+                    .label("运行")
+                    .compact()
+                    .on_click(cx.listener(move |_, _, _window, cx| {
+                        cx.emit(PluginsEvent::RunScript(name.clone()));
+                    })),
+            )
+    }
+
+    fn render_section(title: &str) -> Div {
+        v_flex().gap_3().child(
+            div()
+                .text_sm()
+                .font_weight(FontWeight::SEMIBOLD)
+                .child(title.to_string()),
+        )
+    }
+
+    fn render_plugin(&self, ix: usize, row: &PluginRow, cx: &mut Context<Self>) -> Div {
+        let name = row.name.clone();
+        let mut actions = h_flex().gap_2().flex_wrap();
+        if row.enabled {
+            for command in &row.commands {
+                let (plugin, id) = (name.clone(), command.id.clone());
+                actions = actions.child(
+                    Button::new(SharedString::from(format!(
+                        "plugin-{}-cmd-{}",
+                        ix, command.id
+                    )))
+                    .label(command.title.clone())
+                    .compact()
+                    .on_click(cx.listener(move |_, _, _window, cx| {
+                        cx.emit(PluginsEvent::RunCommand {
+                            plugin: plugin.clone(),
+                            command: id.clone(),
+                        });
+                    })),
+                );
+            }
+            for exporter in &row.exporters {
+                let (plugin, id) = (name.clone(), exporter.id.clone());
+                actions = actions.child(
+                    Button::new(SharedString::from(format!(
+                        "plugin-{}-exp-{}",
+                        ix, exporter.id
+                    )))
+                    .label(format!("导出为 {}", exporter.title))
+                    .compact()
+                    .on_click(cx.listener(move |_, _, _window, cx| {
+                        cx.emit(PluginsEvent::Export {
+                            plugin: plugin.clone(),
+                            exporter: id.clone(),
+                        });
+                    })),
+                );
+            }
+        } else {
+            actions = actions.child(
+                Button::new(("plugin-review", ix))
+                    .label("启用…")
+                    .compact()
+                    .primary()
+                    .on_click(cx.listener(move |_, _, _window, cx| {
+                        cx.emit(PluginsEvent::Review(name.clone()));
+                    })),
+            );
+        }
+
+        v_flex()
+            .p_4()
+            .gap_2()
+            .rounded_md()
+            .border_1()
+            .border_color(gpui::rgb(0xe5e7eb))
+            .child(
+                h_flex()
+                    .gap_2()
+                    .items_center()
+                    .child(
+                        div()
+                            .font_weight(FontWeight::SEMIBOLD)
+                            .child(row.name.clone()),
+                    )
+                    .child(
+                        div()
+                            .text_xs()
+                            .text_color(if row.enabled {
+                                gpui::rgb(0x16a34a)
+                            } else {
+                                gpui::rgb(0x9ca3af)
+                            })
+                            .child(if row.enabled {
+                                "已启用"
+                            } else {
+                                "未启用"
+                            }),
+                    ),
+            )
+            .child(
+                div()
+                    .text_sm()
+                    .text_color(gpui::rgb(0x6b7280))
+                    .child(row.description.clone()),
+            )
+            .child(actions)
+    }
+}
+
+impl EventEmitter<PluginsEvent> for PluginsView {}
+
+impl Render for PluginsView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let mut rows = Vec::with_capacity(self.plugins.len());
+        for (ix, row) in self.plugins.iter().enumerate() {
+            rows.push(self.render_plugin(ix, row, cx));
+        }
+        let mut scripts = Vec::with_capacity(self.scripts.len());
+        for (ix, row) in self.scripts.iter().enumerate() {
+            scripts.push(self.render_script(ix, row, cx));
+        }
+
+        v_flex()
+            .size_full()
+            .bg(gpui::rgb(0xffffff))
+            .child(
+                h_flex()
+                    .px_6()
+                    .py_3()
+                    .border_b_1()
+                    .border_color(gpui::rgb(0xe5e7eb))
+                    .items_center()
+                    .justify_between()
+                    .child(
+                        div()
+                            .text_lg()
+                            .font_weight(FontWeight::SEMIBOLD)
+                            .child("插件与脚本"),
+                    )
+                    .child(
+                        Button::new("close-plugins")
+                            .label("返回")
+                            .on_click(cx.listener(|_, _, _window, cx| {
+                                cx.emit(PluginsEvent::Close);
+                            })),
+                    ),
+            )
+            .child(
+                v_flex()
+                    .id("plugins-body")
+                    .flex_1()
+                    .p_6()
+                    .gap_4()
+                    .overflow_y_scroll()
+                    .child(Self::render_section("插件").children(if rows.is_empty() {
+                        vec![
+                            div()
+                                .text_sm()
+                                .text_color(gpui::rgb(0x6b7280))
+                                .child("插件目录中没有插件。把 .wasm 文件和同名的 .json 清单放进数据目录下的 plugins 文件夹后重启即可加载"),
+                        ]
+                    } else {
+                        rows
+                    }))
+                    .child(Self::render_section("脚本").children(if scripts.is_empty() {
+                        vec![
+                            div()
+                                .text_sm()
+                                .text_color(gpui::rgb(0x6b7280))
+                                .child("把 .rhai 脚本放进数据目录下的 scripts 文件夹后重启即可加载，开头写 // schedule: 08:00 可每天定时运行"),
+                        ]
+                    } else {
+                        scripts
+                    })),
+            )
+    }
+}
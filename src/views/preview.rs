@@ -0,0 +1,330 @@
+use crate::{
+    markup::{self, Block, Mark},
+    note_list::NoteList,
+    settings::AppSettings,
+    stylesheet::{self, TextStyle},
+};
+use gpui::{prelude::FluentBuilder as _, *};
+use gpui_component::{h_flex, v_flex};
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+type OpenNote = Rc<dyn Fn(&u128, &mut Window, &mut App)>;
+
+/// 嵌入的笔记里还可以再嵌入，超过这么多层只显示标题
+const MAX_EMBED_DEPTH: usize = 3;
+
+// 解析 `![[标题]]` 用的笔记列表，stack 是从最外层开始正在显示的笔记
+#[derive(Clone)]
+struct Embeds {
+    notes: Entity<NoteList>,
+    stack: Vec<u128>,
+}
+
+/// 笔记正文的只读预览
+#[derive(IntoElement)]
+pub struct NotePreview {
+    blocks: Vec<Block>,
+    attachments_dir: PathBuf,
+    /// 子笔记的 ID 和标题，设置开启时在正文后面列出
+    child_index: Vec<(u128, SharedString)>,
+    on_open_note: Option<OpenNote>,
+    embeds: Option<Embeds>,
+}
+
+impl NotePreview {
+    pub fn new(content: &str, attachments_dir: PathBuf) -> Self {
+        Self {
+            blocks: markup::parse_blocks(content),
+            attachments_dir,
+            child_index: Vec::new(),
+            on_open_note: None,
+            embeds: None,
+        }
+    }
+
+    /// 从 notes 中找到 `![[标题]]` 指向的笔记显示在正文中，note_id 是正在预览的笔记
+    pub fn embeds(mut self, notes: Entity<NoteList>, note_id: u128) -> Self {
+        self.embeds = Some(Embeds {
+            notes,
+            stack: vec![note_id],
+        });
+        self
+    }
+
+    /// 在正文后面列出子笔记，点击时调用 on_open
+    pub fn child_index(
+        mut self,
+        children: Vec<(u128, SharedString)>,
+        on_open: impl Fn(&u128, &mut Window, &mut App) + 'static,
+    ) -> Self {
+        self.child_index = children;
+        self.on_open_note = Some(Rc::new(on_open));
+        self
+    }
+}
+
+// 把行内格式转成高亮样式，嵌套的格式逐个叠加
+fn styled_text(source: &str, highlight: Hsla, code: &TextStyle) -> StyledText {
+    let inline = markup::parse_inline(source);
+    let mut highlights: Vec<(std::ops::Range<usize>, HighlightStyle)> = Vec::new();
+    for (range, mark) in inline.marks {
+        let style = match mark {
+            Mark::Bold => HighlightStyle {
+                font_weight: Some(FontWeight::BOLD),
+                ..Default::default()
+            },
+            Mark::Italic => HighlightStyle {
+                font_style: Some(FontStyle::Italic),
+                ..Default::default()
+            },
+            Mark::Code => HighlightStyle {
+                background_color: Some(code.background.unwrap_or(gpui::rgb(0xf3f4f6).into())),
+                color: code.color,
+                ..Default::default()
+            },
+            Mark::Highlight => HighlightStyle {
+                background_color: Some(highlight),
+                ..Default::default()
+            },
+            Mark::Link => HighlightStyle {
+                color: Some(gpui::rgb(0x2563eb).into()),
+                underline: Some(UnderlineStyle {
+                    thickness: px(1.0),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        };
+        highlights = combine_highlights(highlights, [(range, style)]).collect();
+    }
+    StyledText::new(inline.text).with_highlights(highlights)
+}
+
+// 被嵌入的笔记显示在带标题的框中，标题可以点击打开；源笔记修改后随预览一起刷新
+fn render_embed(
+    ix: usize,
+    title: &str,
+    heading: Option<&str>,
+    embeds: Option<&Embeds>,
+    attachments_dir: &Path,
+    on_open_note: Option<OpenNote>,
+    cx: &App,
+) -> AnyElement {
+    let label = match heading {
+        Some(heading) => format!("{} › {}", title, heading),
+        None => title.to_string(),
+    };
+    let note = embeds.and_then(|embeds| {
+        let note = embeds.notes.read(cx).find_by_title(title)?;
+        Some((note.id, note.content.clone()))
+    });
+    let body = match (embeds, note) {
+        (None, _) => Err(format!("![[{}]]", label)),
+        (Some(_), None) => Err(format!("找不到笔记「{}」", title)),
+        (Some(embeds), Some((note_id, _))) if embeds.stack.contains(&note_id) => {
+            Err(format!("「{}」嵌入了自己，不再展开", title))
+        }
+        (Some(embeds), Some(_)) if embeds.stack.len() > MAX_EMBED_DEPTH => {
+            Err("嵌入层数太多，不再展开".to_string())
+        }
+        (Some(embeds), Some((note_id, content))) => {
+            let content = match heading {
+                Some(heading) => markup::section(&content, heading)
+                    .map(str::to_string)
+                    .ok_or_else(|| format!("「{}」中没有标题「{}」", title, heading)),
+                None => Ok(content),
+            };
+            content.map(|content| {
+                let mut stack = embeds.stack.clone();
+                stack.push(note_id);
+                (note_id, content, stack)
+            })
+        }
+    };
+
+    let note_id = body.as_ref().ok().map(|(note_id, ..)| *note_id);
+    v_flex()
+        .gap_2()
+        .p_3()
+        .rounded_md()
+        .border_l_4()
+        .border_color(gpui::rgb(0xa5b4fc))
+        .bg(gpui::rgb(0xf8fafc))
+        .child(
+            div()
+                .id(ElementId::Name(
+                    format!("embed-{}-{}", embeds.map_or(0, |e| e.stack.len()), ix).into(),
+                ))
+                .text_sm()
+                .text_color(gpui::rgb(0x4f46e5))
+                .child(label)
+                .when_some(
+                    note_id.zip(on_open_note.clone()),
+                    |this, (note_id, on_open)| {
+                        this.cursor_pointer()
+                            .on_click(move |_, window, cx| on_open(&note_id, window, cx))
+                    },
+                ),
+        )
+        .child(match body {
+            Ok((_, content, stack)) => {
+                let mut preview = NotePreview::new(&content, attachments_dir.to_path_buf());
+                preview.embeds = embeds.map(|embeds| Embeds {
+                    notes: embeds.notes.clone(),
+                    stack,
+                });
+                preview.on_open_note = on_open_note;
+                preview.into_any_element()
+            }
+            Err(message) => div()
+                .text_sm()
+                .text_color(gpui::rgb(0x9ca3af))
+                .child(message)
+                .into_any_element(),
+        })
+        .into_any_element()
+}
+
+// 把样式表中写了的属性叠加到元素的默认样式上
+fn apply_style<E: Styled>(mut element: E, style: &TextStyle) -> E {
+    if let Some(family) = &style.font_family {
+        element = element.font_family(SharedString::from(family.clone()));
+    }
+    if let Some(size) = style.font_size {
+        element = element.text_size(px(size));
+    }
+    if let Some(color) = style.color {
+        element = element.text_color(color);
+    }
+    if let Some(background) = style.background {
+        element = element.bg(background);
+    }
+    if let Some(border) = style.border_color {
+        element = element.border_color(border);
+    }
+    if style.italic {
+        element = element.italic();
+    }
+    element
+}
+
+impl RenderOnce for NotePreview {
+    fn render(self, _window: &mut Window, cx: &mut App) -> impl IntoElement {
+        let sheet = stylesheet::current(cx);
+        let settings = &AppSettings::get(cx).preview;
+        let highlight = settings.highlight_color();
+        let show_index = settings.child_index && !self.child_index.is_empty();
+        let attachments_dir = self.attachments_dir;
+        let on_open_note = self.on_open_note;
+        let child_index = self.child_index;
+        let embeds = self.embeds;
+
+        apply_style(v_flex().gap_3(), &sheet.body)
+            .children(self.blocks.into_iter().enumerate().map(|(ix, block)| {
+                match block {
+                    Block::Heading { level, text } => apply_style(
+                        div().font_weight(FontWeight::BOLD).map(|this| match level {
+                            1 => this.text_2xl(),
+                            2 => this.text_xl(),
+                            3 => this.text_lg(),
+                            _ => this,
+                        }),
+                        sheet.heading(level),
+                    )
+                    .child(styled_text(&text, highlight, &sheet.code))
+                    .into_any_element(),
+                    Block::Paragraph(text) => div()
+                        .child(styled_text(&text, highlight, &sheet.code))
+                        .into_any_element(),
+                    Block::ListItem {
+                        marker,
+                        depth,
+                        text,
+                    } => h_flex()
+                        .items_start()
+                        .gap_2()
+                        .pl(px(20.0 * depth as f32))
+                        .child(div().text_color(gpui::rgb(0x6b7280)).child(marker))
+                        .child(
+                            div()
+                                .flex_1()
+                                .child(styled_text(&text, highlight, &sheet.code)),
+                        )
+                        .into_any_element(),
+                    Block::Quote(text) => apply_style(
+                        div()
+                            .pl_3()
+                            .border_l_4()
+                            .border_color(gpui::rgb(0xd1d5db))
+                            .text_color(gpui::rgb(0x4b5563)),
+                        &sheet.quote,
+                    )
+                    .child(styled_text(&text, highlight, &sheet.code))
+                    .into_any_element(),
+                    Block::Code(code) => apply_style(
+                        div()
+                            .p_3()
+                            .rounded_md()
+                            .bg(gpui::rgb(0xf3f4f6))
+                            .font_family("monospace")
+                            .text_sm(),
+                        &sheet.code,
+                    )
+                    .child(code)
+                    .into_any_element(),
+                    Block::Image { alt, src } => match src.strip_prefix("attachments/") {
+                        Some(name) => img(attachments_dir.join(name))
+                            .max_w_full()
+                            .max_h(px(480.0))
+                            .object_fit(ObjectFit::Contain)
+                            .into_any_element(),
+                        // 外部图片不联网加载，只显示说明文字
+                        None => div()
+                            .text_color(gpui::rgb(0x9ca3af))
+                            .child(format!("[图片] {}", if alt.is_empty() { src } else { alt }))
+                            .into_any_element(),
+                    },
+                    Block::Embed { title, heading } => render_embed(
+                        ix,
+                        &title,
+                        heading.as_deref(),
+                        embeds.as_ref(),
+                        &attachments_dir,
+                        on_open_note.clone(),
+                        cx,
+                    ),
+                    Block::Rule => div().h(px(1.0)).bg(gpui::rgb(0xe5e7eb)).into_any_element(),
+                }
+            }))
+            .when(show_index, |this| {
+                this.child(div().h(px(1.0)).bg(gpui::rgb(0xe5e7eb))).child(
+                    v_flex()
+                        .gap_1()
+                        .child(
+                            div()
+                                .text_sm()
+                                .font_weight(FontWeight::SEMIBOLD)
+                                .text_color(gpui::rgb(0x6b7280))
+                                .child("子笔记"),
+                        )
+                        .children(child_index.into_iter().map(|(note_id, title)| {
+                            let on_open_note = on_open_note.clone();
+                            div()
+                                .id(ElementId::Name(format!("child-link-{}", note_id).into()))
+                                .cursor_pointer()
+                                .text_color(gpui::rgb(0x2563eb))
+                                .hover(|this| this.underline())
+                                .child(format!("• {}", title))
+                                .on_click(move |_, window, cx| {
+                                    if let Some(on_open) = &on_open_note {
+                                        on_open(&note_id, window, cx);
+                                    }
+                                })
+                        })),
+                )
+            })
+    }
+}
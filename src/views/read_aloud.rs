@@ -0,0 +1,286 @@
+use crate::{
+    settings::{AppSettings, SPEECH_RATES},
+    speech,
+};
+use gpui::{prelude::FluentBuilder as _, *};
+use gpui_component::{
+    button::{Button, ButtonVariants as _},
+    h_flex, v_flex,
+};
+use std::{ops::Range, process::Child, time::Duration};
+
+/// 检查语音引擎是否读完当前句子的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+pub enum ReadAloudEvent {
+    /// 读完或点了退出，回到编辑
+    Closed,
+}
+
+/// 朗读当前笔记，逐句交给系统语音引擎并高亮正在读的句子
+pub struct ReadAloudView {
+    text: String,
+    /// 每行的字节范围，按行显示，滚动时以行为单位
+    lines: Vec<Range<usize>>,
+    sentences: Vec<Range<usize>>,
+    current: usize,
+    playing: bool,
+    /// 正在读当前句子的语音引擎进程
+    child: Option<Child>,
+    error: Option<String>,
+    scroll: ScrollHandle,
+    _poll: Task<()>,
+}
+
+impl ReadAloudView {
+    pub fn new(text: String, cx: &mut Context<Self>) -> Self {
+        let mut start = 0;
+        let mut lines = Vec::new();
+        for line in text.split('\n') {
+            lines.push(start..start + line.len());
+            start += line.len() + 1;
+        }
+        let poll = cx.spawn(async move |this, cx| {
+            loop {
+                cx.background_executor().timer(POLL_INTERVAL).await;
+                if this.update(cx, |this, cx| this.poll(cx)).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            sentences: speech::sentences(&text),
+            text,
+            lines,
+            current: 0,
+            playing: false,
+            child: None,
+            error: None,
+            scroll: ScrollHandle::new(),
+            _poll: poll,
+        }
+    }
+
+    pub fn play(&mut self, cx: &mut Context<Self>) {
+        self.playing = true;
+        self.error = None;
+        self.speak_current(cx);
+    }
+
+    pub fn pause(&mut self, cx: &mut Context<Self>) {
+        self.playing = false;
+        self.stop_speaking();
+        cx.notify();
+    }
+
+    fn toggle_play(&mut self, cx: &mut Context<Self>) {
+        if self.playing {
+            self.pause(cx);
+        } else {
+            self.play(cx);
+        }
+    }
+
+    // 依次换到下一档语速，正在读的句子用新语速重读
+    fn next_rate(&mut self, cx: &mut Context<Self>) {
+        let rate = AppSettings::get(cx).speech.rate;
+        let next = SPEECH_RATES
+            .iter()
+            .copied()
+            .find(|&candidate| candidate > rate + f32::EPSILON)
+            .unwrap_or(SPEECH_RATES[0]);
+        AppSettings::update(cx, |s| s.speech.rate = next);
+        if self.playing {
+            self.speak_current(cx);
+        }
+        cx.notify();
+    }
+
+    // 从这一行的第一句开始读
+    fn jump_to_line(&mut self, line: usize, cx: &mut Context<Self>) {
+        let Some(range) = self.lines.get(line) else {
+            return;
+        };
+        let Some(ix) = self
+            .sentences
+            .iter()
+            .position(|sentence| sentence.start >= range.start && sentence.end <= range.end)
+        else {
+            return;
+        };
+        self.current = ix;
+        self.play(cx);
+    }
+
+    fn speak_current(&mut self, cx: &mut Context<Self>) {
+        self.stop_speaking();
+        let Some(range) = self.sentences.get(self.current).cloned() else {
+            self.playing = false;
+            self.current = 0;
+            cx.emit(ReadAloudEvent::Closed);
+            return;
+        };
+        let text = speech::speakable(&self.text[range.clone()]);
+        match speech::speak(&text, &AppSettings::get(cx).speech) {
+            Ok(child) => self.child = Some(child),
+            Err(e) => {
+                self.playing = false;
+                self.error = Some(format!("{:#}", e));
+            }
+        }
+        if let Some(line) = self
+            .lines
+            .iter()
+            .position(|line| line.start <= range.start && range.end <= line.end)
+        {
+            self.scroll.scroll_to_item(line);
+        }
+        cx.notify();
+    }
+
+    // 当前句子读完时接着读下一句
+    fn poll(&mut self, cx: &mut Context<Self>) {
+        let Some(child) = self.child.as_mut() else {
+            return;
+        };
+        match child.try_wait() {
+            Ok(None) => {}
+            Ok(Some(status)) if status.success() => {
+                self.child = None;
+                self.current += 1;
+                self.speak_current(cx);
+            }
+            Ok(Some(_)) | Err(_) => {
+                self.child = None;
+                self.playing = false;
+                self.error = Some("语音引擎异常退出，请在设置中检查朗读命令".to_string());
+                cx.notify();
+            }
+        }
+    }
+
+    fn stop_speaking(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            child.kill().ok();
+            child.wait().ok();
+        }
+    }
+
+    fn render_line(&self, ix: usize, range: &Range<usize>, highlight: Hsla) -> AnyElement {
+        let text = &self.text[range.clone()];
+        if text.trim().is_empty() {
+            return div().h(rems(1.0)).into_any_element();
+        }
+        let highlights = self
+            .sentences
+            .get(self.current)
+            .filter(|sentence| range.start <= sentence.start && sentence.end <= range.end)
+            .map(|sentence| {
+                let style = HighlightStyle {
+                    background_color: Some(highlight),
+                    ..Default::default()
+                };
+                (
+                    sentence.start - range.start..sentence.end - range.start,
+                    style,
+                )
+            });
+        div()
+            .id(("read-aloud-line", ix))
+            .cursor_pointer()
+            .child(StyledText::new(text.to_string()).with_highlights(highlights))
+            .into_any_element()
+    }
+}
+
+impl Drop for ReadAloudView {
+    fn drop(&mut self) {
+        self.stop_speaking();
+    }
+}
+
+impl EventEmitter<ReadAloudEvent> for ReadAloudView {}
+
+impl Render for ReadAloudView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let highlight = AppSettings::get(cx).preview.highlight_color();
+        let rate = AppSettings::get(cx).speech.rate;
+        let progress = if self.sentences.is_empty() {
+            "没有可朗读的文字".to_string()
+        } else {
+            format!("第 {}/{} 句", self.current + 1, self.sentences.len())
+        };
+
+        v_flex()
+            .flex_1()
+            .overflow_hidden()
+            .child(
+                h_flex()
+                    .px_6()
+                    .py_2()
+                    .gap_2()
+                    .border_b_1()
+                    .border_color(gpui::rgb(0xe5e7eb))
+                    .child(
+                        Button::new("read-aloud-play")
+                            .label(if self.playing {
+                                "⏸ 暂停"
+                            } else {
+                                "▶ 朗读"
+                            })
+                            .compact()
+                            .primary()
+                            .on_click(cx.listener(|this, _, _window, cx| this.toggle_play(cx))),
+                    )
+                    .child(
+                        Button::new("read-aloud-rate")
+                            .label(format!("语速 {}×", rate))
+                            .compact()
+                            .on_click(cx.listener(|this, _, _window, cx| this.next_rate(cx))),
+                    )
+                    .child(
+                        div()
+                            .flex_1()
+                            .text_sm()
+                            .text_color(gpui::rgb(0x6b7280))
+                            .child(progress),
+                    )
+                    .child(
+                        Button::new("read-aloud-close")
+                            .label("退出朗读")
+                            .compact()
+                            .on_click(cx.listener(|_, _, _window, cx| {
+                                cx.emit(ReadAloudEvent::Closed);
+                            })),
+                    ),
+            )
+            .when_some(self.error.clone(), |this, error| {
+                this.child(
+                    div()
+                        .px_6()
+                        .py_2()
+                        .text_sm()
+                        .text_color(gpui::rgb(0xdc2626))
+                        .child(error),
+                )
+            })
+            .child(
+                div()
+                    .id("read-aloud-text")
+                    .flex_1()
+                    .px_6()
+                    .py_4()
+                    .overflow_y_scroll()
+                    .track_scroll(&self.scroll)
+                    .children(self.lines.iter().enumerate().map(|(ix, range)| {
+                        let line = self.render_line(ix, range, highlight);
+                        div()
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(move |this, _, _window, cx| this.jump_to_line(ix, cx)),
+                            )
+                            .child(line)
+                    })),
+            )
+    }
+}
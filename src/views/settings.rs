@@ -0,0 +1,977 @@
+use crate::{
+    actions::{ExportSettings, ImportSettings},
+    card::CardTheme,
+    diagnostics,
+    note::DEFAULT_PREVIEW_CHARS,
+    search_index,
+    settings::{
+        AppSettings, DEFAULT_HIGHLIGHT_COLOR, DEFAULT_TIME_FORMAT, LockSettings, NoteSort,
+        is_valid_time_format, parse_color,
+    },
+};
+use gpui::{prelude::FluentBuilder as _, *};
+use gpui_component::{
+    button::{Button, ButtonVariants as _},
+    h_flex,
+    input::{Input, InputState},
+    setting::{NumberFieldOptions, SettingField, SettingGroup, SettingItem, SettingPage, Settings},
+    v_flex,
+};
+
+pub enum SettingsEvent {
+    Close,
+}
+
+pub struct SettingsView {
+    font_options: Vec<(SharedString, SharedString)>,
+    /// 应用锁的新密码，点“设置密码”后才保存
+    password: Entity<InputState>,
+}
+
+impl SettingsView {
+    pub fn new(window: &mut Window, cx: &mut Context<Self>) -> Self {
+        // 系统字体列表查询较慢，打开设置时取一次即可
+        let font_options = cx
+            .text_system()
+            .all_font_names()
+            .into_iter()
+            .map(|name| {
+                let name = SharedString::from(name);
+                (name.clone(), name)
+            })
+            .collect();
+        let password = cx.new(|cx| {
+            InputState::new(window, cx)
+                .masked(true)
+                .placeholder("新密码")
+        });
+        Self {
+            font_options,
+            password,
+        }
+    }
+
+    fn board_page(&self) -> SettingPage {
+        SettingPage::new("看板").group(
+            SettingGroup::new().title("列").item(
+                SettingItem::new(
+                    "状态标签",
+                    SettingField::input(
+                        |cx| AppSettings::get(cx).board.columns.join(", ").into(),
+                        |value, cx| {
+                            let columns = parse_list(&value);
+                            AppSettings::update(cx, |s| s.board.columns = columns)
+                        },
+                    )
+                    .default_value(AppSettings::default().board.columns.join(", ")),
+                )
+                .description("每个标签对应看板中的一列，拖动卡片会替换笔记上的状态标签"),
+            ),
+        )
+    }
+
+    fn api_page(&self) -> SettingPage {
+        SettingPage::new("本机接口").group(
+            SettingGroup::new()
+                .title("本机 HTTP 接口")
+                .item(
+                    SettingItem::new(
+                        "启用",
+                        SettingField::switch(
+                            |cx| AppSettings::get(cx).api.enabled,
+                            |value, cx| AppSettings::update(cx, |s| s.api.enabled = value),
+                        ),
+                    )
+                    .description("在 http://127.0.0.1:<端口> 提供 /notes、/search 和 /clip 接口，供脚本、浏览器扩展和家庭自动化读写笔记，重启后生效"),
+                )
+                .item(SettingItem::new(
+                    "端口",
+                    SettingField::number_input(
+                        NumberFieldOptions {
+                            min: 1024.0,
+                            max: 65535.0,
+                            step: 1.0,
+                        },
+                        |cx| AppSettings::get(cx).api.port as f64,
+                        |value, cx| AppSettings::update(cx, |s| s.api.port = value as u16),
+                    )
+                    .default_value(AppSettings::default().api.port as f64),
+                ))
+                .item(
+                    SettingItem::new(
+                        "访问令牌",
+                        SettingField::input(
+                            |cx| AppSettings::get(cx).api.token.clone().into(),
+                            |value, cx| {
+                                let token = value.trim().to_string();
+                                AppSettings::update(cx, |s| s.api.token = token)
+                            },
+                        ),
+                    )
+                    .description("请求头需携带 Authorization: Bearer <令牌>"),
+                ),
+        )
+    }
+
+    fn ai_page(&self) -> SettingPage {
+        let defaults = AppSettings::default().ai;
+
+        SettingPage::new("AI").group(
+            SettingGroup::new()
+                .title("AI 助手")
+                .item(
+                    SettingItem::new(
+                        "启用",
+                        SettingField::switch(
+                            |cx| AppSettings::get(cx).ai.enabled,
+                            |value, cx| AppSettings::update(cx, |s| s.ai.enabled = value),
+                        ),
+                    )
+                    .description("开启后可在编辑菜单中总结笔记、建议标签和生成标题，只在执行这些命令时发送当前笔记"),
+                )
+                .item(
+                    SettingItem::new(
+                        "接口地址",
+                        SettingField::input(
+                            |cx| AppSettings::get(cx).ai.endpoint.clone().into(),
+                            |value, cx| {
+                                let endpoint = value.trim().to_string();
+                                AppSettings::update(cx, |s| s.ai.endpoint = endpoint)
+                            },
+                        )
+                        .default_value(defaults.endpoint),
+                    )
+                    .description("任意 OpenAI 兼容接口，本地模型可填 http://localhost:11434/v1"),
+                )
+                .item(SettingItem::new(
+                    "API 密钥",
+                    SettingField::input(
+                        |cx| AppSettings::get(cx).ai.api_key.clone().into(),
+                        |value, cx| {
+                            let key = value.trim().to_string();
+                            AppSettings::update(cx, |s| s.ai.api_key = key)
+                        },
+                    ),
+                ))
+                .item(SettingItem::new(
+                    "模型",
+                    SettingField::input(
+                        |cx| AppSettings::get(cx).ai.model.clone().into(),
+                        |value, cx| {
+                            let model = value.trim().to_string();
+                            AppSettings::update(cx, |s| s.ai.model = model)
+                        },
+                    )
+                    .default_value(defaults.model),
+                )),
+        )
+    }
+
+    fn publish_page(&self) -> SettingPage {
+        SettingPage::new("发布").group(
+            SettingGroup::new()
+                .title("GitHub Gist")
+                .description("文件 → 发布为链接 把当前笔记上传为 Gist，没有安装本应用的人也能通过链接查看")
+                .item(
+                    SettingItem::new(
+                        "GitHub 令牌",
+                        SettingField::input(
+                            |cx| AppSettings::get(cx).publish.token.clone().into(),
+                            |value, cx| {
+                                let token = value.trim().to_string();
+                                AppSettings::update(cx, |s| s.publish.token = token)
+                            },
+                        ),
+                    )
+                    .description("需要 gist 权限，在 GitHub 设置 → Developer settings → Personal access tokens 中创建"),
+                )
+                .item(
+                    SettingItem::new(
+                        "公开发布",
+                        SettingField::switch(
+                            |cx| AppSettings::get(cx).publish.public,
+                            |value, cx| AppSettings::update(cx, |s| s.publish.public = value),
+                        ),
+                    )
+                    .description("关闭时只有拿到链接的人能看到；只影响之后第一次发布的笔记"),
+                )
+                .item(
+                    SettingItem::new(
+                        "curl 路径",
+                        SettingField::input(
+                            |cx| AppSettings::get(cx).publish.curl.clone().into(),
+                            |value, cx| {
+                                let curl = value.trim().to_string();
+                                AppSettings::update(cx, |s| s.publish.curl = curl)
+                            },
+                        )
+                        .default_value(AppSettings::default().publish.curl),
+                    )
+                    .description("不在 PATH 中时填写完整路径"),
+                ),
+        )
+    }
+
+    fn read_later_page(&self) -> SettingPage {
+        SettingPage::new("稍后阅读").group(
+            SettingGroup::new()
+                .title("网页剪藏")
+                .description("文件 → 稍后阅读 保存剪贴板中网址对应的网页，也可以打开 notes://read-later?url=<网址> 链接。正文转成 Markdown 存为带 read-later 标签的笔记")
+                .item(
+                    SettingItem::new(
+                        "下载图片",
+                        SettingField::switch(
+                            |cx| AppSettings::get(cx).read_later.download_images,
+                            |value, cx| {
+                                AppSettings::update(cx, |s| s.read_later.download_images = value)
+                            },
+                        ),
+                    )
+                    .description("把正文中的图片保存为附件，网页删除后也能查看；关闭时引用原网址"),
+                )
+                .item(
+                    SettingItem::new(
+                        "curl 路径",
+                        SettingField::input(
+                            |cx| AppSettings::get(cx).read_later.curl.clone().into(),
+                            |value, cx| {
+                                let curl = value.trim().to_string();
+                                AppSettings::update(cx, |s| s.read_later.curl = curl)
+                            },
+                        )
+                        .default_value(AppSettings::default().read_later.curl),
+                    )
+                    .description("不在 PATH 中时填写完整路径"),
+                ),
+        )
+    }
+
+    fn save_page(&self) -> SettingPage {
+        SettingPage::new("保存").group(
+            SettingGroup::new()
+                .title("保存")
+                .item(
+                    SettingItem::new(
+                        "只手动保存",
+                        SettingField::switch(
+                            |cx| AppSettings::get(cx).save.manual,
+                            |value, cx| AppSettings::update(cx, |s| s.save.manual = value),
+                        ),
+                    )
+                    .description("只在按 Ctrl/⌘+S 时保存，有未保存的更改时标题前显示圆点，退出前会询问"),
+                )
+                .item(
+                    SettingItem::new(
+                        "自动保存间隔（秒）",
+                        SettingField::number_input(
+                            NumberFieldOptions {
+                                min: 0.0,
+                                max: 600.0,
+                                step: 1.0,
+                            },
+                            |cx| AppSettings::get(cx).save.autosave_seconds as f64,
+                            |value, cx| {
+                                AppSettings::update(cx, |s| s.save.autosave_seconds = value as u32)
+                            },
+                        )
+                        .default_value(0.0),
+                    )
+                    .description("停止输入多少秒后自动保存，填 0 随输入保存。切换笔记、切到别的应用和退出时总会保存"),
+                ),
+        )
+    }
+
+    fn lock_page(&self) -> SettingPage {
+        let password = self.password.clone();
+        SettingPage::new("应用锁").group(
+            SettingGroup::new()
+                .title("应用锁")
+                .item(
+                    SettingItem::new(
+                        "密码",
+                        SettingField::render(move |_, _window, cx| {
+                            let enabled = AppSettings::get(cx).lock.is_enabled();
+                            let input = password.clone();
+                            h_flex()
+                                .gap_2()
+                                .child(Input::new(&password).w(px(180.0)))
+                                .child(
+                                    Button::new("set-lock-password")
+                                        .label(if enabled { "修改密码" } else { "设置密码" })
+                                        .on_click(move |_, window, cx| {
+                                            let value = input.read(cx).value().to_string();
+                                            if value.is_empty() {
+                                                return;
+                                            }
+                                            input.update(cx, |input, cx| {
+                                                input.set_value("", window, cx)
+                                            });
+                                            // 密钥派生较慢，在后台算好摘要再保存
+                                            let hashing = cx.background_executor().spawn(
+                                                async move {
+                                                    let mut lock = LockSettings::default();
+                                                    lock.set_password(&value);
+                                                    lock
+                                                },
+                                            );
+                                            window
+                                                .spawn(cx, async move |cx| {
+                                                    let lock = hashing.await;
+                                                    cx.update(|_, cx| {
+                                                        AppSettings::update(cx, |s| {
+                                                            s.lock.copy_password(&lock)
+                                                        })
+                                                    })
+                                                    .ok();
+                                                })
+                                                .detach();
+                                        }),
+                                )
+                                .when(enabled, |this| {
+                                    this.child(
+                                        Button::new("clear-lock-password")
+                                            .label("关闭应用锁")
+                                            .danger()
+                                            .on_click(|_, _, cx| {
+                                                AppSettings::update(cx, |s| {
+                                                    s.lock.set_password("")
+                                                })
+                                            }),
+                                    )
+                                })
+                        }),
+                    )
+                    .description("设置密码后，启动时和空闲一段时间后需要输入密码才能查看笔记。暂不支持指纹或面容解锁"),
+                )
+                .item(
+                    SettingItem::new(
+                        "空闲锁定（分钟）",
+                        SettingField::number_input(
+                            NumberFieldOptions {
+                                min: 0.0,
+                                max: 240.0,
+                                step: 1.0,
+                            },
+                            |cx| AppSettings::get(cx).lock.idle_minutes as f64,
+                            |value, cx| {
+                                AppSettings::update(cx, |s| s.lock.idle_minutes = value as u32)
+                            },
+                        )
+                        .default_value(0.0),
+                    )
+                    .description("多少分钟没有操作后自动锁定，填 0 只在启动时锁定"),
+                ),
+        )
+    }
+
+    fn trash_page(&self) -> SettingPage {
+        SettingPage::new("回收站").group(
+            SettingGroup::new().title("自动清理").item(
+                SettingItem::new(
+                    "保留天数",
+                    SettingField::number_input(
+                        NumberFieldOptions {
+                            min: 0.0,
+                            max: 3650.0,
+                            step: 1.0,
+                        },
+                        |cx| AppSettings::get(cx).trash.retention_days as f64,
+                        |value, cx| {
+                            AppSettings::update(cx, |s| s.trash.retention_days = value as u32)
+                        },
+                    )
+                    .default_value(AppSettings::default().trash.retention_days as f64),
+                )
+                .description("删除的笔记在回收站中保留的天数，到期后永久删除，填 0 一直保留。启动时和之后每天清理一次"),
+            ),
+        )
+    }
+
+    fn diagnostics_page(&self) -> SettingPage {
+        SettingPage::new("诊断").group(
+            SettingGroup::new().title("诊断日志").item(
+                SettingItem::new(
+                    "记录诊断日志",
+                    SettingField::switch(
+                        |cx| AppSettings::get(cx).diagnostics.enabled,
+                        |value, cx| AppSettings::update(cx, |s| s.diagnostics.enabled = value),
+                    ),
+                )
+                .description(match diagnostics::log_path() {
+                    Some(path) => format!(
+                        "记录存储错误、各项操作耗时和同步事件，写入 {}，可在 视图 → 诊断日志 中查看和复制",
+                        path.display()
+                    ),
+                    None => "记录存储错误、各项操作耗时和同步事件".to_string(),
+                }),
+            ),
+        )
+    }
+
+    fn search_page(&self) -> SettingPage {
+        SettingPage::new("系统搜索").group(
+            SettingGroup::new()
+                .title("系统搜索")
+                .item(
+                    SettingItem::new(
+                        "在系统搜索中显示笔记",
+                        SettingField::switch(
+                            |cx| AppSettings::get(cx).search_index.enabled,
+                            |value, cx| AppSettings::update(cx, |s| s.search_index.enabled = value),
+                        ),
+                    )
+                    .description("把每篇笔记镜像为一个网页文件，Spotlight 和 Windows 搜索可以按标题和正文找到，打开后跳回本应用"),
+                )
+                .item(
+                    SettingItem::new(
+                        "索引目录",
+                        SettingField::input(
+                            |cx| AppSettings::get(cx).search_index.dir.clone().into(),
+                            |value, cx| {
+                                let dir = value.trim().to_string();
+                                AppSettings::update(cx, |s| s.search_index.dir = dir)
+                            },
+                        ),
+                    )
+                    .description(format!(
+                        "留空时使用 {}，目录需在系统搜索的索引范围内",
+                        search_index::default_dir().display()
+                    )),
+                ),
+        )
+    }
+
+    fn export_page(&self) -> SettingPage {
+        let defaults = AppSettings::default().pandoc;
+
+        SettingPage::new("导出")
+            .group(
+                SettingGroup::new()
+                    .title("Pandoc")
+                    .item(
+                        SettingItem::new(
+                            "pandoc 路径",
+                            SettingField::input(
+                                |cx| AppSettings::get(cx).pandoc.path.clone().into(),
+                                |value, cx| {
+                                    let path = value.trim().to_string();
+                                    AppSettings::update(cx, |s| s.pandoc.path = path)
+                                },
+                            )
+                            .default_value(defaults.path),
+                        )
+                        .description(
+                            "导出 DOCX、EPUB 和 LaTeX 需要安装 pandoc，不在 PATH 中时填写完整路径",
+                        ),
+                    )
+                    .item(
+                        SettingItem::new(
+                            "DOCX 参数",
+                            SettingField::input(
+                                |cx| AppSettings::get(cx).pandoc.docx_args.clone().into(),
+                                |value, cx| {
+                                    AppSettings::update(cx, |s| {
+                                        s.pandoc.docx_args = value.to_string()
+                                    })
+                                },
+                            )
+                            .default_value(defaults.docx_args),
+                        )
+                        .description("例如 --reference-doc=论文模板.docx，多个参数用空格分隔"),
+                    )
+                    .item(
+                        SettingItem::new(
+                            "EPUB 参数",
+                            SettingField::input(
+                                |cx| AppSettings::get(cx).pandoc.epub_args.clone().into(),
+                                |value, cx| {
+                                    AppSettings::update(cx, |s| {
+                                        s.pandoc.epub_args = value.to_string()
+                                    })
+                                },
+                            )
+                            .default_value(defaults.epub_args),
+                        )
+                        .description("例如 --css=epub.css --epub-cover-image=封面.png"),
+                    )
+                    .item(
+                        SettingItem::new(
+                            "LaTeX 参数",
+                            SettingField::input(
+                                |cx| AppSettings::get(cx).pandoc.latex_args.clone().into(),
+                                |value, cx| {
+                                    AppSettings::update(cx, |s| {
+                                        s.pandoc.latex_args = value.to_string()
+                                    })
+                                },
+                            )
+                            .default_value(defaults.latex_args),
+                        )
+                        .description(
+                            "例如 --template=thesis.tex --citeproc --bibliography=refs.bib",
+                        ),
+                    )
+                    .item(
+                        SettingItem::new(
+                            "HTML 参数",
+                            SettingField::input(
+                                |cx| AppSettings::get(cx).pandoc.html_args.clone().into(),
+                                |value, cx| {
+                                    AppSettings::update(cx, |s| {
+                                        s.pandoc.html_args = value.to_string()
+                                    })
+                                },
+                            )
+                            .default_value(defaults.html_args),
+                        )
+                        .description("例如 --toc --number-sections"),
+                    ),
+            )
+            .group(
+                SettingGroup::new()
+                    .title("图片卡片")
+                    .item(SettingItem::new(
+                        "配色",
+                        SettingField::dropdown(
+                            CardTheme::ALL
+                                .iter()
+                                .map(|theme| (theme_key(*theme), theme.label().into()))
+                                .collect(),
+                            |cx| theme_key(AppSettings::get(cx).card.theme),
+                            |value, cx| {
+                                let theme = CardTheme::ALL
+                                    .into_iter()
+                                    .find(|theme| theme_key(*theme) == value)
+                                    .unwrap_or_default();
+                                AppSettings::update(cx, |s| s.card.theme = theme)
+                            },
+                        )
+                        .default_value(theme_key(CardTheme::default())),
+                    ))
+                    .item(
+                        SettingItem::new(
+                            "显示水印",
+                            SettingField::switch(
+                                |cx| AppSettings::get(cx).card.watermark,
+                                |value, cx| AppSettings::update(cx, |s| s.card.watermark = value),
+                            ),
+                        )
+                        .description("在“导出为图片”生成的卡片右下角显示应用名"),
+                    ),
+            )
+    }
+
+    fn attachments_page(&self) -> SettingPage {
+        let defaults = AppSettings::default().ocr;
+        let voice = AppSettings::default().voice;
+
+        SettingPage::new("附件")
+            .group(
+                SettingGroup::new()
+                    .title("图片文字识别")
+                    .item(
+                        SettingItem::new(
+                            "识别图片中的文字",
+                            SettingField::switch(
+                                |cx| AppSettings::get(cx).ocr.enabled,
+                                |value, cx| AppSettings::update(cx, |s| s.ocr.enabled = value),
+                            ),
+                        )
+                        .description(
+                            "拖入图片附件时用 tesseract 识别其中的文字，截图也能按内容搜索到",
+                        ),
+                    )
+                    .item(
+                        SettingItem::new(
+                            "tesseract 路径",
+                            SettingField::input(
+                                |cx| AppSettings::get(cx).ocr.path.clone().into(),
+                                |value, cx| {
+                                    let path = value.trim().to_string();
+                                    AppSettings::update(cx, |s| s.ocr.path = path)
+                                },
+                            )
+                            .default_value(defaults.path),
+                        )
+                        .description("不在 PATH 中时填写完整路径"),
+                    )
+                    .item(
+                        SettingItem::new(
+                            "识别语言",
+                            SettingField::input(
+                                |cx| AppSettings::get(cx).ocr.languages.clone().into(),
+                                |value, cx| {
+                                    let languages = value.trim().to_string();
+                                    AppSettings::update(cx, |s| s.ocr.languages = languages)
+                                },
+                            )
+                            .default_value(defaults.languages),
+                        )
+                        .description("多个语言用 + 连接，需先安装对应的 tesseract 语言包"),
+                    ),
+            )
+            .group(
+                SettingGroup::new()
+                    .title("语音笔记")
+                    .item(
+                        SettingItem::new(
+                            "ffmpeg 路径",
+                            SettingField::input(
+                                |cx| AppSettings::get(cx).voice.ffmpeg.clone().into(),
+                                |value, cx| {
+                                    let path = value.trim().to_string();
+                                    AppSettings::update(cx, |s| s.voice.ffmpeg = path)
+                                },
+                            )
+                            .default_value(voice.ffmpeg),
+                        )
+                        .description("编辑器中的录音按钮通过 ffmpeg 录制麦克风"),
+                    )
+                    .item(
+                        SettingItem::new(
+                            "录音输入参数",
+                            SettingField::input(
+                                |cx| AppSettings::get(cx).voice.input_args.clone().into(),
+                                |value, cx| {
+                                    AppSettings::update(cx, |s| {
+                                        s.voice.input_args = value.to_string()
+                                    })
+                                },
+                            )
+                            .default_value(voice.input_args),
+                        )
+                        .description("传给 ffmpeg 的音频设备参数，录不到声音时按系统修改"),
+                    )
+                    .item(
+                        SettingItem::new(
+                            "转写录音",
+                            SettingField::switch(
+                                |cx| AppSettings::get(cx).voice.transcribe,
+                                |value, cx| AppSettings::update(cx, |s| s.voice.transcribe = value),
+                            ),
+                        )
+                        .description("录音结束后在后台转写为文字，转写结果可被搜索"),
+                    )
+                    .item(
+                        SettingItem::new(
+                            "转写命令",
+                            SettingField::input(
+                                |cx| AppSettings::get(cx).voice.transcribe_command.clone().into(),
+                                |value, cx| {
+                                    let command = value.trim().to_string();
+                                    AppSettings::update(cx, |s| {
+                                        s.voice.transcribe_command = command
+                                    })
+                                },
+                            )
+                            .default_value(voice.transcribe_command),
+                        )
+                        .description("{file} 会替换为录音文件路径，命令需把文字输出到标准输出"),
+                    ),
+            )
+            .group(
+                SettingGroup::new().title("朗读").item(
+                    SettingItem::new(
+                        "朗读命令",
+                        SettingField::input(
+                            |cx| AppSettings::get(cx).speech.command.clone().into(),
+                            |value, cx| {
+                                let command = value.trim().to_string();
+                                AppSettings::update(cx, |s| s.speech.command = command)
+                            },
+                        ),
+                    )
+                    .description(
+                        "留空时使用系统语音（macOS 的 say、Windows 的 SAPI、Linux 的 espeak-ng）；\
+                         自定义命令从标准输入读取文字，{wpm} 会替换为每分钟字数",
+                    ),
+                ),
+            )
+    }
+
+    fn font_page(&self) -> SettingPage {
+        let fallbacks_default = AppSettings::default().fonts.fallbacks.join(", ");
+
+        SettingPage::new("外观")
+            .default_open(true)
+            .group(
+                SettingGroup::new()
+                    .title("字体")
+                    .item(SettingItem::new(
+                        "界面字体",
+                        SettingField::dropdown(
+                            self.font_options.clone(),
+                            |cx| AppSettings::get(cx).fonts.ui_family.clone().into(),
+                            |value, cx| {
+                                AppSettings::update(cx, |s| s.fonts.ui_family = value.to_string())
+                            },
+                        )
+                        .default_value(AppSettings::default().fonts.ui_family),
+                    ))
+                    .item(SettingItem::new(
+                        "编辑器字体",
+                        SettingField::dropdown(
+                            self.font_options.clone(),
+                            |cx| AppSettings::get(cx).fonts.editor_family.clone().into(),
+                            |value, cx| {
+                                AppSettings::update(cx, |s| {
+                                    s.fonts.editor_family = value.to_string()
+                                })
+                            },
+                        )
+                        .default_value(AppSettings::default().fonts.editor_family),
+                    ))
+                    .item(SettingItem::new(
+                        "编辑器字号",
+                        SettingField::number_input(
+                            NumberFieldOptions {
+                                min: 10.0,
+                                max: 32.0,
+                                step: 1.0,
+                            },
+                            |cx| AppSettings::get(cx).fonts.editor_size as f64,
+                            |value, cx| {
+                                AppSettings::update(cx, |s| s.fonts.editor_size = value as f32)
+                            },
+                        )
+                        .default_value(AppSettings::default().fonts.editor_size as f64),
+                    ))
+                    .item(
+                        SettingItem::new(
+                            "中文后备字体",
+                            SettingField::input(
+                                |cx| AppSettings::get(cx).fonts.fallbacks.join(", ").into(),
+                                |value, cx| {
+                                    let fallbacks = parse_list(&value);
+                                    AppSettings::update(cx, |s| s.fonts.fallbacks = fallbacks)
+                                },
+                            )
+                            .default_value(fallbacks_default),
+                        )
+                        .description("主字体缺少的字符按顺序从这些字体中查找，用逗号分隔"),
+                    ),
+            )
+            .group(
+                SettingGroup::new()
+                    .title("侧边栏")
+                    .item(SettingItem::new(
+                        "排序方式",
+                        SettingField::dropdown(
+                            NoteSort::ALL
+                                .iter()
+                                .map(|sort| (sort_key(*sort), sort.label().into()))
+                                .collect(),
+                            |cx| sort_key(AppSettings::get(cx).sidebar.sort),
+                            |value, cx| {
+                                let sort = NoteSort::ALL
+                                    .into_iter()
+                                    .find(|sort| sort_key(*sort) == value)
+                                    .unwrap_or_default();
+                                AppSettings::update(cx, |s| s.sidebar.sort = sort)
+                            },
+                        )
+                        .default_value(sort_key(NoteSort::default())),
+                    ))
+                    .item(
+                        SettingItem::new(
+                            "预览长度",
+                            SettingField::number_input(
+                                NumberFieldOptions {
+                                    min: 10.0,
+                                    max: 500.0,
+                                    step: 1.0,
+                                },
+                                |cx| AppSettings::get(cx).sidebar.preview_length as f64,
+                                |value, cx| {
+                                    AppSettings::update(cx, |s| {
+                                        s.sidebar.preview_length = value as usize
+                                    })
+                                },
+                            )
+                            .default_value(DEFAULT_PREVIEW_CHARS as f64),
+                        )
+                        .description("笔记列表中正文预览显示的字数"),
+                    )
+                    .item(
+                        SettingItem::new(
+                            "时间格式",
+                            SettingField::input(
+                                |cx| AppSettings::get(cx).sidebar.time_format.clone().into(),
+                                |value, cx| {
+                                    let format = value.trim();
+                                    // 无效的格式不保存，留空恢复默认
+                                    let format = if format.is_empty() {
+                                        DEFAULT_TIME_FORMAT
+                                    } else if is_valid_time_format(format) {
+                                        format
+                                    } else {
+                                        return;
+                                    };
+                                    let format = format.to_string();
+                                    AppSettings::update(cx, |s| s.sidebar.time_format = format)
+                                },
+                            )
+                            .default_value(DEFAULT_TIME_FORMAT),
+                        )
+                        .description("strftime 格式，例如 %m月%d日 %H:%M；无效的格式不会保存"),
+                    )
+                    .item(
+                        SettingItem::new(
+                            "显示相对时间",
+                            SettingField::switch(
+                                |cx| AppSettings::get(cx).sidebar.relative_time,
+                                |value, cx| {
+                                    AppSettings::update(cx, |s| s.sidebar.relative_time = value)
+                                },
+                            ),
+                        )
+                        .description("一周内的笔记显示为“5 分钟前”“昨天 14:30”这样的时间"),
+                    ),
+            )
+            .group(
+                SettingGroup::new()
+                    .title("预览")
+                    .item(
+                        SettingItem::new(
+                            "高亮颜色",
+                            SettingField::input(
+                                |cx| AppSettings::get(cx).preview.highlight_color.clone().into(),
+                                |value, cx| {
+                                    let color = value.trim();
+                                    // 写错的颜色不保存，留空恢复默认
+                                    let color = if color.is_empty() {
+                                        DEFAULT_HIGHLIGHT_COLOR
+                                    } else if parse_color(color).is_some() {
+                                        color
+                                    } else {
+                                        return;
+                                    };
+                                    let color = color.to_string();
+                                    AppSettings::update(cx, |s| s.preview.highlight_color = color)
+                                },
+                            )
+                            .default_value(DEFAULT_HIGHLIGHT_COLOR),
+                        )
+                        .description("==文字== 在预览中的背景色，例如 #fde68a"),
+                    )
+                    .item(
+                        SettingItem::new(
+                            "列出子笔记",
+                            SettingField::switch(
+                                |cx| AppSettings::get(cx).preview.child_index,
+                                |value, cx| {
+                                    AppSettings::update(cx, |s| s.preview.child_index = value)
+                                },
+                            )
+                            .default_value(true),
+                        )
+                        .description("有子笔记的笔记在预览末尾显示子笔记目录"),
+                    )
+                    .item(
+                        SettingItem::new(
+                            "样式表",
+                            SettingField::input(
+                                |cx| AppSettings::get(cx).preview.stylesheet.clone().into(),
+                                |value, cx| {
+                                    let path = value.trim().to_string();
+                                    AppSettings::update(cx, |s| s.preview.stylesheet = path)
+                                },
+                            ),
+                        )
+                        .description(
+                            "CSS 文件的完整路径，预览使用其中 body、h1–h6、pre/code、blockquote 的字体、字号和颜色；\
+                             导出 HTML 和 EPUB 时原样带上。留空使用默认样式",
+                        ),
+                    ),
+            )
+    }
+}
+
+// 下拉框的值，与设置文件中的写法一致
+fn sort_key(sort: NoteSort) -> SharedString {
+    serde_key(sort)
+}
+
+fn theme_key(theme: CardTheme) -> SharedString {
+    serde_key(theme)
+}
+
+fn serde_key(value: impl serde::Serialize) -> SharedString {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(|s| s.to_string()))
+        .unwrap_or_default()
+        .into()
+}
+
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split([',', '，'])
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+impl EventEmitter<SettingsEvent> for SettingsView {}
+
+impl Render for SettingsView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        v_flex()
+            .size_full()
+            .bg(gpui::rgb(0xffffff))
+            .child(
+                div()
+                    .px_6()
+                    .py_3()
+                    .border_b_1()
+                    .border_color(gpui::rgb(0xe5e7eb))
+                    .flex()
+                    .flex_row()
+                    .items_center()
+                    .justify_between()
+                    .child(
+                        div()
+                            .text_lg()
+                            .font_weight(FontWeight::SEMIBOLD)
+                            .child("设置"),
+                    )
+                    .child(
+                        h_flex()
+                            .gap_2()
+                            .child(Button::new("import-settings").label("导入…").on_click(
+                                |_, window, cx| {
+                                    window.dispatch_action(ImportSettings.boxed_clone(), cx)
+                                },
+                            ))
+                            .child(Button::new("export-settings").label("导出…").on_click(
+                                |_, window, cx| {
+                                    window.dispatch_action(ExportSettings.boxed_clone(), cx)
+                                },
+                            ))
+                            .child(Button::new("close-settings").label("返回").on_click(
+                                cx.listener(|_, _, _window, cx| {
+                                    cx.emit(SettingsEvent::Close);
+                                }),
+                            )),
+                    ),
+            )
+            .child(
+                div().flex_1().overflow_hidden().child(
+                    Settings::new("app-settings")
+                        .page(self.font_page())
+                        .page(self.save_page())
+                        .page(self.lock_page())
+                        .page(self.board_page())
+                        .page(self.attachments_page())
+                        .page(self.trash_page())
+                        .page(self.export_page())
+                        .page(self.ai_page())
+                        .page(self.publish_page())
+                        .page(self.read_later_page())
+                        .page(self.search_page())
+                        .page(self.api_page())
+                        .page(self.diagnostics_page()),
+                ),
+            )
+    }
+}
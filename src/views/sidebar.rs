@@ -1,28 +1,277 @@
-use crate::note_list::NoteList;
+use crate::{
+    actions::{FocusEditor, SIDEBAR_CONTEXT, SelectNextNote, SelectPrevNote},
+    note::{self, Note, TreeEntry},
+    note_index::{NoteIndex, SummariesReady},
+    note_list::{NoteList, NoteListEvent},
+    settings::{AppSettings, SidebarSettings},
+};
+use chrono::Utc;
 use gpui::*;
-use gpui_component::{button::Button, v_flex};
+use gpui_component::{button::Button, h_flex, v_flex};
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
 
+/// 显示相对时间时刷新“N 分钟前”的间隔
+const RELATIVE_TIME_REFRESH: Duration = Duration::from_secs(60);
+
+/// 子笔记每深一层向右缩进的距离
+const INDENT: f32 = 16.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum SidebarEvent {
     CreateNote,
+    /// 在这篇笔记下面新建子笔记
+    CreateChildNote(u128),
     SelectNote(u128),
+    /// 拖到另一篇笔记上成为它的子笔记，拖到标题栏时 parent 为 None
+    MoveNote {
+        note_id: u128,
+        parent: Option<u128>,
+    },
     DeleteNote(u128),
+    OpenSettings,
+    FocusEditor,
+}
+
+// 列表中一行显示的内容，笔记变化时只重新生成这一行
+struct SidebarRow {
+    id: u128,
+    /// 按笔记 ID 生成，行和行内按钮的元素 ID 都由它派生，重新排序后点击仍落在对应笔记上
+    element_id: ElementId,
+    title: SharedString,
+    preview: SharedString,
+    time: SharedString,
+}
+
+impl SidebarRow {
+    // 预览还没在后台算好时先留空
+    fn new(note: &Note, index: &NoteIndex, settings: &SidebarSettings) -> Self {
+        Self {
+            id: note.id,
+            element_id: ElementId::Name(format!("note-row-{}", note.id).into()),
+            title: note.title.clone().into(),
+            preview: index
+                .summary(note.id)
+                .map(|summary| summary.preview.clone())
+                .unwrap_or_default(),
+            time: settings.format_time(note.updated_at, Utc::now()).into(),
+        }
+    }
+}
+
+// 拖动中的笔记行
+#[derive(Clone)]
+struct DraggedNote {
+    note_id: u128,
+    title: SharedString,
+}
+
+impl Render for DraggedNote {
+    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .w(px(200.))
+            .p_2()
+            .rounded_md()
+            .bg(gpui::rgb(0xffffff))
+            .border_1()
+            .border_color(gpui::rgb(0x6366f1))
+            .shadow_md()
+            .text_sm()
+            .child(self.title.clone())
+    }
 }
 
 pub struct SidebarView {
+    focus_handle: FocusHandle,
     notes: Entity<NoteList>,
+    index: Entity<NoteIndex>,
+    rows: HashMap<u128, SidebarRow>,
+    /// 显示顺序：按 NoteList 的顺序，子笔记排在父笔记下面，收起的子笔记不在其中
+    tree: Vec<TreeEntry>,
+    /// 收起了子笔记的笔记
+    collapsed: HashSet<u128>,
     selected_note_id: Option<u128>,
+    /// 上次生成行时使用的设置，只有预览和时间相关的设置变化才重建
+    settings: SidebarSettings,
+    _notes_changed: Subscription,
+    _summaries_ready: Subscription,
+    _settings_changed: Subscription,
+    _refresh_times: Task<()>,
 }
 
 impl SidebarView {
-    pub fn new(notes: Entity<NoteList>) -> Self {
-        Self {
+    pub fn new(notes: Entity<NoteList>, index: Entity<NoteIndex>, cx: &mut Context<Self>) -> Self {
+        let notes_changed = cx.subscribe(&notes, |this, notes, event, cx| {
+            this.on_notes_event(&notes, event, cx);
+        });
+        let summaries_ready = cx.subscribe(&index, |this, index, ready: &SummariesReady, cx| {
+            let index = index.read(cx);
+            let ready: HashSet<u128> = ready.0.iter().copied().collect();
+            for row in this.rows.values_mut() {
+                if ready.contains(&row.id)
+                    && let Some(summary) = index.summary(row.id)
+                {
+                    row.preview = summary.preview.clone();
+                }
+            }
+            cx.notify();
+        });
+        let settings_changed = cx.observe_global::<AppSettings>(|this: &mut Self, cx| {
+            let settings = &AppSettings::get(cx).sidebar;
+            if settings.time_format != this.settings.time_format
+                || settings.relative_time != this.settings.relative_time
+            {
+                this.rebuild_rows(cx);
+                cx.notify();
+            }
+        });
+        let refresh_times = cx.spawn(async move |this, cx| {
+            loop {
+                cx.background_executor().timer(RELATIVE_TIME_REFRESH).await;
+                let refreshed = this.update(cx, |this, cx| {
+                    if this.settings.relative_time {
+                        this.rebuild_rows(cx);
+                        cx.notify();
+                    }
+                });
+                if refreshed.is_err() {
+                    break;
+                }
+            }
+        });
+        let mut view = Self {
+            focus_handle: cx.focus_handle(),
+            rows: HashMap::new(),
+            tree: Vec::new(),
+            collapsed: HashSet::new(),
+            selected_note_id: notes.read(cx).selected(),
+            settings: AppSettings::get(cx).sidebar.clone(),
             notes,
-            selected_note_id: None,
+            index,
+            _notes_changed: notes_changed,
+            _summaries_ready: summaries_ready,
+            _settings_changed: settings_changed,
+            _refresh_times: refresh_times,
+        };
+        view.rebuild_rows(cx);
+        view
+    }
+
+    fn rebuild_rows(&mut self, cx: &mut Context<Self>) {
+        self.settings = AppSettings::get(cx).sidebar.clone();
+        let index = self.index.read(cx);
+        self.rows = self
+            .notes
+            .read(cx)
+            .get_all()
+            .into_iter()
+            .map(|note| (note.id, SidebarRow::new(note, index, &self.settings)))
+            .collect();
+        self.arrange(cx);
+    }
+
+    // 笔记增删、改变父笔记或展开收起后重新排列显示顺序
+    fn arrange(&mut self, cx: &mut Context<Self>) {
+        let notes = self.notes.read(cx);
+        self.collapsed.retain(|id| notes.get(*id).is_some());
+        self.tree = note::note_tree(&notes.get_all(), &self.collapsed);
+    }
+
+    fn toggle_collapsed(&mut self, note_id: u128, cx: &mut Context<Self>) {
+        if !self.collapsed.remove(&note_id) {
+            self.collapsed.insert(note_id);
+        }
+        self.arrange(cx);
+        cx.notify();
+    }
+
+    // 选中收起的子笔记时展开它的各级父笔记
+    fn reveal(&mut self, note_id: u128, cx: &mut Context<Self>) {
+        let notes = self.notes.read(cx);
+        let before = self.collapsed.len();
+        self.collapsed
+            .retain(|&id| id == note_id || !notes.is_ancestor(id, note_id));
+        if self.collapsed.len() != before {
+            self.arrange(cx);
+        }
+    }
+
+    fn on_notes_event(
+        &mut self,
+        notes: &Entity<NoteList>,
+        event: &NoteListEvent,
+        cx: &mut Context<Self>,
+    ) {
+        match *event {
+            NoteListEvent::Reloaded => self.rebuild_rows(cx),
+            NoteListEvent::NoteAdded(note_id) | NoteListEvent::NoteUpdated(note_id) => {
+                if let Some(note) = notes.read(cx).get(note_id) {
+                    let row = SidebarRow::new(note, self.index.read(cx), &self.settings);
+                    self.rows.insert(note_id, row);
+                }
+                self.arrange(cx);
+                if self.selected_note_id == Some(note_id) {
+                    self.reveal(note_id, cx);
+                }
+            }
+            NoteListEvent::NoteRemoved(note_id) => {
+                self.rows.remove(&note_id);
+                self.arrange(cx);
+            }
+            NoteListEvent::SelectionChanged(note_id) => {
+                self.selected_note_id = note_id;
+                if let Some(note_id) = note_id {
+                    self.reveal(note_id, cx);
+                }
+            }
+            NoteListEvent::WritesFailed
+            | NoteListEvent::WritesRecovered
+            | NoteListEvent::Conflict { .. } => return,
         }
+        cx.notify();
+    }
+
+    /// 按侧边栏的显示顺序，选中项的下一篇或上一篇；没有选中时取第一篇或最后一篇
+    pub fn adjacent(&self, forward: bool) -> Option<u128> {
+        let ids: Vec<u128> = self.tree.iter().map(|entry| entry.id).collect();
+        let current = self
+            .selected_note_id
+            .and_then(|id| ids.iter().position(|&other| other == id));
+        let next = match current {
+            Some(ix) if forward => ids.get(ix + 1),
+            Some(ix) => ix.checked_sub(1).and_then(|ix| ids.get(ix)),
+            None if forward => ids.first(),
+            None => ids.last(),
+        };
+        next.copied()
+    }
+
+    fn select_adjacent(&mut self, forward: bool, cx: &mut Context<Self>) {
+        if let Some(note_id) = self.adjacent(forward) {
+            cx.emit(SidebarEvent::SelectNote(note_id));
+        }
+    }
+
+    fn on_select_prev(&mut self, _: &SelectPrevNote, _window: &mut Window, cx: &mut Context<Self>) {
+        self.select_adjacent(false, cx);
     }
 
-    pub fn set_selected(&mut self, note_id: Option<u128>) {
-        self.selected_note_id = note_id;
+    fn on_select_next(&mut self, _: &SelectNextNote, _window: &mut Window, cx: &mut Context<Self>) {
+        self.select_adjacent(true, cx);
+    }
+
+    fn on_focus_editor(&mut self, _: &FocusEditor, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.selected_note_id.is_some() {
+            cx.emit(SidebarEvent::FocusEditor);
+        }
+    }
+}
+
+impl Focusable for SidebarView {
+    fn focus_handle(&self, _cx: &App) -> FocusHandle {
+        self.focus_handle.clone()
     }
 }
 
@@ -30,10 +279,12 @@ impl EventEmitter<SidebarEvent> for SidebarView {}
 
 impl Render for SidebarView {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        let notes = self.notes.read(cx);
-        let note_list = notes.get_all();
-
         v_flex()
+            .key_context(SIDEBAR_CONTEXT)
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(Self::on_select_prev))
+            .on_action(cx.listener(Self::on_select_next))
+            .on_action(cx.listener(Self::on_focus_editor))
             .h_full()
             .w(px(280.0))
             .bg(gpui::rgb(0xf9fafb))
@@ -41,10 +292,19 @@ impl Render for SidebarView {
             .border_color(gpui::rgb(0xe5e7eb))
             .child(
                 div()
+                    .id("sidebar-header")
                     .px_4()
                     .py_3()
                     .border_b_1()
                     .border_color(gpui::rgb(0xe5e7eb))
+                    // 拖到标题栏移回顶层
+                    .drag_over::<DraggedNote>(|this, _, _, _| this.bg(gpui::rgb(0xe0e7ff)))
+                    .on_drop(cx.listener(|_, dragged: &DraggedNote, _window, cx| {
+                        cx.emit(SidebarEvent::MoveNote {
+                            note_id: dragged.note_id,
+                            parent: None,
+                        });
+                    }))
                     .flex()
                     .flex_row()
                     .items_center()
@@ -55,66 +315,329 @@ impl Render for SidebarView {
                             .font_weight(FontWeight::SEMIBOLD)
                             .child("我的笔记"),
                     )
-                    .child(Button::new("new-note").label("新建").on_click(cx.listener(
-                        |_, _, _window, cx| {
-                            cx.emit(SidebarEvent::CreateNote);
-                        },
-                    ))),
+                    .child(
+                        h_flex()
+                            .gap_2()
+                            .child(Button::new("open-settings").label("设置").on_click(
+                                cx.listener(|_, _, _window, cx| {
+                                    cx.emit(SidebarEvent::OpenSettings);
+                                }),
+                            ))
+                            .child(Button::new("new-note").label("新建").on_click(cx.listener(
+                                |_, _, _window, cx| {
+                                    cx.emit(SidebarEvent::CreateNote);
+                                },
+                            ))),
+                    ),
             )
             .child(
                 v_flex()
                     .flex_1()
                     .overflow_hidden()
-                    .children(note_list.iter().map(|note| {
-                        let note_id = note.id;
+                    .children(self.tree.iter().filter_map(|entry| {
+                        let row = self.rows.get(&entry.id)?;
+                        let note_id = row.id;
                         let is_selected = self.selected_note_id == Some(note_id);
-                        div()
-                            .p_3()
-                            .border_b_1()
-                            .border_color(gpui::rgb(0xe5e7eb))
-                            .cursor_pointer()
-                            .bg(if is_selected {
-                                gpui::rgb(0xe0e7ff)
-                            } else {
-                                gpui::rgb(0xf9fafb)
-                            })
-                            .on_mouse_down(
-                                MouseButton::Left,
-                                cx.listener(move |_, _, _window, cx| {
+                        let dragged = DraggedNote {
+                            note_id,
+                            title: row.title.clone(),
+                        };
+                        let toggle = if entry.has_children {
+                            div()
+                                .id(ElementId::NamedChild(
+                                    Box::new(row.element_id.clone()),
+                                    "toggle".into(),
+                                ))
+                                .debug_selector(|| format!("toggle-note-{}", note_id))
+                                .w(px(INDENT))
+                                .text_xs()
+                                .text_color(gpui::rgb(0x6b7280))
+                                .child(if self.collapsed.contains(&note_id) {
+                                    "▸"
+                                } else {
+                                    "▾"
+                                })
+                                .on_click(cx.listener(move |this, _, _window, cx| {
+                                    cx.stop_propagation();
+                                    this.toggle_collapsed(note_id, cx);
+                                }))
+                        } else {
+                            div()
+                                .id(ElementId::NamedChild(
+                                    Box::new(row.element_id.clone()),
+                                    "toggle".into(),
+                                ))
+                                .w(px(INDENT))
+                        };
+                        Some(
+                            div()
+                                .id(row.element_id.clone())
+                                .debug_selector(|| format!("note-row-{}", note_id))
+                                .p_3()
+                                .pl(px(12.0 + INDENT * entry.depth as f32))
+                                .on_drag(dragged, |dragged: &DraggedNote, _, _, cx| {
+                                    cx.new(|_| dragged.clone())
+                                })
+                                .drag_over::<DraggedNote>(|this, _, _, _| {
+                                    this.bg(gpui::rgb(0xe0e7ff))
+                                })
+                                .on_drop(cx.listener(
+                                    move |_, dragged: &DraggedNote, _window, cx| {
+                                        if dragged.note_id != note_id {
+                                            cx.emit(SidebarEvent::MoveNote {
+                                                note_id: dragged.note_id,
+                                                parent: Some(note_id),
+                                            });
+                                        }
+                                    },
+                                ))
+                                .border_b_1()
+                                .border_color(gpui::rgb(0xe5e7eb))
+                                .cursor_pointer()
+                                .bg(if is_selected {
+                                    gpui::rgb(0xe0e7ff)
+                                } else {
+                                    gpui::rgb(0xf9fafb)
+                                })
+                                .on_click(cx.listener(move |this, _, window, cx| {
+                                    window.focus(&this.focus_handle);
                                     cx.emit(SidebarEvent::SelectNote(note_id));
-                                }),
-                            )
-                            .child(
-                                div()
-                                    .flex()
-                                    .flex_row()
-                                    .justify_between()
-                                    .items_center()
-                                    .child(
-                                        div()
-                                            .font_weight(FontWeight::MEDIUM)
-                                            .text_base()
-                                            .child(note.title.clone()),
-                                    )
-                                    .child(
-                                        Button::new("delete-note")
-                                            .label("删除")
-                                            .compact()
-                                            .on_click(cx.listener(move |_, _, _window, cx| {
-                                                cx.emit(SidebarEvent::DeleteNote(note_id));
-                                            })),
-                                    ),
-                            )
-                            .child(div().mt_1().text_sm().child(note.preview()))
-                            .child(
-                                div()
-                                    .mt_1()
-                                    .text_xs()
-                                    .text_color(gpui::rgb(0x9ca3af))
-                                    .child(note.formatted_time()),
-                            )
+                                }))
+                                .child(
+                                    div()
+                                        .flex()
+                                        .flex_row()
+                                        .justify_between()
+                                        .items_center()
+                                        .child(
+                                            h_flex().child(toggle).child(
+                                                div()
+                                                    .font_weight(FontWeight::MEDIUM)
+                                                    .text_base()
+                                                    .child(row.title.clone()),
+                                            ),
+                                        )
+                                        .child(
+                                            h_flex()
+                                                .gap_1()
+                                                .child(
+                                                    div()
+                                                        .debug_selector(|| {
+                                                            format!("new-child-note-{}", note_id)
+                                                        })
+                                                        .child(
+                                                            Button::new(ElementId::NamedChild(
+                                                                Box::new(row.element_id.clone()),
+                                                                "new-child".into(),
+                                                            ))
+                                                            .label("+")
+                                                            .compact()
+                                                            .tooltip("新建子笔记")
+                                                            .on_click(cx.listener(
+                                                                move |_, _, _window, cx| {
+                                                                    cx.stop_propagation();
+                                                                    cx.emit(
+                                                                    SidebarEvent::CreateChildNote(
+                                                                        note_id,
+                                                                    ),
+                                                                );
+                                                                },
+                                                            )),
+                                                        ),
+                                                )
+                                                .child(
+                                                    div()
+                                                        .debug_selector(|| {
+                                                            format!("delete-note-{}", note_id)
+                                                        })
+                                                        .child(
+                                                            Button::new(ElementId::NamedChild(
+                                                                Box::new(row.element_id.clone()),
+                                                                "delete".into(),
+                                                            ))
+                                                            .label("删除")
+                                                            .compact()
+                                                            .on_click(cx.listener(
+                                                                move |_, _, _window, cx| {
+                                                                    // 不再触发整行的点击，免得先打开再删除
+                                                                    cx.stop_propagation();
+                                                                    cx.emit(
+                                                                        SidebarEvent::DeleteNote(
+                                                                            note_id,
+                                                                        ),
+                                                                    );
+                                                                },
+                                                            )),
+                                                        ),
+                                                ),
+                                        ),
+                                )
+                                .child(div().mt_1().text_sm().child(row.preview.clone()))
+                                .child(
+                                    div()
+                                        .mt_1()
+                                        .text_xs()
+                                        .text_color(gpui::rgb(0x9ca3af))
+                                        .child(row.time.clone()),
+                                ),
+                        )
                     })),
             )
             .into_any_element()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    // 不能 glob 导入 gpui，否则 #[test] 会解析成 gpui::test
+    use super::{SidebarEvent, SidebarView};
+    use crate::{
+        note::Note, note_index::NoteIndex, note_list::NoteList, settings::AppSettings,
+        storage::Storage,
+    };
+    use gpui::{AppContext as _, Entity, Modifiers, TestAppContext, VisualTestContext};
+    use std::{cell::RefCell, collections::HashMap, rc::Rc};
+    use tempfile::TempDir;
+
+    struct TestSidebar {
+        _dir: TempDir,
+        cx: &'static mut VisualTestContext,
+        notes: Entity<NoteList>,
+        sidebar: Entity<SidebarView>,
+        /// 按显示顺序排列的笔记 ID
+        ids: Vec<u128>,
+        events: Rc<RefCell<Vec<SidebarEvent>>>,
+    }
+
+    impl TestSidebar {
+        // 打开只有侧边栏的窗口，列表里有 count 篇笔记
+        fn open(cx: &mut TestAppContext, count: usize) -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            let storage = Storage::with_dir(dir.path().to_path_buf()).unwrap();
+            cx.update(|cx| {
+                gpui_component::init(cx);
+                cx.set_global(AppSettings::default());
+            });
+            let notes = cx.new(|cx| NoteList::new(storage, cx));
+            let loaded: HashMap<u128, Note> = (0..count)
+                .map(|ix| {
+                    let note = Note {
+                        title: format!("笔记 {}", ix),
+                        ..Note::new()
+                    };
+                    (note.id, note)
+                })
+                .collect();
+            notes.update(cx, |notes, cx| notes.set_loaded(loaded, cx));
+            let ids = notes.read_with(cx, |notes, _| {
+                notes.get_all().iter().map(|note| note.id).collect()
+            });
+            let index = cx.new(|cx| NoteIndex::new(notes.clone(), cx));
+
+            let mut sidebar = None;
+            let window = cx.add_window(|_, cx| SidebarView::new(notes.clone(), index, cx));
+            window
+                .update(cx, |_, _, cx| sidebar = Some(cx.entity()))
+                .unwrap();
+            let events = Rc::new(RefCell::new(Vec::new()));
+            let recorded = events.clone();
+            let sidebar = sidebar.unwrap();
+            cx.update(|cx| {
+                cx.subscribe(&sidebar, move |_, event: &SidebarEvent, _| {
+                    recorded.borrow_mut().push(*event);
+                })
+                .detach();
+            });
+            let cx = VisualTestContext::from_window(window.into(), cx).into_mut();
+            cx.run_until_parked();
+            Self {
+                _dir: dir,
+                cx,
+                notes,
+                sidebar,
+                ids,
+                events,
+            }
+        }
+
+        // 侧边栏当前显示的笔记，按显示顺序
+        fn visible(&mut self) -> Vec<u128> {
+            self.sidebar.read_with(self.cx, |sidebar, _| {
+                sidebar.tree.iter().map(|entry| entry.id).collect()
+            })
+        }
+
+        fn click(&mut self, selector: String) -> Vec<SidebarEvent> {
+            self.events.borrow_mut().clear();
+            let bounds = self
+                .cx
+                .debug_bounds(Box::leak(selector.into_boxed_str()))
+                .expect("找不到元素");
+            self.cx.simulate_click(bounds.center(), Modifiers::none());
+            self.cx.run_until_parked();
+            self.events.borrow().clone()
+        }
+    }
+
+    #[gpui::test]
+    fn delete_button_emits_its_own_rows_note(cx: &mut TestAppContext) {
+        let mut test = TestSidebar::open(cx, 3);
+        for note_id in test.ids.clone() {
+            let events = test.click(format!("delete-note-{}", note_id));
+            assert_eq!(events, vec![SidebarEvent::DeleteNote(note_id)]);
+        }
+    }
+
+    #[gpui::test]
+    fn clicking_row_selects_its_note(cx: &mut TestAppContext) {
+        let mut test = TestSidebar::open(cx, 3);
+        let note_id = test.ids[1];
+        let events = test.click(format!("note-row-{}", note_id));
+        assert_eq!(events, vec![SidebarEvent::SelectNote(note_id)]);
+    }
+
+    #[gpui::test]
+    fn child_notes_collapse_under_their_parent(cx: &mut TestAppContext) {
+        let mut test = TestSidebar::open(cx, 3);
+        let (parent, other, child) = (test.ids[0], test.ids[1], test.ids[2]);
+        test.notes
+            .update(test.cx, |notes, cx| {
+                notes.set_parent(child, Some(parent), cx)
+            })
+            .unwrap();
+        test.cx.run_until_parked();
+        let visible = test.visible();
+        let ix = visible.iter().position(|&id| id == parent).unwrap();
+        assert_eq!(visible.len(), 3);
+        assert_eq!(visible[ix + 1], child);
+
+        assert!(test.click(format!("toggle-note-{}", parent)).is_empty());
+        assert!(!test.visible().contains(&child));
+        assert!(test.visible().contains(&other));
+        assert_eq!(
+            test.click(format!("new-child-note-{}", parent)),
+            vec![SidebarEvent::CreateChildNote(parent)]
+        );
+
+        // 选中收起的子笔记时展开父笔记
+        test.notes
+            .update(test.cx, |notes, cx| notes.select(Some(child), cx));
+        test.cx.run_until_parked();
+        assert!(test.visible().contains(&child));
+
+        // 不能移到自己的子笔记下面
+        let moved = test.notes.update(test.cx, |notes, cx| {
+            notes.set_parent(parent, Some(child), cx)
+        });
+        assert!(moved.is_err());
+
+        // 删除父笔记后子笔记留在顶层
+        test.notes
+            .update(test.cx, |notes, cx| notes.remove(parent, cx))
+            .unwrap();
+        let orphaned = test
+            .notes
+            .read_with(test.cx, |notes, _| notes.get(child).unwrap().parent);
+        assert_eq!(orphaned, None);
+    }
+}
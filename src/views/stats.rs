@@ -0,0 +1,209 @@
+use crate::{
+    note_index::NoteIndex,
+    note_list::NoteList,
+    stats::{NoteStats, WEEKS},
+};
+use chrono::Local;
+use gpui::*;
+use gpui_component::{button::Button, h_flex, v_flex};
+
+pub enum StatsEvent {
+    OpenNote(u128),
+    Close,
+}
+
+pub struct StatsView {
+    notes: Entity<NoteList>,
+    index: Entity<NoteIndex>,
+    stats: NoteStats,
+    _notes_changed: Subscription,
+    _summaries_ready: Subscription,
+}
+
+impl StatsView {
+    pub fn new(notes: Entity<NoteList>, index: Entity<NoteIndex>, cx: &mut Context<Self>) -> Self {
+        let stats = Self::compute(&notes, &index, cx);
+        // 字数在后台陆续算好，每到一批就刷新一次
+        let notes_changed = cx.observe(&notes, |this, _, cx| this.recompute(cx));
+        let summaries_ready = cx.observe(&index, |this, _, cx| this.recompute(cx));
+        Self {
+            notes,
+            index,
+            stats,
+            _notes_changed: notes_changed,
+            _summaries_ready: summaries_ready,
+        }
+    }
+
+    fn compute(notes: &Entity<NoteList>, index: &Entity<NoteIndex>, cx: &App) -> NoteStats {
+        let index = index.read(cx);
+        NoteStats::compute(
+            &notes.read(cx).get_all(),
+            |note| index.word_count(note.id),
+            Local::now().date_naive(),
+        )
+    }
+
+    fn recompute(&mut self, cx: &mut Context<Self>) {
+        self.stats = Self::compute(&self.notes, &self.index, cx);
+        cx.notify();
+    }
+
+    fn render_card(label: &str, value: String) -> impl IntoElement {
+        v_flex()
+            .flex_1()
+            .p_4()
+            .gap_1()
+            .rounded_md()
+            .border_1()
+            .border_color(gpui::rgb(0xe5e7eb))
+            .child(
+                div()
+                    .text_xs()
+                    .text_color(gpui::rgb(0x6b7280))
+                    .child(label.to_string()),
+            )
+            .child(
+                div()
+                    .text_2xl()
+                    .font_weight(FontWeight::SEMIBOLD)
+                    .child(value),
+            )
+    }
+
+    fn render_sparkline(&self) -> impl IntoElement {
+        let counts = self.stats.created_per_week.clone();
+        let max = counts.iter().copied().max().unwrap_or(0).max(1) as f32;
+
+        canvas(
+            |_, _, _| {},
+            move |bounds, _, window, _cx| {
+                if counts.len() < 2 {
+                    return;
+                }
+                let step = bounds.size.width / (counts.len() - 1) as f32;
+                let mut builder = PathBuilder::stroke(px(2.));
+                for (ix, count) in counts.iter().enumerate() {
+                    let point = point(
+                        bounds.origin.x + step * ix as f32,
+                        bounds.origin.y + bounds.size.height * (1.0 - *count as f32 / max),
+                    );
+                    if ix == 0 {
+                        builder.move_to(point);
+                    } else {
+                        builder.line_to(point);
+                    }
+                }
+                if let Ok(path) = builder.build() {
+                    window.paint_path(path, gpui::rgb(0x6366f1));
+                }
+            },
+        )
+        .w_full()
+        .h(px(60.))
+    }
+
+    fn render_section(title: &str) -> Div {
+        v_flex().gap_2().child(
+            div()
+                .text_sm()
+                .font_weight(FontWeight::SEMIBOLD)
+                .child(title.to_string()),
+        )
+    }
+}
+
+impl EventEmitter<StatsEvent> for StatsView {}
+
+impl Render for StatsView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let stats = &self.stats;
+        let this_week = stats.created_per_week.last().copied().unwrap_or(0);
+
+        v_flex()
+            .size_full()
+            .bg(gpui::rgb(0xffffff))
+            .child(
+                h_flex()
+                    .px_6()
+                    .py_3()
+                    .border_b_1()
+                    .border_color(gpui::rgb(0xe5e7eb))
+                    .items_center()
+                    .justify_between()
+                    .child(
+                        div()
+                            .text_lg()
+                            .font_weight(FontWeight::SEMIBOLD)
+                            .child("统计"),
+                    )
+                    .child(
+                        Button::new("close-stats")
+                            .label("返回")
+                            .on_click(cx.listener(|_, _, _window, cx| {
+                                cx.emit(StatsEvent::Close);
+                            })),
+                    ),
+            )
+            .child(
+                v_flex()
+                    .id("stats-body")
+                    .flex_1()
+                    .p_6()
+                    .gap_6()
+                    .overflow_y_scroll()
+                    .child(
+                        h_flex()
+                            .gap_4()
+                            .child(Self::render_card("笔记总数", stats.total_notes.to_string()))
+                            .child(Self::render_card("总字数", stats.total_words.to_string()))
+                            .child(Self::render_card(
+                                "连续写作",
+                                format!("{} 天", stats.streak),
+                            )),
+                    )
+                    .child(
+                        Self::render_section("每周新建笔记")
+                            .child(self.render_sparkline())
+                            .child(
+                                div()
+                                    .text_xs()
+                                    .text_color(gpui::rgb(0x6b7280))
+                                    .child(format!("最近 {} 周，本周 {} 篇", WEEKS, this_week)),
+                            ),
+                    )
+                    .child(Self::render_section("常用标签").children(
+                        if stats.top_tags.is_empty() {
+                            vec![
+                                div()
+                                    .text_sm()
+                                    .text_color(gpui::rgb(0x6b7280))
+                                    .child("暂无标签"),
+                            ]
+                        } else {
+                            stats
+                                .top_tags
+                                .iter()
+                                .map(|(tag, count)| {
+                                    div().text_sm().child(format!("#{}  {} 篇", tag, count))
+                                })
+                                .collect()
+                        },
+                    ))
+                    .child(Self::render_section("最长的笔记").children(
+                        stats.longest_notes.iter().map(|(note_id, title, words)| {
+                            let note_id = *note_id;
+                            div()
+                                .id(SharedString::from(format!("stats-note-{}", note_id)))
+                                .text_sm()
+                                .cursor_pointer()
+                                .hover(|this| this.text_color(gpui::rgb(0x6366f1)))
+                                .on_click(cx.listener(move |_, _, _window, cx| {
+                                    cx.emit(StatsEvent::OpenNote(note_id));
+                                }))
+                                .child(format!("{}  {} 字", title, words))
+                        }),
+                    )),
+            )
+    }
+}
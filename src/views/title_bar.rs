@@ -0,0 +1,207 @@
+use crate::actions::{APP_NAME, ToggleClipboardCapture};
+use gpui::{prelude::FluentBuilder as _, *};
+use gpui_component::{
+    Sizable as _, TitleBar,
+    button::{Button, ButtonVariants as _},
+    h_flex,
+    menu::{AppMenuBar, DropdownMenu as _, PopupMenuItem},
+};
+
+pub enum TitleBarEvent {
+    SwitchVault(SharedString),
+    /// 选择一个文件夹作为新的笔记库
+    AddVault,
+}
+
+pub struct AppTitleBar {
+    app_menu_bar: Entity<AppMenuBar>,
+    note_title: Option<SharedString>,
+    /// 当前笔记有未保存的更改，标题前显示圆点
+    dirty: bool,
+    capturing: bool,
+    /// 当前笔记库和全部笔记库的名称，只有一个笔记库时也显示，方便添加
+    vault: Option<SharedString>,
+    vaults: Vec<SharedString>,
+    /// 应用锁定时不显示笔记标题
+    locked: bool,
+    window: AnyWindowHandle,
+}
+
+impl AppTitleBar {
+    pub fn new(window: &mut Window, cx: &mut Context<Self>) -> Self {
+        let this = Self {
+            app_menu_bar: AppMenuBar::new(window, cx),
+            note_title: None,
+            dirty: false,
+            capturing: false,
+            vault: None,
+            vaults: Vec::new(),
+            locked: false,
+            window: window.window_handle(),
+        };
+        window.set_window_title(&this.window_title());
+        this
+    }
+
+    /// 标题栏中间显示的笔记标题，有未保存的更改时前面加圆点
+    fn display_title(&self) -> Option<SharedString> {
+        self.note_title
+            .as_ref()
+            .filter(|_| !self.locked)
+            .map(|note_title| {
+                if self.dirty {
+                    SharedString::from(format!("● {}", note_title))
+                } else {
+                    note_title.clone()
+                }
+            })
+    }
+
+    fn window_title(&self) -> String {
+        match self.display_title() {
+            Some(note_title) => format!("{} — {}", note_title, APP_NAME),
+            None => APP_NAME.to_string(),
+        }
+    }
+
+    // 只在标题相关的状态变化时更新系统窗口标题，不在每次绘制时设置
+    fn update_window_title(&self, cx: &mut Context<Self>) {
+        let title = self.window_title();
+        let window = self.window;
+        // 调用方可能正在更新这个窗口，等它结束后再设置
+        cx.defer(move |cx| {
+            window
+                .update(cx, |_, window, _| window.set_window_title(&title))
+                .ok();
+        });
+    }
+
+    pub fn set_vaults(
+        &mut self,
+        current: SharedString,
+        vaults: Vec<SharedString>,
+        cx: &mut Context<Self>,
+    ) {
+        self.vault = Some(current);
+        self.vaults = vaults;
+        cx.notify();
+    }
+
+    fn render_vault_switcher(
+        &self,
+        current: SharedString,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        let vaults = self.vaults.clone();
+        let this = cx.entity().downgrade();
+        Button::new("vault-switcher")
+            .label(current.clone())
+            .ghost()
+            .xsmall()
+            .dropdown_menu(move |mut menu, _window, _cx| {
+                for name in &vaults {
+                    let this = this.clone();
+                    let target = name.clone();
+                    menu = menu.item(
+                        PopupMenuItem::new(name.clone())
+                            .checked(*name == current)
+                            .on_click(move |_, _, cx| {
+                                this.update(cx, |_, cx| {
+                                    cx.emit(TitleBarEvent::SwitchVault(target.clone()))
+                                })
+                                .ok();
+                            }),
+                    );
+                }
+                let this = this.clone();
+                menu.separator()
+                    .item(
+                        PopupMenuItem::new("打开文件夹作为笔记库…").on_click(move |_, _, cx| {
+                            this.update(cx, |_, cx| cx.emit(TitleBarEvent::AddVault))
+                                .ok();
+                        }),
+                    )
+            })
+    }
+
+    pub fn set_capturing(&mut self, capturing: bool, cx: &mut Context<Self>) {
+        self.capturing = capturing;
+        cx.notify();
+    }
+
+    pub fn set_dirty(&mut self, dirty: bool, cx: &mut Context<Self>) {
+        self.dirty = dirty;
+        self.update_window_title(cx);
+        cx.notify();
+    }
+
+    pub fn set_locked(&mut self, locked: bool, cx: &mut Context<Self>) {
+        self.locked = locked;
+        self.update_window_title(cx);
+        cx.notify();
+    }
+
+    pub fn set_note_title(&mut self, title: Option<String>, cx: &mut Context<Self>) {
+        self.note_title = title.map(SharedString::from);
+        self.update_window_title(cx);
+        cx.notify();
+    }
+}
+
+impl EventEmitter<TitleBarEvent> for AppTitleBar {}
+
+impl Render for AppTitleBar {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let note_title = self.display_title();
+
+        TitleBar::new()
+            .child(
+                h_flex()
+                    .gap_2()
+                    .items_center()
+                    // macOS 使用系统菜单栏，其余平台把菜单画在标题栏里
+                    .when(!cfg!(target_os = "macos"), |this| {
+                        this.child(self.app_menu_bar.clone())
+                    })
+                    .child(
+                        div()
+                            .text_sm()
+                            .font_weight(FontWeight::SEMIBOLD)
+                            .child(APP_NAME),
+                    )
+                    .when_some(self.vault.clone(), |this, vault| {
+                        this.child(self.render_vault_switcher(vault, cx))
+                    }),
+            )
+            .child(
+                div()
+                    .flex_1()
+                    .text_sm()
+                    .text_center()
+                    .text_color(gpui::rgb(0x6b7280))
+                    .truncate()
+                    .children(note_title),
+            )
+            // 为右侧窗口按钮留出与左侧菜单对称的空间，保证标题居中
+            .child(
+                h_flex()
+                    .w(px(120.0))
+                    .justify_end()
+                    .when(self.capturing, |this| {
+                        this.child(
+                            div()
+                                .id("clipboard-capture")
+                                .px_2()
+                                .text_xs()
+                                .text_color(gpui::rgb(0xdc2626))
+                                .cursor_pointer()
+                                .on_click(|_, window, cx| {
+                                    window
+                                        .dispatch_action(ToggleClipboardCapture.boxed_clone(), cx);
+                                })
+                                .child("● 剪藏中"),
+                        )
+                    }),
+            )
+    }
+}
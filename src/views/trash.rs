@@ -0,0 +1,189 @@
+use crate::{settings::AppSettings, storage::TrashedNote};
+use gpui::{prelude::FluentBuilder as _, *};
+use gpui_component::{
+    button::{Button, ButtonVariants},
+    h_flex, v_flex,
+};
+
+pub enum TrashEvent {
+    Restore(u128),
+    /// 永久删除一篇笔记
+    Delete(u128),
+    Empty,
+    Close,
+}
+
+/// 回收站：列出已删除的笔记和到期时间，可以恢复或永久删除
+pub struct TrashView {
+    trashed: Vec<TrashedNote>,
+}
+
+impl TrashView {
+    pub fn new(trashed: Vec<TrashedNote>) -> Self {
+        Self { trashed }
+    }
+
+    /// 从列表中取出一篇笔记，恢复或永久删除后不再显示
+    pub fn take(&mut self, note_id: u128, cx: &mut Context<Self>) -> Option<TrashedNote> {
+        let ix = self
+            .trashed
+            .iter()
+            .position(|trashed| trashed.note.id == note_id)?;
+        cx.notify();
+        Some(self.trashed.remove(ix))
+    }
+
+    pub fn note_ids(&self) -> Vec<u128> {
+        self.trashed.iter().map(|trashed| trashed.note.id).collect()
+    }
+
+    pub fn clear(&mut self, cx: &mut Context<Self>) {
+        self.trashed.clear();
+        cx.notify();
+    }
+
+    fn render_row(
+        &self,
+        ix: usize,
+        trashed: &TrashedNote,
+        retention: Option<chrono::Duration>,
+        cx: &mut Context<Self>,
+    ) -> Div {
+        let note_id = trashed.note.id;
+        let deleted = trashed
+            .deleted_at
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M");
+        let schedule = match trashed.purge_at(retention) {
+            Some(purge_at) => format!(
+                "删除于 {}，将于 {} 永久删除",
+                deleted,
+                purge_at.with_timezone(&chrono::Local).format("%Y-%m-%d")
+            ),
+            None => format!("删除于 {}", deleted),
+        };
+
+        h_flex()
+            .py_2()
+            .gap_4()
+            .justify_between()
+            .border_b_1()
+            .border_color(gpui::rgb(0xf3f4f6))
+            .child(
+                v_flex()
+                    .gap_1()
+                    .min_w_0()
+                    .child(
+                        div()
+                            .font_weight(FontWeight::SEMIBOLD)
+                            .child(trashed.note.title.clone()),
+                    )
+                    .child(
+                        div()
+                            .text_xs()
+                            .text_color(gpui::rgb(0x9ca3af))
+                            .child(schedule),
+                    ),
+            )
+            .child(
+                h_flex()
+                    .gap_2()
+                    .child(
+                        Button::new(("restore-trashed", ix))
+                            .label("恢复")
+                            .primary()
+                            .compact()
+                            .on_click(cx.listener(move |_, _, _window, cx| {
+                                cx.emit(TrashEvent::Restore(note_id));
+                            })),
+                    )
+                    .child(
+                        Button::new(("delete-trashed", ix))
+                            .label("永久删除")
+                            .danger()
+                            .compact()
+                            .on_click(cx.listener(move |_, _, _window, cx| {
+                                cx.emit(TrashEvent::Delete(note_id));
+                            })),
+                    ),
+            )
+    }
+}
+
+impl EventEmitter<TrashEvent> for TrashView {}
+
+impl Render for TrashView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let retention = AppSettings::get(cx).trash.retention();
+        let summary = match retention {
+            Some(retention) => format!(
+                "共 {} 篇笔记。删除 {} 天后自动永久删除，可在设置中修改。",
+                self.trashed.len(),
+                retention.num_days()
+            ),
+            None => format!("共 {} 篇笔记。不会自动删除。", self.trashed.len()),
+        };
+        let rows: Vec<Div> = self
+            .trashed
+            .iter()
+            .enumerate()
+            .map(|(ix, trashed)| self.render_row(ix, trashed, retention, cx))
+            .collect();
+
+        v_flex()
+            .size_full()
+            .bg(gpui::rgb(0xffffff))
+            .child(
+                h_flex()
+                    .px_6()
+                    .py_3()
+                    .border_b_1()
+                    .border_color(gpui::rgb(0xe5e7eb))
+                    .items_center()
+                    .justify_between()
+                    .child(
+                        div()
+                            .text_lg()
+                            .font_weight(FontWeight::SEMIBOLD)
+                            .child("回收站"),
+                    )
+                    .child(
+                        h_flex()
+                            .gap_2()
+                            .when(!rows.is_empty(), |this| {
+                                this.child(Button::new("empty-trash").label("清空回收站").on_click(
+                                    cx.listener(|_, _, _window, cx| {
+                                        cx.emit(TrashEvent::Empty);
+                                    }),
+                                ))
+                            })
+                            .child(
+                                Button::new("close-trash")
+                                    .label("返回")
+                                    .on_click(cx.listener(|_, _, _window, cx| {
+                                        cx.emit(TrashEvent::Close);
+                                    })),
+                            ),
+                    ),
+            )
+            .child(
+                v_flex()
+                    .id("trash-list")
+                    .flex_1()
+                    .px_6()
+                    .py_3()
+                    .overflow_y_scroll()
+                    .child(
+                        div()
+                            .pb_2()
+                            .text_sm()
+                            .text_color(gpui::rgb(0x6b7280))
+                            .child(summary),
+                    )
+                    .when(rows.is_empty(), |this| {
+                        this.child(div().text_color(gpui::rgb(0x9ca3af)).child("回收站是空的"))
+                    })
+                    .children(rows),
+            )
+    }
+}
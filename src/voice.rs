@@ -0,0 +1,76 @@
+use crate::{settings::VoiceSettings, storage::Storage};
+use anyhow::{Context, Result, bail};
+use chrono::Local;
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+};
+
+/// 正在进行的录音，ffmpeg 把麦克风输入写入附件目录
+pub struct Recording {
+    child: Child,
+    /// 附件文件名，录音结束后插入笔记
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl Recording {
+    pub fn start(settings: &VoiceSettings, storage: &Storage) -> Result<Self> {
+        // 同一秒内再次录音时加序号，不会让 ffmpeg 停在是否覆盖的提问上
+        let name = format!("录音-{}.wav", Local::now().format("%Y%m%d-%H%M%S"));
+        let name = storage.unused_attachment_name(Path::new(&name))?;
+        let path = storage.attachments_dir().join(&name);
+        let child = Command::new(&settings.ffmpeg)
+            .args(["-hide_banner", "-loglevel", "error", "-y"])
+            .args(settings.input_args.split_whitespace())
+            // 16kHz 单声道 WAV，whisper.cpp 等转写工具可以直接读取
+            .args(["-ac", "1", "-ar", "16000", "-c:a", "pcm_s16le"])
+            .arg(&path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context(format!("无法启动 ffmpeg: {}", settings.ffmpeg))?;
+        Ok(Self { child, name, path })
+    }
+
+    /// 结束录音。向 ffmpeg 发送 q 让它正常写完文件，直接结束进程会得到损坏的文件
+    pub fn stop(mut self) -> Result<()> {
+        if let Some(mut stdin) = self.child.stdin.take() {
+            stdin.write_all(b"q").ok();
+        }
+        let output = self
+            .child
+            .wait_with_output()
+            .context("等待 ffmpeg 结束失败")?;
+        if !output.status.success() || !self.path.exists() {
+            bail!(
+                "录音失败: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// 用设置中的命令转写录音，命令中的 {file} 替换为录音路径，转写结果从标准输出读取
+pub fn transcribe(path: &Path, settings: &VoiceSettings) -> Result<String> {
+    let file = path.to_string_lossy();
+    let mut args = settings
+        .transcribe_command
+        .split_whitespace()
+        .map(|arg| arg.replace("{file}", &file));
+    let program = args.next().context("没有设置转写命令")?;
+    let output = Command::new(&program)
+        .args(args)
+        .output()
+        .context(format!("无法启动转写命令: {}", program))?;
+    if !output.status.success() {
+        bail!(
+            "转写失败: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
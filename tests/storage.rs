@@ -0,0 +1,344 @@
+//! 存储层测试，每个测试使用独立的临时数据目录
+
+use chrono::{Duration, Utc};
+use notes_app::{
+    note::{CONFLICT_COPY_SUFFIX, Note},
+    storage::{NoteProblem, Storage},
+};
+use std::{collections::HashMap, fs, path::Path, thread};
+use tempfile::TempDir;
+
+fn temp_storage() -> (TempDir, Storage) {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = Storage::with_dir(dir.path().to_path_buf()).unwrap();
+    (dir, storage)
+}
+
+fn load_all(storage: &Storage) -> HashMap<u128, Note> {
+    let mut notes = HashMap::new();
+    storage.load_all_notes(&mut notes).unwrap();
+    notes
+}
+
+fn note_path(dir: &Path, note: &Note) -> std::path::PathBuf {
+    dir.join(format!("{}.json", note.id))
+}
+
+#[test]
+fn saved_notes_load_back_unchanged() {
+    let (_dir, storage) = temp_storage();
+    let mut note = Note::new();
+    note.title = "周会记录".to_string();
+    note.content = "第一行\n第二行".to_string();
+    note.tags = vec!["会议".to_string()];
+    storage.save_note(&note).unwrap();
+
+    let notes = load_all(&storage);
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[&note.id], note);
+}
+
+#[test]
+fn unicode_titles_round_trip() {
+    let (_dir, storage) = temp_storage();
+    let titles = [
+        "中文标题",
+        "日本語のタイトル",
+        "한국어",
+        "emoji 🎉📝",
+        "مرحبا",
+        "  空格  ",
+    ];
+    let notes: Vec<Note> = titles
+        .iter()
+        .map(|title| Note {
+            title: title.to_string(),
+            ..Note::new()
+        })
+        .collect();
+    for note in &notes {
+        storage.save_note(note).unwrap();
+    }
+
+    let loaded = load_all(&storage);
+    for note in &notes {
+        assert_eq!(loaded[&note.id].title, note.title);
+    }
+}
+
+#[test]
+fn resaving_replaces_content() {
+    let (_dir, storage) = temp_storage();
+    let mut note = Note::new();
+    note.content = "旧内容".to_string();
+    storage.save_note(&note).unwrap();
+    note.content = "新内容".to_string();
+    storage.save_note(&note).unwrap();
+
+    assert_eq!(load_all(&storage)[&note.id].content, "新内容");
+}
+
+#[test]
+fn concurrent_saves_of_different_notes() {
+    let (_dir, storage) = temp_storage();
+    let handles: Vec<_> = (0..8)
+        .map(|thread_ix| {
+            let storage = storage.clone();
+            thread::spawn(move || {
+                (0..25)
+                    .map(|ix| {
+                        let note = Note {
+                            title: format!("线程 {} 笔记 {}", thread_ix, ix),
+                            content: "内容".repeat(ix),
+                            ..Note::new()
+                        };
+                        storage.save_note(&note).unwrap();
+                        note
+                    })
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    let saved: Vec<Note> = handles
+        .into_iter()
+        .flat_map(|handle| handle.join().unwrap())
+        .collect();
+
+    let loaded = load_all(&storage);
+    assert_eq!(loaded.len(), saved.len());
+    for note in &saved {
+        assert_eq!(&loaded[&note.id], note);
+    }
+    assert!(storage.verify().unwrap().damaged.is_empty());
+}
+
+#[test]
+fn corrupted_file_is_skipped_when_loading() {
+    let (dir, storage) = temp_storage();
+    let good = Note::new();
+    let bad = Note::new();
+    storage.save_note(&good).unwrap();
+    storage.save_note(&bad).unwrap();
+    fs::write(note_path(dir.path(), &bad), "not json").unwrap();
+
+    let loaded = load_all(&storage);
+    assert_eq!(loaded.len(), 1);
+    assert!(loaded.contains_key(&good.id));
+}
+
+#[test]
+fn non_note_files_are_ignored() {
+    let (dir, storage) = temp_storage();
+    storage.save_note(&Note::new()).unwrap();
+    fs::write(dir.path().join("settings.json"), "{}").unwrap();
+    fs::write(dir.path().join("readme.txt"), "hello").unwrap();
+
+    assert_eq!(load_all(&storage).len(), 1);
+    assert_eq!(storage.verify().unwrap().checked, 1);
+}
+
+#[test]
+fn verify_reports_truncated_file() {
+    let (dir, storage) = temp_storage();
+    let note = Note::new();
+    storage.save_note(&note).unwrap();
+    let path = note_path(dir.path(), &note);
+    let content = fs::read(&path).unwrap();
+    fs::write(&path, &content[..content.len() / 2]).unwrap();
+
+    let report = storage.verify().unwrap();
+    assert_eq!(report.damaged.len(), 1);
+    assert!(matches!(report.damaged[0].problem, NoteProblem::Truncated));
+}
+
+#[test]
+fn verify_reports_checksum_mismatch() {
+    let (dir, storage) = temp_storage();
+    let note = Note {
+        content: "原文".to_string(),
+        ..Note::new()
+    };
+    storage.save_note(&note).unwrap();
+    let path = note_path(dir.path(), &note);
+    let content = fs::read_to_string(&path).unwrap().replace("原文", "改过");
+    fs::write(&path, content).unwrap();
+
+    let report = storage.verify().unwrap();
+    assert_eq!(report.damaged.len(), 1);
+    assert!(matches!(
+        report.damaged[0].problem,
+        NoteProblem::ChecksumMismatch
+    ));
+}
+
+#[test]
+fn damaged_note_offers_last_good_backup() {
+    let (dir, storage) = temp_storage();
+    let mut note = Note {
+        content: "第一版".to_string(),
+        ..Note::new()
+    };
+    storage.save_note(&note).unwrap();
+    note.content = "第二版".to_string();
+    storage.save_note(&note).unwrap();
+    fs::write(note_path(dir.path(), &note), "{").unwrap();
+
+    let report = storage.verify().unwrap();
+    let backup = report.damaged[0].backup.as_ref().unwrap();
+    assert_eq!(backup.content, "第一版");
+}
+
+#[test]
+fn files_without_checksum_are_counted_as_unverified() {
+    let (dir, storage) = temp_storage();
+    let note = Note::new();
+    fs::write(
+        note_path(dir.path(), &note),
+        serde_json::to_string(&note).unwrap(),
+    )
+    .unwrap();
+
+    let report = storage.verify().unwrap();
+    assert_eq!(report.checked, 1);
+    assert_eq!(report.unverified, 1);
+    assert!(report.damaged.is_empty());
+}
+
+#[test]
+fn deleting_removes_note_and_backup() {
+    let (dir, storage) = temp_storage();
+    let note = Note::new();
+    storage.save_note(&note).unwrap();
+    storage.save_note(&note).unwrap();
+    storage.delete_note(note.id).unwrap();
+
+    assert!(load_all(&storage).is_empty());
+    assert!(!note_path(dir.path(), &note).exists());
+    assert!(
+        !storage
+            .backups_dir()
+            .join(format!("{}.json", note.id))
+            .exists()
+    );
+}
+
+#[test]
+fn missing_files_use_defaults() {
+    let (_dir, storage) = temp_storage();
+    assert!(storage.load_note_index().unwrap().is_none());
+    assert!(storage.load_session().is_ok());
+    assert!(storage.load_settings().is_ok());
+}
+
+#[test]
+fn trashed_notes_keep_content_and_deletion_time() {
+    let (dir, storage) = temp_storage();
+    let mut note = Note::new();
+    note.title = "要删除的笔记".to_string();
+    storage.save_note(&note).unwrap();
+    // 回收站存的是传入的内容，不是磁盘上的旧版本
+    note.content = "还没写盘的修改".to_string();
+    let deleted_at = Utc::now();
+    storage.trash_note(&note, deleted_at).unwrap();
+
+    assert!(!note_path(dir.path(), &note).exists());
+    assert!(load_all(&storage).is_empty());
+    let trashed = storage.load_trash().unwrap();
+    assert_eq!(trashed.len(), 1);
+    assert_eq!(trashed[0].note, note);
+    assert_eq!(trashed[0].deleted_at, deleted_at);
+    assert_eq!(
+        trashed[0].purge_at(Some(Duration::days(30))),
+        Some(deleted_at + Duration::days(30))
+    );
+}
+
+#[test]
+fn purge_removes_only_expired_notes() {
+    let (_dir, storage) = temp_storage();
+    let now = Utc::now();
+    let old = Note::new();
+    let recent = Note::new();
+    storage.trash_note(&old, now - Duration::days(31)).unwrap();
+    storage
+        .trash_note(&recent, now - Duration::days(29))
+        .unwrap();
+
+    assert_eq!(storage.purge_trash(Duration::days(30), now).unwrap(), 1);
+    let trashed = storage.load_trash().unwrap();
+    assert_eq!(trashed.len(), 1);
+    assert_eq!(trashed[0].note.id, recent.id);
+
+    storage.remove_from_trash(recent.id).unwrap();
+    assert!(storage.load_trash().unwrap().is_empty());
+}
+
+#[test]
+fn copied_file_with_duplicate_id_becomes_conflict_copy() {
+    let (dir, storage) = temp_storage();
+    let mut note = Note {
+        title: "周报".to_string(),
+        content: "本机".to_string(),
+        ..Note::new()
+    };
+    storage.save_note(&note).unwrap();
+
+    // 另一台机器上的旧版本被复制成了另一个文件名
+    let mut older = note.clone();
+    older.content = "另一台机器".to_string();
+    older.updated_at = note.updated_at - Duration::hours(1);
+    let copied = dir.path().join("1.json");
+    fs::write(&copied, serde_json::to_string(&older).unwrap()).unwrap();
+
+    let notes = load_all(&storage);
+    assert_eq!(notes.len(), 2);
+    assert_eq!(notes[&note.id].content, "本机");
+    let copy = notes.values().find(|n| n.id != note.id).unwrap();
+    assert_eq!(copy.content, "另一台机器");
+    assert_eq!(copy.title, format!("周报{}", CONFLICT_COPY_SUFFIX));
+    assert!(!copied.exists());
+
+    // 再次读取结果不变，不会重复生成副本
+    assert_eq!(load_all(&storage), notes);
+
+    // 文件名不同但内容相同的文件只保留一份
+    note.updated_at -= Duration::minutes(1);
+    fs::write(&copied, serde_json::to_string(&note).unwrap()).unwrap();
+    assert_eq!(load_all(&storage).len(), 2);
+    assert!(!copied.exists());
+}
+
+#[test]
+fn renamed_file_moves_to_its_own_id() {
+    let (dir, storage) = temp_storage();
+    let note = Note::new();
+    let renamed = dir.path().join("1.json");
+    fs::write(&renamed, serde_json::to_string(&note).unwrap()).unwrap();
+
+    let notes = load_all(&storage);
+    assert_eq!(notes[&note.id], note);
+    assert!(!renamed.exists());
+    assert!(note_path(dir.path(), &note).exists());
+}
+
+#[test]
+fn unused_attachment_names_get_a_number() {
+    let (_dir, storage) = temp_storage();
+    let name = storage
+        .unused_attachment_name(Path::new("录音.wav"))
+        .unwrap();
+    assert_eq!(name, "录音.wav");
+    fs::write(storage.attachments_dir().join(&name), b"RIFF").unwrap();
+    assert_eq!(
+        storage
+            .unused_attachment_name(Path::new("录音.wav"))
+            .unwrap(),
+        "录音-2.wav"
+    );
+
+    fs::write(storage.attachments_dir().join("README"), b"").unwrap();
+    assert_eq!(
+        storage.unused_attachment_name(Path::new("README")).unwrap(),
+        "README-2"
+    );
+}